@@ -0,0 +1,103 @@
+#![recursion_limit = "512"]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use moxie_native::bench::Bench;
+use moxie_native::prelude::*;
+
+const PARAGRAPH: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+    eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis \
+    nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.";
+
+define_style! {
+    static ROW_STYLE = {
+        direction: horizontal,
+        padding: 4 px,
+        background_color: rgb(240, 240, 240),
+    };
+
+    static CELL_STYLE = {
+        width: 10 vw,
+        padding: 2 px,
+        background_color: rgb(255, 255, 255),
+    };
+}
+
+#[topo::nested]
+fn grid() -> Node<Window> {
+    mox! {
+        <window>
+            <view>
+                {(0..50).map(|row| mox! {
+                    <view style={ROW_STYLE}>
+                        {(0..8).map(|column| mox! {
+                            <view style={CELL_STYLE}>
+                                <span>{% "{}:{}", row, column}</span>
+                            </view>
+                        }).collect::<Vec<_>>()}
+                    </view>
+                }).collect::<Vec<_>>()}
+            </view>
+        </window>
+    }
+}
+
+#[topo::nested]
+fn article() -> Node<Window> {
+    mox! {
+        <window>
+            <view>
+                {(0..20).map(|_| mox! {
+                    <view>
+                        <span>{PARAGRAPH}</span>
+                    </view>
+                }).collect::<Vec<_>>()}
+            </view>
+        </window>
+    }
+}
+
+fn styles(c: &mut Criterion) {
+    let mut bench = Bench::new();
+    let window = bench.build(|| grid!());
+    c.bench_function("grid: resolve styles", |b| {
+        b.iter(|| bench.resolve_styles(&window, 800.0, 600.0))
+    });
+}
+
+fn layout(c: &mut Criterion) {
+    let mut bench = Bench::new();
+    for (name, window) in vec![
+        ("grid", bench.build(|| grid!())),
+        ("article", bench.build(|| article!())),
+    ] {
+        bench.resolve_styles(&window, 800.0, 600.0);
+        c.bench_function(&format!("{}: layout from scratch", name), |b| {
+            b.iter(|| bench.layout_from_scratch(&window, 800.0, 600.0, 1.0))
+        });
+        c.bench_function(&format!("{}: layout with text shaped", name), |b| {
+            b.iter(|| bench.layout_shaped(&window, 800.0, 600.0, 1.0))
+        });
+        c.bench_function(&format!("{}: layout when unchanged", name), |b| {
+            b.iter(|| bench.layout(&window, 800.0, 600.0, 1.0))
+        });
+        c.bench_function(&format!("{}: layout after one change", name), |b| {
+            b.iter(|| {
+                bench.change_last(&window);
+                bench.layout(&window, 800.0, 600.0, 1.0)
+            })
+        });
+    }
+}
+
+fn text(c: &mut Criterion) {
+    let bench = Bench::new();
+    c.bench_function("paragraph: shape text", |b| {
+        b.iter(|| black_box(bench.shape_text(PARAGRAPH, 16.0, 300.0, 1.0)))
+    });
+    c.bench_function("paragraph: break shaped text", |b| {
+        b.iter(|| black_box(bench.break_lines(PARAGRAPH, 16.0, 300.0, 1.0)))
+    });
+}
+
+criterion_group!(benches, styles, layout, text);
+criterion_main!(benches);
@@ -13,9 +13,9 @@ define_style! {
     };
 
     static CHILD_STYLE = {
-        padding: 10 px auto auto auto,
+        padding: auto auto auto 10 px,
         background_color: rgb(255, 255, 255),
-        border_thickness: 1.0 px auto auto auto,
+        border_thickness: auto auto auto 1.0 px,
         border_color: rgb(210, 210, 210),
     };
 
@@ -0,0 +1,47 @@
+use moxie_native::prelude::*;
+use moxie_native_preview::{preview, theme, Theme};
+
+define_style! {
+    static LIGHT_STYLE = {
+        padding: 10 px,
+        text_size: 20 px,
+        background_color: rgb(66, 135, 245),
+        text_color: rgb(255, 255, 255),
+
+        if state: hover {
+            background_color: rgb(112, 167, 255),
+        }
+    };
+
+    static DARK_STYLE = {
+        padding: 10 px,
+        text_size: 20 px,
+        background_color: rgb(200, 200, 200),
+        text_color: rgb(0, 0, 0),
+
+        if state: hover {
+            background_color: rgb(238, 238, 238),
+        }
+    };
+}
+
+#[topo::nested]
+fn counter() -> Node<Button> {
+    let count: Key<usize> = state!(|| 0);
+    let count_state = count.clone();
+    let on_click = move |_: &ClickEvent| count_state.update(|count| Some(count + 1));
+    let style = match theme() {
+        Theme::Light => LIGHT_STYLE,
+        Theme::Dark => DARK_STYLE,
+    };
+
+    mox! {
+        <button style={style} on={on_click}>
+            <span>"Clicked " {% "{}", count} " times"</span>
+        </button>
+    }
+}
+
+fn main() {
+    preview!(counter!());
+}
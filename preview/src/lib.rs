@@ -0,0 +1,292 @@
+#![recursion_limit = "512"]
+
+//! A harness for developing a single component on its own. The
+//! component is mounted in a window with a toolbar for changing the
+//! size of the area it's drawn in, the theme, and the scale factor,
+//! which is much quicker to iterate on than a full application.
+//!
+//! ```rs
+//! fn main() {
+//!     moxie_native_preview::preview!(counter!());
+//! }
+//! ```
+//!
+//! Components can follow the theme chosen in the toolbar with
+//! `theme()`.
+
+use moxie_native::dom::elements::view::ViewChild;
+use moxie_native::prelude::*;
+use std::rc::Rc;
+
+define_style! {
+    static ROOT_STYLE = {
+        width: 100 vw,
+        height: 100 vh,
+        background_color: rgb(225, 225, 225),
+    };
+
+    static TOOLBAR_STYLE = {
+        direction: horizontal,
+        height: 40 px,
+        padding: 4 px,
+        text_size: 14 px,
+        text_color: rgb(0, 0, 0),
+        background_color: rgba(0, 0, 0, 0),
+    };
+
+    static KNOB_STYLE = {
+        padding: 6 px,
+        margin: 0 px 4 px 0 px 0 px,
+        background_color: rgb(245, 245, 245),
+
+        if state: hover {
+            background_color: rgb(255, 255, 255),
+        }
+    };
+
+    static LIGHT_STAGE_STYLE = {
+        height: 100 vh - 40 px,
+        padding: 16 px,
+        background_color: rgb(250, 250, 250),
+        text_color: rgb(20, 20, 20),
+    };
+
+    static DARK_STAGE_STYLE = {
+        height: 100 vh - 40 px,
+        padding: 16 px,
+        background_color: rgb(30, 30, 30),
+        text_color: rgb(235, 235, 235),
+    };
+
+    static FILL_FRAME_STYLE = {
+        width: 100 vw - 32 px,
+        height: 100 vh - 72 px,
+        border_thickness: 1 px,
+        border_color: rgb(128, 128, 128),
+        background_color: rgba(0, 0, 0, 0),
+    };
+
+    static SMALL_FRAME_STYLE = {
+        width: 320 px,
+        height: 240 px,
+        border_thickness: 1 px,
+        border_color: rgb(128, 128, 128),
+        background_color: rgba(0, 0, 0, 0),
+    };
+
+    static MEDIUM_FRAME_STYLE = {
+        width: 640 px,
+        height: 480 px,
+        border_thickness: 1 px,
+        border_color: rgb(128, 128, 128),
+        background_color: rgba(0, 0, 0, 0),
+    };
+
+    static LARGE_FRAME_STYLE = {
+        width: 1024 px,
+        height: 768 px,
+        border_thickness: 1 px,
+        border_color: rgb(128, 128, 128),
+        background_color: rgba(0, 0, 0, 0),
+    };
+}
+
+/// The scale factors the toolbar cycles through. `None` uses the
+/// display's own.
+const SCALE_FACTORS: [Option<f32>; 5] = [None, Some(1.0), Some(1.5), Some(2.0), Some(3.0)];
+
+/// The color scheme of the area the component is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::Light
+    }
+}
+
+impl Theme {
+    fn next(self) -> Theme {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Theme::Light => LIGHT_STAGE_STYLE,
+            Theme::Dark => DARK_STAGE_STYLE,
+        }
+    }
+}
+
+/// The theme chosen in the preview's toolbar. Outside of a preview,
+/// this is always `Theme::Light`.
+pub fn theme() -> Theme {
+    consume::<Theme>().unwrap_or_default()
+}
+
+/// The size of the area the component is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    /// As much of the window as the toolbar leaves.
+    Fill,
+    /// 320 by 240 logical pixels.
+    Small,
+    /// 640 by 480 logical pixels.
+    Medium,
+    /// 1024 by 768 logical pixels.
+    Large,
+}
+
+impl Size {
+    fn next(self) -> Size {
+        match self {
+            Size::Fill => Size::Small,
+            Size::Small => Size::Medium,
+            Size::Medium => Size::Large,
+            Size::Large => Size::Fill,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Size::Fill => "fill",
+            Size::Small => "320 × 240",
+            Size::Medium => "640 × 480",
+            Size::Large => "1024 × 768",
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Size::Fill => FILL_FRAME_STYLE,
+            Size::Small => SMALL_FRAME_STYLE,
+            Size::Medium => MEDIUM_FRAME_STYLE,
+            Size::Large => LARGE_FRAME_STYLE,
+        }
+    }
+}
+
+fn scale_label(scale_factor: Option<f32>) -> String {
+    match scale_factor {
+        Some(scale_factor) => format!("{}x", scale_factor),
+        None => "native".to_owned(),
+    }
+}
+
+#[topo::nested]
+fn knob(label: String, on_click: impl FnMut(&ClickEvent) + 'static) -> Node<Button> {
+    mox! {
+        <button style={KNOB_STYLE} on={on_click}>
+            <span>{% "{}", label}</span>
+        </button>
+    }
+}
+
+#[topo::nested]
+fn preview_app(
+    title: &str,
+    initial_size: Size,
+    initial_theme: Theme,
+    component: &Rc<dyn Fn() -> ViewChild>,
+) -> Node<App> {
+    let size: Key<Size> = state!(|| initial_size);
+    let theme: Key<Theme> = state!(|| initial_theme);
+    let scale: Key<usize> = state!(|| 0);
+
+    let next_size = bind::update(&size, |size| size.next());
+    let next_theme = bind::update(&theme, |theme| theme.next());
+    let next_scale = bind::update(&scale, |scale| (scale + 1) % SCALE_FACTORS.len());
+
+    let content = provide(*theme, || component());
+
+    mox! {
+        <app>
+            <window title={title} scale_factor={SCALE_FACTORS[*scale]}>
+                <view style={ROOT_STYLE}>
+                    <view style={TOOLBAR_STYLE}>
+                        <knob _=(format!("Size: {}", size.label()), next_size) />
+                        <knob _=(format!("Theme: {}", theme.label()), next_theme) />
+                        <knob _=(format!("Scale: {}", scale_label(SCALE_FACTORS[*scale])), next_scale) />
+                    </view>
+                    <view style={theme.style()}>
+                        <view style={size.style()}>
+                            {vec![content]}
+                        </view>
+                    </view>
+                </view>
+            </window>
+        </app>
+    }
+}
+
+/// Opens a window which shows a single component, with knobs for the
+/// size, theme and scale factor. Usually created with `preview!`.
+pub struct Preview {
+    title: String,
+    size: Size,
+    theme: Theme,
+    component: Rc<dyn Fn() -> ViewChild>,
+}
+
+impl Preview {
+    pub fn new<C>(title: impl Into<String>, component: impl Fn() -> C + 'static) -> Preview
+    where
+        ViewChild: From<C>,
+    {
+        Preview {
+            title: title.into(),
+            size: Size::Fill,
+            theme: Theme::Light,
+            component: Rc::new(move || component().into()),
+        }
+    }
+
+    /// Sets the size the preview starts with.
+    pub fn size(mut self, size: Size) -> Preview {
+        self.size = size;
+        self
+    }
+
+    /// Sets the theme the preview starts with.
+    pub fn theme(mut self, theme: Theme) -> Preview {
+        self.theme = theme;
+        self
+    }
+
+    pub fn start(self) {
+        let Preview {
+            title,
+            size,
+            theme,
+            component,
+        } = self;
+        let runtime =
+            moxie_native::Runtime::new(move || preview_app!(&title, size, theme, &component));
+        runtime.start();
+    }
+}
+
+/// Previews a component, given an expression which creates it.
+///
+/// ```rs
+/// moxie_native_preview::preview!(counter!());
+/// ```
+#[macro_export]
+macro_rules! preview {
+    ($component:expr) => {
+        $crate::Preview::new(stringify!($component), || $component).start()
+    };
+}
@@ -0,0 +1,303 @@
+//! Animation of values, either over a fixed duration with easing
+//! (`Tween`) or physically simulated (`Spring`). The renderer uses this
+//! for layout transitions, and it can also be used directly from
+//! application code, such as in event handlers.
+
+use crate::Color;
+use euclid::{Point2D, Rect, Size2D};
+use std::time::{Duration, Instant};
+
+mod spring;
+
+pub use spring::{Spring, SpringConfig, SpringValue};
+
+/// Values which can be linearly interpolated between.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl<U> Lerp for Point2D<f32, U> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Point2D::new(self.x.lerp(&other.x, t), self.y.lerp(&other.y, t))
+    }
+}
+
+impl<U> Lerp for Size2D<f32, U> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Size2D::new(
+            self.width.lerp(&other.width, t),
+            self.height.lerp(&other.height, t),
+        )
+    }
+}
+
+impl<U> Lerp for Rect<f32, U> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Rect::new(
+            self.origin.lerp(&other.origin, t),
+            self.size.lerp(&other.size, t),
+        )
+    }
+}
+
+/// Blends in linear light, see `Color::mix`.
+impl Lerp for Color {
+    fn lerp(&self, other: &Color, t: f32) -> Color {
+        self.mix(other, t)
+    }
+}
+
+impl<U> SpringValue for Point2D<f32, U> {
+    fn zero() -> Self {
+        Point2D::new(0.0, 0.0)
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        Point2D::new(
+            self.x.add_scaled(&other.x, scale),
+            self.y.add_scaled(&other.y, scale),
+        )
+    }
+
+    fn magnitude(&self) -> f32 {
+        self.x.magnitude().max(self.y.magnitude())
+    }
+}
+
+impl<U> SpringValue for Size2D<f32, U> {
+    fn zero() -> Self {
+        Size2D::new(0.0, 0.0)
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        Size2D::new(
+            self.width.add_scaled(&other.width, scale),
+            self.height.add_scaled(&other.height, scale),
+        )
+    }
+
+    fn magnitude(&self) -> f32 {
+        self.width.magnitude().max(self.height.magnitude())
+    }
+}
+
+impl<U> SpringValue for Rect<f32, U> {
+    fn zero() -> Self {
+        Rect::new(Point2D::zero(), Size2D::zero())
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        Rect::new(
+            self.origin.add_scaled(&other.origin, scale),
+            self.size.add_scaled(&other.size, scale),
+        )
+    }
+
+    fn magnitude(&self) -> f32 {
+        self.origin.magnitude().max(self.size.magnitude())
+    }
+}
+
+/// Maps linear progress through an animation onto eased progress.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Takes a value from 0 to 1 and returns the eased value.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Easing {
+    fn default() -> Easing {
+        Easing::EaseInOut
+    }
+}
+
+/// Animates from one value to another over a fixed duration.
+#[derive(Clone, Debug)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl<T> Tween<T>
+where
+    T: Lerp + Clone,
+{
+    /// Create a tween which starts now.
+    pub fn new(from: T, to: T, duration: Duration, easing: Easing) -> Tween<T> {
+        Tween {
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+            easing,
+        }
+    }
+
+    /// The value this tween ends at.
+    pub fn target(&self) -> &T {
+        &self.to
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    /// Sample the tween at the given point in time.
+    pub fn value_at(&self, now: Instant) -> T {
+        let progress = self.progress(now);
+        if progress >= 1.0 {
+            self.to.clone()
+        } else {
+            self.from.lerp(&self.to, self.easing.apply(progress))
+        }
+    }
+
+    pub fn finished_at(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+}
+
+/// Describes how a change in value should be animated. This is what
+/// styles specify, for example `layout_transition: spring(170, 26)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transition {
+    Tween { duration: Duration, easing: Easing },
+    Spring(SpringConfig),
+}
+
+/// A running animation started from a `Transition`.
+#[derive(Clone, Debug)]
+pub enum Animation<T> {
+    Tween(Tween<T>),
+    Spring(Spring<T>),
+}
+
+impl<T> Animation<T>
+where
+    T: Lerp + SpringValue,
+{
+    pub fn new(transition: Transition, from: T, to: T) -> Animation<T> {
+        Animation::new_at(transition, from, to, Instant::now())
+    }
+
+    /// Like `new`, but tweens start at `start` rather than now, so that
+    /// they line up with the frame time they're sampled at.
+    pub(crate) fn new_at(transition: Transition, from: T, to: T, start: Instant) -> Animation<T> {
+        match transition {
+            Transition::Tween { duration, easing } => {
+                let mut tween = Tween::new(from, to, duration, easing);
+                tween.start = start;
+                Animation::Tween(tween)
+            }
+            Transition::Spring(config) => {
+                let mut spring = Spring::new(from, config);
+                spring.set_target(to);
+                Animation::Spring(spring)
+            }
+        }
+    }
+
+    pub fn target(&self) -> &T {
+        match self {
+            Animation::Tween(tween) => tween.target(),
+            Animation::Spring(spring) => spring.target(),
+        }
+    }
+
+    /// Sample the animation at the given point in time.
+    pub fn value_at(&mut self, now: Instant) -> T {
+        match self {
+            Animation::Tween(tween) => tween.value_at(now),
+            Animation::Spring(spring) => spring.value_at(now),
+        }
+    }
+
+    /// Change the value being animated towards. Tweens restart from the
+    /// current value, while springs keep their velocity.
+    pub fn retarget(&mut self, transition: Transition, to: T, now: Instant) {
+        match self {
+            Animation::Spring(spring) => spring.set_target(to),
+            Animation::Tween(tween) => {
+                let from = tween.value_at(now);
+                *self = Animation::new_at(transition, from, to, now);
+            }
+        }
+    }
+
+    pub fn finished_at(&self, now: Instant) -> bool {
+        match self {
+            Animation::Tween(tween) => tween.finished_at(now),
+            Animation::Spring(spring) => spring.is_resting(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Easing, Tween};
+    use std::time::Duration;
+
+    #[test]
+    fn interpolates_tweens() {
+        let tween = Tween::new(0.0f32, 100.0, Duration::from_millis(100), Easing::Linear);
+        let at = |ms| tween.start + Duration::from_millis(ms);
+        assert_eq!(tween.value_at(at(0)), 0.0);
+        assert_eq!(tween.value_at(at(50)), 50.0);
+        assert_eq!(tween.value_at(at(100)), 100.0);
+        assert_eq!(tween.value_at(at(200)), 100.0);
+        assert!(!tween.finished_at(at(50)));
+        assert!(tween.finished_at(at(100)));
+    }
+
+    #[test]
+    fn eases_tweens() {
+        let tween = Tween::new(0.0f32, 100.0, Duration::from_millis(100), Easing::EaseInOut);
+        let at = |ms| tween.start + Duration::from_millis(ms);
+        assert_eq!(tween.value_at(at(0)), 0.0);
+        assert!(tween.value_at(at(25)) < 25.0);
+        assert_eq!(tween.value_at(at(50)), 50.0);
+        assert!(tween.value_at(at(75)) > 75.0);
+        assert_eq!(tween.value_at(at(100)), 100.0);
+    }
+
+    #[test]
+    fn finishes_empty_tweens() {
+        let tween = Tween::new(0.0f32, 100.0, Duration::from_secs(0), Easing::Linear);
+        assert_eq!(tween.value_at(tween.start), 100.0);
+        assert!(tween.finished_at(tween.start));
+    }
+}
@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+/// Values which a spring can act on. These are treated as vectors of
+/// `f32` components.
+pub trait SpringValue: Clone {
+    fn zero() -> Self;
+    /// Returns `self + other * scale`.
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self;
+    /// The size of the largest component, used to decide when the
+    /// spring has come to rest.
+    fn magnitude(&self) -> f32;
+}
+
+impl SpringValue for f32 {
+    fn zero() -> f32 {
+        0.0
+    }
+
+    fn add_scaled(&self, other: &f32, scale: f32) -> f32 {
+        self + other * scale
+    }
+
+    fn magnitude(&self) -> f32 {
+        self.abs()
+    }
+}
+
+/// The physical parameters of a spring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpringConfig {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+}
+
+impl SpringConfig {
+    pub const fn new(stiffness: f32, damping: f32) -> SpringConfig {
+        SpringConfig {
+            stiffness,
+            damping,
+            mass: 1.0,
+        }
+    }
+
+    /// Slow, overshooting only slightly before it settles.
+    pub const fn gentle() -> SpringConfig {
+        SpringConfig::new(120.0, 14.0)
+    }
+
+    /// Bouncy, with noticeable overshoot.
+    pub const fn wobbly() -> SpringConfig {
+        SpringConfig::new(180.0, 12.0)
+    }
+
+    /// Fast and snappy.
+    pub const fn stiff() -> SpringConfig {
+        SpringConfig::new(210.0, 20.0)
+    }
+}
+
+impl Default for SpringConfig {
+    fn default() -> SpringConfig {
+        SpringConfig::new(170.0, 26.0)
+    }
+}
+
+/// The size of each simulation step, in seconds.
+const TIME_STEP: f32 = 1.0 / 240.0;
+/// Steps longer than this are clamped, so that a stalled frame doesn't
+/// cause the simulation to run for a long time.
+const MAX_STEP: f32 = 0.25;
+/// The spring is considered at rest when both the distance to the
+/// target and the velocity are below this.
+const REST_THRESHOLD: f32 = 0.001;
+
+/// A physically simulated animation which pulls a value towards a
+/// target. Unlike a `Tween`, changing the target keeps the current
+/// velocity, which makes interrupted animations look natural.
+#[derive(Clone, Debug)]
+pub struct Spring<T> {
+    config: SpringConfig,
+    value: T,
+    velocity: T,
+    target: T,
+    last_update: Option<Instant>,
+}
+
+impl<T> Spring<T>
+where
+    T: SpringValue,
+{
+    /// Create a spring which is at rest at the given value.
+    pub fn new(value: T, config: SpringConfig) -> Spring<T> {
+        Spring {
+            config,
+            target: value.clone(),
+            value,
+            velocity: T::zero(),
+            last_update: None,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+
+    pub fn velocity(&self) -> &T {
+        &self.velocity
+    }
+
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    /// Sets the current velocity, in units per second. This is useful
+    /// for continuing the motion of a drag gesture once it's released.
+    pub fn set_velocity(&mut self, velocity: T) {
+        self.velocity = velocity;
+    }
+
+    /// Moves the value immediately, without animating.
+    pub fn snap_to(&mut self, value: T) {
+        self.target = value.clone();
+        self.value = value;
+        self.velocity = T::zero();
+    }
+
+    pub fn is_resting(&self) -> bool {
+        self.velocity.magnitude() < REST_THRESHOLD
+            && self.value.add_scaled(&self.target, -1.0).magnitude() < REST_THRESHOLD
+    }
+
+    /// Advances the simulation by the given amount of time.
+    pub fn step(&mut self, elapsed: Duration) -> &T {
+        let SpringConfig {
+            stiffness,
+            damping,
+            mass,
+        } = self.config;
+        let mut remaining = elapsed.as_secs_f32().min(MAX_STEP);
+        while remaining > 0.0 && !self.is_resting() {
+            let step = remaining.min(TIME_STEP);
+            let displacement = self.value.add_scaled(&self.target, -1.0);
+            let acceleration = T::zero()
+                .add_scaled(&displacement, -stiffness / mass)
+                .add_scaled(&self.velocity, -damping / mass);
+            self.velocity = self.velocity.add_scaled(&acceleration, step);
+            self.value = self.value.add_scaled(&self.velocity, step);
+            remaining -= step;
+        }
+        if self.is_resting() {
+            self.snap_to(self.target.clone());
+        }
+        &self.value
+    }
+
+    /// Advances the simulation up to the given point in time, and
+    /// returns the new value. The first call only records the time.
+    pub fn value_at(&mut self, now: Instant) -> T {
+        if let Some(last_update) = self.last_update {
+            self.step(now.saturating_duration_since(last_update));
+        }
+        self.last_update = Some(now);
+        self.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Spring, SpringConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn settles_on_target() {
+        let mut spring = Spring::new(0.0f32, SpringConfig::default());
+        spring.set_target(100.0);
+        for _ in 0..120 {
+            spring.step(Duration::from_millis(16));
+        }
+        assert!(spring.is_resting());
+        assert_eq!(*spring.value(), 100.0);
+    }
+
+    #[test]
+    fn wobbly_overshoots() {
+        let mut spring = Spring::new(0.0f32, SpringConfig::wobbly());
+        spring.set_target(1.0);
+        let mut max = 0.0f32;
+        for _ in 0..120 {
+            max = max.max(*spring.step(Duration::from_millis(16)));
+        }
+        assert!(max > 1.0);
+    }
+
+    #[test]
+    fn keeps_velocity() {
+        let mut spring = Spring::new(0.0f32, SpringConfig::default());
+        spring.set_velocity(500.0);
+        spring.step(Duration::from_millis(16));
+        assert!(*spring.value() > 0.0);
+        assert!(!spring.is_resting());
+    }
+}
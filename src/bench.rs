@@ -0,0 +1,125 @@
+//! Entry points into the style and layout engines, for benchmarking
+//! them without a window or renderer.
+//!
+//! ```rs
+//! fn layout(c: &mut Criterion) {
+//!     let mut bench = Bench::new();
+//!     let window = bench.build(|| my_window!());
+//!     bench.resolve_styles(&window, 800.0, 600.0);
+//!     c.bench_function("layout", |b| {
+//!         b.iter(|| bench.layout_from_scratch(&window, 800.0, 600.0, 1.0))
+//!     });
+//! }
+//! ```
+
+use crate::dom::{Node, Window};
+use crate::layout::{self, DeviceScale, FontLoader, FontRegistry, Fonts, LayoutEngine};
+use crate::style::{Interactions, ScrollPositions, StyleEngine};
+use euclid::{size2, Scale};
+use moxie::embed::Runtime as MoxieRuntime;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Benchmarks measuring fallback metrics would be meaningless, so this
+/// is much longer than when opening a window.
+const FONT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Holds the engines being benchmarked, along with the fonts they use.
+pub struct Bench {
+    fonts: Rc<RefCell<FontLoader>>,
+    style_engine: StyleEngine,
+    layout_engine: LayoutEngine,
+}
+
+impl Default for Bench {
+    fn default() -> Bench {
+        Bench::new()
+    }
+}
+
+impl Bench {
+    /// Finds the system fonts, blocking until they're loaded.
+    pub fn new() -> Bench {
+        let mut fonts = FontLoader::spawn(FontRegistry::default(), || ());
+        fonts.wait(FONT_TIMEOUT);
+        let fonts = Rc::new(RefCell::new(fonts));
+        Bench {
+            layout_engine: LayoutEngine::new(fonts.clone()),
+            style_engine: StyleEngine::new(),
+            fonts,
+        }
+    }
+
+    /// Runs a component once to build a DOM tree.
+    pub fn build(&self, root: impl FnMut() -> Node<Window>) -> Node<Window> {
+        MoxieRuntime::new(root).run_once()
+    }
+
+    /// Computes the styles of every node in the tree, for a window of
+    /// the given size in logical pixels.
+    pub fn resolve_styles(&mut self, window: &Node<Window>, width: f32, height: f32) {
+        self.style_engine.update(
+            window.clone(),
+            size2(width, height),
+            ScrollPositions::default(),
+            Interactions::default(),
+        );
+    }
+
+    /// Lays out the tree, reusing whatever was cached by earlier calls,
+    /// as happens when a window is rendered again. Styles must have
+    /// been resolved first.
+    pub fn layout(&mut self, window: &Node<Window>, width: f32, height: f32, dpi_scale: f32) {
+        self.layout_engine
+            .layout(window.clone(), size2(width, height), Scale::new(dpi_scale));
+    }
+
+    /// Marks the last element in the tree as changed, so that the next
+    /// `layout` has to lay out it and its ancestors again, as happens
+    /// when one element's style changes.
+    pub fn change_last(&self, window: &Node<Window>) {
+        layout::mark_last_changed(window.into());
+    }
+
+    /// Lays out the tree with nothing cached, as happens the first
+    /// time a window is rendered. Styles must have been resolved first.
+    pub fn layout_from_scratch(
+        &self,
+        window: &Node<Window>,
+        width: f32,
+        height: f32,
+        dpi_scale: f32,
+    ) {
+        self.fonts.borrow().fonts().shaping.clear();
+        self.layout_shaped(window, width, height, dpi_scale);
+    }
+
+    /// Lays out the tree with nothing cached but the shaping of its
+    /// text, as happens when another window shows the same text, or
+    /// when a window's minimum size is measured.
+    pub fn layout_shaped(&self, window: &Node<Window>, width: f32, height: f32, dpi_scale: f32) {
+        LayoutEngine::new(self.fonts.clone()).layout(
+            window.clone(),
+            size2(width, height),
+            Scale::new(dpi_scale),
+        );
+    }
+
+    /// Shapes `text` at the given size and breaks it into lines no
+    /// wider than `width`. Returns the number of lines.
+    pub fn shape_text(&self, text: &str, size: f32, width: f32, dpi_scale: f32) -> usize {
+        self.fonts.borrow().fonts().shaping.clear();
+        self.break_lines(text, size, width, dpi_scale)
+    }
+
+    /// Breaks `text` into lines like `shape_text`, reusing its shaping
+    /// from earlier calls, as happens when it's laid out again.
+    pub fn break_lines(&self, text: &str, size: f32, width: f32, dpi_scale: f32) -> usize {
+        illicit::child_env!(
+            Fonts => self.fonts.borrow().fonts(),
+            DeviceScale => Scale::new(dpi_scale)
+        )
+        .enter(|| layout::shape_lines(text, size, width))
+    }
+}
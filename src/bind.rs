@@ -0,0 +1,69 @@
+//! Click handlers which update a state key, so that a control and the
+//! state it changes can be connected in one line instead of cloning the
+//! key into a closure by hand.
+//!
+//! Buttons are the only controls so far, so these cover the ways a
+//! button changes state: toggling a flag, choosing one of several
+//! values, or stepping a value along.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn settings() -> Node<View> {
+//!     let dark_mode: Key<bool> = state!(|| false);
+//!     let page: Key<Page> = state!(|| Page::General);
+//!     let volume: Key<u32> = state!(|| 5);
+//!     mox! {
+//!         <view>
+//!             <button on={bind::toggle(&dark_mode)}>
+//!                 <span>{% "Dark mode: {}", dark_mode}</span>
+//!             </button>
+//!             <button on={bind::set(&page, Page::Advanced)}>
+//!                 <span>"Advanced"</span>
+//!             </button>
+//!             <button on={bind::update(&volume, |volume| volume + 1)}>
+//!                 <span>{% "Volume: {}", volume}</span>
+//!             </button>
+//!         </view>
+//!     }
+//! }
+//! ```
+
+use crate::dom::events::ClickEvent;
+use moxie::Key;
+
+/// Flips a flag on every click.
+pub fn toggle(key: &Key<bool>) -> impl FnMut(&ClickEvent) + 'static {
+    let key = key.clone();
+    move |_: &ClickEvent| {
+        key.update(|value| Some(!value));
+    }
+}
+
+/// Sets the state to `value` when clicked. Clicking again while it
+/// already holds `value` doesn't cause a new revision.
+pub fn set<T>(key: &Key<T>, value: T) -> impl FnMut(&ClickEvent) + 'static
+where
+    T: Clone + PartialEq + 'static,
+{
+    let key = key.clone();
+    move |_: &ClickEvent| {
+        key.update(|current| {
+            if *current == value {
+                None
+            } else {
+                Some(value.clone())
+            }
+        });
+    }
+}
+
+/// Replaces the state with the result of `f` on every click.
+pub fn update<T>(key: &Key<T>, f: impl Fn(&T) -> T + 'static) -> impl FnMut(&ClickEvent) + 'static
+where
+    T: 'static,
+{
+    let key = key.clone();
+    move |_: &ClickEvent| {
+        key.update(|current| Some(f(current)));
+    }
+}
@@ -0,0 +1,100 @@
+//! Reads and writes text on the system clipboard.
+//!
+//! ```rs
+//! mox! {
+//!     <button on={move |_: &ClickEvent| clipboard::set_text(&link)}>
+//!         <span>"Copy link"</span>
+//!     </button>
+//! }
+//! ```
+//!
+//! Windows also copy the selected text with Ctrl+C (Cmd+C on macOS),
+//! and paste with Ctrl+V, which sends a `PasteEvent`. There's no
+//! keyboard focus yet, so the event goes to the span with the selection
+//! in it, or else to the innermost element under the pointer which
+//! handles it.
+//!
+//! When the system clipboard can't be reached, such as in a headless
+//! session, text is kept in the application instead, so copying and
+//! pasting within it still works.
+//!
+//! Only plain text is supported. The `clipboard` crate reads and writes
+//! text alone, so offering HTML or images alongside it, for pasting
+//! styled content into other applications, needs each platform's
+//! clipboard formats handled here first.
+
+use ::clipboard::{ClipboardContext, ClipboardProvider};
+use std::cell::RefCell;
+
+thread_local! {
+    // Kept open, since on X11 the copied text is only available while
+    // the context that copied it is alive.
+    static CLIPBOARD: RefCell<Clipboard> = RefCell::new(Clipboard::open());
+}
+
+struct Clipboard {
+    system: Option<ClipboardContext>,
+    /// The last text copied from the application.
+    local: Option<String>,
+}
+
+impl Clipboard {
+    fn open() -> Clipboard {
+        let system = match ClipboardContext::new() {
+            Ok(context) => Some(context),
+            Err(err) => {
+                log::warn!("Couldn't open the system clipboard: {}", err);
+                None
+            }
+        };
+        Clipboard {
+            system,
+            local: None,
+        }
+    }
+
+    fn get_text(&mut self) -> Option<String> {
+        if let Some(ref mut system) = self.system {
+            match system.get_contents() {
+                Ok(text) => return Some(text),
+                Err(err) => log::warn!("Couldn't read the clipboard: {}", err),
+            }
+        }
+        self.local.clone()
+    }
+
+    fn set_text(&mut self, text: &str) {
+        self.local = Some(text.to_owned());
+        if let Some(ref mut system) = self.system {
+            if let Err(err) = system.set_contents(text.to_owned()) {
+                log::warn!("Couldn't write to the clipboard: {}", err);
+            }
+        }
+    }
+}
+
+/// The text on the clipboard, if there is any.
+pub fn get_text() -> Option<String> {
+    CLIPBOARD.with(|clipboard| clipboard.borrow_mut().get_text())
+}
+
+/// Replaces what's on the clipboard with `text`.
+pub fn set_text(text: &str) {
+    CLIPBOARD.with(|clipboard| clipboard.borrow_mut().set_text(text))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Clipboard;
+
+    #[test]
+    fn keeps_text_without_system_clipboard() {
+        let mut clipboard = Clipboard {
+            system: None,
+            local: None,
+        };
+        assert_eq!(clipboard.get_text(), None);
+        clipboard.set_text("copied");
+        assert_eq!(clipboard.get_text(), Some("copied".to_owned()));
+    }
+}
@@ -0,0 +1,140 @@
+//! Warnings about mistakes which don't stop an application from
+//! running, but probably don't do what was meant, like two pieces of
+//! persistent state saved under the same name, or text too long for
+//! the box it's in.
+//!
+//! They are logged through the `log` crate at the warn level, with the
+//! `moxie_native::diagnostics` target, so they show up with any logger:
+//!
+//! ```rs
+//! env_logger::init();
+//! let runtime = moxie_native::Runtime::new(|| app!());
+//! runtime.start();
+//! ```
+//!
+//! Each distinct warning is only logged once, since most of them would
+//! otherwise be repeated on every frame. Unknown style attributes and
+//! values aren't reported here, since `define_style!` already rejects
+//! them at compile time.
+
+use crate::dom::node::AnyNode;
+use crate::layout::{describe, LayoutTreeNode, RenderData};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+
+const TARGET: &str = "moxie_native::diagnostics";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    /// `persistent_state!` was used more than once with the same name
+    /// in one revision, so only one of the values will be saved.
+    DuplicateStateName(String),
+    /// A box with content in it was laid out with no width or height,
+    /// usually because its siblings took all of the room.
+    EmptyBox { path: String },
+    /// A box's children didn't fit in it.
+    Overflow { path: String },
+    /// Text had a word wider than the box it's in.
+    TextOverflow { path: String },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::DuplicateStateName(name) => write!(
+                fmt,
+                "persistent_state!(\"{}\") is declared more than once",
+                name
+            ),
+            Diagnostic::EmptyBox { path } => {
+                write!(fmt, "{} has content, but no room to show it", path)
+            }
+            Diagnostic::Overflow { path } => write!(fmt, "{} is too small for its content", path),
+            Diagnostic::TextOverflow { path } => {
+                write!(fmt, "{} has text that is too wide for it", path)
+            }
+        }
+    }
+}
+
+thread_local! {
+    static REPORTED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Returns true the first time a message is seen.
+fn first_report(message: &str) -> bool {
+    REPORTED.with(|reported| reported.borrow_mut().insert(message.to_owned()))
+}
+
+pub(crate) fn report(diagnostic: Diagnostic) {
+    let message = diagnostic.to_string();
+    if first_report(&message) {
+        log::warn!(target: TARGET, "{}", message);
+    }
+}
+
+/// Describes where a node is, from the window down.
+fn node_path(ancestors: &[&AnyNode], node: &AnyNode) -> String {
+    let mut path = String::new();
+    for ancestor in ancestors {
+        path.push_str(&format!("<{}> > ", ancestor.name()));
+    }
+    path.push_str(&describe(&**node));
+    path
+}
+
+/// Looks through a window's layout for boxes that ended up too small
+/// for their content. Does nothing unless warnings are being logged.
+pub(crate) fn check_layout(layout: &LayoutTreeNode) {
+    if log::log_enabled!(target: TARGET, log::Level::Warn) {
+        check_node(&mut vec![], layout);
+    }
+}
+
+fn check_node<'a>(ancestors: &mut Vec<&'a AnyNode>, layout: &'a LayoutTreeNode) {
+    let node = match layout.render {
+        RenderData::Node(ref node) => node,
+        RenderData::Text { .. } => return,
+    };
+    let has_content = layout
+        .children
+        .iter()
+        .any(|child| child.layout.size.width > 0.0 && child.layout.size.height > 0.0);
+    if has_content && (layout.size.width <= 0.0 || layout.size.height <= 0.0) {
+        let path = node_path(ancestors, node);
+        report(Diagnostic::EmptyBox { path });
+    }
+    let text_overflowed = layout
+        .children
+        .iter()
+        .any(|child| match child.layout.render {
+            RenderData::Text { .. } => child.layout.overflowed,
+            RenderData::Node(_) => false,
+        });
+    if text_overflowed {
+        let path = node_path(ancestors, node);
+        report(Diagnostic::TextOverflow { path });
+    } else if layout.overflowed {
+        let path = node_path(ancestors, node);
+        report(Diagnostic::Overflow { path });
+    }
+
+    ancestors.push(node);
+    for child in &layout.children {
+        check_node(ancestors, &child.layout);
+    }
+    ancestors.pop();
+}
+
+#[cfg(test)]
+mod test {
+    use super::first_report;
+
+    #[test]
+    fn reports_once() {
+        assert!(first_report("a"));
+        assert!(first_report("b"));
+        assert!(!first_report("a"));
+    }
+}
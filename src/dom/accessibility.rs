@@ -0,0 +1,109 @@
+//! What elements mean to assistive technology, like screen readers.
+//! Every element takes `label`, `role`, `description` and `live`
+//! attributes, which describe it semantically rather than by how it
+//! looks:
+//!
+//! ```rs
+//! mox! {
+//!     <button label="Delete draft" description="Can't be undone" on={delete}>
+//!         <span>"🗑"</span>
+//!     </button>
+//! }
+//! ```
+//!
+//! Accessibility bridges can follow changes to them with a
+//! `MutationObserver`, and tests can find elements by them with the
+//! queries in `testing`.
+
+/// What kind of thing an element is, from the user's point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    Application,
+    Window,
+    Dialog,
+    /// A container for other elements, which isn't itself interesting.
+    Group,
+    Text,
+    Heading,
+    Button,
+    Link,
+    Checkbox,
+    Image,
+    List,
+    ListItem,
+    /// Important and usually time sensitive information.
+    Alert,
+    /// Advisory information, like the progress of a download.
+    Status,
+    /// Left out of the accessibility tree. The element's children are
+    /// reported as children of its parent instead.
+    Presentation,
+}
+
+/// Allows `role="button"`.
+impl From<&str> for Role {
+    fn from(value: &str) -> Role {
+        match value {
+            "application" => Role::Application,
+            "window" => Role::Window,
+            "dialog" => Role::Dialog,
+            "group" => Role::Group,
+            "text" => Role::Text,
+            "heading" => Role::Heading,
+            "button" => Role::Button,
+            "link" => Role::Link,
+            "checkbox" => Role::Checkbox,
+            "image" => Role::Image,
+            "list" => Role::List,
+            "list-item" => Role::ListItem,
+            "alert" => Role::Alert,
+            "status" => Role::Status,
+            "presentation" | "none" => Role::Presentation,
+            _ => panic!("unknown role {:?}", value),
+        }
+    }
+}
+
+/// How changes to an element's content are announced, for content
+/// which changes without the user moving to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Live {
+    /// Changes aren't announced.
+    Off,
+    /// Changes are announced once the user is idle.
+    Polite,
+    /// Changes are announced straight away, interrupting the user.
+    Assertive,
+}
+
+impl Default for Live {
+    fn default() -> Live {
+        Live::Off
+    }
+}
+
+/// Allows `live="polite"`.
+impl From<&str> for Live {
+    fn from(value: &str) -> Live {
+        match value {
+            "off" => Live::Off,
+            "polite" => Live::Polite,
+            "assertive" => Live::Assertive,
+            _ => panic!(
+                "unknown live setting {:?}, expected \"off\", \"polite\" or \"assertive\"",
+                value
+            ),
+        }
+    }
+}
+
+/// The accessibility attributes of an element.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Accessibility {
+    /// The element's accessible name.
+    pub label: Option<String>,
+    /// Overrides the role the element has by default.
+    pub role: Option<Role>,
+    pub description: Option<String>,
+    pub live: Live,
+}
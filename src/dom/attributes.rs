@@ -1,4 +1,6 @@
-use crate::style::Style;
+use crate::dom::{Live, Placement, Role, Titlebar, UserData, WindowSize};
+use crate::layout::Delegate;
+use crate::style::{ScrollLink, Style};
 
 macro_rules! attribute {
     ($name:ident, $value:ty) => {
@@ -13,3 +15,16 @@ macro_rules! attribute {
 
 attribute!(AttrStyle, Option<Style>);
 attribute!(AttrTitle, String);
+attribute!(AttrScaleFactor, Option<f32>);
+attribute!(AttrMinContentSize, bool);
+attribute!(AttrSize, WindowSize);
+attribute!(AttrPlacement, Placement);
+attribute!(AttrTitlebar, Titlebar);
+attribute!(AttrScrollName, String);
+attribute!(AttrScrollLink, Option<ScrollLink>);
+attribute!(AttrData, UserData);
+attribute!(AttrLabel, String);
+attribute!(AttrRole, Role);
+attribute!(AttrDescription, String);
+attribute!(AttrLive, Live);
+attribute!(AttrLayout, Delegate);
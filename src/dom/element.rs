@@ -1,7 +1,10 @@
 use crate::dom::input::InputEvent;
 use crate::dom::node::{Node, NodeRef};
-use crate::style::{ComputedValues, Style};
+use crate::dom::{Accessibility, Role, UserData};
+use crate::layout::Delegate;
+use crate::style::{ComputedValues, ScrollLink, Style};
 use crate::util::event_handler::EventHandler;
+use crate::IStr;
 use std::fmt::Debug;
 
 /// Represents the attributes and behavior of a single DOM element.
@@ -13,6 +16,10 @@ pub trait Element: Default + Clone + Debug + PartialEq + 'static {
 
     const ELEMENT_NAME: &'static str;
 
+    /// The role the element has when it doesn't have a `role`
+    /// attribute.
+    const ROLE: Role = Role::Group;
+
     /// Creates default style values
     fn create_computed_values(&self) -> ComputedValues {
         Default::default()
@@ -29,6 +36,32 @@ pub trait Element: Default + Clone + Debug + PartialEq + 'static {
 
     /// Returns the list of styles attached to this element.
     fn style(&self) -> Option<Style>;
+
+    /// The name other elements can use to refer to this element's
+    /// scroll position.
+    fn scroll_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the binding of this element's computed values to a
+    /// scroll container's position, if it has one.
+    fn scroll_link(&self) -> Option<ScrollLink> {
+        None
+    }
+
+    /// Returns the value attached with the `data` attribute.
+    fn data(&self) -> Option<&UserData> {
+        None
+    }
+
+    /// Returns the element's accessibility attributes.
+    fn accessibility(&self) -> &Accessibility;
+
+    /// Returns the delegate which lays out this element's children, in
+    /// place of the built-in layout.
+    fn layout_delegate(&self) -> Option<&Delegate> {
+        None
+    }
 }
 
 /// The trait representing all events that can be invoked on an element.
@@ -86,6 +119,12 @@ impl<'a> From<&'a String> for DynamicNode<'a> {
     }
 }
 
+impl<'a> From<&'a IStr> for DynamicNode<'a> {
+    fn from(value: &'a IStr) -> Self {
+        DynamicNode::Text(value.as_str())
+    }
+}
+
 /// Because some elements need to have multiple types of elements
 /// parented to them, their `Element::Child` type is actually an enum
 /// (defined using the `multiple_children!` macro).
@@ -113,6 +152,12 @@ impl NodeChild for String {
     }
 }
 
+impl NodeChild for IStr {
+    fn get_node(&self) -> DynamicNode {
+        DynamicNode::Text(self.as_str())
+    }
+}
+
 pub trait HandlerList: Default + 'static {}
 
 impl HandlerList for () {}
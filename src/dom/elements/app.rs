@@ -1,26 +1,37 @@
 use crate::dom::element::Element;
-use crate::dom::{Node, Window};
+use crate::dom::{Accessibility, Node, Role, Window};
 use crate::style::Style;
 
 /// Corresponds to <app>. This is the root of the DOM and contains
 /// windows.
 #[derive(Clone, Debug, PartialEq)]
-pub struct App {}
+pub struct App {
+    accessibility: Accessibility,
+}
 
 impl Default for App {
     fn default() -> Self {
-        App {}
+        App {
+            accessibility: Accessibility::default(),
+        }
     }
 }
 
+accessibility_attributes!(App);
+
 impl Element for App {
     type Child = Node<Window>;
     type Handlers = ();
     type States = ();
 
     const ELEMENT_NAME: &'static str = "app";
+    const ROLE: Role = Role::Application;
 
     fn style(&self) -> Option<Style> {
         None
     }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
 }
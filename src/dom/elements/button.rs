@@ -1,14 +1,25 @@
 use crate::dom::element::{Element, ElementStates, HasEvent};
-use crate::dom::input::{InputEvent, State};
-use crate::dom::{AttrStyle, ClickEvent, Node, Span, View};
+use crate::dom::input::{InputEvent, PressTimer, State};
+use crate::dom::user_data::HitTest;
+use crate::dom::{
+    Accessibility, ActivateEvent, AttrData, AttrStyle, Capture, ClickEvent, DoubleClickEvent,
+    HoverEndEvent, HoverIntentEvent, LongPressEvent, Node, PasteEvent, PointerDownEvent,
+    PointerEnterEvent, PointerLeaveEvent, PointerMoveEvent, PointerUpEvent, RepeatEvent, Role,
+    Span, UserData, View,
+};
 use crate::style::Style;
 use crate::util::event_handler::EventHandler;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
 
 /// Corresponds to <button>. This element can be hovered and pressed,
 /// resulting in corresponding events.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Button {
     style: Option<Style>,
+    data: Option<UserData>,
+    accessibility: Accessibility,
 }
 
 multiple_children! {
@@ -22,12 +33,30 @@ multiple_children! {
 element_attributes! {
     Button {
         style: AttrStyle,
+        data: AttrData,
     }
 }
 
+accessibility_attributes!(Button);
+
 element_handlers! {
     ButtonHandlers for Button {
         on_click: ClickEvent,
+        on_double_click: DoubleClickEvent,
+        on_activate: ActivateEvent,
+        on_long_press: LongPressEvent,
+        on_repeat: RepeatEvent,
+        on_hover_intent: HoverIntentEvent,
+        on_hover_end: HoverEndEvent,
+        on_paste: PasteEvent,
+        on_pointer_down: PointerDownEvent,
+        on_pointer_up: PointerUpEvent,
+        on_pointer_move: PointerMoveEvent,
+        on_pointer_enter: PointerEnterEvent,
+        on_pointer_leave: PointerLeaveEvent,
+        on_pointer_down_capture: Capture<PointerDownEvent>,
+        on_pointer_up_capture: Capture<PointerUpEvent>,
+        on_pointer_move_capture: Capture<PointerMoveEvent>,
     }
 }
 
@@ -35,6 +64,31 @@ element_handlers! {
 pub struct ButtonStates {
     hovered: bool,
     pressed: bool,
+    /// When the press being handled started, which is `None` when there
+    /// was no press timer to ask.
+    pressed_at: Option<Instant>,
+    /// When the long press is due, until it's been sent.
+    long_press_at: Option<Instant>,
+    long_pressed: bool,
+    next_repeat: Option<Instant>,
+    repeats: u32,
+    /// When and where the last click was, until it's too late for it to
+    /// be the first half of a double click.
+    last_click: Option<(Instant, f32, f32)>,
+}
+
+impl ButtonStates {
+    /// Whether `timer` is for the press this button is handling.
+    fn holding(&self, timer: &PressTimer) -> bool {
+        self.pressed && self.pressed_at.is_some() && self.pressed_at == timer.pressed_at
+    }
+
+    /// Asks for the next event while the button is held.
+    fn request_wake(&self, timer: &PressTimer) {
+        for deadline in self.long_press_at.iter().chain(&self.next_repeat) {
+            timer.wake_at(*deadline);
+        }
+    }
 }
 
 impl ElementStates for ButtonStates {
@@ -53,6 +107,7 @@ impl Element for Button {
     type States = ButtonStates;
 
     const ELEMENT_NAME: &'static str = "button";
+    const ROLE: Role = Role::Button;
 
     fn process(
         &self,
@@ -61,35 +116,98 @@ impl Element for Button {
         event: &InputEvent,
     ) -> (bool, Self::States) {
         match event {
-            InputEvent::MouseMove { .. } => (
-                true,
-                ButtonStates {
-                    hovered: true,
-                    ..states
-                },
-            ),
+            InputEvent::MouseMove { .. } => {
+                // Deadlines which passed while the pointer was elsewhere
+                // are handled once it's back.
+                if let Some(timer) = press_timer() {
+                    if states.holding(&timer) {
+                        states.request_wake(&timer);
+                    }
+                }
+                (
+                    true,
+                    ButtonStates {
+                        hovered: true,
+                        ..states
+                    },
+                )
+            }
             InputEvent::MouseLeft {
                 state: State::Begin,
                 ..
-            } => (
-                true,
-                ButtonStates {
-                    pressed: true,
-                    ..states
-                },
-            ),
+            } => (true, self.press(states, handlers)),
+            InputEvent::MouseHeld { .. } => match press_timer() {
+                Some(ref timer) if states.holding(timer) => self.hold(timer, states, handlers),
+                _ => (false, states),
+            },
+            // Buttons without a hover intent handler leave it to the
+            // button they're in, if any.
+            InputEvent::MouseRested { .. } if handlers.on_hover_intent.present() => {
+                handlers.on_hover_intent.invoke(&HoverIntentEvent {
+                    data: self.data.clone(),
+                });
+                (true, states)
+            }
+            InputEvent::MouseRestEnded => {
+                handlers.on_hover_end.invoke(&HoverEndEvent {
+                    data: self.data.clone(),
+                });
+                (handlers.on_hover_end.present(), states)
+            }
+            InputEvent::Pointer(pointer) => {
+                (process_pointer!(handlers, &self.data, pointer), states)
+            }
+            InputEvent::Paste { text, .. } => {
+                handlers.on_paste.invoke(&PasteEvent {
+                    data: self.data.clone(),
+                    text: text.clone(),
+                });
+                (handlers.on_paste.present(), states)
+            }
             InputEvent::MouseLeft {
-                state: State::End, ..
+                state: State::End,
+                x,
+                y,
             } if states.pressed => {
-                handlers.on_click.invoke(&ClickEvent);
+                let hits = illicit::Env::get::<HitTest>()
+                    .map(|hits| hits.0.clone())
+                    .unwrap_or_default();
+                let click = ClickEvent {
+                    data: self.data.clone(),
+                    hits,
+                    default_prevented: Cell::new(false),
+                };
+                // The long press took the place of the click.
+                let mut last_click = None;
+                if !states.long_pressed {
+                    handlers.on_click.invoke(&click);
+                    if !click.default_prevented() {
+                        handlers.on_activate.invoke(&ActivateEvent(click));
+                    }
+                    last_click = self.double_click(states.last_click, *x, *y, handlers);
+                }
                 (
                     true,
                     ButtonStates {
-                        pressed: false,
-                        ..states
+                        hovered: states.hovered,
+                        last_click,
+                        ..ButtonStates::default()
                     },
                 )
             }
+            // A second finger on a touch screen cancels the press
+            // without clicking, and stops any repeats.
+            InputEvent::MouseLeft {
+                state: State::Cancel,
+                ..
+            } if states.pressed => (
+                true,
+                ButtonStates {
+                    hovered: states.hovered,
+                    last_click: states.last_click,
+                    ..ButtonStates::default()
+                },
+            ),
             _ => (false, states),
         }
     }
@@ -97,4 +215,102 @@ impl Element for Button {
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn data(&self) -> Option<&UserData> {
+        self.data.as_ref()
+    }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
+}
+
+fn press_timer() -> Option<Rc<PressTimer>> {
+    illicit::Env::get::<Rc<PressTimer>>().map(|timer| (*timer).clone())
+}
+
+impl Button {
+    /// Starts a press, sending the first repeat and scheduling the rest.
+    fn press(&self, states: ButtonStates, handlers: &mut ButtonHandlers) -> ButtonStates {
+        let mut states = ButtonStates {
+            hovered: states.hovered,
+            pressed: true,
+            last_click: states.last_click,
+            ..ButtonStates::default()
+        };
+        if handlers.on_repeat.present() {
+            handlers.on_repeat.invoke(&RepeatEvent {
+                data: self.data.clone(),
+                count: 0,
+            });
+            states.repeats = 1;
+        }
+        if let Some(timer) = press_timer() {
+            states.pressed_at = timer.pressed_at;
+            if handlers.on_long_press.present() {
+                states.long_press_at = Some(timer.now + timer.timing.long_press);
+            }
+            if handlers.on_repeat.present() {
+                states.next_repeat = Some(timer.now + timer.timing.repeat_delay);
+            }
+            states.request_wake(&timer);
+        }
+        states
+    }
+
+    /// Sends a double click if the last click was close enough to this
+    /// one, at `x` and `y`. Returns the click the next one should be
+    /// compared with.
+    fn double_click(
+        &self,
+        last_click: Option<(Instant, f32, f32)>,
+        x: f32,
+        y: f32,
+        handlers: &mut ButtonHandlers,
+    ) -> Option<(Instant, f32, f32)> {
+        let timer = press_timer()?;
+        let double = last_click.map_or(false, |(at, last_x, last_y)| {
+            timer.now.duration_since(at) <= timer.timing.double_click
+                && (x - last_x).hypot(y - last_y) <= timer.timing.double_click_distance
+        });
+        if double {
+            handlers.on_double_click.invoke(&DoubleClickEvent {
+                data: self.data.clone(),
+            });
+            None
+        } else {
+            Some((timer.now, x, y))
+        }
+    }
+
+    /// Sends whichever of the long press and the next repeat are due.
+    /// A repeat is sent at most once per event, so that a busy
+    /// application doesn't get a burst of them at once.
+    fn hold(
+        &self,
+        timer: &PressTimer,
+        mut states: ButtonStates,
+        handlers: &mut ButtonHandlers,
+    ) -> (bool, ButtonStates) {
+        let mut ran = false;
+        if states.long_press_at.map_or(false, |at| at <= timer.now) {
+            states.long_press_at = None;
+            states.long_pressed = true;
+            handlers.on_long_press.invoke(&LongPressEvent {
+                data: self.data.clone(),
+            });
+            ran = true;
+        }
+        if states.next_repeat.map_or(false, |at| at <= timer.now) {
+            handlers.on_repeat.invoke(&RepeatEvent {
+                data: self.data.clone(),
+                count: states.repeats,
+            });
+            states.repeats += 1;
+            states.next_repeat = Some(timer.now + timer.timing.repeat_interval);
+            ran = true;
+        }
+        states.request_wake(timer);
+        (ran, states)
+    }
 }
@@ -0,0 +1,90 @@
+use crate::dom::element::{Element, HasEvent};
+use crate::dom::input::InputEvent;
+use crate::dom::{
+    Accessibility, AttrData, AttrLayout, AttrStyle, Button, Capture, Node, PointerDownEvent,
+    PointerEnterEvent, PointerLeaveEvent, PointerMoveEvent, PointerUpEvent, Span, UserData, View,
+};
+use crate::layout::Delegate;
+use crate::style::Style;
+use crate::util::event_handler::EventHandler;
+
+/// Corresponds to <customlayout>. A frame whose children are measured
+/// and placed by the `LayoutDelegate` given as its `layout` attribute.
+/// Without one, it lays out its children like a `<view>`.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct CustomLayout {
+    style: Option<Style>,
+    layout: Option<Delegate>,
+    data: Option<UserData>,
+    accessibility: Accessibility,
+}
+
+multiple_children! {
+    enum CustomLayoutChild {
+        Button(Node<Button>),
+        View(Node<View>),
+        Span(Node<Span>),
+        CustomLayout(Node<CustomLayout>),
+    }
+}
+
+element_attributes! {
+    CustomLayout {
+        style: AttrStyle,
+        layout: AttrLayout,
+        data: AttrData,
+    }
+}
+
+accessibility_attributes!(CustomLayout);
+
+element_handlers! {
+    CustomLayoutHandlers for CustomLayout {
+        on_pointer_down: PointerDownEvent,
+        on_pointer_up: PointerUpEvent,
+        on_pointer_move: PointerMoveEvent,
+        on_pointer_enter: PointerEnterEvent,
+        on_pointer_leave: PointerLeaveEvent,
+        on_pointer_down_capture: Capture<PointerDownEvent>,
+        on_pointer_up_capture: Capture<PointerUpEvent>,
+        on_pointer_move_capture: Capture<PointerMoveEvent>,
+    }
+}
+
+impl Element for CustomLayout {
+    type Child = CustomLayoutChild;
+    type Handlers = CustomLayoutHandlers;
+    type States = ();
+
+    const ELEMENT_NAME: &'static str = "customlayout";
+
+    fn process(
+        &self,
+        states: Self::States,
+        handlers: &mut Self::Handlers,
+        event: &InputEvent,
+    ) -> (bool, Self::States) {
+        match event {
+            InputEvent::Pointer(pointer) => {
+                (process_pointer!(handlers, &self.data, pointer), states)
+            }
+            _ => (false, states),
+        }
+    }
+
+    fn style(&self) -> Option<Style> {
+        self.style
+    }
+
+    fn data(&self) -> Option<&UserData> {
+        self.data.as_ref()
+    }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
+
+    fn layout_delegate(&self) -> Option<&Delegate> {
+        self.layout.as_ref()
+    }
+}
@@ -1,3 +1,8 @@
+use crate::dom::events::{Capture, Phase, PointerEvent};
+use crate::dom::input::PointerInput;
+use crate::dom::UserData;
+use crate::util::event_handler::EventHandler;
+
 /// Defines an enum over multiple types that implement NodeChild and
 /// then implements NodeChild for that enum.
 macro_rules! multiple_children {
@@ -41,6 +46,36 @@ macro_rules! element_attributes {
     };
 }
 
+/// Implements the accessibility attributes for an element, which keeps
+/// them in an `accessibility` field.
+macro_rules! accessibility_attributes {
+    ( $element:ty ) => {
+        impl $crate::dom::element::HasAttribute<$crate::dom::AttrLabel> for $element {
+            fn set_attribute(&mut self, value: String) {
+                self.accessibility.label = Some(value);
+            }
+        }
+
+        impl $crate::dom::element::HasAttribute<$crate::dom::AttrRole> for $element {
+            fn set_attribute(&mut self, value: $crate::dom::Role) {
+                self.accessibility.role = Some(value);
+            }
+        }
+
+        impl $crate::dom::element::HasAttribute<$crate::dom::AttrDescription> for $element {
+            fn set_attribute(&mut self, value: String) {
+                self.accessibility.description = Some(value);
+            }
+        }
+
+        impl $crate::dom::element::HasAttribute<$crate::dom::AttrLive> for $element {
+            fn set_attribute(&mut self, value: $crate::dom::Live) {
+                self.accessibility.live = value;
+            }
+        }
+    };
+}
+
 macro_rules! element_handlers {
     ( $handler_name:ident for $element:ty { $( $name:ident : $class:ty ),+ $(,)* } ) => {
         #[derive(Default)]
@@ -66,8 +101,79 @@ macro_rules! element_handlers {
     };
 }
 
+/// Gives a pointer event to an element's pointer handlers, which are
+/// named `on_pointer_down`, `on_pointer_down_capture` and so on.
+/// Evaluates to whether the element has a handler for it.
+macro_rules! process_pointer {
+    ($handlers:expr, $data:expr, $pointer:expr) => {{
+        use $crate::dom::elements::invoke_pointer;
+        use $crate::dom::events::*;
+        use $crate::dom::input::PointerKind;
+        let (handlers, data, pointer) = ($handlers, $data, $pointer);
+        match pointer.kind() {
+            PointerKind::Down => invoke_pointer(
+                pointer,
+                data,
+                PointerDownEvent,
+                Some(&handlers.on_pointer_down_capture),
+                &handlers.on_pointer_down,
+            ),
+            PointerKind::Up => invoke_pointer(
+                pointer,
+                data,
+                PointerUpEvent,
+                Some(&handlers.on_pointer_up_capture),
+                &handlers.on_pointer_up,
+            ),
+            PointerKind::Move => invoke_pointer(
+                pointer,
+                data,
+                PointerMoveEvent,
+                Some(&handlers.on_pointer_move_capture),
+                &handlers.on_pointer_move,
+            ),
+            PointerKind::Enter => invoke_pointer(
+                pointer,
+                data,
+                PointerEnterEvent,
+                None,
+                &handlers.on_pointer_enter,
+            ),
+            PointerKind::Leave => invoke_pointer(
+                pointer,
+                data,
+                PointerLeaveEvent,
+                None,
+                &handlers.on_pointer_leave,
+            ),
+        }
+    }};
+}
+
+/// Gives a pointer event to the handlers for the phase it's in. Returns
+/// whether there were any.
+pub(crate) fn invoke_pointer<E>(
+    pointer: &PointerInput,
+    data: &Option<UserData>,
+    kind: fn(PointerEvent) -> E,
+    capture: Option<&EventHandler<Capture<E>>>,
+    bubble: &EventHandler<E>,
+) -> bool {
+    let mut present = false;
+    if let Some(capture) = capture.filter(|_| pointer.phase() != Phase::Bubble) {
+        capture.invoke(&Capture(kind(pointer.event(data.clone()))));
+        present |= capture.present();
+    }
+    if pointer.phase() != Phase::Capture {
+        bubble.invoke(&kind(pointer.event(data.clone())));
+        present |= bubble.present();
+    }
+    present
+}
+
 pub mod app;
 pub mod button;
+pub mod custom_layout;
 pub mod span;
 pub mod view;
 pub mod window;
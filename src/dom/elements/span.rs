@@ -1,35 +1,102 @@
-use crate::dom::element::Element;
-use crate::dom::{AttrStyle, Button, Node, View};
-use crate::style::{ComputedValues, DisplayType, InlineValues, Style};
+use crate::dom::element::{Element, HasEvent};
+use crate::dom::input::InputEvent;
+use crate::dom::{
+    Accessibility, AttrData, AttrScrollLink, AttrStyle, Button, Capture, Node, PasteEvent,
+    PointerDownEvent, PointerEnterEvent, PointerLeaveEvent, PointerMoveEvent, PointerUpEvent, Role,
+    SelectionChangedEvent, UserData, View,
+};
+use crate::style::{ComputedValues, DisplayType, InlineValues, ScrollLink, Style};
+use crate::util::event_handler::EventHandler;
+use crate::IStr;
 
 /// Corresponds to <span>. This element is typically used for inline
 /// layout of text.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Span {
     style: Option<Style>,
+    scroll_link: Option<ScrollLink>,
+    data: Option<UserData>,
+    accessibility: Accessibility,
 }
 
 element_attributes! {
     Span {
         style: AttrStyle,
+        scroll_link: AttrScrollLink,
+        data: AttrData,
+    }
+}
+
+accessibility_attributes!(Span);
+
+element_handlers! {
+    SpanHandlers for Span {
+        on_selection_changed: SelectionChangedEvent,
+        on_paste: PasteEvent,
+        on_pointer_down: PointerDownEvent,
+        on_pointer_up: PointerUpEvent,
+        on_pointer_move: PointerMoveEvent,
+        on_pointer_enter: PointerEnterEvent,
+        on_pointer_leave: PointerLeaveEvent,
+        on_pointer_down_capture: Capture<PointerDownEvent>,
+        on_pointer_up_capture: Capture<PointerUpEvent>,
+        on_pointer_move_capture: Capture<PointerMoveEvent>,
     }
 }
 
 multiple_children! {
     enum SpanChild {
-        Text(String),
+        Text(IStr),
         Button(Node<Button>),
         View(Node<View>),
         Span(Node<Span>),
     }
 }
 
+impl From<String> for SpanChild {
+    fn from(text: String) -> SpanChild {
+        SpanChild::Text(text.into())
+    }
+}
+
 impl Element for Span {
     type Child = SpanChild;
-    type Handlers = ();
+    type Handlers = SpanHandlers;
     type States = ();
 
     const ELEMENT_NAME: &'static str = "span";
+    const ROLE: Role = Role::Text;
+
+    fn process(
+        &self,
+        states: Self::States,
+        handlers: &mut Self::Handlers,
+        event: &InputEvent,
+    ) -> (bool, Self::States) {
+        match event {
+            InputEvent::SelectionChanged { start, end, text } => {
+                handlers
+                    .on_selection_changed
+                    .invoke(&SelectionChangedEvent {
+                        data: self.data.clone(),
+                        range: *start..*end,
+                        text: text.clone(),
+                    });
+                (handlers.on_selection_changed.present(), states)
+            }
+            InputEvent::Paste { text, .. } => {
+                handlers.on_paste.invoke(&PasteEvent {
+                    data: self.data.clone(),
+                    text: text.clone(),
+                });
+                (handlers.on_paste.present(), states)
+            }
+            InputEvent::Pointer(pointer) => {
+                (process_pointer!(handlers, &self.data, pointer), states)
+            }
+            _ => (false, states),
+        }
+    }
 
     fn create_computed_values(&self) -> ComputedValues {
         ComputedValues {
@@ -41,4 +108,16 @@ impl Element for Span {
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn scroll_link(&self) -> Option<ScrollLink> {
+        self.scroll_link
+    }
+
+    fn data(&self) -> Option<&UserData> {
+        self.data.as_ref()
+    }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
 }
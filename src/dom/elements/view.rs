@@ -1,12 +1,22 @@
-use crate::dom::element::Element;
-use crate::dom::{AttrStyle, Button, Node, Span};
-use crate::style::{ComputedValues, Style};
+use crate::dom::element::{Element, HasEvent};
+use crate::dom::input::InputEvent;
+use crate::dom::{
+    Accessibility, AttrData, AttrScrollLink, AttrScrollName, AttrStyle, Button, Capture,
+    CustomLayout, Node, PointerDownEvent, PointerEnterEvent, PointerLeaveEvent, PointerMoveEvent,
+    PointerUpEvent, Span, UserData,
+};
+use crate::style::{ComputedValues, ScrollLink, Style};
+use crate::util::event_handler::EventHandler;
 use crate::Color;
 
 /// Corresponds to <view>. Generic frame for layout purposes.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct View {
     style: Option<Style>,
+    scroll_name: Option<String>,
+    scroll_link: Option<ScrollLink>,
+    data: Option<UserData>,
+    accessibility: Accessibility,
 }
 
 multiple_children! {
@@ -14,22 +24,55 @@ multiple_children! {
         Button(Node<Button>),
         View(Node<View>),
         Span(Node<Span>),
+        CustomLayout(Node<CustomLayout>),
     }
 }
 
 element_attributes! {
     View {
         style: AttrStyle,
+        scroll_name: AttrScrollName,
+        scroll_link: AttrScrollLink,
+        data: AttrData,
+    }
+}
+
+accessibility_attributes!(View);
+
+element_handlers! {
+    ViewHandlers for View {
+        on_pointer_down: PointerDownEvent,
+        on_pointer_up: PointerUpEvent,
+        on_pointer_move: PointerMoveEvent,
+        on_pointer_enter: PointerEnterEvent,
+        on_pointer_leave: PointerLeaveEvent,
+        on_pointer_down_capture: Capture<PointerDownEvent>,
+        on_pointer_up_capture: Capture<PointerUpEvent>,
+        on_pointer_move_capture: Capture<PointerMoveEvent>,
     }
 }
 
 impl Element for View {
     type Child = ViewChild;
-    type Handlers = ();
+    type Handlers = ViewHandlers;
     type States = ();
 
     const ELEMENT_NAME: &'static str = "view";
 
+    fn process(
+        &self,
+        states: Self::States,
+        handlers: &mut Self::Handlers,
+        event: &InputEvent,
+    ) -> (bool, Self::States) {
+        match event {
+            InputEvent::Pointer(pointer) => {
+                (process_pointer!(handlers, &self.data, pointer), states)
+            }
+            _ => (false, states),
+        }
+    }
+
     fn create_computed_values(&self) -> ComputedValues {
         ComputedValues {
             background_color: Color::new(50, 180, 200, 255),
@@ -40,4 +83,20 @@ impl Element for View {
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn scroll_name(&self) -> Option<&str> {
+        self.scroll_name.as_ref().map(|name| &name[..])
+    }
+
+    fn scroll_link(&self) -> Option<ScrollLink> {
+        self.scroll_link
+    }
+
+    fn data(&self) -> Option<&UserData> {
+        self.data.as_ref()
+    }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
 }
@@ -1,13 +1,118 @@
 use crate::dom::element::Element;
-use crate::dom::{AttrStyle, AttrTitle, Node, View};
+use crate::dom::{
+    Accessibility, AttrMinContentSize, AttrPlacement, AttrScaleFactor, AttrSize, AttrStyle,
+    AttrTitle, AttrTitlebar, Node, Role, View,
+};
 use crate::style::Style;
 
+/// How a window's size is decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowSize {
+    /// The OS picks the initial size, and the user resizes the window.
+    Manual,
+    /// The window resizes itself to fit its content whenever the
+    /// content changes, up to the size of the monitor it's on. This
+    /// suits dialogs and popups. The root of the content shouldn't be
+    /// sized relative to the window, or it will grow to fill the
+    /// monitor.
+    FitContent,
+}
+
+impl Default for WindowSize {
+    fn default() -> WindowSize {
+        WindowSize::Manual
+    }
+}
+
+/// Allows `size="fit-content"`.
+impl From<&str> for WindowSize {
+    fn from(value: &str) -> WindowSize {
+        match value {
+            "manual" => WindowSize::Manual,
+            "fit-content" => WindowSize::FitContent,
+            _ => panic!(
+                "unknown window size {:?}, expected \"manual\" or \"fit-content\"",
+                value
+            ),
+        }
+    }
+}
+
+/// Where a new window is placed on the desktop. The window a new one is
+/// placed relative to is the one before it in the app's list of
+/// windows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    /// Wherever the OS puts it, which varies between platforms.
+    System,
+    /// Centered on the monitor the OS chose.
+    Center,
+    /// Offset down and to the right of the previous window, or centered
+    /// if it's the first.
+    Cascade,
+    /// Centered over the previous window, as suits dialogs, or centered
+    /// on the monitor if it's the first.
+    CenterOnParent,
+}
+
+impl Default for Placement {
+    fn default() -> Placement {
+        Placement::System
+    }
+}
+
+/// How a window's titlebar is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Titlebar {
+    /// Drawn by the system above the content.
+    System,
+    /// Transparent, with the content extending under it, so that the
+    /// application can draw its own chrome there. The window's buttons
+    /// are still drawn by the system, and the titlebar's height is left
+    /// out of the safe area. Only macOS supports this, and elsewhere
+    /// it's the same as `System`.
+    Overlay,
+}
+
+impl Default for Titlebar {
+    fn default() -> Titlebar {
+        Titlebar::System
+    }
+}
+
+/// Allows `titlebar="overlay"`.
+impl From<&str> for Titlebar {
+    fn from(value: &str) -> Titlebar {
+        match value {
+            "system" => Titlebar::System,
+            "overlay" => Titlebar::Overlay,
+            _ => panic!(
+                "unknown titlebar {:?}, expected \"system\" or \"overlay\"",
+                value
+            ),
+        }
+    }
+}
+
 /// Corresponds to <window>. This is the top-level container for UI and
 /// corresponds to an OS window.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Window {
     style: Option<Style>,
     pub title: String,
+    /// Overrides the scale factor reported by the OS, to see how the UI
+    /// looks on other displays.
+    pub scale_factor: Option<f32>,
+    /// Keeps the window from being resized smaller than its content.
+    /// Content sized with `vw` and `vh` counts as fixed at the window's
+    /// current size, so the root of the content should use percentages.
+    pub min_content_size: bool,
+    pub size: WindowSize,
+    /// Only used when the window is opened.
+    pub placement: Placement,
+    /// Only used when the window is opened.
+    pub titlebar: Titlebar,
+    accessibility: Accessibility,
 }
 
 impl Default for Window {
@@ -15,6 +120,12 @@ impl Default for Window {
         Window {
             style: None,
             title: "Untitled Window".to_owned(),
+            scale_factor: None,
+            min_content_size: false,
+            size: WindowSize::Manual,
+            placement: Placement::System,
+            titlebar: Titlebar::System,
+            accessibility: Accessibility::default(),
         }
     }
 }
@@ -23,17 +134,29 @@ element_attributes! {
     Window {
         style: AttrStyle,
         title: AttrTitle,
+        scale_factor: AttrScaleFactor,
+        min_content_size: AttrMinContentSize,
+        size: AttrSize,
+        placement: AttrPlacement,
+        titlebar: AttrTitlebar,
     }
 }
 
+accessibility_attributes!(Window);
+
 impl Element for Window {
     type Child = Node<View>;
     type Handlers = ();
     type States = ();
 
     const ELEMENT_NAME: &'static str = "window";
+    const ROLE: Role = Role::Window;
 
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn accessibility(&self) -> &Accessibility {
+        &self.accessibility
+    }
 }
@@ -1,6 +1,390 @@
 use super::element::Event;
+use super::input::{PointerId, Propagation};
+use super::UserData;
+use std::any::Any;
+use std::cell::Cell;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
 
 /// The element associated with this event was activated by the user.
-pub struct ClickEvent;
+pub struct ClickEvent {
+    pub(crate) data: Option<UserData>,
+    /// The data of every element under the pointer, innermost first.
+    pub(crate) hits: Vec<UserData>,
+    pub(crate) default_prevented: Cell<bool>,
+}
+
+impl ClickEvent {
+    /// Returns the value attached to the clicked element with the
+    /// `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+
+    /// Returns the innermost value of type `T` attached to any element
+    /// under the pointer, which includes the clicked element and its
+    /// ancestors. This lets a button find the list item it's part of.
+    pub fn find<T: Any>(&self) -> Option<&T> {
+        self.hits.iter().filter_map(UserData::get).next()
+    }
+
+    /// The data of every element under the pointer, innermost first.
+    pub fn hits(&self) -> &[UserData] {
+        &self.hits
+    }
+
+    /// Stops the element's default action from running after this
+    /// handler.
+    pub fn prevent_default(&self) {
+        self.default_prevented.set(true);
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented.get()
+    }
+}
 
 impl Event for ClickEvent {}
+
+/// The default action of an element, which runs after its click
+/// handler unless the handler calls `ClickEvent::prevent_default`.
+///
+/// Widgets built from buttons use this for their built-in behavior, so
+/// that the application can still stop it from a click handler:
+///
+/// ```rs
+/// mox! {
+///     <button on={|_: &ActivateEvent| checked.update(|checked| Some(!checked))}
+///             on={move |click: &ClickEvent| if locked { click.prevent_default() }}>
+///         <span>{% "Checked: {}", checked}</span>
+///     </button>
+/// }
+/// ```
+///
+/// Clicks are only given to the innermost button under the pointer, so
+/// the default action runs once that button's click handler returns.
+pub struct ActivateEvent(pub(crate) ClickEvent);
+
+impl Deref for ActivateEvent {
+    type Target = ClickEvent;
+
+    fn deref(&self) -> &ClickEvent {
+        &self.0
+    }
+}
+
+impl Event for ActivateEvent {}
+
+/// The element has been held down for `PressTiming::long_press` without
+/// being released. A button which has a long press handler doesn't get
+/// a click when it's released after a long press.
+pub struct LongPressEvent {
+    pub(crate) data: Option<UserData>,
+}
+
+impl LongPressEvent {
+    /// Returns the value attached to the pressed element with the
+    /// `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+}
+
+impl Event for LongPressEvent {}
+
+/// The element was clicked twice in quick succession, within
+/// `PressTiming::double_click` and `PressTiming::double_click_distance`
+/// of each other. Both clicks are sent as `ClickEvent`s first, and a
+/// third click starts counting again.
+pub struct DoubleClickEvent {
+    pub(crate) data: Option<UserData>,
+}
+
+impl DoubleClickEvent {
+    /// Returns the value attached to the clicked element with the
+    /// `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+}
+
+impl Event for DoubleClickEvent {}
+
+/// Sent when the element is pressed, and then repeatedly while it's held
+/// down, for controls like stepper buttons and scroll arrows which keep
+/// going until they're let go of:
+///
+/// ```rs
+/// mox! {
+///     <button on={move |_: &RepeatEvent| count.update(|count| Some(count + 1))}>
+///         <span>"+"</span>
+///     </button>
+/// }
+/// ```
+///
+/// The first repeat follows the press after
+/// `PressTiming::repeat_delay`, and the rest come every
+/// `PressTiming::repeat_interval`. Repeats pause while the pointer is
+/// off the element. The button still gets a click when it's released,
+/// so a repeating button shouldn't handle clicks as well.
+pub struct RepeatEvent {
+    pub(crate) data: Option<UserData>,
+    pub(crate) count: u32,
+}
+
+impl RepeatEvent {
+    /// Returns the value attached to the pressed element with the
+    /// `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+
+    /// How many times the event has been sent before during this press,
+    /// which is zero for the press itself.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+impl Event for RepeatEvent {}
+
+/// The pointer has rested over the element for the window's hover
+/// intent delay, set with `Runtime::hover_intent_delay`. Unlike the
+/// `hover` state, this isn't set by a pointer passing over the element
+/// on its way somewhere else, so it suits tooltips and menus which
+/// would otherwise flicker open and closed.
+///
+/// ```rs
+/// mox! {
+///     <button on={move |_: &HoverIntentEvent| tooltip.set(true)}
+///             on={move |_: &HoverEndEvent| tooltip.set(false)}>
+///         <span>"?"</span>
+///     </button>
+/// }
+/// ```
+pub struct HoverIntentEvent {
+    pub(crate) data: Option<UserData>,
+}
+
+impl HoverIntentEvent {
+    /// Returns the value attached to the hovered element with the
+    /// `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+}
+
+impl Event for HoverIntentEvent {}
+
+/// The pointer has left an element which was sent a `HoverIntentEvent`.
+/// Leaving before the delay is up sends neither event.
+pub struct HoverEndEvent {
+    pub(crate) data: Option<UserData>,
+}
+
+impl HoverEndEvent {
+    /// Returns the value attached to the element with the `data`
+    /// attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+}
+
+impl Event for HoverEndEvent {}
+
+/// The text selected in the element changed, as the user dragged across
+/// it. Selections don't reach past the element they started in, so
+/// each span sees only its own part of the text:
+///
+/// ```rs
+/// mox! {
+///     <span on={move |selection: &SelectionChangedEvent| {
+///         selected.set(selection.text().to_owned())
+///     }}>
+///         {% "{}", article}
+///     </span>
+/// }
+/// ```
+///
+/// A span the selection left is sent an empty selection.
+pub struct SelectionChangedEvent {
+    pub(crate) data: Option<UserData>,
+    pub(crate) range: Range<usize>,
+    pub(crate) text: String,
+}
+
+impl SelectionChangedEvent {
+    /// Returns the value attached to the element with the `data`
+    /// attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+
+    /// Where the selection is in the element's text, which is all of
+    /// its text children joined together, as byte offsets.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether nothing is selected.
+    pub fn is_empty(&self) -> bool {
+        self.range.start == self.range.end
+    }
+}
+
+impl Event for SelectionChangedEvent {}
+
+/// Text was pasted from the clipboard with Ctrl+V (Cmd+V on macOS). See
+/// the `clipboard` module for which element gets it.
+pub struct PasteEvent {
+    pub(crate) data: Option<UserData>,
+    pub(crate) text: String,
+}
+
+impl PasteEvent {
+    /// Returns the value attached to the element with the `data`
+    /// attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Event for PasteEvent {}
+
+/// Which part of its trip through the DOM a pointer event is on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    /// On the way from the window down to the target, given to
+    /// `Capture` handlers.
+    Capture,
+    /// At the innermost element under the pointer, given to both kinds
+    /// of handler.
+    Target,
+    /// On the way from the target back up to the window.
+    Bubble,
+}
+
+/// A pointer event, which is sent to the innermost element under the
+/// pointer and each of its ancestors. It goes down from the outermost
+/// element to the target, to handlers for `Capture<E>`, and then back
+/// up, to handlers for `E`:
+///
+/// ```rs
+/// mox! {
+///     <view on={move |down: &Capture<PointerDownEvent>| if locked { down.stop_propagation() }}>
+///         <button on={move |_: &PointerDownEvent| pressed.set(true)}>
+///             <span>"Press me"</span>
+///         </button>
+///     </view>
+/// }
+/// ```
+///
+/// The pointer events are `PointerDownEvent`, `PointerUpEvent`,
+/// `PointerMoveEvent`, `PointerEnterEvent` and `PointerLeaveEvent`.
+/// Enter and leave events don't travel through the DOM. Each element
+/// the pointer moves onto or off of gets its own.
+pub struct PointerEvent {
+    pub(crate) data: Option<UserData>,
+    pub(crate) pointer: PointerId,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) phase: Phase,
+    pub(crate) propagation: Rc<Propagation>,
+}
+
+impl PointerEvent {
+    /// Returns the value attached to the element handling the event with
+    /// the `data` attribute, if it has the type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(UserData::get)
+    }
+
+    /// Where the pointer is, in logical pixels from the top left of the
+    /// window.
+    pub fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Which pointer the event came from, for telling touches apart
+    /// from the mouse.
+    pub fn pointer(&self) -> PointerId {
+        self.pointer
+    }
+
+    /// Keeps the event from going on to any more elements, which also
+    /// keeps them from acting on it themselves, like a button clicking.
+    /// The other handlers on this element still run.
+    pub fn stop_propagation(&self) {
+        self.propagation.stopped.set(true);
+    }
+
+    /// Sends every pointer event to the element handling this one and
+    /// its ancestors, wherever the pointer is, until the button is
+    /// released or `release_pointer_capture` is called. This keeps a
+    /// drag going when the pointer moves faster than the element.
+    pub fn set_pointer_capture(&self) {
+        self.propagation.capture.set(Some(true));
+    }
+
+    pub fn release_pointer_capture(&self) {
+        self.propagation.capture.set(Some(false));
+    }
+}
+
+macro_rules! pointer_events {
+    ($( $(#[$doc:meta])* $name:ident ),+ $(,)*) => {
+        $(
+            $(#[$doc])*
+            pub struct $name(pub(crate) PointerEvent);
+
+            impl Deref for $name {
+                type Target = PointerEvent;
+
+                fn deref(&self) -> &PointerEvent {
+                    &self.0
+                }
+            }
+
+            impl Event for $name {}
+        )+
+    };
+}
+
+pointer_events! {
+    /// The left button was pressed over the element.
+    PointerDownEvent,
+    /// The left button was released over the element.
+    PointerUpEvent,
+    /// The pointer moved over the element.
+    PointerMoveEvent,
+    /// The pointer moved onto the element.
+    PointerEnterEvent,
+    /// The pointer moved off of the element.
+    PointerLeaveEvent,
+}
+
+/// A pointer event on its way down to the element under the pointer,
+/// before the element's own handlers see it. See `PointerEvent`.
+pub struct Capture<E>(pub(crate) E);
+
+impl<E> Deref for Capture<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.0
+    }
+}
+
+impl<E: Event> Event for Capture<E> {}
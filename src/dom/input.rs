@@ -1,9 +1,130 @@
+use crate::dom::events::{Phase, PointerEvent};
+use crate::dom::UserData;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
 pub enum InputEvent {
-    MouseLeft { state: State, x: f32, y: f32 },
-    MouseMove { x: f32, y: f32 },
+    MouseLeft {
+        state: State,
+        x: f32,
+        y: f32,
+    },
+    /// Mouse movement is coalesced, so one event is dispatched per
+    /// frame. `x` and `y` are the latest position, and `trace` has every
+    /// position reported since the last event, oldest first.
+    MouseMove {
+        x: f32,
+        y: f32,
+        trace: Vec<(f32, f32)>,
+    },
+    /// The left button is still held down, and a deadline requested with
+    /// `PressTimer::wake_at` has passed.
+    MouseHeld {
+        x: f32,
+        y: f32,
+    },
+    /// The pointer has stopped moving for the window's hover intent
+    /// delay.
+    MouseRested {
+        x: f32,
+        y: f32,
+    },
+    /// The pointer has left the element which handled the last
+    /// `MouseRested` event. This is only given to that element.
+    MouseRestEnded,
+    /// The text selected in the element changed. This is only given to
+    /// the element the text is in, and to the element the selection
+    /// left, with an empty range.
+    SelectionChanged {
+        start: usize,
+        end: usize,
+        text: String,
+    },
+    /// Text was pasted from the clipboard while the pointer was at `x`
+    /// and `y`.
+    Paste {
+        x: f32,
+        y: f32,
+        text: String,
+    },
+    /// A pointer event on its way through the elements under the
+    /// pointer. See `PointerEvent`.
+    Pointer(PointerInput),
+}
+
+/// Which pointer event is being dispatched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerKind {
+    Down,
+    Up,
+    Move,
+    Enter,
+    Leave,
+}
+
+/// What a pointer handler asked of the dispatch it's part of.
+#[derive(Default)]
+pub(crate) struct Propagation {
+    pub stopped: Cell<bool>,
+    /// Set to capture the pointer for the element being dispatched to,
+    /// or to release it.
+    pub capture: Cell<Option<bool>>,
+}
+
+pub struct PointerInput {
+    pub(crate) kind: PointerKind,
+    pub(crate) pointer: PointerId,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) phase: Phase,
+    pub(crate) propagation: Rc<Propagation>,
+}
+
+impl PointerInput {
+    pub fn kind(&self) -> PointerKind {
+        self.kind
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn pointer(&self) -> PointerId {
+        self.pointer
+    }
+
+    /// The event given to the element's handlers, with the element's
+    /// `data` attribute.
+    pub(crate) fn event(&self, data: Option<UserData>) -> PointerEvent {
+        PointerEvent {
+            data,
+            pointer: self.pointer,
+            x: self.x,
+            y: self.y,
+            phase: self.phase,
+            propagation: self.propagation.clone(),
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
+/// Which pointer an event came from. Only one pointer is followed at a
+/// time: the mouse, or the first finger put down on a touch screen
+/// while nothing else was pressed. Other fingers only pinch zoom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerId {
+    Mouse,
+    /// A finger, with the id the OS gave it for as long as it's down.
+    Touch(u64),
+}
+
+impl Default for PointerId {
+    fn default() -> PointerId {
+        PointerId::Mouse
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
 pub enum State {
     Begin,
     End,
@@ -15,7 +136,101 @@ impl InputEvent {
     pub fn get_position(&self) -> Option<(f32, f32)> {
         match self {
             InputEvent::MouseLeft { x, y, .. } => Some((*x, *y)),
-            InputEvent::MouseMove { x, y } => Some((*x, *y)),
+            InputEvent::MouseMove { x, y, .. } => Some((*x, *y)),
+            InputEvent::MouseHeld { x, y } => Some((*x, *y)),
+            InputEvent::MouseRested { x, y } => Some((*x, *y)),
+            InputEvent::Paste { x, y, .. } => Some((*x, *y)),
+            InputEvent::Pointer(pointer) => Some((pointer.x, pointer.y)),
+            InputEvent::MouseRestEnded | InputEvent::SelectionChanged { .. } => None,
+        }
+    }
+}
+
+/// How long a button has to be held down before it's long pressed, how
+/// often it repeats while held, and how close together two clicks have
+/// to be to make a double click, set with `Runtime::press_timing`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PressTiming {
+    pub long_press: Duration,
+    /// The time from the press to the first repeat.
+    pub repeat_delay: Duration,
+    /// The time between later repeats.
+    pub repeat_interval: Duration,
+    /// The longest time from one click to the next for them to count
+    /// as a double click.
+    pub double_click: Duration,
+    /// How far apart two clicks can be, in logical pixels, to count as
+    /// a double click.
+    pub double_click_distance: f32,
+}
+
+/// The system default on each platform. GTK waits less than Windows
+/// and macOS.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+impl Default for PressTiming {
+    fn default() -> PressTiming {
+        PressTiming {
+            long_press: Duration::from_millis(500),
+            repeat_delay: Duration::from_millis(400),
+            repeat_interval: Duration::from_millis(80),
+            double_click: DOUBLE_CLICK,
+            double_click_distance: 4.0,
         }
     }
 }
+
+/// The state of the left button while an input event is being
+/// dispatched, which elements use to schedule events for while it's
+/// held down.
+#[derive(Debug)]
+pub(crate) struct PressTimer {
+    pub timing: PressTiming,
+    /// When the button was pressed, if it's down. This also tells
+    /// presses apart, so an element which missed a release can't mistake
+    /// a later press for its own.
+    pub pressed_at: Option<Instant>,
+    pub now: Instant,
+    wake_at: Cell<Option<Instant>>,
+}
+
+impl PressTimer {
+    pub fn new(timing: PressTiming, pressed_at: Option<Instant>, now: Instant) -> PressTimer {
+        PressTimer {
+            timing,
+            pressed_at,
+            now,
+            wake_at: Cell::new(None),
+        }
+    }
+
+    /// Asks for a `MouseHeld` event at `deadline`, if the button is
+    /// still down by then.
+    pub fn wake_at(&self, deadline: Instant) {
+        let earliest = match self.wake_at.get() {
+            Some(wake_at) if wake_at < deadline => wake_at,
+            _ => deadline,
+        };
+        self.wake_at.set(Some(earliest));
+    }
+
+    /// The earliest deadline requested while the event was dispatched.
+    pub fn requested(&self) -> Option<Instant> {
+        self.wake_at.get()
+    }
+}
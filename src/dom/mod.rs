@@ -2,6 +2,7 @@
 //! moxie-native. It implements the DOM hierarchy which is used to
 //! represent the UI.
 
+pub mod accessibility;
 pub mod attributes;
 pub mod devtools;
 pub mod element;
@@ -9,8 +10,16 @@ pub mod elements;
 pub mod events;
 pub mod input;
 pub mod node;
+pub mod observer;
+pub mod user_data;
 
+pub use accessibility::{Accessibility, Live, Role};
 pub use attributes::*;
-pub use elements::{app::App, button::Button, span::Span, view::View, window::Window};
+pub use elements::{
+    app::App, button::Button, custom_layout::CustomLayout, span::Span, view::View,
+    window::Placement, window::Titlebar, window::Window, window::WindowSize,
+};
 pub use events::*;
-pub use node::Node;
+pub use node::{Bounds, Node};
+pub use observer::{Mutation, MutationObserver};
+pub use user_data::UserData;
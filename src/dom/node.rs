@@ -1,13 +1,31 @@
 use crate::dom::element::{DynamicNode, Element, ElementStates, NodeChild};
 use crate::dom::input::InputEvent;
-use crate::style::{ComputedValues, Style};
-use std::any::{type_name, TypeId};
+use crate::dom::{Live, Role, UserData};
+use crate::layout::{Delegate, LayoutCell};
+use crate::style::{ComputedValues, ComputedValuesCell, Interaction, ScrollLink, Style};
+use std::any::{type_name, Any, TypeId};
 use std::cell::{Cell, RefCell};
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::rc::Rc;
 
+/// Where a node was drawn in its window, in logical pixels from the
+/// top left of the window's content, after scrolling.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Bounds {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
 pub struct NodeData<Elt>
 where
     Elt: Element,
@@ -15,8 +33,13 @@ where
     element: Elt,
     handlers: RefCell<Elt::Handlers>,
     states: Cell<Elt::States>,
-    computed_values: Cell<Option<ComputedValues>>,
+    /// Set by the style engine from its window's interactions.
+    interaction: Cell<Interaction>,
+    computed_values: ComputedValuesCell,
+    layout: LayoutCell,
     children: Vec<Elt::Child>,
+    id: Cell<Option<topo::Id>>,
+    bounds: Cell<Option<Bounds>>,
 }
 
 impl<Elt> Debug for NodeData<Elt>
@@ -44,8 +67,12 @@ where
             element: element,
             handlers: RefCell::new(Default::default()),
             states: Cell::new(Default::default()),
-            computed_values: Cell::new(None),
+            interaction: Cell::new(Interaction::default()),
+            computed_values: ComputedValuesCell::default(),
+            layout: LayoutCell::default(),
             children: children,
+            id: Cell::new(None),
+            bounds: Cell::new(None),
         }
     }
 
@@ -63,13 +90,32 @@ where
         &self.states
     }
 
-    pub fn computed_values(&self) -> &Cell<Option<ComputedValues>> {
+    pub fn computed_values(&self) -> &ComputedValuesCell {
         &self.computed_values
     }
 
     pub fn handlers(&self) -> &RefCell<Elt::Handlers> {
         &self.handlers
     }
+
+    /// Returns the identity of the place in the component tree where
+    /// this node was created, which stays the same across revisions.
+    /// This is `None` for nodes which weren't created with `mox!`.
+    pub fn id(&self) -> Option<topo::Id> {
+        self.id.get()
+    }
+
+    pub(crate) fn set_id(&self, id: topo::Id) {
+        self.id.set(Some(id));
+    }
+
+    /// Where the node was in the last frame its window drew, for
+    /// positioning popups next to it or scrolling it into view. This is
+    /// `None` until the node has been drawn, and isn't cleared when the
+    /// node is removed.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds.get()
+    }
 }
 
 pub struct NodeDataChildrenIter<'a> {
@@ -88,25 +134,53 @@ impl<'a> Iterator for NodeDataChildrenIter<'a> {
 }
 
 pub trait AnyNodeData: Debug {
-    fn computed_values(&self) -> &Cell<Option<ComputedValues>>;
+    fn computed_values(&self) -> &ComputedValuesCell;
+    #[doc(hidden)]
+    fn layout_cell(&self) -> &LayoutCell;
     fn get_child(&self, index: usize) -> Option<DynamicNode>;
     fn children(&self) -> NodeDataChildrenIter;
     fn process(&self, event: &InputEvent) -> bool;
     fn create_computed_values(&self) -> ComputedValues;
     fn style(&self) -> Option<Style>;
+    fn scroll_name(&self) -> Option<&str>;
+    fn scroll_link(&self) -> Option<ScrollLink>;
+    fn data(&self) -> Option<&UserData>;
+    /// The node's accessible name, from its `label` attribute.
+    fn label(&self) -> Option<&str>;
+    fn role(&self) -> Role;
+    fn description(&self) -> Option<&str>;
+    fn live(&self) -> Live;
+    fn layout_delegate(&self) -> Option<&Delegate>;
+    /// Whether the element is in the given state, either one of its
+    /// own or one of the `Interaction` states.
     fn has_state(&self, key: &str) -> bool;
+    #[doc(hidden)]
+    fn set_interaction(&self, interaction: Interaction);
     fn type_id(&self) -> TypeId;
     fn name(&self) -> &'static str;
+    fn id(&self) -> Option<topo::Id>;
+    fn bounds(&self) -> Option<Bounds>;
+    #[doc(hidden)]
+    fn set_bounds(&self, bounds: Bounds);
+    #[doc(hidden)]
+    fn element_any(&self) -> &dyn Any;
+    /// Whether the other node has the same type of element, with the
+    /// same attributes.
+    fn element_eq(&self, other: &dyn AnyNodeData) -> bool;
 }
 
 impl<Elt> AnyNodeData for NodeData<Elt>
 where
     Elt: Element,
 {
-    fn computed_values(&self) -> &Cell<Option<ComputedValues>> {
+    fn computed_values(&self) -> &ComputedValuesCell {
         &self.computed_values
     }
 
+    fn layout_cell(&self) -> &LayoutCell {
+        &self.layout
+    }
+
     fn get_child(&self, index: usize) -> Option<DynamicNode> {
         self.children.get(index).map(|child| child.get_node())
     }
@@ -135,8 +209,46 @@ where
         self.element.style()
     }
 
+    fn scroll_name(&self) -> Option<&str> {
+        self.element.scroll_name()
+    }
+
+    fn scroll_link(&self) -> Option<ScrollLink> {
+        self.element.scroll_link()
+    }
+
+    fn data(&self) -> Option<&UserData> {
+        self.element.data()
+    }
+
+    fn label(&self) -> Option<&str> {
+        let label = &self.element.accessibility().label;
+        label.as_ref().map(String::as_str)
+    }
+
+    fn role(&self) -> Role {
+        self.element.accessibility().role.unwrap_or(Elt::ROLE)
+    }
+
+    fn description(&self) -> Option<&str> {
+        let description = &self.element.accessibility().description;
+        description.as_ref().map(String::as_str)
+    }
+
+    fn live(&self) -> Live {
+        self.element.accessibility().live
+    }
+
+    fn layout_delegate(&self) -> Option<&Delegate> {
+        self.element.layout_delegate()
+    }
+
     fn has_state(&self, key: &str) -> bool {
-        self.states.get().has_state(key)
+        self.states.get().has_state(key) || self.interaction.get().has_state(key)
+    }
+
+    fn set_interaction(&self, interaction: Interaction) {
+        self.interaction.set(interaction);
     }
 
     fn type_id(&self) -> TypeId {
@@ -146,6 +258,26 @@ where
     fn name(&self) -> &'static str {
         Elt::ELEMENT_NAME
     }
+
+    fn id(&self) -> Option<topo::Id> {
+        self.id.get()
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bounds.get()
+    }
+
+    fn set_bounds(&self, bounds: Bounds) {
+        self.bounds.set(Some(bounds));
+    }
+
+    fn element_any(&self) -> &dyn Any {
+        &self.element
+    }
+
+    fn element_eq(&self, other: &dyn AnyNodeData) -> bool {
+        other.element_any().downcast_ref::<Elt>() == Some(&self.element)
+    }
 }
 
 /// Typed handle to a DOM node.
@@ -0,0 +1,264 @@
+//! Lets tools which mirror the DOM, like inspectors and accessibility
+//! bridges, follow changes to it without walking the whole tree after
+//! every revision.
+//!
+//! ```rs
+//! let observer = MutationObserver::new(|mutations| {
+//!     for mutation in mutations {
+//!         println!("{:?}", mutation);
+//!     }
+//! });
+//! Runtime::new(|| app!()).observe_mutations(observer).start();
+//! ```
+
+use crate::dom::element::DynamicNode;
+use crate::dom::node::{AnyNode, AnyNodeData};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A change between one revision of the DOM and the next.
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    /// `node` was added to `parent`. Its children came with it, and
+    /// aren't reported on their own.
+    Added { node: AnyNode, parent: AnyNode },
+    /// `node` was taken out of `parent`, along with its children.
+    Removed { node: AnyNode, parent: AnyNode },
+    /// `node` has different attributes than `previous`, the node it
+    /// replaced.
+    AttributesChanged { node: AnyNode, previous: AnyNode },
+    /// The text directly inside `node` is different than the text in
+    /// `previous`, the node it replaced.
+    TextChanged { node: AnyNode, previous: AnyNode },
+}
+
+impl Mutation {
+    /// The node which changed, which is the parent for added and
+    /// removed nodes.
+    pub fn target(&self) -> &AnyNode {
+        match self {
+            Mutation::Added { parent, .. } | Mutation::Removed { parent, .. } => parent,
+            Mutation::AttributesChanged { node, .. } | Mutation::TextChanged { node, .. } => node,
+        }
+    }
+}
+
+/// A callback for changes to the DOM, registered with
+/// `Runtime::observe_mutations`.
+///
+/// Nodes are matched up between revisions by the place in the
+/// component tree they were created at, or by their position among
+/// their siblings of the same type when they weren't created with
+/// `mox!`. A node which moves to another parent is reported as removed
+/// and added again.
+pub struct MutationObserver {
+    subtree: Option<topo::Id>,
+    callback: Box<dyn FnMut(&[Mutation])>,
+}
+
+impl MutationObserver {
+    /// Calls `callback` with the changes made by each revision, for the
+    /// revisions which change anything. The windows of the application
+    /// are reported as added by the first revision.
+    pub fn new(callback: impl FnMut(&[Mutation]) + 'static) -> MutationObserver {
+        MutationObserver {
+            subtree: None,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Only reports changes to the node created at `id`, and to the
+    /// nodes inside it.
+    pub fn subtree(mut self, id: topo::Id) -> MutationObserver {
+        self.subtree = Some(id);
+        self
+    }
+}
+
+struct Record {
+    mutation: Mutation,
+    /// The ids of the mutation's target and of its ancestors.
+    ancestors: Vec<topo::Id>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ChildKey {
+    Id(topo::Id),
+    /// The type of the child, and how many children of the same type
+    /// came before it.
+    Position(TypeId, usize),
+}
+
+/// The observers registered with a runtime, along with the DOM they
+/// last saw.
+#[derive(Default)]
+pub(crate) struct MutationObservers {
+    observers: RefCell<Vec<MutationObserver>>,
+    previous: RefCell<Option<AnyNode>>,
+}
+
+impl MutationObservers {
+    pub(crate) fn add(&self, observer: MutationObserver) {
+        self.observers.borrow_mut().push(observer);
+    }
+
+    /// Reports the changes from the last revision to `root`. The
+    /// previous DOM is only kept while there are observers.
+    pub(crate) fn update(&self, root: AnyNode) {
+        let mut observers = self.observers.borrow_mut();
+        if observers.is_empty() {
+            return;
+        }
+        let previous = self.previous.replace(Some(root.clone()));
+        let mut records = vec![];
+        diff_node(previous.as_ref(), &root, &mut vec![], &mut records);
+
+        for observer in observers.iter_mut() {
+            let subtree = observer.subtree;
+            let mutations = records
+                .iter()
+                .filter(|record| subtree.map_or(true, |id| record.ancestors.contains(&id)))
+                .map(|record| record.mutation.clone())
+                .collect::<Vec<_>>();
+            if !mutations.is_empty() {
+                (observer.callback)(&mutations);
+            }
+        }
+    }
+}
+
+fn keyed_children(node: &AnyNode) -> Vec<(ChildKey, AnyNode)> {
+    let mut counts = HashMap::new();
+    let mut children = vec![];
+    for child in node.children() {
+        let child = match child {
+            DynamicNode::Node(child) => child.to_owned(),
+            DynamicNode::Text(_) => continue,
+        };
+        let key = match child.id() {
+            Some(id) => ChildKey::Id(id),
+            None => {
+                let count = counts
+                    .entry(AnyNodeData::type_id(&*child))
+                    .or_insert(0usize);
+                *count += 1;
+                ChildKey::Position(AnyNodeData::type_id(&*child), *count - 1)
+            }
+        };
+        children.push((key, child));
+    }
+    children
+}
+
+fn text_children(node: &AnyNode) -> Vec<&str> {
+    node.children()
+        .filter_map(|child| match child {
+            DynamicNode::Text(text) => Some(text),
+            DynamicNode::Node(_) => None,
+        })
+        .collect()
+}
+
+/// Compares `node` with the node it replaced, if there was one.
+/// Subtrees which were reused from the last revision are skipped, since
+/// nothing in them can have changed.
+fn diff_node(
+    previous: Option<&AnyNode>,
+    node: &AnyNode,
+    ancestors: &mut Vec<topo::Id>,
+    records: &mut Vec<Record>,
+) {
+    if previous == Some(node) {
+        return;
+    }
+    if let Some(id) = node.id() {
+        ancestors.push(id);
+    }
+    let mut record = |mutation| {
+        records.push(Record {
+            mutation,
+            ancestors: ancestors.clone(),
+        })
+    };
+
+    let mut previous_children = vec![];
+    if let Some(previous) = previous {
+        if !node.element_eq(&**previous) {
+            record(Mutation::AttributesChanged {
+                node: node.clone(),
+                previous: previous.clone(),
+            });
+        }
+        if text_children(node) != text_children(previous) {
+            record(Mutation::TextChanged {
+                node: node.clone(),
+                previous: previous.clone(),
+            });
+        }
+        previous_children = keyed_children(previous);
+    }
+
+    let mut matched = vec![];
+    for (key, child) in keyed_children(node) {
+        match previous_children.iter().position(|(old, _)| *old == key) {
+            Some(index) => matched.push((previous_children.remove(index).1, child)),
+            None => record(Mutation::Added {
+                node: child,
+                parent: node.clone(),
+            }),
+        }
+    }
+    for (_, child) in previous_children {
+        record(Mutation::Removed {
+            node: child,
+            parent: node.clone(),
+        });
+    }
+    for (previous_child, child) in matched {
+        diff_node(Some(&previous_child), &child, ancestors, records);
+    }
+
+    if node.id().is_some() {
+        ancestors.pop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_node, Mutation};
+    use crate::dom::element::HasAttribute;
+    use crate::dom::elements::view::ViewChild;
+    use crate::dom::node::AnyNode;
+    use crate::dom::{AttrScrollName, Node, View};
+
+    fn view(scroll_name: &str, children: Vec<Node<View>>) -> Node<View> {
+        let mut element = View::default();
+        if !scroll_name.is_empty() {
+            HasAttribute::<AttrScrollName>::set_attribute(&mut element, scroll_name.to_owned());
+        }
+        Node::new(element, children.into_iter().map(ViewChild::View).collect())
+    }
+
+    #[test]
+    fn finds_mutations() {
+        let kept = view("", vec![]);
+        let previous = view("", vec![kept.clone(), view("", vec![]), view("", vec![])]);
+        let node = view("", vec![kept, view("renamed", vec![])]);
+        let (previous, node): (AnyNode, AnyNode) = (previous.into(), node.into());
+        let mut records = vec![];
+        diff_node(Some(&previous), &node, &mut vec![], &mut records);
+
+        assert_eq!(records.len(), 2);
+        match records[0].mutation {
+            Mutation::Removed { ref node, .. } => assert_eq!(node.scroll_name(), None),
+            ref other => panic!("expected a removal, found {:?}", other),
+        }
+        match records[1].mutation {
+            Mutation::AttributesChanged { ref node, .. } => {
+                assert_eq!(node.scroll_name(), Some("renamed"))
+            }
+            ref other => panic!("expected an attribute change, found {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+trait DataValue: fmt::Debug {
+    fn as_any(&self) -> &dyn Any;
+    fn eq_value(&self, other: &dyn DataValue) -> bool;
+}
+
+impl<T> DataValue for T
+where
+    T: Any + fmt::Debug + PartialEq,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_value(&self, other: &dyn DataValue) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+/// A typed value attached to an element with the `data` attribute,
+/// such as the id of the model item the element shows. Event handlers
+/// can get it back from the event, which saves capturing indices in
+/// the handlers of generated lists.
+///
+/// ```rs
+/// mox! {
+///     <button data={UserData::new(item.id)} on={on_click}>
+///         ...
+///     </button>
+/// }
+/// ```
+///
+/// Values are compared by value, so attaching equal data in the next
+/// revision doesn't count as a change to the element.
+#[derive(Clone)]
+pub struct UserData(Rc<dyn DataValue>);
+
+impl UserData {
+    pub fn new<T>(value: T) -> UserData
+    where
+        T: Any + fmt::Debug + PartialEq,
+    {
+        UserData(Rc::new(value))
+    }
+
+    /// Returns the value, if it has the type `T`.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for UserData {
+    fn eq(&self, other: &UserData) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0.eq_value(&*other.0)
+    }
+}
+
+/// The data of every element under the pointer, innermost first, while
+/// an input event is being dispatched.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HitTest(pub Vec<UserData>);
+
+#[cfg(test)]
+mod test {
+    use super::UserData;
+
+    #[test]
+    fn typed_access() {
+        let data = UserData::new(42u32);
+        assert_eq!(data.get::<u32>(), Some(&42));
+        assert_eq!(data.get::<i32>(), None);
+    }
+
+    #[test]
+    fn compares_values() {
+        assert_eq!(UserData::new("a"), UserData::new("a"));
+        assert_ne!(UserData::new("a"), UserData::new("b"));
+        assert_ne!(UserData::new(1u32), UserData::new(1u64));
+    }
+}
@@ -0,0 +1,213 @@
+//! Validation and submission for groups of state keys which make up a
+//! form.
+//!
+//! A form is declared with `form!`, and each field is registered on
+//! every render with the key holding its value and any validators.
+//! Submitting runs every validator, and if they all pass, hands the
+//! values to the submit handler, keyed by field name. Errors from the
+//! last submission are kept in state, so fields can be styled by
+//! whether they're valid.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn sign_up() -> Node<View> {
+//!     let email: Key<String> = state!(|| String::new());
+//!     let accepted: Key<bool> = state!(|| false);
+//!     let form = form!();
+//!     form.field("email", &email)
+//!         .validate(|email: &String| if email.contains('@') { Ok(()) } else { Err("not an email address".into()) });
+//!     form.field("accepted", &accepted)
+//!         .validate(|accepted: &bool| if *accepted { Ok(()) } else { Err("required".into()) });
+//!     let submit = form.on_submit(|values| create_account(values.get::<String>("email").unwrap()));
+//!     mox! {
+//!         <view>
+//!             <button style={if form.error("accepted").is_some() { INVALID_STYLE } else { FIELD_STYLE }}
+//!                     on={bind::toggle(&accepted)}>
+//!                 <span>{% "Accept the terms: {}", accepted}</span>
+//!             </button>
+//!             <button on={submit}><span>"Sign up"</span></button>
+//!         </view>
+//!     }
+//! }
+//! ```
+
+use crate::dom::events::ClickEvent;
+use moxie::Key;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// The message from the first failing validator of every invalid field,
+/// as of the last submission.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Errors(BTreeMap<String, String>);
+
+impl Errors {
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0.get(field).map(|message| &message[..])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Every invalid field and its message, ordered by field name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(field, message)| (&field[..], &message[..]))
+    }
+}
+
+/// The values of a form's fields when it was submitted.
+#[derive(Default)]
+pub struct FormValues {
+    values: HashMap<String, Box<dyn Any>>,
+}
+
+impl FormValues {
+    /// Returns the value of the named field, if it has the type `T`.
+    pub fn get<T: Any>(&self, field: &str) -> Option<&T> {
+        self.values
+            .get(field)
+            .and_then(|value| value.downcast_ref())
+    }
+}
+
+type Validator = Box<dyn Fn() -> Result<(), String>>;
+
+struct Field {
+    name: String,
+    value: Box<dyn Fn() -> Box<dyn Any>>,
+    validators: Vec<Validator>,
+}
+
+/// A group of fields which are validated and submitted together. Create
+/// one with `form!`.
+#[derive(Clone)]
+pub struct Form {
+    errors: Key<Errors>,
+    fields: Rc<RefCell<Vec<Field>>>,
+}
+
+impl Form {
+    /// Used by `form!`.
+    #[doc(hidden)]
+    pub fn new(errors: Key<Errors>) -> Form {
+        Form {
+            errors,
+            fields: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    /// Adds a field whose value is held by `key`. Submitted values are
+    /// cloned out of the key as of the last render.
+    pub fn field<T>(&self, name: &str, key: &Key<T>) -> FieldRef<T>
+    where
+        T: Clone + 'static,
+    {
+        let value_key = key.clone();
+        let mut fields = self.fields.borrow_mut();
+        fields.push(Field {
+            name: name.to_owned(),
+            value: Box::new(move || Box::new((*value_key).clone())),
+            validators: vec![],
+        });
+        FieldRef {
+            fields: self.fields.clone(),
+            index: fields.len() - 1,
+            key: key.clone(),
+        }
+    }
+
+    /// The message the named field failed validation with when the form
+    /// was last submitted.
+    pub fn error(&self, field: &str) -> Option<&str> {
+        self.errors().get(field)
+    }
+
+    /// Every field that failed validation when the form was last
+    /// submitted.
+    pub fn errors(&self) -> &Errors {
+        &self.errors
+    }
+
+    fn validate(&self) -> Errors {
+        let mut errors = BTreeMap::new();
+        for field in self.fields.borrow().iter() {
+            if let Some(Err(message)) = field
+                .validators
+                .iter()
+                .map(|validator| validator())
+                .find(Result::is_err)
+            {
+                errors.entry(field.name.clone()).or_insert(message);
+            }
+        }
+        Errors(errors)
+    }
+
+    /// Creates a click handler which validates every field, and calls
+    /// `submit` with their values if they're all valid. Errors from
+    /// the previous submission are replaced either way.
+    pub fn on_submit(
+        &self,
+        submit: impl Fn(&FormValues) + 'static,
+    ) -> impl FnMut(&ClickEvent) + 'static {
+        let form = self.clone();
+        move |_: &ClickEvent| {
+            let errors = form.validate();
+            let valid = errors.is_empty();
+            form.errors.update(|previous| {
+                if *previous == errors {
+                    None
+                } else {
+                    Some(errors)
+                }
+            });
+            if valid {
+                let values = form
+                    .fields
+                    .borrow()
+                    .iter()
+                    .map(|field| (field.name.clone(), (field.value)()))
+                    .collect();
+                submit(&FormValues { values });
+            }
+        }
+    }
+}
+
+/// A field which has just been added to a form, for adding validators.
+pub struct FieldRef<T> {
+    fields: Rc<RefCell<Vec<Field>>>,
+    index: usize,
+    key: Key<T>,
+}
+
+impl<T> FieldRef<T>
+where
+    T: 'static,
+{
+    /// Adds a check run against the field's value on submission. Only
+    /// the first failing check's message is kept.
+    pub fn validate(self, validator: impl Fn(&T) -> Result<(), String> + 'static) -> Self {
+        let key = self.key.clone();
+        self.fields.borrow_mut()[self.index]
+            .validators
+            .push(Box::new(move || validator(&key)));
+        self
+    }
+}
+
+/// Declares a form, which keeps the errors from its last submission in
+/// state.
+#[macro_export]
+macro_rules! form {
+    () => {
+        $crate::form::Form::new($crate::prelude::state!(|| {
+            $crate::form::Errors::default()
+        }))
+    };
+}
@@ -0,0 +1,134 @@
+//! Lets components schedule low priority work, like prefetching images
+//! or warming caches, to run once the application has nothing else to
+//! do.
+//!
+//! Work runs after all pending events have been handled, while no
+//! window is animating, one callback per turn of the event loop so
+//! that input arriving in the meantime is handled first. A callback is
+//! run at its deadline even if the application never becomes idle.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn gallery() -> Node<View> {
+//!     once!(|| run_when_idle(prefetch_thumbnails, Instant::now() + Duration::from_secs(2)));
+//!     // ...
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Instant;
+
+struct IdleTask {
+    callback: Box<dyn FnOnce()>,
+    deadline: Instant,
+}
+
+/// Callbacks waiting for the application to become idle, in the order
+/// they were scheduled.
+#[derive(Default)]
+pub(crate) struct IdleQueue {
+    tasks: RefCell<VecDeque<IdleTask>>,
+}
+
+impl fmt::Debug for IdleQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IdleQueue")
+            .field("tasks", &self.tasks.borrow().len())
+            .finish()
+    }
+}
+
+impl IdleQueue {
+    pub(crate) fn push(&self, callback: Box<dyn FnOnce()>, deadline: Instant) {
+        self.tasks
+            .borrow_mut()
+            .push_back(IdleTask { callback, deadline });
+    }
+
+    /// The earliest deadline of any waiting callback.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.tasks.borrow().iter().map(|task| task.deadline).min()
+    }
+
+    /// Runs every callback whose deadline has passed, and if `idle` is
+    /// true, the oldest remaining one. Returns whether anything ran.
+    pub(crate) fn run(&self, idle: bool, now: Instant) -> bool {
+        let mut ran = false;
+        while let Some(task) = self.take(|task| task.deadline <= now) {
+            (task.callback)();
+            ran = true;
+        }
+        if idle && !ran {
+            if let Some(task) = self.take(|_| true) {
+                (task.callback)();
+                ran = true;
+            }
+        }
+        ran
+    }
+
+    /// Removes the first task matching `predicate`. The queue isn't
+    /// borrowed while the task runs, so it can schedule more work.
+    fn take(&self, predicate: impl Fn(&IdleTask) -> bool) -> Option<IdleTask> {
+        let mut tasks = self.tasks.borrow_mut();
+        let index = tasks.iter().position(predicate)?;
+        tasks.remove(index)
+    }
+}
+
+/// Schedules `callback` to run once there are no events or frames
+/// pending, or at `deadline`, whichever comes first.
+///
+/// This should usually be called from inside `once!`, since components
+/// run again every revision.
+pub fn run_when_idle(callback: impl FnOnce() + 'static, deadline: Instant) {
+    let queue = illicit::Env::expect::<Rc<IdleQueue>>();
+    queue.push(Box::new(callback), deadline);
+}
+
+#[cfg(test)]
+mod test {
+    use super::IdleQueue;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn runs_one_task_when_idle() {
+        let queue = IdleQueue::default();
+        let log = Rc::new(RefCell::new(vec![]));
+        let now = Instant::now();
+        for i in 0..2 {
+            let log = log.clone();
+            queue.push(
+                Box::new(move || log.borrow_mut().push(i)),
+                now + Duration::from_secs(10),
+            );
+        }
+        assert!(!queue.run(false, now));
+        assert!(queue.run(true, now));
+        assert_eq!(*log.borrow(), vec![0]);
+        assert!(queue.run(true, now));
+        assert_eq!(*log.borrow(), vec![0, 1]);
+        assert_eq!(queue.next_deadline(), None);
+    }
+
+    #[test]
+    fn runs_overdue_tasks_when_busy() {
+        let queue = IdleQueue::default();
+        let log = Rc::new(RefCell::new(vec![]));
+        let now = Instant::now();
+        let deadlines = [now + Duration::from_secs(10), now, now];
+        for (i, &deadline) in deadlines.iter().enumerate() {
+            let log = log.clone();
+            queue.push(Box::new(move || log.borrow_mut().push(i)), deadline);
+        }
+        assert_eq!(queue.next_deadline(), Some(now));
+        assert!(queue.run(false, now));
+        assert_eq!(*log.borrow(), vec![1, 2]);
+        assert_eq!(queue.next_deadline(), Some(now + Duration::from_secs(10)));
+    }
+}
@@ -1,25 +1,126 @@
-use super::{inline, LayoutChild, LayoutTreeNode, LogicalSize, RenderData};
+use super::{
+    finite, finite_size, inline, layout_node, measure, not_nan, LayoutChild, LayoutTreeNode,
+    LogicalSize, RenderData,
+};
+use crate::dom::node::AnyNodeData;
 use crate::dom::{element::DynamicNode, node::AnyNode, node::NodeRef};
-use crate::style::{BlockValues, ComputedValues, Direction, DisplayType};
+use crate::style::{
+    BlockLength, BlockValues, ComputedValues, Direction, DisplayType, MarginCollapse, Overflow,
+};
 use crate::util::equal_rc::EqualRc;
 use euclid::{point2, size2, vec2};
 use moxie::*;
 
-fn calc_max_size(values: &BlockValues, parent_size: LogicalSize) -> LogicalSize {
+/// Limits a size to the node's minimum and maximum sizes. When they
+/// conflict, the minimum wins.
+pub(super) fn constrain(
+    size: LogicalSize,
+    values: &BlockValues,
+    parent_size: LogicalSize,
+) -> LogicalSize {
+    let clamp = |length: f32, min: Option<BlockLength>, max: Option<BlockLength>, parent: f32| {
+        let mut length = length;
+        if let Some(max) = max.and_then(|max| max.resolve(parent)) {
+            length = length.min(max);
+        }
+        if let Some(min) = min.and_then(|min| min.resolve(parent)) {
+            length = length.max(min);
+        }
+        length
+    };
+    size2(
+        clamp(
+            size.width,
+            values.min_width,
+            values.max_width,
+            parent_size.width,
+        ),
+        clamp(
+            size.height,
+            values.min_height,
+            values.max_height,
+            parent_size.height,
+        ),
+    )
+}
+
+pub(super) fn calc_max_size(
+    node: &dyn AnyNodeData,
+    values: &BlockValues,
+    parent_size: LogicalSize,
+) -> LogicalSize {
     let mut outer = parent_size;
-    if let Some(width) = values.width {
-        outer.width = width.get();
+    if let Some(width) = values
+        .width
+        .and_then(|width| width.resolve(parent_size.width))
+    {
+        outer.width = finite(width, node, "width");
+    }
+    if let Some(height) = values
+        .height
+        .and_then(|height| height.resolve(parent_size.height))
+    {
+        outer.height = finite(height, node, "height");
+    }
+    // The parent may leave unlimited space, so this can still be
+    // infinite.
+    let outer = constrain(outer, values, parent_size);
+    let padding = size2(
+        finite(values.padding.horizontal(), node, "padding"),
+        finite(values.padding.vertical(), node, "padding"),
+    );
+    let inner = outer - padding;
+    // Padding larger than the node leaves no space, rather than a
+    // negative amount.
+    let mut inner = size2(
+        not_nan(inner.width, node, "content width").max(0.0),
+        not_nan(inner.height, node, "content height").max(0.0),
+    );
+    // Scroll containers don't constrain their children along the
+    // direction they scroll in.
+    if values.overflow == Overflow::Scroll {
+        match values.direction {
+            Direction::Vertical => inner.height = std::f32::INFINITY,
+            Direction::Horizontal => inner.width = std::f32::INFINITY,
+        }
+    }
+    inner
+}
+
+pub(super) fn horizontal_margin(node: NodeRef) -> f32 {
+    match node.computed_values().get().map(|values| values.display) {
+        Some(DisplayType::Block(block)) => block.margin.horizontal(),
+        _ => 0.0,
     }
-    if let Some(height) = values.height {
-        outer.height = height.get();
+}
+
+/// Whether a child's left and right margins are `auto`.
+fn auto_margins(layout: &LayoutTreeNode) -> (bool, bool) {
+    if let RenderData::Node(ref node) = layout.render {
+        if let Some(values) = node.computed_values().get() {
+            if let DisplayType::Block(block) = values.display {
+                return (block.auto_margin_left, block.auto_margin_right);
+            }
+        }
     }
-    outer - size2(values.padding.horizontal(), values.padding.vertical())
+    (false, false)
+}
+
+/// The space left between two margins which collapse: the largest
+/// positive margin less the most negative one.
+fn collapse_margins(first: f32, second: f32) -> f32 {
+    first.max(second).max(0.0) + first.min(second).min(0.0)
 }
 
 fn calc_block_layout(
-    input: &(ComputedValues, Vec<EqualRc<LayoutTreeNode>>, AnyNode),
+    input: &(
+        ComputedValues,
+        Vec<EqualRc<LayoutTreeNode>>,
+        AnyNode,
+        LogicalSize,
+    ),
 ) -> EqualRc<LayoutTreeNode> {
-    let (values, children, node) = input;
+    let (values, children, node, parent_max_size) = input;
 
     let block_values = if let DisplayType::Block(block) = values.display {
         block
@@ -35,10 +136,19 @@ fn calc_block_layout(
     let mut width = 0.0f32;
     let mut height = 0.0f32;
     let mut child_positions = vec![];
+    let collapse = block_values.margin_collapse == MarginCollapse::Collapse;
+    let mut previous_margin = None;
     for child in children {
         let child = child.clone();
         let size = child.size + size2(child.margin.horizontal(), child.margin.vertical());
         if block_values.direction == Direction::Vertical {
+            // The child moves up by however much of the two margins
+            // collapsing removes.
+            if let (true, Some(previous)) = (collapse, previous_margin) {
+                height -=
+                    previous + child.margin.top - collapse_margins(previous, child.margin.top);
+            }
+            previous_margin = Some(child.margin.bottom);
             width = width.max(size.width);
             child_positions.push(LayoutChild {
                 position: inset + vec2(0.0, height),
@@ -65,13 +175,41 @@ fn calc_block_layout(
         values.border_thickness.vertical(),
     );
 
-    let mut size = size + padding + border;
+    let content_size = size + padding + border;
+    let mut size = content_size;
 
-    if let Some(width) = block_values.width {
-        size.width = width.get();
+    if let Some(width) = block_values
+        .width
+        .and_then(|width| width.resolve(parent_max_size.width))
+    {
+        size.width = width;
     }
-    if let Some(height) = block_values.height {
-        size.height = height.get();
+    if let Some(height) = block_values
+        .height
+        .and_then(|height| height.resolve(parent_max_size.height))
+    {
+        size.height = height;
+    }
+    let size = constrain(size, &block_values, *parent_max_size);
+    let size = finite_size(size, &**node, "size");
+    // Scroll and zoom containers are expected to be smaller than their
+    // content.
+    let overflowed = block_values.overflow == Overflow::Visible
+        && (content_size.width > size.width || content_size.height > size.height);
+
+    // Auto margins take up the width left beside each child, which is
+    // only known once this node's own width is.
+    if block_values.direction == Direction::Vertical {
+        let inner_width = size.width - padding.width - border.width;
+        for child in &mut child_positions {
+            let layout = &child.layout;
+            let free = inner_width - layout.size.width - layout.margin.horizontal();
+            child.position.x += match auto_margins(layout) {
+                (true, true) => free.max(0.0) / 2.0,
+                (true, false) => free.max(0.0),
+                _ => 0.0,
+            };
+        }
     }
 
     let margin = block_values.margin;
@@ -81,6 +219,7 @@ fn calc_block_layout(
         margin,
         children: child_positions,
         render: RenderData::Node(node.clone()),
+        overflowed,
     })
 }
 
@@ -90,26 +229,42 @@ pub fn layout_block(
     block_values: &BlockValues,
     parent_max_size: LogicalSize,
 ) -> EqualRc<LayoutTreeNode> {
-    let max_size = calc_max_size(block_values, parent_max_size);
+    let max_size = calc_max_size(&*node, block_values, parent_max_size);
+
+    // Children placed side by side would each be offered the whole
+    // width, so when they don't all fit, each is given a share of it
+    // based on how wide its content can be.
+    let mut shares = None;
+    if block_values.direction == Direction::Horizontal && max_size.width.is_finite() {
+        let mut widths = vec![];
+        for child in node.children() {
+            topo::call! {
+                {
+                    widths.push(match child {
+                        DynamicNode::Node(node) => measure::measure_node(node),
+                        DynamicNode::Text(text) => measure::measure_text(text, values),
+                    });
+                }
+            }
+        }
+        shares = measure::distribute(&widths, max_size.width);
+    }
 
     let mut children = vec![];
-    for child in node.children() {
+    for (index, child) in node.children().enumerate() {
+        let width = shares
+            .as_ref()
+            .map_or(max_size.width, |shares| shares[index]);
         topo::call! {
             {
                 match child {
                     DynamicNode::Node(node) => {
-                        let values = node.computed_values().get().unwrap();
-                        match values.display {
-                            DisplayType::Block(ref block) => {
-                                children.push(layout_block(node, &values, block, max_size));
-                            }
-                            DisplayType::Inline(_) => {
-                                children.push(inline::layout_inline(node, &values, max_size));
-                            }
-                        }
+                        // Shares include the child's margins.
+                        let width = width - horizontal_margin(node);
+                        children.push(layout_node(node, size2(width, max_size.height)));
                     }
                     DynamicNode::Text(text) => {
-                        children.push(inline::layout_text(node.to_owned(), text, max_size.width, values));
+                        children.push(inline::layout_text(node.to_owned(), text, width, values));
                     }
                 }
             }
@@ -117,7 +272,7 @@ pub fn layout_block(
     }
 
     moxie::memo!(
-        (values.clone(), children, node.to_owned()),
+        (values.clone(), children, node.to_owned(), parent_max_size),
         calc_block_layout
     )
 }
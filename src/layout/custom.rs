@@ -0,0 +1,212 @@
+//! Layout for `<customlayout>`, which hands its children to an
+//! application-defined `LayoutDelegate` to measure and place.
+
+use super::block::{calc_max_size, constrain, horizontal_margin};
+use super::{
+    finite, finite_size, layout_node, measure, LayoutChild, LayoutTreeNode, LogicalSize, RenderData,
+};
+use crate::dom::element::DynamicNode;
+use crate::dom::node::NodeRef;
+use crate::dom::UserData;
+use crate::style::{BlockValues, ComputedValues, Overflow};
+use crate::util::equal_rc::EqualRc;
+use euclid::{point2, size2};
+use std::any::Any;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::rc::Rc;
+
+/// Arranges the children of a `<customlayout>`, for layouts the
+/// built-in ones can't express, like circular menus or masonry grids:
+///
+/// ```rs
+/// struct Row;
+///
+/// impl LayoutDelegate for Row {
+///     fn layout(&self, space: Constraints, children: &mut [CustomChild]) -> (f32, f32) {
+///         let (mut x, mut height) = (0.0, 0.0f32);
+///         for child in children {
+///             let size = child.measure(space.max_width - x, space.max_height);
+///             child.place(x, 0.0);
+///             x += size.0;
+///             height = height.max(size.1);
+///         }
+///         (x, height)
+///     }
+/// }
+///
+/// let row = once!(|| Rc::new(Row));
+/// mox! { <customlayout layout={row}>{ items }</customlayout> }
+/// ```
+pub trait LayoutDelegate: 'static {
+    /// Measures and places each of `children`, and returns the width and
+    /// height of the content. The element's padding and border are
+    /// added around it, and its `width` and `height` styles take
+    /// precedence over it.
+    ///
+    /// Children which aren't measured are laid out at the largest size
+    /// allowed, and children which aren't placed stay at the top left.
+    fn layout(&self, constraints: Constraints, children: &mut [CustomChild]) -> (f32, f32);
+}
+
+/// The space a `LayoutDelegate` has for its content, in logical pixels.
+/// Either may be infinite, such as inside a scroll container.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constraints {
+    pub max_width: f32,
+    pub max_height: f32,
+}
+
+/// The `layout` attribute of a `<customlayout>`. Delegates are compared
+/// by identity, so one created on every revision lays the element out
+/// again each time. Create it with `once!`, or keep it in state.
+#[derive(Clone)]
+pub struct Delegate(pub(super) Rc<dyn LayoutDelegate>);
+
+impl<T: LayoutDelegate> From<Rc<T>> for Delegate {
+    fn from(delegate: Rc<T>) -> Delegate {
+        Delegate(delegate)
+    }
+}
+
+impl Debug for Delegate {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str("Delegate")
+    }
+}
+
+impl PartialEq for Delegate {
+    fn eq(&self, other: &Delegate) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A child of a `<customlayout>`, which its delegate measures and
+/// places. Sizes and positions are of the child's margin box.
+pub struct CustomChild<'a> {
+    node: NodeRef<'a>,
+    layout: Option<EqualRc<LayoutTreeNode>>,
+    x: f32,
+    y: f32,
+}
+
+impl<'a> CustomChild<'a> {
+    /// Lays the child out with at most the given space, and returns its
+    /// size. The child can be measured again with different space, and
+    /// the last measurement is the one used.
+    pub fn measure(&mut self, max_width: f32, max_height: f32) -> (f32, f32) {
+        let node = self.node;
+        let width = max_width - horizontal_margin(node);
+        let layout = topo::call!({ layout_node(node, size2(width, max_height)) });
+        let width = layout.size.width + layout.margin.horizontal();
+        let height = layout.size.height + layout.margin.vertical();
+        self.layout = Some(layout);
+        (width, height)
+    }
+
+    /// The narrowest the child can be without its content overflowing,
+    /// and the width it takes when it has as much room as it wants.
+    pub fn intrinsic_widths(&self) -> (f32, f32) {
+        let node = self.node;
+        let widths = topo::call!({ measure::measure_node(node) });
+        (widths.min, widths.max)
+    }
+
+    /// Puts the child's top left corner at `x` and `y`, relative to the
+    /// top left of the content.
+    pub fn place(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Returns the value attached to the child with the `data`
+    /// attribute, if it has the type `T`, so that the delegate can tell
+    /// its children apart.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.node.data().and_then(UserData::get)
+    }
+}
+
+pub fn layout_custom(
+    node: NodeRef,
+    values: &ComputedValues,
+    block_values: &BlockValues,
+    delegate: &dyn LayoutDelegate,
+    parent_max_size: LogicalSize,
+) -> EqualRc<LayoutTreeNode> {
+    let max_size = calc_max_size(&*node, block_values, parent_max_size);
+    let mut children = node
+        .children()
+        .filter_map(|child| match child {
+            DynamicNode::Node(node) => Some(CustomChild {
+                node,
+                layout: None,
+                x: 0.0,
+                y: 0.0,
+            }),
+            DynamicNode::Text(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let constraints = Constraints {
+        max_width: max_size.width,
+        max_height: max_size.height,
+    };
+    let (width, height) = delegate.layout(constraints, &mut children);
+
+    let inset = point2(
+        block_values.padding.left + values.border_thickness.left,
+        block_values.padding.top + values.border_thickness.top,
+    );
+    let child_positions = children
+        .into_iter()
+        .map(|mut child| {
+            if child.layout.is_none() {
+                child.measure(max_size.width, max_size.height);
+            }
+            LayoutChild {
+                position: point2(
+                    inset.x + finite(child.x, &*node, "child x"),
+                    inset.y + finite(child.y, &*node, "child y"),
+                ),
+                layout: child.layout.unwrap(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let content = size2(
+        finite(width, &*node, "content width"),
+        finite(height, &*node, "content height"),
+    );
+    let content_size = content
+        + size2(
+            block_values.padding.horizontal() + values.border_thickness.horizontal(),
+            block_values.padding.vertical() + values.border_thickness.vertical(),
+        );
+    let mut size = content_size;
+    if let Some(width) = block_values
+        .width
+        .and_then(|width| width.resolve(parent_max_size.width))
+    {
+        size.width = width;
+    }
+    if let Some(height) = block_values
+        .height
+        .and_then(|height| height.resolve(parent_max_size.height))
+    {
+        size.height = height;
+    }
+    let size = finite_size(
+        constrain(size, block_values, parent_max_size),
+        &*node,
+        "size",
+    );
+    let overflowed = block_values.overflow == Overflow::Visible
+        && (content_size.width > size.width || content_size.height > size.height);
+
+    EqualRc::new(LayoutTreeNode {
+        size,
+        margin: block_values.margin,
+        children: child_positions,
+        render: RenderData::Node(node.to_owned()),
+        overflowed,
+    })
+}
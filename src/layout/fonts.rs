@@ -0,0 +1,412 @@
+use super::text::ShapingCache;
+use crate::style::FontStyle;
+use crate::util::equal_rc::EqualRc;
+use font_kit::family_name::FamilyName;
+use font_kit::handle::Handle;
+use font_kit::matching;
+use font_kit::properties::{Properties, Style, Weight};
+use font_kit::source::SystemSource;
+use skribo::{FontCollection, FontFamily};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// The `font_family` which text uses unless its style says otherwise.
+const DEFAULT_FAMILY: &str = "sans-serif";
+
+/// Installed families with emoji and CJK characters, which are tried
+/// after every other family for characters none of them have.
+#[cfg(target_os = "macos")]
+const SYSTEM_FALLBACK: &[&str] = &[
+    "Apple Color Emoji",
+    "PingFang SC",
+    "Hiragino Sans",
+    "Apple SD Gothic Neo",
+];
+#[cfg(target_os = "windows")]
+const SYSTEM_FALLBACK: &[&str] = &[
+    "Segoe UI Emoji",
+    "Segoe UI Symbol",
+    "Microsoft YaHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const SYSTEM_FALLBACK: &[&str] = &[
+    "Noto Color Emoji",
+    "Noto Sans CJK SC",
+    "Noto Sans CJK JP",
+    "Noto Sans CJK KR",
+    "WenQuanYi Micro Hei",
+];
+
+/// A `font_family` list, along with the weight and style to look for
+/// in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontKey {
+    pub family: &'static str,
+    pub weight: u32,
+    pub style: FontStyle,
+}
+
+impl FontKey {
+    /// The fonts text uses unless its style says otherwise.
+    pub const DEFAULT: FontKey = FontKey {
+        family: DEFAULT_FAMILY,
+        weight: 400,
+        style: FontStyle::Normal,
+    };
+
+    fn properties(self) -> Properties {
+        let mut properties = Properties::new();
+        properties.weight(Weight(self.weight as f32));
+        properties.style(match self.style {
+            FontStyle::Normal => Style::Normal,
+            FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique => Style::Oblique,
+        });
+        properties
+    }
+}
+
+/// The fonts available for text layout. Until the system fonts have
+/// been found, there is no collection, and text is laid out using
+/// estimated metrics without being drawn.
+#[derive(Clone)]
+pub struct Fonts {
+    /// The collection for `FontKey::DEFAULT`.
+    pub collection: Option<EqualRc<FontCollection>>,
+    /// The collections for other family lists, weights and styles, by
+    /// the key they were found for.
+    families: Rc<HashMap<FontKey, EqualRc<FontCollection>>>,
+    /// Every key which has been asked for, so that each is only looked
+    /// for once.
+    requested: Rc<RefCell<HashSet<FontKey>>>,
+    requests: mpsc::Sender<FontKey>,
+    /// Incremented whenever the collections change, so that memoized
+    /// text layouts can be invalidated.
+    pub generation: usize,
+    /// Shared by every layout engine using these fonts.
+    pub shaping: ShapingCache,
+}
+
+impl Fonts {
+    /// Returns the collection for a family list, weight and style.
+    /// Fonts which haven't been found yet are looked for in the
+    /// background, and the default collection is used until they have
+    /// been.
+    pub fn collection_for(&self, key: FontKey) -> Option<&EqualRc<FontCollection>> {
+        if key == FontKey::DEFAULT {
+            return self.collection.as_ref();
+        }
+        if let Some(collection) = self.families.get(&key) {
+            return Some(collection);
+        }
+        if self.requested.borrow_mut().insert(key) {
+            let _ = self.requests.send(key);
+        }
+        self.collection.as_ref()
+    }
+}
+
+impl fmt::Debug for Fonts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fonts")
+            .field("loaded", &self.collection.is_some())
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// Fonts shipped with the application, given to `Runtime::fonts`, so
+/// that it looks the same whatever fonts the system has installed.
+///
+/// ```rs
+/// let fonts = FontRegistry::new()
+///     .add_font_bytes("Inter", include_bytes!("../fonts/Inter-Regular.otf"))
+///     .add_font_bytes("Inter", include_bytes!("../fonts/Inter-Bold.otf"));
+/// Runtime::new(|| app!()).fonts(fonts).start();
+/// ```
+///
+/// Each font is registered under a family name, which `font_family`
+/// lists can then use like the name of an installed family. Registered
+/// families are used in place of installed ones with the same name.
+///
+/// Characters which none of the families in a `font_family` list have
+/// are looked for in the fallback families, in the order they were
+/// added, then in the system's sans-serif font, and finally in the
+/// system's emoji and CJK fonts.
+#[derive(Clone, Default)]
+pub struct FontRegistry {
+    families: HashMap<String, Vec<Arc<Vec<u8>>>>,
+    fallback: Vec<String>,
+}
+
+impl FontRegistry {
+    pub fn new() -> FontRegistry {
+        FontRegistry::default()
+    }
+
+    /// Adds a font file, in any format font-kit can load, to the family
+    /// `name`. A family can have several fonts with different weights
+    /// and styles, and the closest one to what the text asks for is
+    /// used.
+    pub fn add_font_bytes(mut self, name: impl Into<String>, bytes: &[u8]) -> FontRegistry {
+        self.families
+            .entry(name.into())
+            .or_insert_with(Vec::new)
+            .push(Arc::new(bytes.to_vec()));
+        self
+    }
+
+    /// Adds a family, registered or installed, to the end of the list
+    /// tried for characters the text's own families don't have.
+    pub fn add_fallback(mut self, name: impl Into<String>) -> FontRegistry {
+        self.fallback.push(name.into());
+        self
+    }
+
+    /// Loads every registered font, to find out its weight and style.
+    /// Fonts which can't be loaded are reported and left out.
+    fn load(self) -> HashMap<String, Vec<(Handle, Properties)>> {
+        let mut families = HashMap::new();
+        for (name, fonts) in self.families {
+            let mut faces = vec![];
+            for bytes in fonts {
+                let handle = Handle::from_memory(bytes, 0);
+                match handle.load() {
+                    Ok(font) => faces.push((handle, font.properties())),
+                    Err(err) => log::warn!("Failed to load font for {}: {:?}", name, err),
+                }
+            }
+            families.insert(name, faces);
+        }
+        families
+    }
+}
+
+/// Names a font for error messages, by its path if it has one.
+fn describe(handle: &Handle) -> String {
+    match handle {
+        Handle::Path { path, font_index } => format!("{}#{}", path.display(), font_index),
+        Handle::Memory { font_index, .. } => format!("<registered>#{}", font_index),
+    }
+}
+
+impl fmt::Debug for FontRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut families = self.families.keys().collect::<Vec<_>>();
+        families.sort();
+        f.debug_struct("FontRegistry")
+            .field("families", &families)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+/// Parses a family list like `"Fira Code", monospace`, where
+/// `serif`, `sans-serif`, `monospace`, `cursive` and `fantasy` stand
+/// for the system's fonts of that kind.
+fn parse_families(families: &str) -> Vec<FamilyName> {
+    families
+        .split(',')
+        .map(|name| name.trim().trim_matches(|ch| ch == '"' || ch == '\''))
+        .filter(|name| !name.is_empty())
+        .map(|name| match name {
+            "serif" => FamilyName::Serif,
+            "sans-serif" => FamilyName::SansSerif,
+            "monospace" => FamilyName::Monospace,
+            "cursive" => FamilyName::Cursive,
+            "fantasy" => FamilyName::Fantasy,
+            name => FamilyName::Title(name.to_owned()),
+        })
+        .collect()
+}
+
+/// The families to look in for text with the `font_family` list
+/// `families`, in order: the list itself, then the fallback chain,
+/// which ends with the default family and the system's emoji and CJK
+/// families. Text layout uses the first of them with a glyph for each
+/// character.
+fn family_chain(families: &str, fallback: &[String]) -> Vec<FamilyName> {
+    let fallback = fallback
+        .iter()
+        .map(|name| FamilyName::Title(name.clone()))
+        .chain(Some(FamilyName::SansSerif))
+        .chain(
+            SYSTEM_FALLBACK
+                .iter()
+                .map(|name| FamilyName::Title((*name).to_owned())),
+        );
+    let mut names = parse_families(families);
+    for name in fallback {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Finds the font closest to the key's weight and style for each
+/// family in its chain which is registered or installed. Families
+/// which aren't either are skipped.
+fn select_families(
+    source: &SystemSource,
+    registered: &HashMap<String, Vec<(Handle, Properties)>>,
+    fallback: &[String],
+    key: FontKey,
+) -> Vec<Handle> {
+    let names = family_chain(key.family, fallback);
+    let properties = key.properties();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let faces = match name {
+                FamilyName::Title(ref title) => registered.get(title),
+                _ => None,
+            };
+            match faces {
+                Some(faces) => {
+                    let candidates = faces.iter().map(|face| face.1).collect::<Vec<_>>();
+                    let index = matching::find_best_match(&candidates, &properties).ok()?;
+                    Some(faces[index].0.clone())
+                }
+                None => source.select_best_match(&[name], &properties).ok(),
+            }
+        })
+        .collect()
+}
+
+/// Finds fonts on a background thread, since enumerating the system
+/// fonts can take a noticeable amount of time on some platforms. The
+/// default fonts are found first, and then any others used by styles
+/// as layout asks for them.
+pub struct FontLoader {
+    rx: mpsc::Receiver<(FontKey, Vec<Handle>)>,
+    fonts: Fonts,
+}
+
+impl FontLoader {
+    /// Starts looking for fonts, among the registered ones and then
+    /// the system's. `notify` is called from the background thread
+    /// whenever some have been found, after which `poll` should be
+    /// called.
+    pub fn spawn(registry: FontRegistry, notify: impl Fn() + Send + 'static) -> FontLoader {
+        let (tx, rx) = mpsc::channel();
+        let (requests, requests_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let source = SystemSource::new();
+            let fallback = registry.fallback.clone();
+            let registered = registry.load();
+            let keys = Some(FontKey::DEFAULT).into_iter().chain(requests_rx);
+            for key in keys {
+                let handles = select_families(&source, &registered, &fallback, key);
+                if tx.send((key, handles)).is_err() {
+                    return;
+                }
+                notify();
+            }
+        });
+        FontLoader {
+            rx,
+            fonts: Fonts {
+                collection: None,
+                families: Rc::new(HashMap::new()),
+                requested: Rc::new(RefCell::new(HashSet::new())),
+                requests,
+                generation: 0,
+                shaping: ShapingCache::new(),
+            },
+        }
+    }
+
+    /// Loads any fonts which the background thread has found. Returns
+    /// true if the fonts changed, in which case layout needs to be
+    /// performed again.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((key, handles)) = self.rx.try_recv() {
+            self.load(key, handles);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Blocks until the fonts have been found, or the timeout expires.
+    /// Returns true if the fonts changed.
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        match self.rx.recv_timeout(timeout) {
+            Ok((key, handles)) => {
+                self.load(key, handles);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Fonts which were found but can't be loaded, such as files which
+    /// were removed or are corrupt, are reported and left out.
+    fn load(&mut self, key: FontKey, handles: Vec<Handle>) {
+        let mut collection = FontCollection::new();
+        for handle in handles {
+            match handle.load() {
+                Ok(font) => collection.add_family(FontFamily::new_from_font(font)),
+                Err(err) => log::warn!("Failed to load font {}: {:?}", describe(&handle), err),
+            }
+        }
+        let collection = EqualRc::new(collection);
+        if key == FontKey::DEFAULT {
+            self.fonts.collection = Some(collection);
+        } else {
+            Rc::make_mut(&mut self.fonts.families).insert(key, collection);
+        }
+        self.fonts.generation += 1;
+    }
+
+    pub fn fonts(&self) -> Fonts {
+        self.fonts.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{family_chain, parse_families, SYSTEM_FALLBACK};
+    use font_kit::family_name::FamilyName;
+
+    #[test]
+    fn parses_family_lists() {
+        assert_eq!(
+            parse_families("\"Fira Code\", Menlo,monospace"),
+            vec![
+                FamilyName::Title("Fira Code".to_owned()),
+                FamilyName::Title("Menlo".to_owned()),
+                FamilyName::Monospace,
+            ]
+        );
+        assert_eq!(parse_families("serif, "), vec![FamilyName::Serif]);
+    }
+
+    #[test]
+    fn falls_back_in_order() {
+        let chain = family_chain("Inter, sans-serif", &["Twemoji".to_owned()]);
+        assert_eq!(chain.len(), 3 + SYSTEM_FALLBACK.len());
+        assert_eq!(
+            chain[..3],
+            [
+                FamilyName::Title("Inter".to_owned()),
+                FamilyName::SansSerif,
+                FamilyName::Title("Twemoji".to_owned()),
+            ]
+        );
+        assert_eq!(
+            chain.last(),
+            Some(&FamilyName::Title(
+                SYSTEM_FALLBACK.last().unwrap().to_string()
+            ))
+        );
+    }
+}
@@ -1,10 +1,11 @@
 use super::{
-    block,
-    text::{TextLayoutInfo, TextState},
-    LayoutChild, LayoutText, LayoutTreeNode, LogicalSideOffsets, LogicalSize, RenderData,
+    finite, finite_size, layout_node,
+    text::{LineWidth, TextLayoutInfo, TextOptions, TextState},
+    DeviceScale, Fonts, LayoutChild, LayoutText, LayoutTreeNode, LogicalSideOffsets, LogicalSize,
+    RenderData,
 };
 use crate::dom::{element::DynamicNode, node::AnyNode, node::NodeRef};
-use crate::style::{ComputedValues, DisplayType};
+use crate::style::{ComputedValues, DisplayType, Float, TextDirection, TextOverflow};
 use crate::util::equal_rc::EqualRc;
 use euclid::{point2, size2};
 use moxie::*;
@@ -12,20 +13,148 @@ use moxie::*;
 #[derive(PartialEq)]
 enum InlineLayoutItem {
     Block(EqualRc<LayoutTreeNode>),
+    Float {
+        layout: EqualRc<LayoutTreeNode>,
+        side: Float,
+    },
     Text {
         text: EqualRc<TextLayoutInfo>,
         parent: AnyNode,
+        /// Where the text starts in the text content of `parent`.
+        offset: usize,
     },
 }
 
+/// A block moved to the side of its container, which the lines beside
+/// it are shortened to make room for.
+struct PlacedFloat {
+    side: Float,
+    top: f32,
+    bottom: f32,
+    /// The distance from the side of the container to the float's outer
+    /// edge.
+    inset: f32,
+    width: f32,
+    /// Where the float is in the container's children.
+    index: usize,
+}
+
+impl PlacedFloat {
+    fn covers(&self, y: f32) -> bool {
+        self.top <= y && y < self.bottom
+    }
+}
+
+/// How many lines text can take before the rest of it is cut off.
+#[derive(Clone, Copy, PartialEq)]
+struct LineClamp {
+    max_lines: usize,
+    ellipsis: bool,
+}
+
+impl LineClamp {
+    fn new(max_lines: Option<u32>, text_overflow: TextOverflow) -> Option<LineClamp> {
+        max_lines.map(|max_lines| LineClamp {
+            max_lines: max_lines.max(1) as usize,
+            ellipsis: text_overflow == TextOverflow::Ellipsis,
+        })
+    }
+}
+
 struct LayoutState {
     children: Vec<LayoutChild>,
+    max_width: f32,
     longest_line: f32,
     height: f32,
+    overflowed: bool,
+    floats: Vec<PlacedFloat>,
+    /// Floats met partway through a line, which go below it.
+    pending_floats: Vec<(EqualRc<LayoutTreeNode>, Float)>,
+    clamp: Option<LineClamp>,
+    lines: usize,
 }
 
 impl LayoutState {
+    /// How much room the floats at `y` take from the left and right of
+    /// the container.
+    fn edges(&self, y: f32) -> (f32, f32) {
+        let mut edges = (0.0f32, 0.0f32);
+        for float in self.floats.iter().filter(|float| float.covers(y)) {
+            let edge = float.inset + float.width;
+            match float.side {
+                Float::Right => edges.1 = edges.1.max(edge),
+                _ => edges.0 = edges.0.max(edge),
+            }
+        }
+        edges
+    }
+
+    /// Whether the line being filled is the last one there's room for.
+    fn on_last_line(&self) -> bool {
+        self.clamp
+            .map_or(false, |clamp| self.lines + 1 >= clamp.max_lines)
+    }
+
+    fn new_line(&self) -> LineState {
+        let (left, right) = self.edges(self.height);
+        LineState::new(left, self.max_width - right, right)
+    }
+
+    /// Finishes `line` and starts the next one below it.
+    fn break_line(&mut self, line: &mut LineState) {
+        let finished = std::mem::replace(line, LineState::new(0.0, 0.0, 0.0));
+        self.add_line(finished);
+        for (layout, side) in std::mem::replace(&mut self.pending_floats, vec![]) {
+            self.place_float(layout, side);
+        }
+        *line = self.new_line();
+    }
+
+    /// Puts a float beside the floats already on its side at the top of
+    /// the next line, or further down if there isn't room for it there.
+    fn place_float(&mut self, layout: EqualRc<LayoutTreeNode>, side: Float) {
+        let width = layout.size.width + layout.margin.horizontal();
+        let height = layout.size.height + layout.margin.vertical();
+        let mut top = self.height;
+        let (left, right) = loop {
+            let (left, right) = self.edges(top);
+            if left + right + width <= self.max_width {
+                break (left, right);
+            }
+            let next = self
+                .floats
+                .iter()
+                .filter(|float| float.covers(top))
+                .map(|float| float.bottom)
+                .fold(std::f32::INFINITY, f32::min);
+            if next.is_infinite() {
+                // Too wide for the container even on its own.
+                self.overflowed = true;
+                break (left, right);
+            }
+            top = next;
+        };
+        let inset = if side == Float::Right { right } else { left };
+        self.floats.push(PlacedFloat {
+            side,
+            top,
+            bottom: top + height,
+            inset,
+            width,
+            index: self.children.len(),
+        });
+        // Right floats are positioned once the container's width is
+        // known.
+        self.children.push(LayoutChild {
+            position: point2(inset, top),
+            layout,
+        });
+    }
+
     fn add_line(&mut self, line: LineState) {
+        if !line.line_items.is_empty() {
+            self.lines += 1;
+        }
         for item in line.line_items {
             let LineItem {
                 ascender,
@@ -39,41 +168,54 @@ impl LayoutState {
         }
 
         self.height += line.height;
-        self.longest_line = self.longest_line.max(line.x);
+        self.longest_line = self.longest_line.max(line.x + line.right_edge);
+        self.overflowed |= line.overflowed;
     }
 }
 
 // Turns into LayoutChild
+#[derive(Clone)]
 struct LineItem {
     ascender: f32,
     x: f32,
     layout: EqualRc<LayoutTreeNode>,
 }
 
+#[derive(Clone)]
 struct LineState {
     line_items: Vec<LineItem>,
+    /// Where the line ends, before any right floats beside it.
     max_width: f32,
+    /// The room taken by right floats beside the line.
+    right_edge: f32,
     x: f32,
     height: f32,
     ascender: f32,
+    overflowed: bool,
 }
 
 impl LineState {
-    fn new(max_width: f32) -> Self {
+    fn new(start: f32, max_width: f32, right_edge: f32) -> Self {
         LineState {
             max_width,
-            x: 0.0f32,
+            right_edge,
+            x: start,
             height: 0.0f32,
             ascender: 0.0f32,
+            overflowed: false,
             line_items: vec![],
         }
     }
 
     fn insert_block_item(&mut self, layout: EqualRc<LayoutTreeNode>) -> bool {
         let size = layout.size;
-        if self.x + size.width > self.max_width {
+        let fits = self.x + size.width <= self.max_width;
+        // Items too wide for a line of their own overflow it, since
+        // moving them to another line wouldn't help.
+        if !fits && !self.line_items.is_empty() {
             return false;
         }
+        self.overflowed |= !fits;
         self.line_items.push(LineItem {
             x: self.x,
             ascender: size.height,
@@ -84,42 +226,103 @@ impl LineState {
         true
     }
 
-    fn insert_text_item(&mut self, parent: AnyNode, state: &mut TextState) -> bool {
-        if let Some(line) = state.fill_line(self.max_width - self.x, self.line_items.is_empty()) {
+    /// `offset` is where the text starts in the text content of
+    /// `parent`, and is `None` for text which isn't part of it, like an
+    /// ellipsis.
+    fn insert_text_item(
+        &mut self,
+        parent: AnyNode,
+        state: &mut TextState,
+        offset: Option<usize>,
+        truncated: bool,
+    ) -> bool {
+        let width = if self.max_width.is_finite() {
+            LineWidth::Max(self.max_width - self.x)
+        } else {
+            LineWidth::Unconstrained
+        };
+        if let Some(line) = state.fill_line(width, self.line_items.is_empty()) {
+            let size = finite_size(size2(line.width, line.height), &*parent, "text size");
+            let ascender = finite(line.ascender, &*parent, "text ascender");
+            let mut clusters = line.clusters;
+            match offset {
+                Some(offset) => {
+                    for cluster in &mut clusters {
+                        cluster.start += offset;
+                        cluster.end += offset;
+                    }
+                }
+                None => clusters.clear(),
+            }
             self.line_items.push(LineItem {
-                ascender: line.ascender,
+                ascender,
                 x: self.x,
                 layout: EqualRc::new(LayoutTreeNode {
                     render: RenderData::Text {
                         text: LayoutText {
                             fragments: line.fragments,
+                            clusters,
+                            decorations: line.decorations,
                             size: line.text_size,
+                            truncated,
                         },
                         parent,
                     },
-                    size: size2(line.width, line.height),
+                    size,
                     margin: LogicalSideOffsets::default(),
                     children: vec![],
+                    overflowed: line.overflowed,
                 }),
             });
 
-            self.x += line.width;
-            self.height = self.height.max(line.height);
-            self.ascender = self.ascender.max(line.ascender);
+            self.x += size.width;
+            self.height = self.height.max(size.height);
+            self.ascender = self.ascender.max(ascender);
+            self.overflowed |= line.overflowed;
 
             true
         } else {
             false
         }
     }
+
+    /// Fills the rest of the last line there's room for with as much of
+    /// `state` as fits, leaving room for `ellipsis` after it if given.
+    fn insert_truncated_text(
+        &mut self,
+        parent: AnyNode,
+        state: &mut TextState,
+        offset: usize,
+        ellipsis: Option<&TextLayoutInfo>,
+    ) {
+        let ellipsis = match ellipsis {
+            Some(ellipsis) => ellipsis,
+            None => {
+                self.insert_text_item(parent, state, Some(offset), true);
+                return;
+            }
+        };
+        let width = ellipsis.intrinsic_widths().max;
+        self.max_width -= width;
+        self.insert_text_item(parent.clone(), state, Some(offset), false);
+        self.max_width += width;
+        // The ellipsis goes on the line even if a word too wide for it
+        // left no room.
+        let max_width = std::mem::replace(&mut self.max_width, std::f32::INFINITY);
+        self.insert_text_item(parent, &mut TextState::new(ellipsis), None, true);
+        self.max_width = max_width;
+        self.overflowed |= self.x > max_width;
+    }
 }
 
+#[illicit::from_env(fonts: &Fonts)]
 fn collect_inline_items(
     node: NodeRef,
     parent_values: &ComputedValues,
     max_size: LogicalSize,
     items: &mut Vec<InlineLayoutItem>,
 ) {
+    let mut offset = 0;
     for child in node.children() {
         topo::call! {
             {
@@ -128,76 +331,170 @@ fn collect_inline_items(
                         let values = node.computed_values().get().unwrap();
                         match values.display {
                             DisplayType::Block(ref block) => {
-                                let layout = block::layout_block(node, &values, block, max_size).into();
-                                items.push(InlineLayoutItem::Block(layout));
+                                let layout = layout_node(node, max_size);
+                                items.push(match block.float {
+                                    Float::None => InlineLayoutItem::Block(layout),
+                                    side => InlineLayoutItem::Float { layout, side },
+                                });
                             }
                             DisplayType::Inline(_) => {
                                 collect_inline_items(node, &values, max_size, items);
+                                node.layout_cell().mark_clean();
                             }
                         }
                     }
-                    DynamicNode::Text(text) => items.push(InlineLayoutItem::Text {
-                        text: memo!((text.to_owned(), parent_values.text_size.get()), move |(text, size)| {
-                            EqualRc::new(TextLayoutInfo::new(
-                                (*text).to_owned(),
-                                *size,
-                            ))
-                        }).into(),
-                        parent: node.to_owned(),
-                    })
+                    DynamicNode::Text(text) => {
+                        items.push(InlineLayoutItem::Text {
+                            text: memo!(
+                                (
+                                    text.to_owned(),
+                                    TextOptions::new(parent_values),
+                                    fonts.generation
+                                ),
+                                move |(text, options, _)| {
+                                    EqualRc::new(TextLayoutInfo::new((*text).to_owned(), *options))
+                                }
+                            ).into(),
+                            parent: node.to_owned(),
+                            offset,
+                        });
+                        offset += text.len();
+                    }
                 }
             }
         }
     }
 }
 
+/// Right-to-left content is laid out as if it were left-to-right, and
+/// then mirrored, so floats swap sides first to end up on the side they
+/// asked for.
+///
+/// Once `clamp` runs out of lines, the rest of the items are left out.
 fn calc_inline_layout(
     node: AnyNode,
     max_width: f32,
+    direction: TextDirection,
+    clamp: Option<LineClamp>,
     items: &[InlineLayoutItem],
 ) -> EqualRc<LayoutTreeNode> {
+    let rtl = direction == TextDirection::Rtl;
     let mut state = LayoutState {
         height: 0.0f32,
+        max_width,
         longest_line: 0.0f32,
         children: vec![],
+        overflowed: false,
+        floats: vec![],
+        pending_floats: vec![],
+        clamp,
+        lines: 0,
     };
 
-    let mut line = LineState::new(max_width);
+    let mut line = state.new_line();
 
-    for item in items {
+    'items: for item in items {
         match item {
             InlineLayoutItem::Block(layout) => {
                 if !line.insert_block_item(layout.clone().into()) {
-                    let old_line = std::mem::replace(&mut line, LineState::new(max_width));
-                    state.add_line(old_line);
+                    if state.on_last_line() {
+                        break 'items;
+                    }
+                    state.break_line(&mut line);
                     line.insert_block_item(layout.clone().into());
                 }
             }
-            InlineLayoutItem::Text { text, parent } => {
+            InlineLayoutItem::Float { layout, side } => {
+                let side = match *side {
+                    Float::Left if rtl => Float::Right,
+                    Float::Right if rtl => Float::Left,
+                    side => side,
+                };
+                if line.line_items.is_empty() {
+                    state.place_float(layout.clone(), side);
+                    line = state.new_line();
+                } else {
+                    state.pending_floats.push((layout.clone(), side));
+                }
+            }
+            InlineLayoutItem::Text {
+                text,
+                parent,
+                offset,
+            } => {
                 let mut text_state = TextState::new(&**text);
                 loop {
-                    line.insert_text_item(parent.clone(), &mut text_state);
+                    let before = clamp.map(|_| (line.clone(), text_state.clone()));
+                    line.insert_text_item(parent.clone(), &mut text_state, Some(*offset), false);
                     if text_state.finished() {
+                        if text_state.line_ended() {
+                            state.break_line(&mut line);
+                        }
                         break;
                     }
-                    let old_line = std::mem::replace(&mut line, LineState::new(max_width));
-                    state.add_line(old_line);
+                    match before {
+                        Some((before_line, before_state)) if state.on_last_line() => {
+                            // Fill the line again, knowing it's the last one.
+                            line = before_line;
+                            text_state = before_state;
+                            let ellipsis = if clamp.map_or(false, |clamp| clamp.ellipsis) {
+                                Some(text.ellipsis())
+                            } else {
+                                None
+                            };
+                            line.insert_truncated_text(
+                                parent.clone(),
+                                &mut text_state,
+                                *offset,
+                                ellipsis.as_ref(),
+                            );
+                            break 'items;
+                        }
+                        _ => state.break_line(&mut line),
+                    }
                 }
             }
         }
     }
-    state.add_line(line);
-    let size = size2(state.longest_line, state.height);
-    let children = state.children;
+    state.break_line(&mut line);
+    let mut width = state.longest_line;
+    let mut height = state.height;
+    for float in &state.floats {
+        width = width.max(float.inset + float.width);
+        height = height.max(float.bottom);
+    }
+    // Right-to-left lines start at the right edge of the space given.
+    if rtl && max_width.is_finite() {
+        width = width.max(max_width);
+    }
+    let mut children = state.children;
+    for float in state
+        .floats
+        .iter()
+        .filter(|float| float.side == Float::Right)
+    {
+        let position = &mut children[float.index].position;
+        position.x = width - float.inset - float.width;
+    }
+    if rtl {
+        for child in &mut children {
+            let layout = &child.layout;
+            child.position.x = width - child.position.x - layout.size.width;
+            child.position.x -= layout.margin.horizontal();
+        }
+    }
+    let size = finite_size(size2(width, height), &*node, "size");
 
     EqualRc::new(LayoutTreeNode {
         render: RenderData::Node(node),
         margin: LogicalSideOffsets::default(),
         size,
         children,
+        overflowed: state.overflowed,
     })
 }
 
+#[illicit::from_env(scale: &DeviceScale)]
 pub fn layout_inline(
     node: NodeRef,
     values: &ComputedValues,
@@ -207,32 +504,49 @@ pub fn layout_inline(
 
     collect_inline_items(node, values, max_size, &mut items);
 
-    memo!((node.to_owned(), max_size.width, items), |(
-        node,
-        max_width,
-        items,
-    )| {
-        calc_inline_layout(node.clone(), *max_width, &items[..])
-    })
+    memo!(
+        (
+            node.to_owned(),
+            max_size.width,
+            values.text_direction,
+            values.max_lines,
+            values.text_overflow,
+            items,
+            scale.get()
+        ),
+        |(node, max_width, direction, max_lines, text_overflow, items, _)| {
+            let clamp = LineClamp::new(*max_lines, *text_overflow);
+            calc_inline_layout(node.clone(), *max_width, *direction, clamp, &items[..])
+        }
+    )
 }
 
+#[illicit::from_env(scale: &DeviceScale, fonts: &Fonts)]
 pub fn layout_text(
     node: AnyNode,
     text: &str,
     max_width: f32,
     values: &ComputedValues,
 ) -> EqualRc<LayoutTreeNode> {
-    let size = values.text_size;
-    memo!((max_width, text.to_owned(), node, size), |(
-        max_width,
-        text,
-        node,
-        size,
-    )| {
-        let item = InlineLayoutItem::Text {
-            text: EqualRc::new(TextLayoutInfo::new(text.to_owned(), size.get())),
-            parent: node.clone(),
-        };
-        calc_inline_layout(node.clone(), *max_width, &[item])
-    })
+    memo!(
+        (
+            max_width,
+            text.to_owned(),
+            node,
+            TextOptions::new(values),
+            values.max_lines,
+            values.text_overflow,
+            scale.get(),
+            fonts.generation
+        ),
+        |(max_width, text, node, options, max_lines, text_overflow, _, _)| {
+            let item = InlineLayoutItem::Text {
+                text: EqualRc::new(TextLayoutInfo::new(text.to_owned(), *options)),
+                parent: node.clone(),
+                offset: 0,
+            };
+            let clamp = LineClamp::new(*max_lines, *text_overflow);
+            calc_inline_layout(node.clone(), *max_width, options.direction, clamp, &[item])
+        }
+    )
 }
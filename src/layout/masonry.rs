@@ -0,0 +1,82 @@
+//! Masonry layout, which fills columns of equal width from the top,
+//! putting each child in whichever column is shortest so far. This
+//! keeps galleries and dashboards of children with different heights
+//! free of the gaps rows would leave.
+
+use super::custom::{Constraints, CustomChild, LayoutDelegate};
+
+/// Lays out children in the given number of columns.
+pub struct Masonry(pub u32);
+
+impl LayoutDelegate for Masonry {
+    fn layout(&self, constraints: Constraints, children: &mut [CustomChild]) -> (f32, f32) {
+        let count = self.0.max(1) as usize;
+        // Without a width to divide up, columns are as wide as the
+        // widest child wants to be.
+        let width = if constraints.max_width.is_finite() {
+            constraints.max_width / count as f32
+        } else {
+            children
+                .iter()
+                .map(|child| child.intrinsic_widths().1)
+                .fold(0.0, f32::max)
+        };
+
+        let heights = children
+            .iter_mut()
+            .map(|child| child.measure(width, std::f32::INFINITY).1)
+            .collect::<Vec<_>>();
+        let (places, height) = place_in_columns(&heights, count);
+        for (child, (column, top)) in children.iter_mut().zip(places) {
+            child.place(column as f32 * width, top);
+        }
+        (width * count as f32, height)
+    }
+}
+
+/// Puts items of the given heights into `count` columns, each into the
+/// shortest column so far, or the leftmost of the shortest. Returns the
+/// column and top of each item, and the height of the tallest column.
+fn place_in_columns(heights: &[f32], count: usize) -> (Vec<(usize, f32)>, f32) {
+    let mut columns = vec![0.0f32; count];
+    let places = heights
+        .iter()
+        .map(|height| {
+            let (column, top) = columns.iter().cloned().enumerate().fold(
+                (0, std::f32::INFINITY),
+                |shortest, (column, top)| {
+                    if top < shortest.1 {
+                        (column, top)
+                    } else {
+                        shortest
+                    }
+                },
+            );
+            columns[column] = top + height;
+            (column, top)
+        })
+        .collect();
+    (places, columns.into_iter().fold(0.0, f32::max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::place_in_columns;
+
+    #[test]
+    fn fills_the_shortest_column() {
+        let (places, height) = place_in_columns(&[30.0, 10.0, 20.0, 5.0, 40.0], 3);
+        assert_eq!(
+            places,
+            vec![(0, 0.0), (1, 0.0), (2, 0.0), (1, 10.0), (1, 15.0)]
+        );
+        assert_eq!(height, 55.0);
+    }
+
+    #[test]
+    fn handles_more_columns_than_items() {
+        let (places, height) = place_in_columns(&[10.0], 4);
+        assert_eq!(places, vec![(0, 0.0)]);
+        assert_eq!(height, 10.0);
+    }
+}
@@ -0,0 +1,183 @@
+use super::{
+    text::{TextLayoutInfo, TextOptions},
+    Fonts,
+};
+use crate::dom::{element::DynamicNode, node::NodeRef};
+use crate::style::{BlockLength, BlockValues, ComputedValues, Direction, DisplayType, Overflow};
+use moxie::*;
+
+/// The range of widths a node can usefully be laid out at, including
+/// its margins.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IntrinsicWidths {
+    /// The narrowest the node can be without its content overflowing,
+    /// which for text is the width of its longest word.
+    pub min: f32,
+    /// The width the node takes when it has as much room as it wants,
+    /// with no text wrapped.
+    pub max: f32,
+}
+
+impl IntrinsicWidths {
+    fn beside(self, other: IntrinsicWidths) -> IntrinsicWidths {
+        IntrinsicWidths {
+            min: self.min + other.min,
+            max: self.max + other.max,
+        }
+    }
+
+    fn stacked(self, other: IntrinsicWidths) -> IntrinsicWidths {
+        IntrinsicWidths {
+            min: self.min.max(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    fn grow(self, extra: f32) -> IntrinsicWidths {
+        IntrinsicWidths {
+            min: self.min + extra,
+            max: self.max + extra,
+        }
+    }
+}
+
+/// Measures a node which is a box of its own, without laying it out.
+pub fn measure_node(node: NodeRef) -> IntrinsicWidths {
+    let values = node.computed_values().get().unwrap();
+    match values.display {
+        DisplayType::Block(ref block) => measure_block(node, &values, block),
+        DisplayType::Inline(_) => measure_inline(node, &values),
+    }
+}
+
+fn measure_block(node: NodeRef, values: &ComputedValues, block: &BlockValues) -> IntrinsicWidths {
+    let mut content = IntrinsicWidths::default();
+    for child in node.children() {
+        topo::call! {
+            {
+                let child = match child {
+                    DynamicNode::Node(node) => measure_node(node),
+                    DynamicNode::Text(text) => measure_text(text, values),
+                };
+                content = match (block.columns, block.direction) {
+                    (Some(_), _) | (None, Direction::Vertical) => content.stacked(child),
+                    (None, Direction::Horizontal) => content.beside(child),
+                };
+            }
+        }
+    }
+    // Masonry columns are each as wide as the widest child.
+    if let Some(columns) = block.columns {
+        let count = columns.max(1) as f32;
+        content = IntrinsicWidths {
+            min: content.min * count,
+            max: content.max * count,
+        };
+    }
+    // Horizontal scroll containers and zoom containers can be narrower
+    // than their content.
+    let shrinks = match block.overflow {
+        Overflow::Visible => false,
+        Overflow::Scroll => block.direction == Direction::Horizontal,
+        Overflow::Zoom => true,
+    };
+    if shrinks {
+        content.min = 0.0;
+    }
+    let mut widths =
+        content.grow(block.padding.horizontal() + values.border_thickness.horizontal());
+
+    // Percentages depend on the width the node is given, so only fixed
+    // lengths are known while measuring.
+    let fixed = |length: Option<BlockLength>| length.and_then(|l| l.resolve(std::f32::INFINITY));
+    if let Some(width) = fixed(block.width) {
+        widths = IntrinsicWidths {
+            min: width,
+            max: width,
+        };
+    }
+    if let Some(max) = fixed(block.max_width) {
+        widths.min = widths.min.min(max);
+        widths.max = widths.max.min(max);
+    }
+    if let Some(min) = fixed(block.min_width) {
+        widths.min = widths.min.max(min);
+        widths.max = widths.max.max(min);
+    }
+    widths.grow(block.margin.horizontal())
+}
+
+/// Inline content can wrap between any of its items, and fits on one
+/// line when it has room for all of them.
+fn measure_inline(node: NodeRef, values: &ComputedValues) -> IntrinsicWidths {
+    let mut widths = IntrinsicWidths::default();
+    for child in node.children() {
+        topo::call! {
+            {
+                let child = match child {
+                    DynamicNode::Node(node) => measure_node(node),
+                    DynamicNode::Text(text) => measure_text(text, values),
+                };
+                widths = IntrinsicWidths {
+                    min: widths.min.max(child.min),
+                    max: widths.max + child.max,
+                };
+            }
+        }
+    }
+    widths
+}
+
+#[illicit::from_env(fonts: &Fonts)]
+pub fn measure_text(text: &str, values: &ComputedValues) -> IntrinsicWidths {
+    memo!(
+        (text.to_owned(), TextOptions::new(values), fonts.generation),
+        |(text, options, _)| TextLayoutInfo::new(text.to_owned(), *options).intrinsic_widths()
+    )
+}
+
+/// Shares `available` width between items placed side by side, or
+/// returns `None` if every item fits at its max width. Each item gets
+/// its min width, and the space left beyond that is shared in
+/// proportion to how much more each item would take, so that text
+/// wraps evenly across the items rather than in the first one to run
+/// out of room.
+pub fn distribute(items: &[IntrinsicWidths], available: f32) -> Option<Vec<f32>> {
+    let total = items
+        .iter()
+        .fold(IntrinsicWidths::default(), |total, item| {
+            total.beside(*item)
+        });
+    if total.max <= available {
+        return None;
+    }
+    if total.min >= available {
+        return Some(items.iter().map(|item| item.min).collect());
+    }
+    let spare = available - total.min;
+    let wanted = total.max - total.min;
+    Some(
+        items
+            .iter()
+            .map(|item| item.min + (item.max - item.min) * spare / wanted)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{distribute, IntrinsicWidths};
+
+    fn widths(min: f32, max: f32) -> IntrinsicWidths {
+        IntrinsicWidths { min, max }
+    }
+
+    #[test]
+    fn shares_space_between_items() {
+        let items = [widths(20.0, 100.0), widths(40.0, 60.0)];
+        assert_eq!(distribute(&items, 200.0), None);
+        // 40 of the 100 pixels wanted beyond the min widths are left.
+        assert_eq!(distribute(&items, 100.0), Some(vec![52.0, 48.0]));
+        assert_eq!(distribute(&items, 30.0), Some(vec![20.0, 40.0]));
+    }
+}
@@ -1,27 +1,107 @@
 //! This module handles creating the layout tree, which includes
 //! arranging elements and performing text layout.
 
-use crate::dom::node::AnyNode;
+use crate::dom::element::DynamicNode;
+use crate::dom::node::{AnyNode, AnyNodeData, NodeRef};
 use crate::dom::{Node, Window};
+use crate::perf::{self, Counter};
 use crate::style::DisplayType;
 use crate::util::equal_rc::EqualRc;
-use euclid::{Length, Point2D, SideOffsets2D, Size2D};
-use font_kit::family_name::FamilyName;
-use font_kit::properties::Properties;
-use font_kit::source::SystemSource;
+use euclid::{Length, Point2D, Rect, Scale, SideOffsets2D, Size2D, Vector2D};
 use moxie::embed::Runtime;
-use moxie::*;
-use skribo::{FontCollection, FontFamily, FontRef};
+use skribo::FontRef;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 mod block;
+mod custom;
+mod fonts;
 mod inline;
+mod masonry;
+mod measure;
 mod text;
 
+pub use custom::{Constraints, CustomChild, Delegate, LayoutDelegate};
+pub use fonts::{FontLoader, FontRegistry, Fonts};
+#[cfg(feature = "bench")]
+pub use text::shape_lines;
+
 pub struct LogicalPixel;
 pub type LogicalPoint = Point2D<f32, LogicalPixel>;
 pub type LogicalSize = Size2D<f32, LogicalPixel>;
 pub type LogicalLength = Length<f32, LogicalPixel>;
 pub type LogicalSideOffsets = SideOffsets2D<f32, LogicalPixel>;
+pub type LogicalRect = Rect<f32, LogicalPixel>;
+pub type LogicalVector = Vector2D<f32, LogicalPixel>;
+/// The indices into `children` leading from a layout node down to one
+/// of its descendants.
+pub type NodePath = Vec<usize>;
+
+/// The physical pixels of the display being rendered to.
+pub struct DevicePixel;
+pub type DeviceScale = Scale<f32, LogicalPixel, DevicePixel>;
+
+/// Rounds a logical length so that it covers a whole number of device
+/// pixels, which keeps text baselines from landing between pixels.
+pub fn snap_to_device(value: f32, scale: DeviceScale) -> f32 {
+    (value * scale.get()).round() / scale.get()
+}
+
+/// Describes a node for error messages, along with where its style was
+/// defined.
+pub(crate) fn describe(node: &dyn AnyNodeData) -> String {
+    match node.style() {
+        Some(style) => {
+            let (file, line) = style.file();
+            format!(
+                "<{} style={}> (defined at {}:{})",
+                node.name(),
+                style.name(),
+                file,
+                line
+            )
+        }
+        None => format!("<{}>", node.name()),
+    }
+}
+
+/// Checks a length computed during layout. NaN or infinite sizes would
+/// otherwise be carried all the way to the renderer, which then draws
+/// nothing at all, so debug builds panic with the node they came from.
+/// Release builds use zero instead.
+pub(crate) fn finite(value: f32, node: &dyn AnyNodeData, what: &str) -> f32 {
+    if value.is_finite() {
+        return value;
+    }
+    if cfg!(debug_assertions) {
+        panic!(
+            "layout of {} produced a {} of {}",
+            describe(node),
+            what,
+            value
+        );
+    }
+    0.0
+}
+
+pub(crate) fn finite_size(size: LogicalSize, node: &dyn AnyNodeData, what: &str) -> LogicalSize {
+    Size2D::new(
+        finite(size.width, node, what),
+        finite(size.height, node, what),
+    )
+}
+
+/// Like `finite`, for the space available to a node's children, which
+/// is infinite inside of scroll containers.
+pub(crate) fn not_nan(value: f32, node: &dyn AnyNodeData, what: &str) -> f32 {
+    if value.is_infinite() {
+        value
+    } else {
+        finite(value, node, what)
+    }
+}
 
 /// Each edge of the layout tree contains information on the positions
 /// of the child elements, since elements are positioned relative to
@@ -41,11 +121,37 @@ pub struct TextFragment {
     pub glyphs: Vec<Glyph>,
 }
 
+/// Where one character of a line of text is, which the renderer uses to
+/// find the character under the pointer and to draw selections.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cluster {
+    /// Where the character starts in the text content of the node the
+    /// text is in, which is all of its text children joined together.
+    pub start: usize,
+    pub end: usize,
+    /// The horizontal extent of the character, relative to the same
+    /// origin as the glyphs.
+    pub left: f32,
+    pub right: f32,
+    /// Set for characters in right-to-left runs, which start on the
+    /// right.
+    pub rtl: bool,
+}
+
 /// Information passed to the renderer for rendering text.
 pub struct LayoutText {
     pub fragments: Vec<TextFragment>,
+    /// The characters on the line from left to right. Text which isn't
+    /// part of the node's content, like an ellipsis, has none.
+    pub clusters: Vec<Cluster>,
+    /// Underlines and other lines drawn along the text, relative to
+    /// the same origin as the glyphs.
+    pub decorations: Vec<LogicalRect>,
     /// The text size of the text.
     pub size: f32,
+    /// Set on the last line of text cut off by `max_lines`, so that the
+    /// full text can be shown some other way, like in a tooltip.
+    pub truncated: bool,
 }
 
 pub enum RenderData {
@@ -54,62 +160,468 @@ pub enum RenderData {
 }
 
 /// One node in the layout tree, which corresponds n:1 with DOM nodes.
+///
+/// Nodes are reference counted rather than allocated from a per-frame
+/// arena. DOM nodes keep the layouts of unchanged subtrees alive between
+/// frames, and relayout and the renderer's subtree cache both compare
+/// nodes by `EqualRc` identity to skip work, so nodes need to outlive
+/// the frame which created them.
+///
+/// That reuse also keeps allocation low: a relayout after one element
+/// changes only allocates nodes for it and its ancestors, which
+/// `perf::counters` reports as `nodes_laid_out`. The `layout after one
+/// change` benchmarks measure that case, and `layout from scratch` the
+/// one an arena would help most with.
 pub struct LayoutTreeNode {
     /// The computed size of the node.
     pub size: LogicalSize,
     pub margin: LogicalSideOffsets,
     pub render: RenderData,
     pub children: Vec<LayoutChild>,
+    /// Set when the node's content didn't fit in it, such as a word
+    /// wider than its container, or children larger than a fixed size.
+    pub overflowed: bool,
+}
+
+impl LayoutTreeNode {
+    /// The size of the area covered by the node's children, including
+    /// their margins.
+    pub fn content_extent(&self) -> LogicalSize {
+        self.children
+            .iter()
+            .fold(LogicalSize::zero(), |extent, child| {
+                let layout = &child.layout;
+                let right = child.position.x + layout.size.width + layout.margin.horizontal();
+                let bottom = child.position.y + layout.size.height + layout.margin.vertical();
+                Size2D::new(extent.width.max(right), extent.height.max(bottom))
+            })
+    }
+
+    /// Finds every node containing `point`, which is relative to this
+    /// node, innermost first. Each is returned with its path from this
+    /// node and the point relative to the node's own origin.
+    ///
+    /// Scroll positions and zoom aren't part of layout, so this finds
+    /// nodes where they would be if nothing were scrolled or zoomed.
+    /// The renderer uses `hit_test_scrolled` instead.
+    pub fn hit_test(&self, point: LogicalPoint) -> Vec<(NodePath, LogicalPoint)> {
+        self.hit_test_scrolled(point, &|_, point| point)
+    }
+
+    /// Like `hit_test`, with `to_children` converting a point relative
+    /// to each node into the space its children are positioned in,
+    /// which differs when the node is scrolled or zoomed.
+    pub fn hit_test_scrolled(
+        &self,
+        point: LogicalPoint,
+        to_children: &dyn Fn(&AnyNode, LogicalPoint) -> LogicalPoint,
+    ) -> Vec<(NodePath, LogicalPoint)> {
+        let mut hits = vec![];
+        self.collect_hits(point, to_children, &mut vec![], &mut hits);
+        hits
+    }
+
+    fn collect_hits(
+        &self,
+        point: LogicalPoint,
+        to_children: &dyn Fn(&AnyNode, LogicalPoint) -> LogicalPoint,
+        path: &mut NodePath,
+        hits: &mut Vec<(NodePath, LogicalPoint)>,
+    ) {
+        if let RenderData::Node(ref node) = self.render {
+            let inner = to_children(node, point);
+            for (index, child) in self.children.iter().enumerate() {
+                path.push(index);
+                let point = inner - child.position.to_vector();
+                child.layout.collect_hits(point, to_children, path, hits);
+                path.pop();
+            }
+        }
+        if LogicalRect::new(LogicalPoint::origin(), self.size).contains(point) {
+            hits.push((path.clone(), point));
+        }
+    }
+
+    /// Follows a path returned by `hit_test`.
+    pub fn descendant(&self, path: &[usize]) -> Option<&LayoutTreeNode> {
+        path.iter().try_fold(self, |node, &index| {
+            node.children.get(index).map(|child| &*child.layout)
+        })
+    }
+}
+
+/// Everything a node's layout depends on apart from its subtree. Each
+/// engine lays nodes out at its own sizes, so layouts are only reused
+/// by the engine which created them.
+#[derive(Clone, Copy, PartialEq)]
+struct CacheKey {
+    engine: usize,
+    parent_size: LogicalSize,
+    scale: f32,
+    fonts_generation: usize,
+}
+
+/// The most layouts a node keeps, which is enough for a window's own
+/// engine and the two used to find its minimum size.
+const MAX_CACHED_LAYOUTS: usize = 4;
+
+/// Holds the last layout of a node by each engine, so that subtrees in
+/// which nothing has changed can be skipped without visiting their
+/// descendants. A node is dirty when it's created, and when the style
+/// engine finds that the computed values of it or any of its
+/// descendants changed, which throws away every engine's layout.
+pub struct LayoutCell {
+    dirty: Cell<bool>,
+    /// At most one layout per engine, oldest first, and at most
+    /// `MAX_CACHED_LAYOUTS` in all. This is searched rather than hashed
+    /// since it's so short.
+    last: RefCell<Vec<(CacheKey, EqualRc<LayoutTreeNode>)>>,
+}
+
+impl Default for LayoutCell {
+    fn default() -> LayoutCell {
+        LayoutCell {
+            dirty: Cell::new(true),
+            last: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl LayoutCell {
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.get()
+    }
+
+    pub fn mark_dirty(&self) {
+        self.dirty.set(true);
+        self.last.borrow_mut().clear();
+    }
+
+    /// Marks a node which is laid out as part of its parent, rather
+    /// than as a box of its own, as up to date. Nothing is cached for
+    /// it, but it would otherwise keep its ancestors dirty.
+    fn mark_clean(&self) {
+        self.dirty.set(false);
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<EqualRc<LayoutTreeNode>> {
+        self.last
+            .borrow()
+            .iter()
+            .find(|(last_key, _)| last_key == key)
+            .map(|(_, layout)| layout.clone())
+    }
+
+    fn set(&self, key: CacheKey, layout: EqualRc<LayoutTreeNode>) {
+        let mut last = self.last.borrow_mut();
+        last.retain(|(last_key, _)| last_key.engine != key.engine);
+        if last.len() >= MAX_CACHED_LAYOUTS {
+            last.remove(0);
+        }
+        last.push((key, layout));
+        self.dirty.set(false);
+    }
+
+    /// Drops the layout by an engine which is being dropped.
+    fn forget(&self, engine: usize) {
+        self.last
+            .borrow_mut()
+            .retain(|(last_key, _)| last_key.engine != engine);
+    }
+}
+
+/// Drops the layouts an engine left in a tree, so that they don't keep
+/// old layout trees alive after it's gone.
+fn forget_layouts(node: NodeRef, engine: usize) {
+    node.layout_cell().forget(engine);
+    for child in node.children() {
+        if let DynamicNode::Node(child) = child {
+            forget_layouts(child, engine);
+        }
+    }
+}
+
+/// Identifies the engine performing the current layout.
+#[derive(Clone, Copy, Debug)]
+struct EngineId(usize);
+
+static NEXT_ENGINE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Lays out a node which is a box of its own, reusing its last layout
+/// if nothing in its subtree has changed since.
+#[illicit::from_env(engine: &EngineId, scale: &DeviceScale, fonts: &Fonts)]
+fn layout_node(node: NodeRef, parent_size: LogicalSize) -> EqualRc<LayoutTreeNode> {
+    let key = CacheKey {
+        engine: engine.0,
+        parent_size,
+        scale: scale.get(),
+        fonts_generation: fonts.generation,
+    };
+    let cell = node.layout_cell();
+    if let Some(layout) = cell.get(&key) {
+        perf::count(Counter::LayoutCacheHits, 1);
+        return layout;
+    }
+    perf::count(Counter::NodesLaidOut, 1);
+    let values = node.computed_values().get().unwrap();
+    let layout = match values.display {
+        DisplayType::Block(ref block) => match node.layout_delegate() {
+            Some(delegate) => {
+                custom::layout_custom(node, &values, block, &*delegate.0, parent_size)
+            }
+            None => match block.columns {
+                Some(columns) => {
+                    let masonry = masonry::Masonry(columns);
+                    custom::layout_custom(node, &values, block, &masonry, parent_size)
+                }
+                None => block::layout_block(node, &values, block, parent_size),
+            },
+        },
+        DisplayType::Inline(_) => inline::layout_inline(node, &values, parent_size),
+    };
+    cell.set(key, layout.clone());
+    layout
+}
+
+/// Marks the last node in the tree as changed, along with each of its
+/// ancestors, as the style engine does when one element's style
+/// changes.
+#[cfg(feature = "bench")]
+pub fn mark_last_changed(node: NodeRef) {
+    node.layout_cell().mark_dirty();
+    let last = node
+        .children()
+        .filter_map(|child| match child {
+            DynamicNode::Node(node) => Some(node),
+            DynamicNode::Text(_) => None,
+        })
+        .last();
+    if let Some(last) = last {
+        mark_last_changed(last);
+    }
 }
 
 /// Used to build the layout tree, with internal caching for
 /// performance.
 pub struct LayoutEngine {
+    id: EngineId,
     runtime: Runtime<fn() -> EqualRc<LayoutTreeNode>>,
+    fonts: Rc<RefCell<FontLoader>>,
+    /// The font generation this engine last saw, since other windows
+    /// may be the ones to poll the shared loader.
+    fonts_generation: usize,
+    /// The tree laid out last, whose cached layouts are dropped along
+    /// with the engine.
+    last_window: Option<Node<Window>>,
 }
 
 impl LayoutEngine {
-    pub fn new() -> LayoutEngine {
+    /// Create a layout engine using fonts from a loader, which may be
+    /// shared with other windows.
+    pub fn new(fonts: Rc<RefCell<FontLoader>>) -> LayoutEngine {
         LayoutEngine {
+            id: EngineId(NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed)),
             runtime: Runtime::new(LayoutEngine::run_layout),
+            fonts,
+            fonts_generation: 0,
+            last_window: None,
         }
     }
 
+    /// Load fonts which have been found since the last call. Returns
+    /// true if layout needs to be performed again.
+    pub fn poll_fonts(&mut self) -> bool {
+        self.fonts.borrow_mut().poll();
+        self.check_fonts()
+    }
+
+    /// Waits up to `timeout` for the fonts to be found, so that the
+    /// first frame can include text.
+    pub fn wait_for_fonts(&mut self, timeout: Duration) -> bool {
+        self.fonts.borrow_mut().wait(timeout);
+        self.check_fonts()
+    }
+
+    fn check_fonts(&mut self) -> bool {
+        let generation = self.fonts.borrow().fonts().generation;
+        let changed = generation != self.fonts_generation;
+        self.fonts_generation = generation;
+        changed
+    }
+
     #[illicit::from_env(node: &Node<Window>, size: &LogicalSize)]
     fn run_layout() -> EqualRc<LayoutTreeNode> {
-        let collection = once!(|| {
-            let mut collection = FontCollection::new();
-            let source = SystemSource::new();
-            let font = source
-                .select_best_match(&[FamilyName::SansSerif], &Properties::new())
-                .unwrap()
-                .load()
-                .unwrap();
-            collection.add_family(FontFamily::new_from_font(font));
-
-            EqualRc::new(collection)
-        });
-
-        illicit::child_env!(EqualRc<FontCollection> => collection).enter(|| {
-            topo::call!({
-                let values = node.computed_values().get().unwrap();
-                match values.display {
-                    DisplayType::Block(ref block) => {
-                        block::layout_block(node.into(), &values, block, *size)
-                    }
-                    DisplayType::Inline(_) => inline::layout_inline(node.into(), &values, *size),
-                }
-            },)
-        })
+        topo::call!({ layout_node(node.into(), *size) },)
     }
 
-    /// Perform a layout step based on the new DOM and content size, and
-    /// return a fresh layout tree.
-    pub fn layout(&mut self, node: Node<Window>, size: LogicalSize) -> EqualRc<LayoutTreeNode> {
+    /// Perform a layout step based on the new DOM, content size and
+    /// device scale factor, and return a fresh layout tree.
+    pub fn layout(
+        &mut self,
+        node: Node<Window>,
+        size: LogicalSize,
+        scale: DeviceScale,
+    ) -> EqualRc<LayoutTreeNode> {
+        debug_assert!(
+            size.width.is_finite() && size.height.is_finite() && scale.get().is_finite(),
+            "window laid out at {:?} with a scale of {}",
+            size,
+            scale.get()
+        );
+        self.last_window = Some(node.clone());
         illicit::child_env! (
             Node<Window> => node,
-            LogicalSize => size
+            LogicalSize => size,
+            DeviceScale => scale,
+            Fonts => self.fonts.borrow().fonts(),
+            EngineId => self.id
         )
         .enter(|| topo::call!({ self.runtime.run_once() },))
     }
 }
+
+impl Drop for LayoutEngine {
+    fn drop(&mut self) {
+        if let Some(window) = self.last_window.take() {
+            forget_layouts((&window).into(), self.id.0);
+        }
+    }
+}
+
+/// Finds the smallest size a window's content fits in, by laying it out
+/// with no space along each axis in turn. Each axis has its own engine,
+/// so that they don't evict each other's cached layouts, or those of
+/// the engine used for rendering.
+pub struct MinContentEngine {
+    narrow: LayoutEngine,
+    short: LayoutEngine,
+}
+
+impl MinContentEngine {
+    pub fn new(fonts: Rc<RefCell<FontLoader>>) -> MinContentEngine {
+        MinContentEngine {
+            narrow: LayoutEngine::new(fonts.clone()),
+            short: LayoutEngine::new(fonts),
+        }
+    }
+
+    /// The minimum width is found at the window's current height, and
+    /// the minimum height at its current width, so that text wrapped at
+    /// the narrowest width doesn't inflate the minimum height.
+    pub fn min_size(
+        &mut self,
+        node: Node<Window>,
+        size: LogicalSize,
+        scale: DeviceScale,
+    ) -> LogicalSize {
+        let narrow = self
+            .narrow
+            .layout(node.clone(), Size2D::new(0.0, size.height), scale);
+        let short = self.short.layout(node, Size2D::new(size.width, 0.0), scale);
+        Size2D::new(narrow.content_extent().width, short.content_extent().height)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CacheKey, FontLoader, FontRegistry, LayoutCell, LayoutEngine, LayoutTreeNode, RenderData,
+        MAX_CACHED_LAYOUTS,
+    };
+    use crate::dom::node::AnyNodeData;
+    use crate::dom::{Node, View, Window};
+    use crate::style::{Interactions, ScrollPositions, StyleEngine};
+    use crate::util::equal_rc::EqualRc;
+    use euclid::{size2, Scale, SideOffsets2D};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn key(engine: usize, width: f32) -> CacheKey {
+        CacheKey {
+            engine,
+            parent_size: size2(width, 100.0),
+            scale: 1.0,
+            fonts_generation: 0,
+        }
+    }
+
+    fn layout() -> EqualRc<LayoutTreeNode> {
+        EqualRc::new(LayoutTreeNode {
+            size: size2(10.0, 10.0),
+            margin: SideOffsets2D::zero(),
+            render: RenderData::Node(Node::new(View::default(), vec![]).into()),
+            children: vec![],
+            overflowed: false,
+        })
+    }
+
+    #[test]
+    fn reuses_layouts_with_the_same_key() {
+        let cell = LayoutCell::default();
+        assert!(cell.is_dirty());
+        assert!(cell.get(&key(0, 100.0)).is_none());
+
+        let first = layout();
+        cell.set(key(0, 100.0), first.clone());
+        assert!(!cell.is_dirty());
+        assert!(cell.get(&key(0, 100.0)) == Some(first));
+        assert!(cell.get(&key(0, 50.0)).is_none());
+    }
+
+    #[test]
+    fn keeps_a_layout_per_engine() {
+        let cell = LayoutCell::default();
+        let (main, narrow) = (layout(), layout());
+        cell.set(key(0, 100.0), main.clone());
+        cell.set(key(1, 0.0), narrow.clone());
+        assert!(cell.get(&key(0, 100.0)) == Some(main));
+        assert!(cell.get(&key(1, 0.0)) == Some(narrow));
+
+        // A new layout by the same engine replaces its last one.
+        let resized = layout();
+        cell.set(key(0, 50.0), resized.clone());
+        assert!(cell.get(&key(0, 100.0)).is_none());
+        assert!(cell.get(&key(0, 50.0)) == Some(resized));
+
+        cell.mark_dirty();
+        assert!(cell.get(&key(0, 50.0)).is_none());
+        assert!(cell.get(&key(1, 0.0)).is_none());
+    }
+
+    #[test]
+    fn keeps_a_few_layouts() {
+        let cell = LayoutCell::default();
+        for engine in 0..10 {
+            cell.set(key(engine, 100.0), layout());
+        }
+        assert_eq!(cell.last.borrow().len(), MAX_CACHED_LAYOUTS);
+        assert!(cell.get(&key(9, 100.0)).is_some());
+        assert!(cell.get(&key(0, 100.0)).is_none());
+    }
+
+    #[test]
+    fn drops_layouts_with_their_engine() {
+        let fonts = Rc::new(RefCell::new(FontLoader::spawn(FontRegistry::new(), || ())));
+        let window = Node::new(Window::default(), vec![Node::new(View::default(), vec![])]);
+        let size = size2(100.0, 100.0);
+        StyleEngine::new().update(
+            window.clone(),
+            size,
+            ScrollPositions::default(),
+            Interactions::default(),
+        );
+
+        let mut engine = LayoutEngine::new(fonts.clone());
+        engine.layout(window.clone(), size, Scale::new(1.0));
+        for _ in 0..10 {
+            LayoutEngine::new(fonts.clone()).layout(window.clone(), size, Scale::new(1.0));
+        }
+        // Only the engine that's still alive has layouts cached.
+        let view = &window.children()[0];
+        assert_eq!(window.layout_cell().last.borrow().len(), 1);
+        assert_eq!(view.layout_cell().last.borrow().len(), 1);
+        drop(engine);
+        assert_eq!(window.layout_cell().last.borrow().len(), 0);
+        assert_eq!(view.layout_cell().last.borrow().len(), 0);
+    }
+}
@@ -1,12 +1,193 @@
-use super::{Glyph, TextFragment};
-use crate::util::equal_rc::EqualRc;
+use super::fonts::FontKey;
+use super::{
+    measure::IntrinsicWidths, snap_to_device, Cluster, DeviceScale, Fonts, Glyph, LogicalRect,
+    TextFragment,
+};
+use crate::perf::{self, Counter};
+use crate::style::{
+    ComputedValues, OverflowWrap, TextDecoration, TextDirection, WhiteSpace, WordBreak,
+};
+use crate::util::lru::Lru;
 use crate::util::word_break_iter;
-use euclid::point2;
+use euclid::{point2, rect};
 use skribo::{FontCollection, LayoutSession, TextStyle};
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use unicode_bidi::{BidiInfo, Level};
+
+/// The size of a single word, in logical pixels.
+#[derive(Clone, Copy)]
+struct WordMetrics {
+    width: f32,
+    height: f32,
+    ascent: f32,
+}
+
+/// Estimates used before any fonts have been loaded, as multiples of
+/// the text size.
+const PLACEHOLDER_ADVANCE: f32 = 0.5;
+const PLACEHOLDER_HEIGHT: f32 = 1.2;
+const PLACEHOLDER_ASCENT: f32 = 0.95;
+
+/// How many shaped texts are kept for reuse.
+const SHAPING_CACHE_SIZE: usize = 2048;
+
+/// Text shaped in one font collection and size, along with the words in
+/// it which have been measured so far.
+struct Shaped {
+    /// This is `None` while fonts are still loading, in which case
+    /// placeholder metrics are used and no glyphs are produced.
+    session: Option<RefCell<LayoutSession<String>>>,
+    /// Words are measured the first time they're needed, and reused
+    /// for every line fill after that, so that laying out a paragraph
+    /// at a new width doesn't shape it again.
+    words: RefCell<HashMap<(usize, usize), WordMetrics>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShapingKey {
+    text: String,
+    size: u32,
+    letter_spacing: u32,
+    font: FontKey,
+    generation: usize,
+}
+
+/// Shaped text, kept so that laying out the same text in the same
+/// style again, whether by another pass of layout, an intrinsic size
+/// measurement, or a node which was rebuilt, doesn't shape it again.
+/// The least recently used texts are dropped once there are too many.
+#[derive(Clone)]
+pub struct ShapingCache(Rc<RefCell<Lru<ShapingKey, Rc<Shaped>>>>);
+
+impl ShapingCache {
+    pub fn new() -> ShapingCache {
+        ShapingCache(Rc::new(RefCell::new(Lru::new(SHAPING_CACHE_SIZE))))
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    fn get_or_shape(&self, key: ShapingKey, shape: impl FnOnce(&str) -> Shaped) -> Rc<Shaped> {
+        if let Some(shaped) = self.0.borrow_mut().get(&key) {
+            perf::count(Counter::ShapingCacheHits, 1);
+            return shaped.clone();
+        }
+        perf::count(Counter::TextsShaped, 1);
+        let shaped = Rc::new(shape(&key.text));
+        self.0.borrow_mut().insert(key, shaped.clone());
+        shaped
+    }
+}
+
+impl Default for ShapingCache {
+    fn default() -> ShapingCache {
+        ShapingCache::new()
+    }
+}
+
+/// Where text can be broken into lines, taken from its style.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineBreaking {
+    pub word_break: WordBreak,
+    pub overflow_wrap: OverflowWrap,
+    pub white_space: WhiteSpace,
+}
+
+impl LineBreaking {
+    pub fn new(values: &ComputedValues) -> LineBreaking {
+        LineBreaking {
+            word_break: values.word_break,
+            overflow_wrap: values.overflow_wrap,
+            white_space: values.white_space,
+        }
+    }
+
+    /// Whether lines break when they run out of room.
+    fn wraps(self) -> bool {
+        match self.white_space {
+            WhiteSpace::Normal | WhiteSpace::PreWrap => true,
+            WhiteSpace::Pre | WhiteSpace::NoWrap => false,
+        }
+    }
+
+    /// Whether newlines break lines, and spaces at the start of lines
+    /// are kept.
+    fn preserves(self) -> bool {
+        match self.white_space {
+            WhiteSpace::Pre | WhiteSpace::PreWrap => true,
+            WhiteSpace::Normal | WhiteSpace::NoWrap => false,
+        }
+    }
+}
+
+impl Default for LineBreaking {
+    fn default() -> Self {
+        LineBreaking {
+            word_break: WordBreak::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            white_space: WhiteSpace::Normal,
+        }
+    }
+}
+
+/// The parts of a style which text layout depends on, with lengths
+/// resolved to logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextOptions {
+    pub size: f32,
+    /// The height of each line, if it isn't taken from the font.
+    pub line_height: Option<f32>,
+    /// Added after every glyph.
+    pub letter_spacing: f32,
+    pub direction: TextDirection,
+    pub breaking: LineBreaking,
+    /// How far apart tab stops are, in spaces.
+    pub tab_size: u32,
+    pub font: FontKey,
+    pub decoration: TextDecoration,
+}
+
+impl TextOptions {
+    pub fn new(values: &ComputedValues) -> TextOptions {
+        let size = values.text_size.get();
+        TextOptions {
+            size,
+            line_height: values.line_height.resolve(size),
+            letter_spacing: values.letter_spacing.get(),
+            direction: values.text_direction,
+            breaking: LineBreaking::new(values),
+            tab_size: values.tab_size,
+            font: FontKey {
+                family: values.font_family,
+                weight: values.font_weight,
+                style: values.font_style,
+            },
+            decoration: values.text_decoration,
+        }
+    }
+}
 
 pub struct TextLayoutInfo {
-    session: RefCell<LayoutSession<String>>,
+    text: String,
+    size: f32,
+    /// The height of each line, if it isn't taken from the font.
+    line_height: Option<f32>,
+    /// Added after every glyph.
+    letter_spacing: f32,
+    font: FontKey,
+    /// The bidi embedding level of each byte of the text, which is odd
+    /// for right-to-left text. Empty when all of it is left-to-right.
+    levels: Vec<u8>,
+    breaking: LineBreaking,
+    /// How far apart tab stops are, which is zero when there are no
+    /// tabs in the text.
+    tab_width: f32,
+    decoration: TextDecoration,
+    /// Shared with other layouts of the same text in the same style.
+    shaped: Rc<Shaped>,
 }
 
 pub struct FilledLine {
@@ -14,132 +195,815 @@ pub struct FilledLine {
     pub height: f32,
     pub ascender: f32,
     pub fragments: Vec<TextFragment>,
+    /// Where each character is, with offsets into this text.
+    pub clusters: Vec<Cluster>,
+    /// The lines drawn along the text by `text_decoration`.
+    pub decorations: Vec<LogicalRect>,
     pub text_size: f32,
+    /// Set when a word was too wide for the line, even on its own.
+    pub overflowed: bool,
+}
+
+/// How much space a line of text has.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineWidth {
+    /// Words which would go past this width are moved to the next line.
+    Max(f32),
+    /// The rest of the text is laid out on one line, to measure its
+    /// natural width.
+    Unconstrained,
+}
+
+impl LineWidth {
+    fn fits(self, width: f32) -> bool {
+        match self {
+            LineWidth::Max(max) => width <= max,
+            LineWidth::Unconstrained => true,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct TextState<'a> {
     offset: usize,
     layout: &'a TextLayoutInfo,
+    /// Set when the last line filled ended at a preserved newline.
+    line_ended: bool,
 }
 
 impl TextLayoutInfo {
-    #[illicit::from_env(collection: &EqualRc<FontCollection>)]
-    pub fn new(text: String, size: f32) -> Self {
+    #[illicit::from_env(fonts: &Fonts)]
+    pub fn new(text: String, options: TextOptions) -> Self {
+        let size = options.size;
+        let levels = bidi_levels(&text, options.direction);
+        let collection = fonts.collection_for(options.font);
+        let key = ShapingKey {
+            text: text.clone(),
+            size: size.to_bits(),
+            letter_spacing: options.letter_spacing.to_bits(),
+            font: options.font,
+            generation: fonts.generation,
+        };
+        let shaped = fonts.shaping.get_or_shape(key, |text| Shaped {
+            session: collection.map(|collection| {
+                RefCell::new(LayoutSession::create(
+                    text.to_owned(),
+                    &TextStyle { size },
+                    collection,
+                ))
+            }),
+            words: RefCell::new(HashMap::new()),
+        });
+        let tab_width = if text.contains('\t') {
+            let space = match collection {
+                Some(collection) => space_width(collection, size),
+                None => size * PLACEHOLDER_ADVANCE,
+            };
+            options.tab_size as f32 * (space + options.letter_spacing)
+        } else {
+            0.0
+        };
         TextLayoutInfo {
-            session: RefCell::new(LayoutSession::create(text, &TextStyle { size }, collection)),
+            text,
+            size,
+            line_height: options.line_height,
+            letter_spacing: options.letter_spacing,
+            font: options.font,
+            levels,
+            breaking: options.breaking,
+            tab_width,
+            decoration: options.decoration,
+            shaped,
+        }
+    }
+
+    /// Creates the "…" which ends text cut off by `max_lines`, in the
+    /// same style as this text.
+    pub fn ellipsis(&self) -> TextLayoutInfo {
+        TextLayoutInfo::new(
+            "\u{2026}".to_owned(),
+            TextOptions {
+                size: self.size,
+                line_height: self.line_height,
+                letter_spacing: self.letter_spacing,
+                direction: TextDirection::Ltr,
+                breaking: LineBreaking::default(),
+                tab_size: 0,
+                font: self.font,
+                decoration: self.decoration,
+            },
+        )
+    }
+
+    fn measure_word(&self, start: usize, end: usize) -> WordMetrics {
+        if let Some(&metrics) = self.shaped.words.borrow().get(&(start, end)) {
+            return metrics;
+        }
+
+        let size = self.size;
+        let spacing = self.letter_spacing;
+        let mut session = match self.shaped.session {
+            Some(ref session) => session.borrow_mut(),
+            None => {
+                return WordMetrics {
+                    width: self.text[start..end].chars().count() as f32
+                        * (size * PLACEHOLDER_ADVANCE + spacing),
+                    height: size * PLACEHOLDER_HEIGHT,
+                    ascent: size * PLACEHOLDER_ASCENT,
+                }
+            }
+        };
+        perf::count(Counter::WordsShaped, 1);
+        let mut metrics = WordMetrics {
+            width: 0.0,
+            height: 0.0,
+            ascent: 0.0,
+        };
+        let mut glyph_count = 0;
+        for run in session.iter_substr(start..end) {
+            let font = run.font();
+            let font_metrics = font.font.metrics();
+            let units_per_px = font_metrics.units_per_em as f32 / size;
+            metrics.height = metrics
+                .height
+                .max((font_metrics.ascent - font_metrics.descent) / units_per_px);
+            metrics.ascent = metrics.ascent.max(font_metrics.ascent / units_per_px);
+
+            for glyph in run.glyphs() {
+                glyph_count += 1;
+                let right = glyph.offset.x
+                    + glyph_count as f32 * spacing
+                    + font.font.advance(glyph.glyph_id).unwrap().x / units_per_px;
+                metrics.width = metrics.width.max(right);
+            }
+        }
+
+        self.shaped.words.borrow_mut().insert((start, end), metrics);
+        metrics
+    }
+
+    /// Where the tabs between `start` and `end` are.
+    fn tabs(&self, start: usize, end: usize) -> impl Iterator<Item = usize> + '_ {
+        self.text[start..end]
+            .match_indices('\t')
+            .map(move |(index, _)| start + index)
+    }
+
+    /// The next tab stop after `x`.
+    fn tab_stop(&self, x: f32) -> f32 {
+        if self.tab_width > 0.0 {
+            ((x / self.tab_width).floor() + 1.0) * self.tab_width
+        } else {
+            x
+        }
+    }
+
+    /// Measures the text from `start` to `end` when it's `x` from the
+    /// start of the line, with each tab in it moving on to the next tab
+    /// stop. The width is from `x` to the end of the text.
+    fn measure_from(&self, start: usize, end: usize, x: f32) -> WordMetrics {
+        let mut tabs = self.tabs(start, end).peekable();
+        let first_tab = match tabs.peek() {
+            Some(&tab) => tab,
+            None => return self.measure_word(start, end),
+        };
+        // Text with nothing but tabs still takes the height of the font.
+        let mut metrics = self.measure_word(first_tab, first_tab + 1);
+        let mut right = x;
+        let mut segment_start = start;
+        for tab in tabs.chain(Some(end)) {
+            if segment_start < tab {
+                let segment = self.measure_word(segment_start, tab);
+                metrics.height = metrics.height.max(segment.height);
+                metrics.ascent = metrics.ascent.max(segment.ascent);
+                right += segment.width;
+            }
+            if tab < end {
+                right = self.tab_stop(right);
+            }
+            segment_start = tab + 1;
+        }
+        metrics.width = right - x;
+        metrics
+    }
+
+    /// Adds a cluster for each character from `start` to `end`, which
+    /// together take up `width` starting `x` from the start of the
+    /// line. Characters are measured on their own, and then scaled to
+    /// add up to the width of the text they're in, which accounts for
+    /// kerning between them.
+    fn push_clusters(
+        &self,
+        start: usize,
+        end: usize,
+        x: f32,
+        width: f32,
+        rtl: bool,
+        clusters: &mut Vec<Cluster>,
+    ) {
+        let mut chars = self.text[start..end]
+            .char_indices()
+            .map(|(offset, ch)| {
+                let char_start = start + offset;
+                let char_end = char_start + ch.len_utf8();
+                let width = self.measure_word(char_start, char_end).width;
+                (char_start, char_end, width)
+            })
+            .collect::<Vec<_>>();
+        if rtl {
+            chars.reverse();
+        }
+        let total = chars.iter().map(|&(_, _, width)| width).sum::<f32>();
+        let scale = if total > 0.0 { width / total } else { 0.0 };
+        let mut left = x;
+        for (start, end, width) in chars {
+            let right = left + width * scale;
+            clusters.push(Cluster {
+                start,
+                end,
+                left,
+                right,
+                rtl,
+            });
+            left = right;
+        }
+    }
+
+    /// Splits a range of the text into runs with the same bidi level,
+    /// in logical order.
+    fn bidi_runs(&self, start: usize, end: usize) -> Vec<(usize, usize, u8)> {
+        if self.levels.is_empty() {
+            return vec![(start, end, 0)];
+        }
+        let mut runs: Vec<(usize, usize, u8)> = vec![];
+        for (offset, ch) in self.text[start..end].char_indices() {
+            let index = start + offset;
+            let level = self.levels[index];
+            let char_end = index + ch.len_utf8();
+            match runs.last_mut() {
+                Some(run) if run.2 == level => run.1 = char_end,
+                _ => runs.push((index, char_end, level)),
+            }
+        }
+        runs
+    }
+
+    /// The narrowest the text can be laid out at, which is the width of
+    /// its longest word, or of its widest character if words can be
+    /// broken anywhere, and its width on a single line.
+    pub fn intrinsic_widths(&self) -> IntrinsicWidths {
+        let breaking = self.breaking;
+        let text = if breaking.preserves() {
+            &self.text[..]
+        } else {
+            self.text.trim_start()
+        };
+        let mut widths = IntrinsicWidths::default();
+        // Preserved newlines always end a line, so the widest line is
+        // as wide as the text gets.
+        let lines = if breaking.preserves() {
+            text.split('\n').collect()
+        } else {
+            vec![text]
+        };
+        for line in lines {
+            let mut line_width = 0.0f32;
+            let words = word_break_iter::WordBreakIterator::with_mode(line, breaking.word_break);
+            for word in words {
+                let start = word.as_ptr() as usize - self.text.as_ptr() as usize;
+                let end = start + word.len();
+                line_width += self.measure_from(start, end, line_width).width;
+                // Words after the first include the space before them,
+                // which is trimmed when they start a line.
+                let trimmed = end - word.trim_start().len();
+                if breaking.overflow_wrap == OverflowWrap::Normal {
+                    widths.min = widths.min.max(self.measure_word(trimmed, end).width);
+                }
+            }
+            widths.max = widths.max.max(line_width);
         }
+        if !breaking.wraps() {
+            widths.min = widths.max;
+        } else if breaking.overflow_wrap == OverflowWrap::Anywhere {
+            // Words can be broken between any two characters.
+            for (index, ch) in text.char_indices().filter(|(_, ch)| !ch.is_whitespace()) {
+                let start = index + text.as_ptr() as usize - self.text.as_ptr() as usize;
+                let width = self.measure_word(start, start + ch.len_utf8()).width;
+                widths.min = widths.min.max(width);
+            }
+        }
+        widths
+    }
+
+    /// Finds how much of the word from `start` to `end` fits in
+    /// `width`, keeping at least its first character. Returns where the
+    /// part that fits ends.
+    fn split_word(&self, start: usize, end: usize, width: LineWidth) -> usize {
+        let mut split = end;
+        for (offset, ch) in self.text[start..end].char_indices() {
+            let char_end = start + offset + ch.len_utf8();
+            if offset > 0 && !width.fits(self.measure_word(start, char_end).width) {
+                break;
+            }
+            split = char_end;
+        }
+        split
     }
 }
 
 impl<'a> TextState<'a> {
     pub fn new(layout: &'a TextLayoutInfo) -> TextState<'a> {
-        TextState { offset: 0, layout }
+        TextState {
+            offset: 0,
+            layout,
+            line_ended: false,
+        }
     }
 
-    fn create_fragments(
+    /// Creates the fragments for a line of text. The baseline is snapped
+    /// to a whole device pixel from the top of the line, and moved down
+    /// by `leading`. Runs of text in different directions are placed in
+    /// the order they're read in, and tabs aren't drawn, but move the
+    /// text after them to the next tab stop.
+    fn create_fragments(&self, start: usize, end: usize, leading: f32) -> Vec<TextFragment> {
+        let mut fragments = vec![];
+        if self.layout.shaped.session.is_none() {
+            return fragments;
+        }
+        let runs = self.layout.bidi_runs(start, end);
+        let levels = runs.iter().map(|run| run.2).collect::<Vec<_>>();
+        let order = visual_order(&levels);
+        let mut x = 0.0;
+        for (position, &index) in order.iter().enumerate() {
+            let (run_start, run_end, level) = runs[index];
+            let rtl = level % 2 == 1;
+            let mut segment_start = run_start;
+            for tab in self.layout.tabs(run_start, run_end) {
+                if segment_start < tab {
+                    self.push_fragments(segment_start, tab, x, leading, rtl, &mut fragments);
+                    x += self.layout.measure_word(segment_start, tab).width;
+                }
+                x = self.layout.tab_stop(x);
+                segment_start = tab + 1;
+            }
+            if segment_start < run_end {
+                self.push_fragments(segment_start, run_end, x, leading, rtl, &mut fragments);
+            }
+            if position + 1 < order.len() {
+                x += self.layout.measure_word(segment_start, run_end).width;
+            }
+        }
+        fragments
+    }
+
+    /// Adds the fragments for a run of text in one direction, starting
+    /// `x` from the start of the line. Text is shaped left to right in
+    /// logical order, so right-to-left runs are mirrored with
+    /// `glyph_lefts`.
+    #[illicit::from_env(scale: &DeviceScale)]
+    fn push_fragments(
         &self,
-        session: &mut LayoutSession<String>,
         start: usize,
         end: usize,
-    ) -> Vec<TextFragment> {
-        let mut fragments = vec![];
-        let size = session.style().size;
+        x: f32,
+        leading: f32,
+        rtl: bool,
+        fragments: &mut Vec<TextFragment>,
+    ) {
+        let size = self.layout.size;
+        let spacing = self.layout.letter_spacing;
+        let mut session = match self.layout.shaped.session {
+            Some(ref session) => session.borrow_mut(),
+            None => return,
+        };
+        // The glyphs of each font run, with their vertical offsets,
+        // and where each starts and how far it advances.
+        let mut runs = vec![];
+        let mut extents = vec![];
         for run in session.iter_substr(start..end) {
             let font = run.font().to_owned();
             let metrics = font.font.metrics();
             let units_per_px = metrics.units_per_em as f32 / size;
-            let baseline_offset = metrics.ascent / units_per_px;
-
+            let baseline_offset = snap_to_device(metrics.ascent / units_per_px, *scale) + leading;
             let glyphs = run
                 .glyphs()
-                .map(|glyph| Glyph {
-                    index: glyph.glyph_id,
-                    offset: point2(glyph.offset.x, glyph.offset.y + baseline_offset),
+                .map(|glyph| {
+                    let left = glyph.offset.x + extents.len() as f32 * spacing;
+                    let advance = font.font.advance(glyph.glyph_id).unwrap().x / units_per_px;
+                    extents.push((left, advance));
+                    (glyph.glyph_id, glyph.offset.y + baseline_offset)
+                })
+                .collect::<Vec<_>>();
+            runs.push((font, glyphs));
+        }
+
+        let mut lefts = glyph_lefts(&extents, spacing, rtl).into_iter();
+        for (font, glyphs) in runs {
+            let glyphs = glyphs
+                .into_iter()
+                .zip(&mut lefts)
+                .map(|((index, y), left)| Glyph {
+                    index,
+                    offset: point2(x + left, y),
                 })
                 .collect();
             fragments.push(TextFragment { font, glyphs });
         }
+    }
 
-        fragments
+    /// Finds where each character of a line of text is, from left to
+    /// right. This follows `create_fragments`, with each tab taking up
+    /// the space up to the tab stop it moves the text after it to.
+    fn clusters(&self, start: usize, end: usize) -> Vec<Cluster> {
+        let layout = self.layout;
+        let runs = layout.bidi_runs(start, end);
+        let levels = runs.iter().map(|run| run.2).collect::<Vec<_>>();
+        let mut clusters = vec![];
+        let mut x = 0.0;
+        for index in visual_order(&levels) {
+            let (run_start, run_end, level) = runs[index];
+            let rtl = level % 2 == 1;
+            let mut segment_start = run_start;
+            for tab in layout.tabs(run_start, run_end).chain(Some(run_end)) {
+                if segment_start < tab {
+                    let width = layout.measure_word(segment_start, tab).width;
+                    layout.push_clusters(segment_start, tab, x, width, rtl, &mut clusters);
+                    x += width;
+                }
+                if tab < run_end {
+                    let stop = layout.tab_stop(x);
+                    clusters.push(Cluster {
+                        start: tab,
+                        end: tab + 1,
+                        left: x,
+                        right: stop,
+                        rtl,
+                    });
+                    x = stop;
+                }
+                segment_start = tab + 1;
+            }
+        }
+        clusters
+    }
+
+    /// The decoration lines for a line of text `width` wide, placed
+    /// using the metrics of the first font on the line, so that they
+    /// don't jump around between characters from fallback fonts.
+    #[illicit::from_env(scale: &DeviceScale)]
+    fn decorations(
+        &self,
+        fragments: &[TextFragment],
+        width: f32,
+        leading: f32,
+    ) -> Vec<LogicalRect> {
+        let font = match fragments.first() {
+            Some(fragment) if width > 0.0 => &fragment.font,
+            _ => return vec![],
+        };
+        let metrics = font.font.metrics();
+        let units_per_px = metrics.units_per_em as f32 / self.layout.size;
+        let baseline = snap_to_device(metrics.ascent / units_per_px, *scale) + leading;
+        let line = DecorationMetrics {
+            ascent: metrics.ascent / units_per_px,
+            underline_position: metrics.underline_position / units_per_px,
+            underline_thickness: metrics.underline_thickness / units_per_px,
+            x_height: metrics.x_height / units_per_px,
+        }
+        .line(self.layout.decoration);
+        match line {
+            Some((offset, thickness)) => {
+                // At least one device pixel thick, so that it isn't
+                // lost when rasterized.
+                let thickness = snap_to_device(thickness, *scale).max(1.0 / scale.get());
+                let top = snap_to_device(baseline + offset, *scale);
+                vec![rect(0.0, top, width, thickness)]
+            }
+            None => vec![],
+        }
     }
 
     pub fn finished(&self) -> bool {
-        self.offset == self.layout.session.borrow().text().len()
+        self.offset == self.layout.text.len()
     }
 
-    pub fn fill_line(&mut self, width: f32, is_new_line: bool) -> Option<FilledLine> {
-        let mut session = self.layout.session.borrow_mut();
+    /// Whether the last line filled ended at a newline, so that nothing
+    /// else should go on it.
+    pub fn line_ended(&self) -> bool {
+        self.line_ended
+    }
 
-        let mut x = 0.0;
-        let mut height = 0.0f32;
-        let mut ascender = 0.0f32;
+    /// Lays out as much of the remaining text as fits in `width`. The
+    /// height and ascender of the line are whole device pixels, so that
+    /// consecutive lines keep their baselines on the pixel grid. With a
+    /// line height set, the difference from the font's height is split
+    /// evenly above and below the text, and may be negative.
+    ///
+    /// Returns `None` if not even one word fits after the items already
+    /// on the line. A word which doesn't fit on a new line is laid out
+    /// anyway, and the line is marked as overflowed, since moving it to
+    /// another line wouldn't help.
+    ///
+    /// Text which doesn't wrap is laid out as if `width` were
+    /// unconstrained. When newlines are preserved, the line ends at the
+    /// next one, which isn't drawn.
+    #[illicit::from_env(scale: &DeviceScale)]
+    pub fn fill_line(&mut self, width: LineWidth, is_new_line: bool) -> Option<FilledLine> {
         let mut last_word_end = 0;
         let mut last_word_x = 0.0;
-        let mut last_word_height = 0.0;
-        let mut last_word_ascender = 0.0;
-        let size = session.style().size;
-        let text = session.text().to_owned();
+        let mut last_word_height = 0.0f32;
+        let mut last_word_ascender = 0.0f32;
+        let mut overflowed = false;
+        let size = self.layout.size;
+        let text = &self.layout.text[..];
+        let breaking = self.layout.breaking;
+        let width = if breaking.wraps() {
+            width
+        } else {
+            LineWidth::Unconstrained
+        };
 
-        if is_new_line {
-            let trimmed = text[self.offset..].trim_start();
+        // Preserved spaces are only trimmed from lines which wrapped.
+        let wrapped = self.offset > 0 && !self.line_ended;
+        if is_new_line && (!breaking.preserves() || wrapped) {
+            let keep_newlines = breaking.preserves();
+            let trimmed = text[self.offset..].trim_start_matches(|ch: char| {
+                ch.is_whitespace() && !(ch == '\n' && keep_newlines)
+            });
             self.offset = trimmed.as_ptr() as usize - text.as_ptr() as usize;
         };
+        let line_end = match text[self.offset..].find('\n') {
+            Some(index) if breaking.preserves() => self.offset + index,
+            _ => text.len(),
+        };
+        self.line_ended = false;
 
-        for word in word_break_iter::WordBreakIterator::new(&text[self.offset..]) {
+        let words = word_break_iter::WordBreakIterator::with_mode(
+            &text[self.offset..line_end],
+            breaking.word_break,
+        );
+        for word in words {
             let start = word.as_ptr() as usize - text.as_ptr() as usize;
-            let end = start + word.len();
-
-            for run in session.iter_substr(start..end) {
-                let font = run.font();
-                let metrics = font.font.metrics();
-                let units_per_px = metrics.units_per_em as f32 / size;
-                let line_height = (metrics.ascent - metrics.descent) / units_per_px;
-                let line_ascent = metrics.ascent / units_per_px;
-
-                for glyph in run.glyphs() {
-                    let new_x = glyph.offset.x
-                        + font.font.advance(glyph.glyph_id).unwrap().x / units_per_px;
-
-                    if last_word_x + new_x > width {
-                        let start = self.offset;
-                        self.offset += last_word_end;
-                        if last_word_end > 0 {
-                            // soft break
-                            return Some(FilledLine {
-                                fragments: self.create_fragments(&mut *session, start, self.offset),
-                                width: last_word_x,
-                                height: last_word_height,
-                                ascender: last_word_ascender,
-                                text_size: size,
-                            });
-                        } else {
-                            // todo: force progress by hard breaking if is_new_line is true
-                            return None;
-                        }
-                    }
-                    x = last_word_x + new_x;
-                    height = height.max(line_height);
-                    ascender = ascender.max(line_ascent);
-                }
+            let mut end = start + word.len();
+            let mut metrics = self.layout.measure_from(start, end, last_word_x);
+
+            let mut fits = width.fits(last_word_x + metrics.width);
+            if !fits && word.trim_start().is_empty() {
+                // Spaces at the end of a line hang past it, rather than
+                // starting the next one.
+                last_word_end = end - self.offset;
+                continue;
+            }
+            if !fits && (last_word_end > 0 || !is_new_line) {
+                break;
             }
+            // This is the first word on the line, so the rest of it can
+            // go on the next one.
+            let split = !fits && breaking.overflow_wrap == OverflowWrap::Anywhere;
+            if split {
+                end = self.layout.split_word(start, end, width);
+                metrics = self.layout.measure_from(start, end, last_word_x);
+                fits = width.fits(metrics.width);
+            }
+            overflowed |= !fits;
+
             last_word_end = end - self.offset;
-            last_word_x = x;
-            last_word_height = height;
-            last_word_ascender = ascender;
+            last_word_x += metrics.width;
+            last_word_height = last_word_height.max(snap_to_device(metrics.height, *scale));
+            last_word_ascender = last_word_ascender.max(snap_to_device(metrics.ascent, *scale));
+            if !fits || split {
+                break;
+            }
+        }
+
+        let ends_line = line_end < text.len() && self.offset + last_word_end == line_end;
+        if ends_line && last_word_end == 0 {
+            // An empty line still takes the height of the font.
+            let metrics = self.layout.measure_word(line_end, line_end + 1);
+            last_word_height = snap_to_device(metrics.height, *scale);
+            last_word_ascender = snap_to_device(metrics.ascent, *scale);
+        }
+
+        let mut leading = 0.0;
+        if let Some(line_height) = self.layout.line_height {
+            let line_height = snap_to_device(line_height, *scale);
+            leading = snap_to_device((line_height - last_word_height) / 2.0, *scale);
+            last_word_height = line_height;
+            last_word_ascender += leading;
         }
 
         let start = self.offset;
         self.offset += last_word_end;
-        if last_word_end > 0 {
+        let end = self.offset;
+        if ends_line {
+            // Skip over the newline.
+            self.offset += 1;
+            self.line_ended = true;
+        }
+        if last_word_end > 0 || ends_line {
+            let (fragments, clusters) = if end > start {
+                (
+                    self.create_fragments(start, end, leading),
+                    self.clusters(start, end),
+                )
+            } else {
+                (vec![], vec![])
+            };
             Some(FilledLine {
-                fragments: self.create_fragments(&mut *session, start, self.offset),
+                decorations: self.decorations(&fragments, last_word_x, leading),
+                fragments,
+                clusters,
                 width: last_word_x,
                 height: last_word_height,
                 ascender: last_word_ascender,
                 text_size: size,
+                overflowed,
             })
         } else {
             None
         }
     }
 }
+
+/// The parts of a font's metrics which decorations are placed with, in
+/// logical pixels, and measured up from the baseline.
+struct DecorationMetrics {
+    ascent: f32,
+    underline_position: f32,
+    underline_thickness: f32,
+    x_height: f32,
+}
+
+impl DecorationMetrics {
+    /// Where the top of a decoration line goes relative to the
+    /// baseline, with positive values below it, and how thick it is.
+    fn line(&self, decoration: TextDecoration) -> Option<(f32, f32)> {
+        // Some fonts leave the underline thickness unset.
+        let thickness = if self.underline_thickness > 0.0 {
+            self.underline_thickness
+        } else {
+            self.ascent / 12.0
+        };
+        let center = match decoration {
+            TextDecoration::None => return None,
+            TextDecoration::Underline => -self.underline_position,
+            TextDecoration::LineThrough => -self.x_height / 2.0,
+            TextDecoration::Overline => -self.ascent,
+        };
+        Some((center - thickness / 2.0, thickness))
+    }
+}
+
+/// The width of a space in the first font in `collection` with one.
+fn space_width(collection: &FontCollection, size: f32) -> f32 {
+    let mut session = LayoutSession::create(" ".to_owned(), &TextStyle { size }, collection);
+    let mut width = 0.0;
+    for run in session.iter_substr(0..1) {
+        let font = run.font();
+        let units_per_px = font.font.metrics().units_per_em as f32 / size;
+        for glyph in run.glyphs() {
+            width += font.font.advance(glyph.glyph_id).unwrap().x / units_per_px;
+        }
+    }
+    width
+}
+
+fn bidi_levels(text: &str, direction: TextDirection) -> Vec<u8> {
+    let base = match direction {
+        TextDirection::Ltr => Level::ltr(),
+        TextDirection::Rtl => Level::rtl(),
+    };
+    let info = BidiInfo::new(text, Some(base));
+    if !info.levels.iter().any(|level| level.is_rtl()) {
+        return vec![];
+    }
+    info.levels.iter().map(|level| level.number()).collect()
+}
+
+/// Where each glyph of a run of text in one direction starts, relative
+/// to the start of the run, given where it starts and how far it
+/// advances when shaped left to right in logical order. Right-to-left
+/// runs are mirrored, so that their first glyph ends up on the right,
+/// with the letter spacing after each glyph on its left.
+fn glyph_lefts(extents: &[(f32, f32)], spacing: f32, rtl: bool) -> Vec<f32> {
+    if !rtl {
+        return extents.iter().map(|&(left, _)| left).collect();
+    }
+    let width = extents
+        .iter()
+        .map(|&(left, advance)| left + advance + spacing)
+        .fold(0.0, f32::max);
+    extents
+        .iter()
+        .map(|&(left, advance)| width - left - advance)
+        .collect()
+}
+
+/// Orders runs of text with the given bidi levels for display, by rule
+/// L2 of the Unicode bidi algorithm: from the highest level down to the
+/// lowest odd one, each sequence of runs at that level or above is
+/// reversed. Returns the indices of the runs from left to right.
+fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order = (0..levels.len()).collect::<Vec<_>>();
+    let highest = levels.iter().cloned().max().unwrap_or(0);
+    let lowest_odd = match levels.iter().cloned().filter(|level| level % 2 == 1).min() {
+        Some(level) => level,
+        None => return order,
+    };
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+            i += 1;
+        }
+    }
+    order
+}
+
+/// Shapes `text` and breaks it into lines no wider than `width`,
+/// returning how many lines it took.
+#[cfg(feature = "bench")]
+pub fn shape_lines(text: &str, size: f32, width: f32) -> usize {
+    let options = TextOptions {
+        size,
+        ..TextOptions::new(&ComputedValues::default())
+    };
+    let layout = TextLayoutInfo::new(text.to_owned(), options);
+    let mut state = TextState::new(&layout);
+    let mut lines = 0;
+    while !state.finished() {
+        match state.fill_line(LineWidth::Max(width), true) {
+            Some(_) => lines += 1,
+            None => break,
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glyph_lefts, visual_order, DecorationMetrics};
+    use crate::style::TextDecoration;
+
+    #[test]
+    fn reorders_bidi_runs() {
+        assert_eq!(visual_order(&[0]), vec![0]);
+        // Right-to-left text with a number in the middle of a
+        // left-to-right paragraph.
+        assert_eq!(visual_order(&[0, 1, 2, 1, 0]), vec![0, 3, 2, 1, 4]);
+        // Left-to-right text in a right-to-left paragraph.
+        assert_eq!(visual_order(&[1, 2, 1]), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn mirrors_right_to_left_glyphs() {
+        let extents = [(0.0, 10.0), (10.0, 6.0), (16.0, 4.0)];
+        assert_eq!(glyph_lefts(&extents, 0.0, false), vec![0.0, 10.0, 16.0]);
+        assert_eq!(glyph_lefts(&extents, 0.0, true), vec![10.0, 4.0, 0.0]);
+        // Spacing comes after each glyph in reading order.
+        let spaced = [(0.0, 10.0), (12.0, 10.0)];
+        assert_eq!(glyph_lefts(&spaced, 2.0, true), vec![14.0, 2.0]);
+    }
+
+    #[test]
+    fn places_glyphs_of_mixed_direction_lines() {
+        // "ab" followed by right-to-left "CD" in a left-to-right
+        // paragraph, as `create_fragments` lays them out.
+        let runs = [
+            (vec![(0.0, 10.0), (10.0, 10.0)], 0),
+            (vec![(0.0, 8.0), (8.0, 12.0)], 1),
+        ];
+        let levels = runs.iter().map(|run| run.1).collect::<Vec<_>>();
+        let mut x = 0.0;
+        let mut lefts = vec![];
+        for index in visual_order(&levels) {
+            let (ref extents, level) = runs[index];
+            lefts.extend(
+                glyph_lefts(extents, 0.0, level % 2 == 1)
+                    .into_iter()
+                    .map(|left| x + left),
+            );
+            x += 20.0;
+        }
+        // C is read first, so it's on the right of D.
+        assert_eq!(lefts, vec![0.0, 10.0, 32.0, 20.0]);
+    }
+
+    #[test]
+    fn places_decorations() {
+        let metrics = DecorationMetrics {
+            ascent: 12.0,
+            underline_position: -2.0,
+            underline_thickness: 1.0,
+            x_height: 6.0,
+        };
+        assert_eq!(metrics.line(TextDecoration::None), None);
+        assert_eq!(metrics.line(TextDecoration::Underline), Some((1.5, 1.0)));
+        assert_eq!(metrics.line(TextDecoration::LineThrough), Some((-3.5, 1.0)));
+        assert_eq!(metrics.line(TextDecoration::Overline), Some((-12.5, 1.0)));
+    }
+}
@@ -31,15 +31,41 @@
 
 pub use moxie_native_style::define_style;
 
+pub mod animation;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod bind;
+pub mod clipboard;
+pub mod diagnostics;
 pub mod dom;
+pub mod form;
+pub mod idle;
 mod layout;
 #[doc(hidden)]
 pub mod moxie;
+pub mod offload;
+pub mod open;
+pub mod perf;
+pub mod persist;
 pub mod prelude;
+#[cfg(feature = "print")]
+pub mod print;
+pub mod provide;
 mod render;
+pub mod router;
 mod runtime;
+pub mod select;
 pub mod style;
+pub mod suspense;
+pub mod system;
+#[cfg(feature = "templates")]
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod util;
 
-pub use runtime::Runtime;
-pub use util::color::Color;
+pub use layout::{Constraints, CustomChild, Delegate, FontRegistry, LayoutDelegate};
+pub use render::{DebugOverlay, FrameInfo, Overscroll, RendererDebug, ScrollPhysics};
+pub use runtime::{batch, update_channel, Runtime, UpdateSender};
+pub use util::color::{Color, ColorSpace};
+pub use util::intern::IStr;
@@ -10,3 +10,16 @@ macro_rules! attribute {
 
 attribute!(attr_style -> AttrStyle);
 attribute!(attr_title -> AttrTitle);
+attribute!(attr_scale_factor -> AttrScaleFactor);
+attribute!(attr_min_content_size -> AttrMinContentSize);
+attribute!(attr_size -> AttrSize);
+attribute!(attr_placement -> AttrPlacement);
+attribute!(attr_titlebar -> AttrTitlebar);
+attribute!(attr_scroll_name -> AttrScrollName);
+attribute!(attr_scroll_link -> AttrScrollLink);
+attribute!(attr_data -> AttrData);
+attribute!(attr_label -> AttrLabel);
+attribute!(attr_role -> AttrRole);
+attribute!(attr_description -> AttrDescription);
+attribute!(attr_live -> AttrLive);
+attribute!(attr_layout -> AttrLayout);
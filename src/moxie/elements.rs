@@ -1,6 +1,7 @@
 use crate::dom::element::{Attribute, Element, Event, HasAttribute, HasEvent};
 use crate::dom::Node;
 use crate::util::event_handler::EventHandler;
+use crate::IStr;
 use moxie::*;
 
 /// Builder pattern for creating a DOM node, typically used from the
@@ -99,6 +100,19 @@ where
     }
 }
 
+impl<Parent> IntoChildren<Parent> for IStr
+where
+    Parent: Element,
+    Parent::Child: From<IStr>,
+{
+    type Item = IStr;
+    type IntoIter = std::iter::Once<IStr>;
+
+    fn into_children(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
 impl<Elt> Builder<Elt>
 where
     Elt: Element,
@@ -175,6 +189,7 @@ where
         ));
 
         node.handlers().replace(handlers);
+        node.set_id(topo::Id::current());
 
         node
     }
@@ -212,6 +227,14 @@ macro_rules! button {
     };
 }
 
+/// Container whose children are laid out by a `LayoutDelegate`.
+#[macro_export]
+macro_rules! customlayout {
+    ($with_elem:expr) => {
+        $crate::moxie::Builder::<$crate::dom::CustomLayout>::create($with_elem)
+    };
+}
+
 /// Container for inline text.
 #[macro_export]
 macro_rules! span {
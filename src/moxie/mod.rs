@@ -17,6 +17,8 @@ mod elements;
 pub use attributes::*;
 pub use elements::Builder;
 
+use crate::IStr;
+
 /// Used by the mox! macro for free-standing text, which is then passed
 /// to `Builder::add_content`.
 #[doc(hidden)]
@@ -27,7 +29,8 @@ macro_rules! text {
     };
 }
 
-/// Text node
-pub fn text(s: impl ToString) -> String {
-    s.to_string()
+/// Text node. Short strings are interned, so repeated labels share
+/// their memory.
+pub fn text(s: impl ToString) -> IStr {
+    s.to_string().into()
 }
@@ -0,0 +1,144 @@
+//! Keeps expensive components from holding up the UI thread, for views
+//! which are generated from a lot of data.
+//!
+//! DOM nodes can't be sent between threads, so the subtree is built in
+//! two steps. `prepare` runs on a worker thread with a snapshot of the
+//! inputs, and does the expensive work of turning them into something
+//! to show, like a list of rows. `build` then creates the nodes from
+//! that on the UI thread, once per result. While a result for new
+//! inputs is being prepared, the subtree built from the last one is
+//! kept, instead of switching to a placeholder like `suspend!` does.
+//!
+//! The inputs are sent to the worker thread, so they have to be `Send`,
+//! and large ones are best shared with an `Arc` rather than an `Rc`:
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn report(entries: Arc<Vec<Entry>>) -> Node<View> {
+//!     let table = offload!(
+//!         entries,
+//!         |entries| summarize(&entries),
+//!         |summary: &Summary| mox! { <view>{ summary.rows.iter().map(row) }</view> }
+//!     );
+//!     table.show(|| mox! { <view><span>"Summarizing..."</span></view> })
+//! }
+//! ```
+
+use crate::suspense::Loaded;
+use moxie::Key;
+use std::rc::Rc;
+
+/// The subtree built by `offload!`.
+pub struct Offloaded<R> {
+    content: Option<R>,
+    pending: bool,
+}
+
+impl<R> Offloaded<R> {
+    /// Whether the inputs have changed since the content was built,
+    /// and a new result is still being prepared. The content is out of
+    /// date while this is true, which can be shown with a spinner over
+    /// it.
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Returns the content built from the latest result, or the
+    /// placeholder if the first result hasn't arrived yet.
+    pub fn show(self, placeholder: impl FnOnce() -> R) -> R {
+        self.content.unwrap_or_else(placeholder)
+    }
+}
+
+/// The latest result, compared by identity so that the subtree is only
+/// built once for each result. Used by `offload!`.
+#[doc(hidden)]
+pub struct Latest<T>(pub Option<Rc<T>>);
+
+impl<T> Clone for Latest<T> {
+    fn clone(&self) -> Self {
+        Latest(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Latest<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(this), Some(other)) => Rc::ptr_eq(this, other),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Latest<T> {
+    /// Builds the content for this result. Used by `offload!`.
+    #[doc(hidden)]
+    pub fn build<R>(&self, build: impl FnOnce(&T) -> R) -> Option<R> {
+        self.0.as_ref().map(|value| build(value))
+    }
+}
+
+/// Returns the latest result, whichever inputs it was prepared for.
+/// Used by `offload!`.
+#[doc(hidden)]
+pub fn latest<I, T>(result: &Key<Loaded<I, T>>) -> Latest<T> {
+    match **result {
+        Some((_, ref value)) => Latest(Some(value.clone())),
+        None => Latest(None),
+    }
+}
+
+/// Wraps the content built by `offload!`, noting whether it's out of
+/// date for `inputs`.
+#[doc(hidden)]
+pub fn offloaded<I, T, R>(
+    inputs: &I,
+    result: &Key<Loaded<I, T>>,
+    content: Option<R>,
+) -> Offloaded<R>
+where
+    I: PartialEq,
+{
+    let pending = match **result {
+        Some((ref prepared_for, _)) => prepared_for != inputs,
+        None => true,
+    };
+    Offloaded { content, pending }
+}
+
+/// Prepares the content of a subtree on a worker thread with
+/// `prepare`, which is given `inputs`, so both have to be `Send`, then
+/// builds the subtree from the result with `build` on the UI thread.
+/// Preparing starts again whenever `inputs` changes, and the subtree
+/// built from the last result is kept until the new one arrives.
+#[macro_export]
+macro_rules! offload {
+    ($inputs:expr, $prepare:expr, $build:expr) => {{
+        let inputs = $inputs;
+        let result = $crate::prelude::state!(|| None);
+        let sender = $crate::prelude::once!(|| $crate::suspense::channel(&result));
+        $crate::prelude::memo!(inputs.clone(), |inputs| {
+            $crate::suspense::spawn(inputs.clone(), sender.clone(), $prepare)
+        });
+        let content = $crate::prelude::memo!($crate::offload::latest(&result), |latest| {
+            latest.build($build)
+        });
+        $crate::offload::offloaded(&inputs, &result, content)
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::Latest;
+    use std::rc::Rc;
+
+    #[test]
+    fn compares_results_by_identity() {
+        let first = Rc::new(vec![1, 2]);
+        let same = Latest(Some(first.clone()));
+        assert!(Latest(Some(first)) == same);
+        assert!(Latest(Some(Rc::new(vec![1, 2]))) != same);
+        assert!(Latest::<Vec<i32>>(None) == Latest(None));
+    }
+}
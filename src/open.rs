@@ -0,0 +1,203 @@
+//! Lets applications open the documents and links they're launched
+//! with, such as when a file associated with the application is
+//! double-clicked.
+//!
+//! Windows and Linux launch the application with the paths of the
+//! files as command line arguments, which are read when the runtime
+//! starts. Requests are held until a component sets a handler with
+//! `on_open`, so none are lost while the application starts up. With
+//! `Runtime::single_instance`, the arguments of later launches are
+//! handed to the same handler.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn editor() -> Node<Window> {
+//!     let documents = state!(|| vec![]);
+//!     once!(|| {
+//!         let documents = documents.clone();
+//!         on_open(move |request| {
+//!             if let OpenRequest::File(path) = request {
+//!                 documents.update(|open| Some(open.iter().cloned().chain(Some(path)).collect()));
+//!             }
+//!         })
+//!     });
+//!     // ...
+//! }
+//! ```
+//!
+//! macOS hands files and URLs to a running application as Apple
+//! events rather than arguments, which winit doesn't report, so only
+//! the arguments an application is launched with are seen there.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A document or link the application was asked to open.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpenRequest {
+    /// An absolute path to an existing file.
+    File(PathBuf),
+    /// A URL, for applications registered to handle a scheme.
+    Url(String),
+}
+
+impl OpenRequest {
+    /// Reads a command line argument. Flags and arguments which are
+    /// neither a URL nor an existing file, like routes, are ignored.
+    /// Relative paths are resolved against `working_dir`.
+    pub fn from_arg(arg: &str, working_dir: &Path) -> Option<OpenRequest> {
+        if arg.starts_with('-') {
+            return None;
+        }
+        if is_url(arg) {
+            return Some(OpenRequest::Url(arg.to_owned()));
+        }
+        let path = working_dir.join(arg);
+        if path.is_file() {
+            Some(OpenRequest::File(path))
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the argument starts with a scheme like `https:` or
+/// `myapp:`. Single letter schemes are taken to be Windows drives.
+fn is_url(arg: &str) -> bool {
+    match arg.find(':') {
+        Some(end) if end > 1 => {
+            let scheme = &arg[..end];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+/// Requests waiting to be handled, and the handler set by the
+/// application.
+#[derive(Default)]
+pub(crate) struct OpenQueue {
+    pending: RefCell<VecDeque<OpenRequest>>,
+    handler: RefCell<Option<Box<dyn FnMut(OpenRequest)>>>,
+}
+
+impl fmt::Debug for OpenQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpenQueue")
+            .field("pending", &self.pending.borrow())
+            .field("handler", &self.handler.borrow().is_some())
+            .finish()
+    }
+}
+
+impl OpenQueue {
+    pub(crate) fn push(&self, request: OpenRequest) {
+        self.pending.borrow_mut().push_back(request);
+    }
+
+    /// Queues the files and URLs among the arguments, skipping the
+    /// first, which is the name of the program.
+    pub(crate) fn push_args(&self, args: impl IntoIterator<Item = String>, working_dir: &Path) {
+        for arg in args.into_iter().skip(1) {
+            if let Some(request) = OpenRequest::from_arg(&arg, working_dir) {
+                self.push(request);
+            }
+        }
+    }
+
+    fn set_handler(&self, handler: Box<dyn FnMut(OpenRequest)>) {
+        self.handler.replace(Some(handler));
+    }
+
+    /// Hands every waiting request to the handler, if one has been set.
+    /// Returns true if any were handled, since the handler may have
+    /// changed state.
+    pub(crate) fn deliver(&self) -> bool {
+        let mut handler = match self.handler.borrow_mut().take() {
+            Some(handler) => handler,
+            None => return false,
+        };
+        let mut delivered = false;
+        // Neither is borrowed while the handler runs, so it can queue
+        // more requests or replace itself.
+        loop {
+            let request = self.pending.borrow_mut().pop_front();
+            match request {
+                Some(request) => handler(request),
+                None => break,
+            }
+            delivered = true;
+        }
+        let mut current = self.handler.borrow_mut();
+        if current.is_none() {
+            *current = Some(handler);
+        }
+        delivered
+    }
+}
+
+/// Sets the function which handles requests to open files and URLs,
+/// replacing any set before. Requests which arrived before there was a
+/// handler, like those on the command line, are handed to this one.
+///
+/// This should usually be called from inside `once!`, since components
+/// run again every revision.
+pub fn on_open(handler: impl FnMut(OpenRequest) + 'static) {
+    let queue = illicit::Env::expect::<Rc<OpenQueue>>();
+    queue.set_handler(Box::new(handler));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OpenQueue, OpenRequest};
+    use std::cell::RefCell;
+    use std::env;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    #[test]
+    fn reads_arguments() {
+        let dir = env::temp_dir();
+        let file = dir.join("moxie-native-open-test.txt");
+        std::fs::write(&file, "").unwrap();
+        assert_eq!(
+            OpenRequest::from_arg("moxie-native-open-test.txt", &dir),
+            Some(OpenRequest::File(file.clone()))
+        );
+        assert_eq!(
+            OpenRequest::from_arg("myapp://note/4", &dir),
+            Some(OpenRequest::Url("myapp://note/4".to_owned()))
+        );
+        assert_eq!(OpenRequest::from_arg("--verbose", &dir), None);
+        assert_eq!(OpenRequest::from_arg("message/4", &dir), None);
+        assert_eq!(
+            OpenRequest::from_arg("C:\\missing.txt", Path::new("")),
+            None
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn holds_requests_until_handled() {
+        let queue = OpenQueue::default();
+        let url = |url: &str| OpenRequest::Url(url.to_owned());
+        queue.push(url("a:1"));
+        assert!(!queue.deliver());
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let handler_log = log.clone();
+        queue.set_handler(Box::new(move |request| {
+            handler_log.borrow_mut().push(request)
+        }));
+        queue.push(url("a:2"));
+        assert!(queue.deliver());
+        assert_eq!(*log.borrow(), vec![url("a:1"), url("a:2")]);
+        assert!(!queue.deliver());
+    }
+}
@@ -0,0 +1,83 @@
+//! Counters for diagnosing memory use and performance of the UI
+//! runtime.
+
+use crate::util::intern;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub use crate::util::intern::InternStats;
+
+/// Statistics about the strings which have been interned for text
+/// nodes on the current thread.
+pub fn intern_stats() -> InternStats {
+    intern::stats()
+}
+
+/// Totals of the work the framework has done since the application
+/// started, across every window. Sampling them regularly and charting
+/// the differences shows how much of each frame is framework overhead.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counters {
+    /// Nodes whose layout was computed.
+    pub nodes_laid_out: usize,
+    /// Nodes whose last layout was reused, since nothing in their
+    /// subtree changed.
+    pub layout_cache_hits: usize,
+    /// Texts shaped with the system fonts. Each distinct text is shaped
+    /// once per style, until it hasn't been laid out for a while.
+    pub texts_shaped: usize,
+    /// Texts laid out again with the shaping from an earlier layout.
+    pub shaping_cache_hits: usize,
+    /// Words of text measured after shaping. Each word is measured
+    /// once per shaped text.
+    pub words_shaped: usize,
+    /// Subtrees whose display lists were reused from the last frame.
+    pub display_list_cache_hits: usize,
+    /// Subtrees whose display lists were rebuilt.
+    pub display_list_cache_misses: usize,
+    /// The total size of the display lists sent to the renderer.
+    pub display_list_bytes: usize,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Counter {
+    NodesLaidOut,
+    LayoutCacheHits,
+    TextsShaped,
+    ShapingCacheHits,
+    WordsShaped,
+    DisplayListCacheHits,
+    DisplayListCacheMisses,
+    DisplayListBytes,
+}
+
+// Atomic so that they can be read from other threads, such as one
+// which uploads telemetry.
+static COUNTERS: [AtomicUsize; 8] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+pub(crate) fn count(counter: Counter, amount: usize) {
+    COUNTERS[counter as usize].fetch_add(amount, Ordering::Relaxed);
+}
+
+/// Reads the framework's work counters.
+pub fn counters() -> Counters {
+    let get = |counter: Counter| COUNTERS[counter as usize].load(Ordering::Relaxed);
+    Counters {
+        nodes_laid_out: get(Counter::NodesLaidOut),
+        layout_cache_hits: get(Counter::LayoutCacheHits),
+        texts_shaped: get(Counter::TextsShaped),
+        shaping_cache_hits: get(Counter::ShapingCacheHits),
+        words_shaped: get(Counter::WordsShaped),
+        display_list_cache_hits: get(Counter::DisplayListCacheHits),
+        display_list_cache_misses: get(Counter::DisplayListCacheMisses),
+        display_list_bytes: get(Counter::DisplayListBytes),
+    }
+}
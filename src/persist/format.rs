@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+fn escape(input: &str, output: &mut String) {
+    for ch in input.chars() {
+        match ch {
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '=' => output.push_str("\\="),
+            _ => output.push(ch),
+        }
+    }
+}
+
+/// Serializes the persisted values as `name=value` lines, sorted by
+/// name so that the output is stable.
+pub fn encode(values: &HashMap<String, String>) -> String {
+    let mut names = values.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut output = String::new();
+    for name in names {
+        escape(name, &mut output);
+        output.push('=');
+        escape(&values[name], &mut output);
+        output.push('\n');
+    }
+    output
+}
+
+/// Parses the output of `encode`. Malformed lines are skipped, since a
+/// damaged state file shouldn't prevent the application from starting.
+pub fn decode(input: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in input.lines() {
+        let mut name = String::new();
+        let mut value = String::new();
+        let mut seen_separator = false;
+        let mut chars = line.chars();
+        while let Some(ch) = chars.next() {
+            let current = if seen_separator {
+                &mut value
+            } else {
+                &mut name
+            };
+            match ch {
+                '\\' => match chars.next() {
+                    Some('n') => current.push('\n'),
+                    Some('r') => current.push('\r'),
+                    Some(ch) => current.push(ch),
+                    None => (),
+                },
+                '=' if !seen_separator => seen_separator = true,
+                _ => current.push(ch),
+            }
+        }
+        if seen_separator {
+            values.insert(name, value);
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode};
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let mut values = HashMap::new();
+        values.insert("sidebar.scroll".to_owned(), "120.5".to_owned());
+        values.insert("a=b".to_owned(), "line 1\nline 2\\".to_owned());
+        values.insert("empty".to_owned(), "".to_owned());
+        let encoded = encode(&values);
+        assert_eq!(encoded.lines().count(), 3);
+        assert_eq!(decode(&encoded), values);
+    }
+
+    #[test]
+    fn skips_malformed() {
+        let values = decode("no separator\nkey=value\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["key"], "value");
+    }
+}
@@ -0,0 +1,196 @@
+//! Lets components keep state across application restarts, for things
+//! like scroll positions, expanded nodes and input values.
+//!
+//! Persistence is enabled with `Runtime::persist_to`, after which state
+//! declared with `persistent_state!` is restored on startup and saved
+//! when a window is closed or the application exits.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn sidebar() -> Node<View> {
+//!     let expanded: Key<bool> = persistent_state!("sidebar.expanded", || true);
+//!     // ...
+//! }
+//! ```
+
+use crate::diagnostics::{self, Diagnostic};
+use moxie::Key;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+mod format;
+
+/// Values that can be written to and read back from the state file.
+pub trait Persist: Sized {
+    fn save(&self) -> String;
+    fn restore(value: &str) -> Option<Self>;
+}
+
+macro_rules! persist_from_str {
+    ($($ty:ty),+) => {
+        $(
+            impl Persist for $ty {
+                fn save(&self) -> String {
+                    self.to_string()
+                }
+
+                fn restore(value: &str) -> Option<Self> {
+                    value.parse().ok()
+                }
+            }
+        )+
+    };
+}
+
+persist_from_str!(bool, char, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, String);
+
+impl<T> Persist for Option<T>
+where
+    T: Persist,
+{
+    fn save(&self) -> String {
+        match self {
+            Some(value) => format!("+{}", value.save()),
+            None => "-".to_owned(),
+        }
+    }
+
+    fn restore(value: &str) -> Option<Self> {
+        if value.starts_with('+') {
+            T::restore(&value[1..]).map(Some)
+        } else if value == "-" {
+            Some(None)
+        } else {
+            None
+        }
+    }
+}
+
+/// Holds the values loaded from the state file, and the state keys
+/// which will be written back to it.
+#[derive(Default)]
+pub(crate) struct PersistStore {
+    path: RefCell<Option<PathBuf>>,
+    values: RefCell<HashMap<String, String>>,
+    savers: RefCell<HashMap<String, Box<dyn Fn() -> String>>>,
+    /// The names registered during the current revision.
+    registered: RefCell<HashSet<String>>,
+}
+
+impl fmt::Debug for PersistStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PersistStore")
+            .field("path", &self.path)
+            .field("values", &self.values)
+            .field("savers", &"...")
+            .finish()
+    }
+}
+
+impl PersistStore {
+    pub(crate) fn set_path(&self, path: PathBuf) {
+        self.path.replace(Some(path));
+    }
+
+    /// Called before each revision, so that names registered twice in
+    /// one revision can be told apart from the same state registering
+    /// again in the next one.
+    pub(crate) fn begin_revision(&self) {
+        self.registered.borrow_mut().clear();
+    }
+
+    /// Read the state file, if persistence is enabled and the file
+    /// exists.
+    pub(crate) fn load(&self) -> io::Result<()> {
+        if let Some(ref path) = *self.path.borrow() {
+            match fs::read_to_string(path) {
+                Ok(contents) => {
+                    self.values.replace(format::decode(&contents));
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the current value of every registered key to the state
+    /// file. Values restored on startup but not used since are kept.
+    pub(crate) fn save(&self) -> io::Result<()> {
+        if let Some(ref path) = *self.path.borrow() {
+            let mut values = self.values.borrow_mut();
+            for (name, saver) in self.savers.borrow().iter() {
+                values.insert(name.clone(), saver());
+            }
+            fs::write(path, format::encode(&values))?;
+        }
+        Ok(())
+    }
+
+    /// Looks up a value loaded from the state file.
+    pub(crate) fn get<T>(&self, name: &str) -> Option<T>
+    where
+        T: Persist,
+    {
+        let values = self.values.borrow();
+        values.get(name).and_then(|value| T::restore(value))
+    }
+
+    /// Sets a value to be written to the state file, for state kept by
+    /// the runtime rather than by components.
+    pub(crate) fn set<T>(&self, name: &str, value: &T)
+    where
+        T: Persist,
+    {
+        self.values
+            .borrow_mut()
+            .insert(name.to_owned(), value.save());
+    }
+}
+
+/// Looks up the value for `name` that was loaded from the state file.
+/// Used by `persistent_state!`.
+#[doc(hidden)]
+pub fn restore<T>(name: &str) -> Option<T>
+where
+    T: Persist,
+{
+    illicit::Env::expect::<Rc<PersistStore>>().get(name)
+}
+
+/// Registers a state key to be saved under `name`. Used by
+/// `persistent_state!`.
+#[doc(hidden)]
+pub fn register<T>(name: &str, key: &Key<T>)
+where
+    T: Persist + 'static,
+{
+    let store = illicit::Env::expect::<Rc<PersistStore>>();
+    if !store.registered.borrow_mut().insert(name.to_owned()) {
+        diagnostics::report(Diagnostic::DuplicateStateName(name.to_owned()));
+    }
+    let key = key.clone();
+    store
+        .savers
+        .borrow_mut()
+        .insert(name.to_owned(), Box::new(move || Persist::save(&*key)));
+}
+
+/// Declares a state variable like `state!`, except that its value is
+/// saved under the given name on exit and restored on the next startup.
+/// The initializer is only used when there is no saved value.
+#[macro_export]
+macro_rules! persistent_state {
+    ($name:expr, $init:expr) => {{
+        let name: &str = $name;
+        let key =
+            $crate::prelude::state!(|| { $crate::persist::restore(name).unwrap_or_else($init) });
+        $crate::persist::register(name, &key);
+        key
+    }};
+}
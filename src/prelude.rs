@@ -1,12 +1,41 @@
 // For naming the type result of mox!()
-pub use crate::dom::{events::*, App, Button, Node, Span, View, Window};
+pub use crate::dom::{events::*, App, Button, CustomLayout, Node, Span, UserData, View, Window};
 // Required for attributes to work
 pub use crate::moxie::*;
 // For easily defining styles
-pub use crate::style::{Direction, Display, Style, Value};
+pub use crate::style::{Direction, Display, Overflow, ScrollLink, ScrollPosition, Style, Value};
 pub use crate::Color;
 pub use moxie_native_style::define_style;
 // Required for mox to work
-pub use crate::{app, button, span, text, view, window};
+pub use crate::{app, button, customlayout, span, text, view, window};
+// For layouts the built-in ones can't express
+pub use crate::layout::{Constraints, CustomChild, LayoutDelegate};
+// For connecting controls to state
+pub use crate::bind;
+// For validating and submitting groups of fields
+pub use crate::form;
+// For passing values down to a subtree
+pub use crate::provide::{consume, provide};
+// For showing placeholders while data loads
+pub use crate::suspend;
+pub use crate::suspense::Load;
+// For building expensive subtrees off the UI thread
+pub use crate::offload;
+// For navigating between views
+pub use crate::router;
+pub use crate::router::{Route, Router};
+// For sharing expensive derived state
+pub use crate::select::Selector;
+// For state that survives restarts
+pub use crate::persist::Persist;
+pub use crate::persistent_state;
+// For scheduling low priority work
+pub use crate::idle::run_when_idle;
+// For opening the files the application was launched with
+pub use crate::open::{on_open, OpenRequest};
+// For refreshing anything derived from the current time
+pub use crate::system::time_changes;
+// For committing state changes made outside of event handlers
+pub use crate::{batch, update_channel};
 // Re-export important moxie pieces
-pub use moxie::{__memo_state_impl, memo, mox, state, Key};
+pub use moxie::{__memo_state_impl, memo, mox, once, state, Key};
@@ -0,0 +1,107 @@
+//! Printing an application's content to a PDF, for things like
+//! invoices and reports.
+//!
+//! The first window of the application is laid out as wide as the area
+//! inside a page's margins, and as tall as its content needs, then cut
+//! into pages. Pages break between boxes rather than through them where
+//! possible, so a paragraph or row which would cross the bottom of a
+//! page starts the next one instead.
+//!
+//! ```rs
+//! print_to_pdf(|| invoice_app!(), &PrintOptions::default(), "invoice.pdf")?;
+//! ```
+//!
+//! The window's content should have an automatic height, since a height
+//! relative to the window would be relative to a single page.
+//!
+//! Each page is a single image, so text in the PDF can't be selected or
+//! searched.
+
+mod pages;
+mod pdf;
+
+pub(crate) use self::pages::page_breaks;
+pub(crate) use self::pdf::PageImage;
+
+use self::pdf::{write_pdf, PageLayout};
+use crate::dom::{App, Node};
+use crate::Runtime;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// The number of logical pixels in a point, since there are 96 logical
+/// pixels to an inch and 72 points.
+const PIXELS_PER_POINT: f32 = 96.0 / 72.0;
+
+/// The size of a sheet of paper, in points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PageSize {
+    pub const A4: PageSize = PageSize {
+        width: 595.0,
+        height: 842.0,
+    };
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+
+    /// Turns the page sideways.
+    pub fn landscape(self) -> PageSize {
+        PageSize {
+            width: self.height,
+            height: self.width,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintOptions {
+    pub page_size: PageSize,
+    /// The blank space around the content on every side, in points.
+    pub margin: f32,
+    /// The resolution pages are rendered at, in dots per inch.
+    pub dpi: f32,
+}
+
+impl Default for PrintOptions {
+    fn default() -> PrintOptions {
+        PrintOptions {
+            page_size: PageSize::A4,
+            margin: 36.0,
+            dpi: 150.0,
+        }
+    }
+}
+
+impl PrintOptions {
+    /// The size of the area inside the margins, in logical pixels.
+    pub(crate) fn content_size(&self) -> (f32, f32) {
+        (
+            (self.page_size.width - self.margin * 2.0) * PIXELS_PER_POINT,
+            (self.page_size.height - self.margin * 2.0) * PIXELS_PER_POINT,
+        )
+    }
+}
+
+/// Renders the first window of the application without opening it, and
+/// writes it to a PDF at `path`.
+pub fn print_to_pdf(
+    root: impl FnMut() -> Node<App> + 'static,
+    options: &PrintOptions,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let pages = Runtime::new(root).render_pages(options);
+    let layout = PageLayout {
+        page_width: options.page_size.width,
+        page_height: options.page_size.height,
+        margin: options.margin,
+    };
+    let mut file = BufWriter::new(File::create(path)?);
+    write_pdf(&mut file, &pages, layout)
+}
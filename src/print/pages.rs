@@ -0,0 +1,62 @@
+/// Decides where pages start, given the vertical extent of every box in
+/// the document, in the same units as `page_height`.
+///
+/// Each page is filled as far as it can be without cutting through a
+/// box which would fit on a page of its own, so that short blocks like
+/// paragraphs and table rows are moved to the next page whole. Boxes
+/// taller than a page have to be cut somewhere, so they don't affect
+/// where pages break.
+pub fn page_breaks(boxes: &[(f32, f32)], page_height: f32, document_height: f32) -> Vec<f32> {
+    let mut breaks = vec![0.0];
+    let mut start = 0.0;
+    while start + page_height < document_height {
+        let limit = start + page_height;
+        let mut cut = limit;
+        // Moving the cut up to the top of one box can put it inside
+        // another, so this repeats until no box is cut through.
+        while let Some(&(top, _)) = boxes.iter().find(|&&(top, bottom)| {
+            top > start && top < cut && cut < bottom && bottom - top <= page_height
+        }) {
+            cut = top;
+        }
+        breaks.push(cut);
+        start = cut;
+    }
+    breaks
+}
+
+#[cfg(test)]
+mod test {
+    use super::page_breaks;
+
+    #[test]
+    fn fits_on_one_page() {
+        assert_eq!(page_breaks(&[(0.0, 50.0)], 100.0, 80.0), vec![0.0]);
+    }
+
+    #[test]
+    fn moves_boxes_to_next_page() {
+        let boxes = [(0.0, 40.0), (40.0, 80.0), (80.0, 120.0), (120.0, 160.0)];
+        assert_eq!(page_breaks(&boxes, 100.0, 160.0), vec![0.0, 80.0]);
+    }
+
+    #[test]
+    fn moves_nested_boxes_with_their_parent() {
+        // A section starting at 60 holds two rows, the second of which
+        // crosses the end of the first page.
+        let boxes = [(0.0, 60.0), (60.0, 140.0), (60.0, 90.0), (90.0, 140.0)];
+        assert_eq!(page_breaks(&boxes, 100.0, 140.0), vec![0.0, 60.0]);
+    }
+
+    #[test]
+    fn cuts_boxes_taller_than_a_page() {
+        let boxes = [(0.0, 250.0)];
+        assert_eq!(page_breaks(&boxes, 100.0, 250.0), vec![0.0, 100.0, 200.0]);
+    }
+
+    #[test]
+    fn moves_boxes_exactly_a_page_tall() {
+        let boxes = [(0.0, 10.0), (10.0, 110.0)];
+        assert_eq!(page_breaks(&boxes, 100.0, 110.0), vec![0.0, 10.0]);
+    }
+}
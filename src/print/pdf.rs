@@ -0,0 +1,173 @@
+use std::io::{self, Write};
+
+/// An image printed on its own page.
+pub struct PageImage {
+    pub width: u32,
+    pub height: u32,
+    /// RGB, with rows from top to bottom.
+    pub pixels: Vec<u8>,
+}
+
+/// The area of a page an image is drawn in, in points from the bottom
+/// left corner of the page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageLayout {
+    pub page_width: f32,
+    pub page_height: f32,
+    pub margin: f32,
+}
+
+/// Builds the file while keeping track of where each object starts, for
+/// the cross-reference table.
+struct PdfWriter {
+    output: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn object(&mut self, id: usize, dictionary: &str, stream: Option<&[u8]>) {
+        debug_assert_eq!(id, self.offsets.len() + 1, "objects are written in order");
+        self.offsets.push(self.output.len());
+        self.output
+            .extend_from_slice(format!("{} 0 obj\n{}\n", id, dictionary).as_bytes());
+        if let Some(stream) = stream {
+            self.output.extend_from_slice(b"stream\n");
+            self.output.extend_from_slice(stream);
+            self.output.extend_from_slice(b"\nendstream\n");
+        }
+        self.output.extend_from_slice(b"endobj\n");
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.output.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            xref
+        ));
+        self.output.extend_from_slice(table.as_bytes());
+        self.output
+    }
+}
+
+/// Writes a PDF with a page for each image, with the image stretched
+/// over the area inside the page's margins.
+pub fn write_pdf(
+    output: &mut impl Write,
+    pages: &[PageImage],
+    layout: PageLayout,
+) -> io::Result<()> {
+    let mut writer = PdfWriter {
+        output: b"%PDF-1.4\n".to_vec(),
+        offsets: vec![],
+    };
+    // The catalog and page tree come first, and then three objects for
+    // each page: the page, its content, and its image.
+    let page_ids = (0..pages.len())
+        .map(|index| format!("{} 0 R", 3 + index * 3))
+        .collect::<Vec<_>>();
+    writer.object(1, "<< /Type /Catalog /Pages 2 0 R >>", None);
+    writer.object(
+        2,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids.join(" "),
+            pages.len()
+        ),
+        None,
+    );
+    for (index, page) in pages.iter().enumerate() {
+        let id = 3 + index * 3;
+        writer.object(
+            id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Image {} 0 R >> >> /Contents {} 0 R >>",
+                layout.page_width,
+                layout.page_height,
+                id + 2,
+                id + 1
+            ),
+            None,
+        );
+        let content = format!(
+            "q {} 0 0 {} {} {} cm /Image Do Q",
+            layout.page_width - layout.margin * 2.0,
+            layout.page_height - layout.margin * 2.0,
+            layout.margin,
+            layout.margin
+        );
+        writer.object(
+            id + 1,
+            &format!("<< /Length {} >>", content.len()),
+            Some(content.as_bytes()),
+        );
+        let image = deflate::deflate_bytes_zlib(&page.pixels);
+        writer.object(
+            id + 2,
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+                page.width,
+                page.height,
+                image.len()
+            ),
+            Some(&image),
+        );
+    }
+    output.write_all(&writer.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_pdf, PageImage, PageLayout};
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    #[test]
+    fn cross_references_point_at_objects() {
+        let page = PageImage {
+            width: 1,
+            height: 1,
+            pixels: vec![255, 0, 0],
+        };
+        let layout = PageLayout {
+            page_width: 612.0,
+            page_height: 792.0,
+            margin: 36.0,
+        };
+        let mut output = vec![];
+        write_pdf(&mut output, &[page], layout).unwrap();
+        assert!(output.starts_with(b"%PDF-1.4\n"));
+        assert!(output.ends_with(b"%%EOF\n"));
+
+        let text = String::from_utf8_lossy(&output);
+        let xref = find(&output, b"xref\n").unwrap();
+        let startxref = text.rsplit("startxref\n").next().unwrap();
+        assert_eq!(startxref.lines().next().unwrap().parse(), Ok(xref));
+
+        // The image isn't text, so the table is read from the bytes.
+        let table = String::from_utf8_lossy(&output[xref..]);
+        let entries = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 5);
+        for (index, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let header = format!("{} 0 obj", index + 1);
+            assert!(output[offset..].starts_with(header.as_bytes()));
+        }
+        assert!(text.contains("/Count 1"));
+        assert!(text.contains("q 540 0 0 720 36 36 cm /Image Do Q"));
+    }
+}
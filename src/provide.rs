@@ -0,0 +1,54 @@
+//! Passes values like a theme, a locale or a service handle down to
+//! every component in a subtree, without threading them through each
+//! component's arguments.
+//!
+//! Values are keyed by their type, so wrapping them in a type specific
+//! to the application avoids clashing with anything else provided. The
+//! innermost value of a type is the one consumed.
+//!
+//! ```rs
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! struct Locale(&'static str);
+//!
+//! #[topo::nested]
+//! fn app() -> Node<App> {
+//!     let content = provide(Locale("fr"), || greeting!());
+//!     // ...
+//! }
+//!
+//! #[topo::nested]
+//! fn greeting() -> Node<Span> {
+//!     let locale = consume::<Locale>().unwrap_or(Locale("en"));
+//!     // ...
+//! }
+//! ```
+
+use std::any::type_name;
+use std::fmt::Debug;
+
+/// Makes `value` available to everything called by `children`,
+/// including the components they create.
+pub fn provide<T, R>(value: T, children: impl FnOnce() -> R) -> R
+where
+    T: Debug + 'static,
+{
+    illicit::child_env!(T => value).enter(children)
+}
+
+/// Returns the innermost value of type `T` provided by an enclosing
+/// call to `provide`, if there is one.
+pub fn consume<T>() -> Option<T>
+where
+    T: Clone + Debug + 'static,
+{
+    illicit::Env::get::<T>().map(|value| (*value).clone())
+}
+
+/// Returns the innermost value of type `T` provided by an enclosing
+/// call to `provide`, panicking if there isn't one.
+pub fn expect<T>() -> T
+where
+    T: Clone + Debug + 'static,
+{
+    consume().unwrap_or_else(|| panic!("no {} has been provided", type_name::<T>()))
+}
@@ -0,0 +1,130 @@
+use crate::layout::LayoutTreeNode;
+use crate::perf::{self, Counter};
+use crate::util::equal_rc::EqualRc;
+use std::collections::HashMap;
+use webrender::api::{Epoch, PipelineId, Transaction};
+
+/// A subtree which is rendered into its own pipeline, and embedded
+/// into its parent's display list.
+struct CachedSubtree {
+    pipeline_id: PipelineId,
+    layout: Option<EqualRc<LayoutTreeNode>>,
+    scroll_generation: usize,
+    transitioned: Vec<topo::Id>,
+    used: bool,
+}
+
+/// Keeps track of subtrees with `render_cache: subtree` set.
+///
+/// Layout is memoized, so a subtree whose layout node is the same
+/// `EqualRc` as in the previous frame hasn't changed, and the display
+/// list that Webrender already has for its pipeline can be used again
+/// without being rebuilt or resent.
+pub struct SubtreeCache {
+    root: PipelineId,
+    next_pipeline: u32,
+    epoch: u32,
+    subtrees: HashMap<topo::Id, CachedSubtree>,
+}
+
+/// Whether a cached subtree needs to be rebuilt.
+pub enum CacheStatus {
+    Clean(PipelineId),
+    Dirty(PipelineId),
+}
+
+impl SubtreeCache {
+    pub fn new(root: PipelineId) -> SubtreeCache {
+        SubtreeCache {
+            root,
+            next_pipeline: 1,
+            epoch: 0,
+            subtrees: HashMap::new(),
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        for subtree in self.subtrees.values_mut() {
+            subtree.used = false;
+        }
+    }
+
+    /// Looks up the pipeline for the subtree rooted at the node with the
+    /// given id. It's dirty if its layout has changed, or if something
+    /// which isn't part of layout (scrolling or transitions) might have.
+    pub fn lookup(
+        &mut self,
+        id: topo::Id,
+        layout: &EqualRc<LayoutTreeNode>,
+        scroll_generation: usize,
+        animating: bool,
+    ) -> CacheStatus {
+        let root = self.root;
+        let next_pipeline = &mut self.next_pipeline;
+        let subtree = self.subtrees.entry(id).or_insert_with(|| {
+            let pipeline_id = PipelineId(root.0, *next_pipeline);
+            *next_pipeline += 1;
+            CachedSubtree {
+                pipeline_id,
+                layout: None,
+                scroll_generation,
+                transitioned: vec![],
+                used: false,
+            }
+        });
+        subtree.used = true;
+
+        let clean = subtree.layout.as_ref() == Some(layout)
+            && subtree.scroll_generation == scroll_generation
+            && !animating;
+        if clean {
+            perf::count(Counter::DisplayListCacheHits, 1);
+            CacheStatus::Clean(subtree.pipeline_id)
+        } else {
+            perf::count(Counter::DisplayListCacheMisses, 1);
+            subtree.layout = Some(layout.clone());
+            subtree.scroll_generation = scroll_generation;
+            CacheStatus::Dirty(subtree.pipeline_id)
+        }
+    }
+
+    /// The ids of the nodes with layout transitions that were in the
+    /// subtree when it was last rebuilt.
+    pub fn transitioned(&self, id: topo::Id) -> &[topo::Id] {
+        self.subtrees
+            .get(&id)
+            .map(|subtree| &subtree.transitioned[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn set_transitioned(&mut self, id: topo::Id, transitioned: Vec<topo::Id>) {
+        if let Some(subtree) = self.subtrees.get_mut(&id) {
+            subtree.transitioned = transitioned;
+        }
+    }
+
+    /// Rebuilds every subtree on the next frame, for changes which
+    /// aren't part of layout, like the selection.
+    pub fn invalidate(&mut self) {
+        for subtree in self.subtrees.values_mut() {
+            subtree.layout = None;
+        }
+    }
+
+    /// The epoch to use for a display list that's being sent.
+    pub fn next_epoch(&mut self) -> Epoch {
+        self.epoch += 1;
+        Epoch(self.epoch)
+    }
+
+    /// Removes the pipelines of subtrees that weren't rendered this
+    /// frame.
+    pub fn end_frame(&mut self, transaction: &mut Transaction) {
+        self.subtrees.retain(|_, subtree| {
+            if !subtree.used {
+                transaction.remove_pipeline(subtree.pipeline_id);
+            }
+            subtree.used
+        });
+    }
+}
@@ -1,129 +1,471 @@
-use crate::dom::input::InputEvent;
+use super::cache::{CacheStatus, SubtreeCache};
+use super::debug::RendererDebug;
+use super::hooks::{FrameHooks, FrameInfo, FrameStage};
+use super::overlay::{DebugOverlay, OverlayPainter};
+use super::pointer::PointerRouter;
+use super::presence::PresenceTransitions;
+use super::scroll::{self, ScrollPhysics, ScrollState, ZoomTransform};
+use super::selection::{self, Selection};
+use super::shared::SharedResources;
+use super::thread::{Frame, RenderSurface, RenderTarget};
+use super::transitions::LayoutTransitions;
+use crate::diagnostics;
+use crate::dom::input::{InputEvent, PointerId, State};
+use crate::dom::node::{AnyNode, Bounds};
+use crate::dom::user_data::HitTest;
 use crate::dom::{Node, Window};
 use crate::layout::{
-    LayoutEngine, LayoutText, LayoutTreeNode, LogicalPixel, LogicalSideOffsets, RenderData,
+    snap_to_device, FontLoader, LayoutEngine, LayoutText, LayoutTreeNode, LogicalPixel,
+    LogicalPoint, LogicalSideOffsets, LogicalSize, LogicalVector, MinContentEngine, RenderData,
+};
+use crate::perf::{self, Counter};
+use crate::style::{
+    ComputedValues, DisplayType, Interactions, Overflow, RenderCache, SafeArea, Scrollbar,
+    StyleEngine, TextSettings,
 };
-use crate::style::StyleEngine;
 use crate::util::equal_rc::EqualRc;
-use gleam::gl;
+use crate::{Color, ColorSpace};
 use skribo::FontRef;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use webrender::{
     api::{
-        units::Au, units::DeviceIntRect, units::DevicePixel, units::LayoutPixel,
-        units::LayoutSideOffsets, BorderDetails, BorderRadius, BorderSide, BorderStyle, ClipMode,
-        ColorF, CommonItemProperties, ComplexClipRegion, DisplayListBuilder, DocumentId, Epoch,
-        FontInstanceKey, FontKey, GlyphInstance, NormalBorder, PipelineId, PrimitiveFlags,
-        RenderApi, RenderNotifier, SpaceAndClipInfo, SpatialId, Transaction,
+        units::Au, units::DevicePixel, units::LayoutPixel, units::LayoutSideOffsets,
+        units::LayoutTransform, BorderDetails, BorderRadius, BorderSide, BorderStyle, ClipMode,
+        CommonItemProperties, ComplexClipRegion, DebugCommand, DisplayListBuilder, DocumentId,
+        Epoch, FilterOp, FontInstanceKey, FontKey, GlyphInstance, NormalBorder, PipelineId,
+        PrimitiveFlags, PropertyBinding, ReferenceFrameKind, RenderApi, SpaceAndClipInfo,
+        SpatialId, Transaction, TransformStyle,
     },
-    euclid::{point2, size2, Point2D, Rect, Scale, Size2D},
-    Renderer, RendererOptions,
+    euclid::{point2, size2, vec3, Point2D, Rect, Scale, Size2D},
 };
-use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window as WinitWindow};
-
-/// Used to wait for frames to be ready in Webrender.
-#[derive(Clone)]
-struct Notifier {
-    events_proxy: EventLoopProxy<()>,
-    tx: mpsc::Sender<()>,
-}
-
-impl RenderNotifier for Notifier {
-    fn clone(&self) -> Box<dyn RenderNotifier> {
-        Box::new(Clone::clone(self))
-    }
-
-    fn wake_up(&self) {
-        self.tx.send(()).unwrap();
-        let _ = self.events_proxy.send_event(());
-    }
-
-    fn new_frame_ready(&self, _: DocumentId, _: bool, _: bool, _: Option<u64>) {
-        self.wake_up();
-    }
-}
+use winit::{dpi::PhysicalSize, window::WindowId};
 
-/// Contains everything needed to display the DOM. It creates an
-/// Angle-based GL context, a Webrender rendering context, and manages
-/// the `LayoutEngine` and `RenderEngine` for creating the DOM's layout
-/// and paint trees. It handles bubbling input events through the DOM as
+/// Contains everything needed to display the DOM. It creates a
+/// Webrender instance on the render thread, and manages the
+/// `LayoutEngine` and `RenderEngine` for creating the DOM's layout and
+/// paint trees. It handles bubbling input events through the DOM as
 /// well.
 pub struct Context {
     api: RenderApi,
     document: DocumentId,
-    rx: mpsc::Receiver<()>,
-    renderer: Renderer,
+    surface: RenderSurface,
     layout_engine: LayoutEngine,
+    /// Only created once the window's minimum size is needed.
+    min_content_engine: Option<MinContentEngine>,
+    /// Only created once the window's natural size is needed.
+    natural_engine: Option<LayoutEngine>,
+    fonts_loader: Rc<RefCell<FontLoader>>,
+    /// When printing, the content is laid out at this size rather than
+    /// the size of the window, and drawn starting `document_offset`
+    /// down from its top.
+    document_size: Option<LogicalSize>,
+    document_offset: LogicalVector,
     style_engine: StyleEngine,
     window: Node<Window>,
     client_size: Size2D<i32, DevicePixel>,
     dpi_scale: f32,
+    clear_color: Color,
+    /// The color space of the surface, which colors are converted to
+    /// before they're drawn.
+    color_space: ColorSpace,
     fonts: HashMap<String, FontKey>,
     font_instances: HashMap<(FontKey, usize), FontInstanceKey>,
+    transitions: LayoutTransitions,
+    presence: PresenceTransitions,
+    scroll: ScrollState,
+    cache: SubtreeCache,
+    hooks: Rc<FrameHooks>,
+    debug_overlay: Option<DebugOverlay>,
+    renderer_debug: RendererDebug,
+    /// The node which handled the last `MouseRested` event, until the
+    /// pointer leaves it.
+    hover_intent: Option<AnyNode>,
+    pointer: PointerRouter,
+    selection: Option<Selection>,
+    window_id: Option<WindowId>,
+    revision: u64,
+    frame: u64,
+}
+
+/// Finds the ids of all the nodes in a subtree with layout, enter or
+/// exit transitions.
+fn collect_transitioned(layout: &LayoutTreeNode, ids: &mut Vec<topo::Id>) {
+    if let RenderData::Node(ref node) = layout.render {
+        let values = node.computed_values().get().unwrap();
+        let transitioned = values.layout_transition.is_some()
+            || values.enter_transition.is_some()
+            || values.exit_transition.is_some();
+        if let (true, Some(id)) = (transitioned, node.id()) {
+            ids.push(id);
+        }
+        for child in &layout.children {
+            collect_transitioned(&child.layout, ids);
+        }
+    }
+}
+
+/// Draws the items pushed until the matching `pop_stacking_context`
+/// with the given opacity.
+fn push_opacity(builder: &mut DisplayListBuilder, space_and_clip: SpaceAndClipInfo, opacity: f32) {
+    builder.push_simple_stacking_context_with_filters(
+        point2(0.0, 0.0),
+        space_and_clip.spatial_id,
+        PrimitiveFlags::IS_BACKFACE_VISIBLE,
+        &[FilterOp::Opacity(PropertyBinding::Value(opacity), opacity)],
+        &[],
+        &[],
+    );
+}
+
+/// How far into a scroll container the glow from overscrolling it
+/// reaches, in logical pixels.
+const GLOW_SIZE: f32 = 24.0;
+
+/// Draws a shadow along each edge of a scroll container which has been
+/// scrolled past, which is darker the further it went.
+fn push_glow(
+    builder: &mut DisplayListBuilder,
+    space_and_clip: SpaceAndClipInfo,
+    rect: Rect<f32, LayoutPixel>,
+    glow: LogicalVector,
+    color_space: ColorSpace,
+) {
+    let alpha = |glow: f32| (glow.abs() / scroll::MAX_GLOW * 64.0) as u8;
+    let size = rect.size;
+    let edges = [
+        (
+            glow.x < 0.0,
+            alpha(glow.x),
+            rect.origin,
+            size2(GLOW_SIZE, size.height),
+        ),
+        (
+            glow.x > 0.0,
+            alpha(glow.x),
+            point2(rect.max_x() - GLOW_SIZE, rect.origin.y),
+            size2(GLOW_SIZE, size.height),
+        ),
+        (
+            glow.y < 0.0,
+            alpha(glow.y),
+            rect.origin,
+            size2(size.width, GLOW_SIZE),
+        ),
+        (
+            glow.y > 0.0,
+            alpha(glow.y),
+            point2(rect.origin.x, rect.max_y() - GLOW_SIZE),
+            size2(size.width, GLOW_SIZE),
+        ),
+    ];
+    for &(glowing, alpha, origin, size) in &edges {
+        if glowing && alpha > 0 {
+            let common = CommonItemProperties::new(Rect::new(origin, size), space_and_clip);
+            let color = Color::new(0, 0, 0, alpha).to_color_f(color_space);
+            builder.push_rect(&common, color);
+        }
+    }
+}
+
+/// Draws a scrollbar along the right edge of a scroll container for
+/// vertical scrolling, and along the bottom for horizontal scrolling.
+fn push_scrollbars(
+    builder: &mut DisplayListBuilder,
+    space_and_clip: SpaceAndClipInfo,
+    rect: Rect<f32, LayoutPixel>,
+    values: &ComputedValues,
+    offset: LogicalVector,
+    max_offset: LogicalVector,
+    opacity: f32,
+    color_space: ColorSpace,
+) {
+    let fade = |color: Color| Color {
+        alpha: (color.alpha as f32 * opacity) as u8,
+        ..color
+    };
+    let thickness = values
+        .scrollbar_thickness
+        .get()
+        .min(rect.size.width.min(rect.size.height));
+    let mut push = |rect: Rect<f32, LayoutPixel>, color: Color| {
+        if color.alpha > 0 {
+            builder.push_rect(
+                &CommonItemProperties::new(rect, space_and_clip),
+                color.to_color_f(color_space),
+            );
+        }
+    };
+    let vertical = Rect::new(
+        point2(rect.max_x() - thickness, rect.origin.y),
+        size2(thickness, rect.size.height),
+    );
+    if let Some((start, length)) = scroll::thumb(vertical.size.height, offset.y, max_offset.y) {
+        push(vertical, fade(values.scrollbar_track_color));
+        let thumb = Rect::new(
+            point2(vertical.origin.x, vertical.origin.y + start),
+            size2(thickness, length),
+        );
+        push(thumb, fade(values.scrollbar_thumb_color));
+    }
+    let horizontal = Rect::new(
+        point2(rect.origin.x, rect.max_y() - thickness),
+        size2(rect.size.width, thickness),
+    );
+    if let Some((start, length)) = scroll::thumb(horizontal.size.width, offset.x, max_offset.x) {
+        push(horizontal, fade(values.scrollbar_track_color));
+        let thumb = Rect::new(
+            point2(horizontal.origin.x + start, horizontal.origin.y),
+            size2(length, thickness),
+        );
+        push(thumb, fade(values.scrollbar_thumb_color));
+    }
+}
+
+/// Collects the vertical extent of every box in a subtree, with `top`
+/// being where the subtree starts.
+#[cfg_attr(not(feature = "print"), allow(dead_code))]
+fn collect_boxes(layout: &LayoutTreeNode, top: f32, boxes: &mut Vec<(f32, f32)>) {
+    for child in &layout.children {
+        let child_top = top + child.position.y;
+        boxes.push((child_top, child_top + child.layout.size.height));
+        collect_boxes(&child.layout, child_top, boxes);
+    }
+}
+
+/// A container which the mouse wheel can move the content of.
+#[derive(Clone, Copy, Debug)]
+enum ScrollTarget {
+    Scroll(topo::Id),
+    /// A zoom container, with the point the pointer is over, relative
+    /// to the container.
+    Zoom(topo::Id, LogicalPoint),
 }
 
-fn convert_offsets(input: LogicalSideOffsets) -> LayoutSideOffsets {
+pub(super) fn convert_offsets(input: LogicalSideOffsets) -> LayoutSideOffsets {
     LayoutSideOffsets::new(input.top, input.right, input.bottom, input.left)
 }
 
 impl Context {
+    /// Creates a context drawing into `target`. `window_id` is the id
+    /// of the native window, if there is one, and is passed on to frame
+    /// hooks. `clear_color` is drawn as is, in the target's color space.
+    /// Fails if Webrender can't draw into the target.
     pub fn new(
-        gl: Rc<dyn gl::Gl>,
-        parent_window: &WinitWindow,
-        events_proxy: EventLoopProxy<()>,
+        target: RenderTarget,
+        window_id: Option<WindowId>,
+        client_size: Size2D<i32, DevicePixel>,
+        dpi_scale: f32,
         window: Node<Window>,
-    ) -> Context {
-        let (tx, rx) = mpsc::channel();
-        let notifier = Box::new(Notifier { events_proxy, tx });
-
-        let dpi_scale = parent_window.hidpi_factor() as f32;
-        let inner_size = parent_window.inner_size().to_physical(dpi_scale as f64);
-        let client_size =
-            Size2D::<i32, DevicePixel>::new(inner_size.width as i32, inner_size.height as i32);
-
-        let (renderer, sender) = Renderer::new(
-            gl,
-            notifier.clone(),
-            RendererOptions {
-                clear_color: Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
-                device_pixel_ratio: dpi_scale,
-                ..Default::default()
-            },
-            None,
-            client_size,
-        )
-        .unwrap();
-        let api = sender.create_api();
-        let document = api.add_document(client_size, 0);
+        clear_color: Color,
+        shared: &SharedResources,
+    ) -> Result<Context, String> {
+        let render_thread = &shared.render_thread;
+        let surface = render_thread.add_surface(target, client_size, dpi_scale, clear_color)?;
+        let api = surface.api_sender.create_api();
+        let document = surface.document;
 
-        Context {
+        Ok(Context {
             api,
             document,
-            rx,
-            renderer,
+            surface,
             window,
-            layout_engine: LayoutEngine::new(),
+            layout_engine: LayoutEngine::new(shared.fonts.clone()),
+            min_content_engine: None,
+            natural_engine: None,
+            fonts_loader: shared.fonts.clone(),
+            document_size: None,
+            document_offset: LogicalVector::zero(),
             style_engine: StyleEngine::new(),
             client_size,
             dpi_scale,
+            clear_color,
+            color_space: ColorSpace::Srgb,
             fonts: HashMap::new(),
             font_instances: HashMap::new(),
-        }
+            transitions: LayoutTransitions::default(),
+            presence: PresenceTransitions::default(),
+            scroll: ScrollState::default(),
+            cache: SubtreeCache::new(PipelineId(0, 0)),
+            hooks: shared.hooks.clone(),
+            debug_overlay: None,
+            renderer_debug: RendererDebug::default(),
+            hover_intent: None,
+            pointer: PointerRouter::default(),
+            selection: None,
+            window_id,
+            revision: 0,
+            frame: 0,
+        })
+    }
+
+    /// Whether the GL context being drawn to was lost, in which case
+    /// nothing is drawn until it's replaced with `replace_target`.
+    pub fn is_lost(&self) -> bool {
+        self.surface.is_lost()
+    }
+
+    /// Starts drawing into `target` instead of the lost one. Fonts and
+    /// cached subtrees were lost along with the old Webrender instance,
+    /// and are sent to the new one with the next frame.
+    pub fn replace_target(
+        &mut self,
+        target: RenderTarget,
+        window_id: Option<WindowId>,
+        shared: &SharedResources,
+    ) -> Result<(), String> {
+        self.surface = shared.render_thread.add_surface(
+            target,
+            self.client_size,
+            self.dpi_scale,
+            self.clear_color,
+        )?;
+        self.api = self.surface.api_sender.create_api();
+        self.document = self.surface.document;
+        self.window_id = window_id;
+        self.fonts.clear();
+        self.font_instances.clear();
+        self.cache = SubtreeCache::new(PipelineId(0, 0));
+        self.set_renderer_debug(self.renderer_debug);
+        Ok(())
+    }
+
+    pub fn dom_window(&self) -> &Node<Window> {
+        &self.window
     }
 
     pub fn set_dom_window(&mut self, new_node: Node<Window>) {
         if new_node != self.window {
             self.window = new_node;
+            self.revision += 1;
         }
     }
 
+    /// Picks up fonts that finished loading in the background. Returns
+    /// true if the window needs to be rendered again.
+    pub fn poll_fonts(&mut self) -> bool {
+        self.layout_engine.poll_fonts()
+    }
+
+    /// Blocks until fonts are loaded or the timeout expires.
+    pub fn wait_for_fonts(&mut self, timeout: Duration) {
+        self.layout_engine.wait_for_fonts(timeout);
+    }
+
+    /// The elements the user is interacting with, which the next frame
+    /// styles with `:hover`, `:active` and `:focus`.
+    pub fn interactions(&self) -> &Interactions {
+        self.pointer.interactions()
+    }
+
+    /// Sets the pointer the following mouse events come from.
+    pub fn set_pointer(&mut self, pointer: PointerId) {
+        self.pointer.set_pointer(pointer);
+    }
+
+    pub fn set_text_settings(&mut self, text_settings: TextSettings) {
+        self.style_engine.set_text_settings(text_settings);
+    }
+
+    pub fn set_safe_area(&mut self, safe_area: SafeArea) {
+        self.style_engine.set_safe_area(safe_area);
+    }
+
+    pub fn set_scroll_physics(&mut self, physics: ScrollPhysics) {
+        self.scroll.set_physics(physics);
+    }
+
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    pub fn debug_overlay(&self) -> Option<DebugOverlay> {
+        self.debug_overlay
+    }
+
+    /// Shows layout information over the content from the next frame on,
+    /// or stops showing it.
+    pub fn set_debug_overlay(&mut self, overlay: Option<DebugOverlay>) {
+        self.debug_overlay = overlay;
+    }
+
+    /// Shows Webrender's debugging views in the window.
+    pub fn set_renderer_debug(&mut self, debug: RendererDebug) {
+        self.renderer_debug = debug;
+        self.api
+            .send_debug_cmd(DebugCommand::SetFlags(debug.flags()));
+    }
+
     pub fn resize(&mut self, size: PhysicalSize, dpi_scale: f32) {
         self.client_size = size2(size.width as i32, size.height as i32);
         self.dpi_scale = dpi_scale;
     }
 
+    /// The smallest size the window's content fits in, in the window's
+    /// logical pixels. Styles are those of the last frame.
+    pub fn min_content_size(&mut self) -> LogicalSize {
+        let content_size = self.client_size.to_f32() / Scale::new(self.dpi_scale);
+        let fonts = &self.fonts_loader;
+        self.min_content_engine
+            .get_or_insert_with(|| MinContentEngine::new(fonts.clone()))
+            .min_size(
+                self.window.clone(),
+                content_size * Scale::new(1.0),
+                Scale::new(self.dpi_scale),
+            )
+    }
+
+    /// The size the window's content takes up when given up to
+    /// `max_size`, in the window's logical pixels. Styles are those of
+    /// the last frame.
+    pub fn natural_size(&mut self, max_size: LogicalSize) -> LogicalSize {
+        let fonts = &self.fonts_loader;
+        let layout = self
+            .natural_engine
+            .get_or_insert_with(|| LayoutEngine::new(fonts.clone()))
+            .layout(self.window.clone(), max_size, Scale::new(self.dpi_scale));
+        let extent = layout.content_extent();
+        size2(
+            extent.width.min(max_size.width),
+            extent.height.min(max_size.height),
+        )
+    }
+
+    /// Lays the content out `width` logical pixels wide with no limit
+    /// on its height, to be printed. Frames show this layout from then
+    /// on, starting from `set_document_offset`. Returns the height of
+    /// the content and the vertical extent of every box in it.
+    #[cfg_attr(not(feature = "print"), allow(dead_code))]
+    pub fn layout_document(&mut self, width: f32) -> (f32, Vec<(f32, f32)>) {
+        let content_size = self.client_size.to_f32() / Scale::new(self.dpi_scale);
+        self.style_engine.update(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            self.scroll.positions(),
+            self.pointer.interactions().clone(),
+        );
+        let layout = self.layout_engine.layout(
+            self.window.clone(),
+            size2(width, f32::INFINITY),
+            Scale::new(self.dpi_scale),
+        );
+        let height = layout.content_extent().height;
+        let mut boxes = vec![];
+        collect_boxes(&layout, 0.0, &mut boxes);
+        self.document_size = Some(size2(width, height));
+        (height, boxes)
+    }
+
+    /// How far down the document laid out by `layout_document` frames
+    /// start, in logical pixels.
+    #[cfg_attr(not(feature = "print"), allow(dead_code))]
+    pub fn set_document_offset(&mut self, offset: f32) {
+        self.document_offset = LogicalVector::new(0.0, offset);
+    }
+
     fn get_font(&mut self, font: &FontRef, txn: &mut Transaction) -> FontKey {
         let full_name = font.font.full_name();
         if let Some(&key) = self.fonts.get(&full_name) {
@@ -160,17 +502,215 @@ impl Context {
         instance
     }
 
+    /// Returns the id of the node if it's a scroll container.
+    fn scroll_container(node: &AnyNode) -> Option<topo::Id> {
+        match node.computed_values().get().unwrap().display {
+            DisplayType::Block(ref block) if block.overflow == Overflow::Scroll => node.id(),
+            _ => None,
+        }
+    }
+
+    /// How far the children of the node have been scrolled.
+    fn scroll_offset(&self, node: &AnyNode) -> LogicalVector {
+        match Self::scroll_container(node) {
+            Some(id) => self.scroll.offset(id),
+            None => LogicalVector::zero(),
+        }
+    }
+
+    /// Returns the id of the node if it's a zoom container.
+    fn zoom_container(node: &AnyNode) -> Option<topo::Id> {
+        match node.computed_values().get().unwrap().display {
+            DisplayType::Block(ref block) if block.overflow == Overflow::Zoom => node.id(),
+            _ => None,
+        }
+    }
+
+    /// How the children of a zoom container at `position` are drawn,
+    /// relative to what the container's position is relative to.
+    fn zoom(&self, node: &AnyNode, position: LogicalPoint) -> Option<ZoomTransform> {
+        Self::zoom_container(node).map(|id| self.scroll.zoom(id).about(position))
+    }
+
+    /// Converts a point relative to a node into the space its children
+    /// are positioned in.
+    fn to_children(&self, node: &AnyNode, point: LogicalPoint) -> LogicalPoint {
+        let point = match Self::zoom_container(node) {
+            Some(id) => self.scroll.zoom(id).invert(point),
+            None => point,
+        };
+        point + self.scroll_offset(node)
+    }
+
     fn render_child(
         &mut self,
         pipeline_id: PipelineId,
         builder: &mut DisplayListBuilder,
         transaction: &mut Transaction,
+        space_and_clip: SpaceAndClipInfo,
+        position: Point2D<f32, LogicalPixel>,
+        layout: &EqualRc<LayoutTreeNode>,
+    ) {
+        if let RenderData::Node(ref node) = layout.render {
+            let values = node.computed_values().get().unwrap();
+            if let (RenderCache::Subtree, Some(id)) = (values.render_cache, node.id()) {
+                self.render_cached(id, builder, transaction, space_and_clip, position, layout);
+                return;
+            }
+        }
+        self.render_node(
+            pipeline_id,
+            builder,
+            transaction,
+            space_and_clip,
+            position,
+            layout,
+        );
+    }
+
+    /// Records where each node is in the window, for
+    /// `NodeData::bounds`. `zoom` takes positions inside any zoom
+    /// containers the node is in to the window.
+    fn record_bounds(&self, position: LogicalPoint, layout: &LayoutTreeNode, zoom: ZoomTransform) {
+        if let RenderData::Node(ref node) = layout.render {
+            let origin = zoom.apply(position);
+            node.set_bounds(Bounds {
+                x: origin.x,
+                y: origin.y,
+                width: layout.size.width * zoom.scale,
+                height: layout.size.height * zoom.scale,
+            });
+            let scroll_offset = self.scroll_offset(node);
+            let child_zoom = match self.zoom(node, position) {
+                Some(own) => own.then(zoom),
+                None => zoom,
+            };
+            for child in &layout.children {
+                let position = position + child.position.to_vector() - scroll_offset;
+                self.record_bounds(position, &child.layout, child_zoom);
+            }
+        }
+    }
+
+    /// Renders a subtree into its own pipeline, which is then embedded
+    /// into the parent display list. The pipeline is only rebuilt when
+    /// the subtree changes. Children which overflow the subtree's root
+    /// are clipped.
+    fn render_cached(
+        &mut self,
+        id: topo::Id,
+        builder: &mut DisplayListBuilder,
+        transaction: &mut Transaction,
+        space_and_clip: SpaceAndClipInfo,
         position: Point2D<f32, LogicalPixel>,
         layout: &EqualRc<LayoutTreeNode>,
     ) {
+        let animating = self.transitions.is_animating() || self.presence.is_animating();
+        let status = self
+            .cache
+            .lookup(id, layout, self.scroll.generation(), animating);
+        let pipeline_id = match status {
+            CacheStatus::Clean(pipeline_id) => {
+                self.transitions.keep(self.cache.transitioned(id));
+                self.presence.keep(self.cache.transitioned(id));
+                pipeline_id
+            }
+            CacheStatus::Dirty(pipeline_id) => {
+                let size = layout.size * Scale::new(1.0);
+                let mut subtree_builder = DisplayListBuilder::new(pipeline_id, size);
+                self.presence.push_subtree(position);
+                self.render_node(
+                    pipeline_id,
+                    &mut subtree_builder,
+                    transaction,
+                    SpaceAndClipInfo::root_scroll(pipeline_id),
+                    Point2D::zero(),
+                    layout,
+                );
+                self.presence.pop_parent();
+
+                let mut transitioned = vec![];
+                collect_transitioned(layout, &mut transitioned);
+                self.cache.set_transitioned(id, transitioned);
+
+                let epoch = self.cache.next_epoch();
+                let display_list = subtree_builder.finalize();
+                perf::count(Counter::DisplayListBytes, display_list.2.data().len());
+                transaction.set_display_list(epoch, None, size, display_list, true);
+                pipeline_id
+            }
+        };
+
+        // The subtree's text is snapped relative to its origin, so the
+        // origin needs to be snapped as well.
+        let scale = Scale::new(self.dpi_scale);
+        let position = point2(
+            snap_to_device(position.x, scale),
+            snap_to_device(position.y, scale),
+        );
         let rect = Rect::new(position, layout.size) * Scale::new(1.0);
+        builder.push_iframe(rect, rect, &space_and_clip, pipeline_id, true);
+    }
 
-        let space_and_clip = SpaceAndClipInfo::root_scroll(pipeline_id);
+    /// Draws the removed children of the node whose children were just
+    /// drawn which are still fading out, over its other children, where
+    /// they were last drawn.
+    fn render_exits(
+        &mut self,
+        pipeline_id: PipelineId,
+        builder: &mut DisplayListBuilder,
+        transaction: &mut Transaction,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let exits = self.presence.begin_exits();
+        if exits.is_empty() {
+            return;
+        }
+        for (position, layout, opacity) in exits {
+            push_opacity(builder, space_and_clip, opacity);
+            self.render_node(
+                pipeline_id,
+                builder,
+                transaction,
+                space_and_clip,
+                position,
+                &layout,
+            );
+            builder.pop_stacking_context();
+        }
+        self.presence.end_exits();
+    }
+
+    fn render_node(
+        &mut self,
+        pipeline_id: PipelineId,
+        builder: &mut DisplayListBuilder,
+        transaction: &mut Transaction,
+        space_and_clip: SpaceAndClipInfo,
+        position: Point2D<f32, LogicalPixel>,
+        layout: &EqualRc<LayoutTreeNode>,
+    ) {
+        let mut position = position;
+        let mut size = layout.size;
+        let mut opacity = 1.0;
+        if let RenderData::Node(ref node) = layout.render {
+            let values = node.computed_values().get().unwrap();
+            if let (Some(transition), Some(id)) = (values.layout_transition, node.id()) {
+                let rect = self
+                    .transitions
+                    .resolve(id, Rect::new(position, size), transition);
+                position = rect.origin;
+                size = rect.size;
+            }
+            let (enter, exit) = (values.enter_transition, values.exit_transition);
+            if let (true, Some(id)) = (enter.is_some() || exit.is_some(), node.id()) {
+                opacity = self.presence.resolve(id, position, layout, enter, exit);
+            }
+        }
+        let rect = Rect::new(position, size) * Scale::new(1.0);
+        if opacity < 1.0 {
+            push_opacity(builder, space_and_clip, opacity);
+        }
 
         match layout.render {
             RenderData::Node(ref node) => {
@@ -182,7 +722,7 @@ impl Context {
                     let common = CommonItemProperties::new(rect, space_and_clip);
                     let side = BorderSide {
                         style: BorderStyle::Solid,
-                        color: values.border_color.into(),
+                        color: values.border_color.to_color_f(self.color_space),
                     };
                     builder.push_border(
                         &common,
@@ -207,56 +747,143 @@ impl Context {
                             BorderRadius::uniform(values.border_radius.get()),
                             ClipMode::Clip,
                         );
-                        let clip = builder.define_clip(
-                            &SpaceAndClipInfo::root_scroll(pipeline_id),
-                            rect,
-                            vec![region],
-                            None,
-                        );
+                        let clip = builder.define_clip(&space_and_clip, rect, vec![region], None);
                         CommonItemProperties::new(
                             rect,
                             SpaceAndClipInfo {
-                                spatial_id: SpatialId::root_scroll_node(pipeline_id),
+                                spatial_id: space_and_clip.spatial_id,
                                 clip_id: clip,
                             },
                         )
                     } else {
                         CommonItemProperties::new(rect, space_and_clip)
                     };
-                    builder.push_rect(&item_props, values.background_color.into());
+                    let color = values.background_color.to_color_f(self.color_space);
+                    builder.push_rect(&item_props, color);
                 }
 
+                let mut child_space_and_clip = space_and_clip;
+                let zoom = self.zoom(node, position);
+                if Self::scroll_container(node).is_some() || zoom.is_some() {
+                    let clip_rect = rect.inner_rect(convert_offsets(values.border_thickness));
+                    child_space_and_clip.clip_id = builder.define_clip(
+                        &space_and_clip,
+                        clip_rect,
+                        Vec::<ComplexClipRegion>::new(),
+                        None,
+                    );
+                }
+                if let Some(id) = Self::scroll_container(node) {
+                    self.scroll.update_extent(
+                        id,
+                        node.scroll_name(),
+                        layout.size,
+                        scroll::content_size(layout),
+                    );
+                }
+                // Zoomed children are laid out and positioned as usual,
+                // inside a reference frame which scales and moves them.
+                if let Some(zoom) = zoom {
+                    let transform = LayoutTransform::create_scale(zoom.scale, zoom.scale, 1.0)
+                        .post_translate(vec3(zoom.pan.x, zoom.pan.y, 0.0));
+                    child_space_and_clip.spatial_id = builder.push_reference_frame(
+                        point2(0.0, 0.0),
+                        space_and_clip.spatial_id,
+                        TransformStyle::Flat,
+                        PropertyBinding::Value(transform),
+                        ReferenceFrameKind::Transform,
+                    );
+                }
+                let origin = position - self.scroll_offset(node);
+
+                self.presence.push_parent(node.id(), origin);
                 for layout in &layout.children {
                     self.render_child(
                         pipeline_id,
                         builder,
                         transaction,
-                        position + layout.position.to_vector(),
+                        child_space_and_clip,
+                        origin + layout.position.to_vector(),
                         &layout.layout,
                     );
                 }
+                self.render_exits(pipeline_id, builder, transaction, child_space_and_clip);
+                self.presence.pop_parent();
+                if zoom.is_some() {
+                    builder.pop_reference_frame();
+                }
+                if let Some(id) = Self::scroll_container(node) {
+                    let clip_rect = rect.inner_rect(convert_offsets(values.border_thickness));
+                    push_glow(
+                        builder,
+                        child_space_and_clip,
+                        clip_rect,
+                        self.scroll.glow(id),
+                        self.color_space,
+                    );
+                    let opacity = match values.scrollbar {
+                        Scrollbar::Overlay => self.scroll.scrollbar_opacity(id),
+                        Scrollbar::Always => 1.0,
+                        Scrollbar::Hidden => 0.0,
+                    };
+                    if opacity > 0.0 {
+                        push_scrollbars(
+                            builder,
+                            child_space_and_clip,
+                            clip_rect,
+                            &values,
+                            self.scroll.offset(id),
+                            self.scroll.max_offset(id),
+                            opacity,
+                            self.color_space,
+                        );
+                    }
+                }
             }
             RenderData::Text {
                 text:
                     LayoutText {
                         ref fragments,
+                        ref clusters,
+                        ref decorations,
                         size,
+                        ..
                     },
                 ref parent,
             } => {
                 let values = parent.computed_values().get().unwrap();
-                let color = values.text_color;
+                let color = values.text_color.to_color_f(self.color_space);
                 builder.push_simple_stacking_context(
                     point2(0.0, 0.0),
                     space_and_clip.spatial_id,
                     PrimitiveFlags::IS_BACKFACE_VISIBLE,
                 );
+                let selected = match self.selection {
+                    Some(selection) if parent.id() == Some(selection.node) => {
+                        selection::highlight(clusters, selection.range())
+                    }
+                    _ => vec![],
+                };
+                for (left, right) in selected {
+                    let highlight = Rect::new(
+                        point2(position.x + left, position.y),
+                        size2(right - left, layout.size.height),
+                    ) * Scale::new(1.0);
+                    builder.push_rect(
+                        &CommonItemProperties::new(highlight, space_and_clip),
+                        values.selection_color.to_color_f(self.color_space),
+                    );
+                }
                 for fragment in fragments {
+                    let scale = Scale::new(self.dpi_scale);
                     let glyphs = fragment
                         .glyphs
                         .iter()
                         .map(|glyph| {
+                            // Keep the baseline on a whole device pixel,
+                            // otherwise the glyphs are rasterized blurry.
                             let pos = position + glyph.offset.to_vector();
+                            let pos = point2(pos.x, snap_to_device(pos.y, scale));
                             GlyphInstance {
                                 index: glyph.index,
                                 point: pos * Scale::new(1.0),
@@ -270,109 +897,508 @@ impl Context {
                         rect,
                         &glyphs[..],
                         key,
-                        color.into(),
+                        color,
                         None,
                     );
                 }
+                for decoration in decorations {
+                    let decoration = decoration.translate(position.to_vector()) * Scale::new(1.0);
+                    builder.push_rect(
+                        &CommonItemProperties::new(decoration, space_and_clip),
+                        color,
+                    );
+                }
                 builder.pop_stacking_context();
             }
         }
+        if opacity < 1.0 {
+            builder.pop_stacking_context();
+        }
+    }
+
+    /// Renders a frame. Returns true if layout transitions are still
+    /// running or content is still scrolling on its own, in which case
+    /// another frame should be rendered soon.
+    pub fn render(&mut self) -> bool {
+        self.render_frame(None)
+    }
+
+    /// Renders a frame, and waits until it's been drawn.
+    pub fn render_and_wait(&mut self) -> bool {
+        let (tx, rx) = mpsc::channel();
+        let animating = self.render_frame(Some(tx));
+        let _ = rx.recv();
+        animating
+    }
+
+    /// Renders a frame, waits until it's been drawn, and returns its
+    /// pixels as RGBA rows from top to bottom. The context must have
+    /// been created with a headless target.
+    pub fn capture(&mut self) -> Vec<u8> {
+        let (tx, rx) = mpsc::channel();
+        self.render_frame(Some(tx));
+        rx.recv().unwrap()
     }
 
-    pub fn render(&mut self) {
+    /// Builds the display list for a frame, and hands it to the render
+    /// thread.
+    fn render_frame(&mut self, presented: Option<mpsc::Sender<Vec<u8>>>) -> bool {
         let client_size = self.client_size;
         let dpi_scale = Scale::new(self.dpi_scale);
         let content_size = client_size.to_f32() / dpi_scale;
 
         println!("render()");
+        let mut info = FrameInfo {
+            window: self.window_id,
+            revision: self.revision,
+            frame: self.frame,
+            width: content_size.width,
+            height: content_size.height,
+            dpi_scale: self.dpi_scale,
+            started: Instant::now(),
+            layout_time: None,
+            render_time: None,
+        };
+        self.frame += 1;
+        self.hooks.run(FrameStage::BeforeFrame, &info);
+        let scrolling = self.scroll.animate(info.started);
+
         let pipeline_id = PipelineId(0, 0);
         let mut builder = DisplayListBuilder::new(pipeline_id, content_size);
         let mut transaction = Transaction::new();
 
-        self.style_engine
-            .update(self.window.clone(), content_size * Scale::new(1.0));
+        self.style_engine.update(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            self.scroll.positions(),
+            self.pointer.interactions().clone(),
+        );
 
-        let root_layout = self
-            .layout_engine
-            .layout(self.window.clone(), content_size * Scale::new(1.0));
+        let layout_size = self.document_size.unwrap_or(content_size * Scale::new(1.0));
+        let root_layout =
+            self.layout_engine
+                .layout(self.window.clone(), layout_size, Scale::new(self.dpi_scale));
+        info.layout_time = Some(info.started.elapsed());
+        diagnostics::check_layout(&root_layout);
+        self.record_bounds(
+            LogicalPoint::origin() - self.document_offset,
+            &root_layout,
+            ZoomTransform::identity(),
+        );
+        self.hooks.run(FrameStage::AfterLayout, &info);
 
+        let render_started = Instant::now();
+        self.transitions.begin_frame();
+        self.presence.begin_frame();
+        self.cache.begin_frame();
+        let origin = LogicalPoint::origin() - self.document_offset;
+        self.presence.push_parent(None, origin);
         for layout in &root_layout.children {
             self.render_child(
                 pipeline_id,
                 &mut builder,
                 &mut transaction,
-                layout.position,
+                SpaceAndClipInfo::root_scroll(pipeline_id),
+                origin + layout.position.to_vector(),
                 &layout.layout,
             );
         }
-
-        transaction.set_display_list(Epoch(0), None, content_size, builder.finalize(), true);
-        transaction.set_root_pipeline(pipeline_id);
-        transaction.generate_frame();
-        self.api.set_document_view(
-            self.document,
-            DeviceIntRect::new(Point2D::zero(), client_size.to_i32()),
-            dpi_scale.get(),
+        self.render_exits(
+            pipeline_id,
+            &mut builder,
+            &mut transaction,
+            SpaceAndClipInfo::root_scroll(pipeline_id),
         );
+        self.presence.pop_parent();
+        if let Some(overlay) = self.debug_overlay {
+            let mut painter = OverlayPainter {
+                overlay,
+                builder: &mut builder,
+                space_and_clip: SpaceAndClipInfo::root_scroll(pipeline_id),
+                hairline: 1.0 / self.dpi_scale,
+                color_space: self.color_space,
+                scroll_offset: &|node| self.scroll_offset(node),
+            };
+            for layout in &root_layout.children {
+                painter.draw_node(layout.position - self.document_offset, &layout.layout);
+            }
+            painter.draw_grid(content_size * Scale::new(1.0));
+        }
+
+        self.cache.end_frame(&mut transaction);
+        // Fonts and cached subtrees are sent right away rather than with
+        // the frame, so they aren't lost if the frame is replaced by a
+        // newer one before it's drawn.
         self.api.send_transaction(self.document, transaction);
-        self.rx.recv().unwrap();
-        self.renderer.update();
-        let _ = self.renderer.render(client_size.to_i32());
-        let _ = self.renderer.flush_pipeline_info();
+
+        let mut frame = Transaction::new();
+        let epoch = self.cache.next_epoch();
+        let display_list = builder.finalize();
+        perf::count(Counter::DisplayListBytes, display_list.2.data().len());
+        frame.set_display_list(epoch, None, content_size, display_list, true);
+        frame.set_root_pipeline(pipeline_id);
+        frame.generate_frame();
+        self.surface.present(Frame {
+            transaction: frame,
+            size: client_size,
+            dpi_scale: dpi_scale.get(),
+            presented,
+        });
+        info.render_time = Some(render_started.elapsed());
+        self.hooks.run(FrameStage::AfterRender, &info);
+
+        let presence_animating = self.presence.end_frame();
+        self.transitions.end_frame() || presence_animating || scrolling
     }
 
-    pub fn process_child(
-        &self,
-        event: &InputEvent,
-        position: Point2D<f32, LogicalPixel>,
-        layout: &EqualRc<LayoutTreeNode>,
-    ) -> bool {
-        let rect = Rect::new(position, layout.size);
+    pub fn process(&mut self, event: &InputEvent) -> bool {
+        let client_size = self.client_size;
+        let dpi_scale = Scale::new(self.dpi_scale);
+        let content_size: Size2D<f32, LayoutPixel> = client_size.to_f32() / dpi_scale;
 
-        if let RenderData::Node(ref node) = layout.render {
-            for layout in &layout.children {
-                if self.process_child(
-                    event,
-                    position + layout.position.to_vector(),
-                    &layout.layout,
-                ) {
-                    return true;
+        self.style_engine.update(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            self.scroll.positions(),
+            self.pointer.interactions().clone(),
+        );
+
+        let root_layout = self.layout_engine.layout(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            Scale::new(self.dpi_scale),
+        );
+
+        let mut hits = vec![];
+        // The path to the innermost element under the pointer.
+        let mut target_path = None;
+        // The innermost text under the pointer, and the caret position
+        // nearest to it.
+        let mut text_hit = None;
+        if let Some((x, y)) = event.get_position() {
+            let to_children = |node: &AnyNode, point| self.to_children(node, point);
+            for (path, point) in root_layout.hit_test_scrolled(point2(x, y), &to_children) {
+                // The window itself isn't a target.
+                if path.is_empty() {
+                    continue;
+                }
+                match root_layout.descendant(&path).map(|layout| &layout.render) {
+                    Some(RenderData::Node(node)) => {
+                        hits.extend(node.data().cloned());
+                        if target_path.is_none() {
+                            target_path = Some(path);
+                        }
+                    }
+                    Some(RenderData::Text { text, parent }) if text_hit.is_none() => {
+                        text_hit = selection::offset_at(&text.clusters, point.x)
+                            .map(|offset| (parent.clone(), offset));
+                    }
+                    _ => (),
                 }
             }
+        }
 
-            let do_process = match event.get_position() {
-                Some((x, y)) => rect.contains(point2(x, y)),
-                None => true,
-            };
+        let chain = self.pointer.chain(
+            &root_layout,
+            target_path.as_ref().map_or(&[][..], |path| &path[..]),
+        );
+
+        // The pointer keeps its hover intent until it leaves the node
+        // which took it.
+        let kept = self.hover_intent.as_ref().map_or(false, |target| {
+            chain.iter().any(|node| match (node.id(), target.id()) {
+                (Some(id), Some(target_id)) => id == target_id,
+                _ => node == target,
+            })
+        });
+        let mut ended = false;
+        match event {
+            InputEvent::MouseMove { .. } if !kept => {
+                if let Some(target) = self.hover_intent.take() {
+                    ended = target.process(&InputEvent::MouseRestEnded);
+                }
+            }
+            InputEvent::MouseRested { .. } if kept => return false,
+            _ => (),
+        }
+
+        // Pasted text goes to the selection first, standing in for the
+        // focused element.
+        if let InputEvent::Paste { .. } = event {
+            let selected = self
+                .selection
+                .and_then(|selection| selection::find_text_node(&root_layout, selection.node));
+            if selected.map_or(false, |node| node.process(event)) {
+                return true;
+            }
+        }
 
-            if do_process {
-                if node.process(event) {
-                    return true;
+        let routed = illicit::child_env!(HitTest => HitTest(hits))
+            .enter(|| self.pointer.route(event, &chain));
+        if let (InputEvent::MouseRested { .. }, Some(target)) = (event, &routed.target) {
+            self.hover_intent = Some(target.clone());
+        }
+        let handled = routed.handled;
+        let selected = self.update_selection(event, handled, text_hit, &root_layout);
+        handled || ended || selected
+    }
+
+    /// Selects text as the pointer is dragged across it. A press on text
+    /// which no element handled starts a selection, and one anywhere
+    /// else which wasn't handled clears it. The nodes the selection
+    /// changed in are sent `SelectionChanged` events. Returns true if
+    /// it changed, in which case the window should be redrawn.
+    fn update_selection(
+        &mut self,
+        event: &InputEvent,
+        handled: bool,
+        text_hit: Option<(AnyNode, usize)>,
+        root_layout: &LayoutTreeNode,
+    ) -> bool {
+        let previous = self.selection;
+        match *event {
+            InputEvent::MouseLeft {
+                state: State::Begin,
+                ..
+            } if !handled => {
+                self.selection = text_hit.and_then(|(node, offset)| {
+                    Some(Selection {
+                        node: node.id()?,
+                        anchor: offset,
+                        focus: offset,
+                        dragging: true,
+                    })
+                });
+            }
+            InputEvent::MouseLeft { .. } => {
+                if let Some(selection) = &mut self.selection {
+                    selection.dragging = false;
                 }
             }
+            InputEvent::MouseMove { x, y, .. } => match self.selection {
+                Some(selection) if selection.dragging => {
+                    let mut nearest = None;
+                    for child in &root_layout.children {
+                        self.nearest_offset(
+                            selection.node,
+                            point2(x, y),
+                            child.position,
+                            &child.layout,
+                            &mut nearest,
+                        );
+                    }
+                    if let Some((_, focus)) = nearest {
+                        self.selection = Some(Selection { focus, ..selection });
+                    }
+                }
+                _ => (),
+            },
+            _ => (),
         }
 
-        false
+        let range = |selection: Option<Selection>| {
+            selection.map(|selection| (selection.node, selection.range()))
+        };
+        if range(self.selection) == range(previous) {
+            return false;
+        }
+        // Cached subtrees don't know the selection is drawn in them.
+        self.cache.invalidate();
+        let current = self.selection.map(|selection| selection.node);
+        if let Some(previous) = previous.filter(|previous| current != Some(previous.node)) {
+            if let Some(node) = selection::find_text_node(root_layout, previous.node) {
+                node.process(&InputEvent::SelectionChanged {
+                    start: previous.focus,
+                    end: previous.focus,
+                    text: String::new(),
+                });
+            }
+        }
+        if let Some(selection) = self.selection {
+            if let Some(node) = selection::find_text_node(root_layout, selection.node) {
+                let range = selection.range();
+                let content = selection::text_content(&node);
+                node.process(&InputEvent::SelectionChanged {
+                    start: range.start,
+                    end: range.end,
+                    text: selection::selected_text(&content, range).to_owned(),
+                });
+            }
+        }
+        true
     }
 
-    pub fn process(&mut self, event: &InputEvent) -> bool {
+    /// Finds the caret position nearest to `point` in the text of the
+    /// node created at `id`, going by the distance to each of its lines,
+    /// so that dragging past the end of the text selects up to it.
+    /// `point` and `position` are in the same space, as in
+    /// `find_scroll_targets`.
+    fn nearest_offset(
+        &self,
+        id: topo::Id,
+        point: Point2D<f32, LogicalPixel>,
+        position: Point2D<f32, LogicalPixel>,
+        layout: &LayoutTreeNode,
+        nearest: &mut Option<((f32, f32), usize)>,
+    ) {
+        match layout.render {
+            RenderData::Node(ref node) => {
+                let scroll_offset = self.scroll_offset(node);
+                let point = match Self::zoom_container(node) {
+                    Some(zoom) => self.scroll.zoom(zoom).about(position).invert(point),
+                    None => point,
+                };
+                for child in &layout.children {
+                    self.nearest_offset(
+                        id,
+                        point,
+                        position + child.position.to_vector() - scroll_offset,
+                        &child.layout,
+                        nearest,
+                    );
+                }
+            }
+            RenderData::Text {
+                ref text,
+                ref parent,
+            } if parent.id() == Some(id) => {
+                let point = point - position.to_vector();
+                let outside = |value: f32, size: f32| (-value).max(value - size).max(0.0);
+                // Lines the point is beside come before lines above or
+                // below it.
+                let distance = (
+                    outside(point.y, layout.size.height),
+                    outside(point.x, layout.size.width),
+                );
+                if nearest.map_or(true, |(nearest, _)| distance < nearest) {
+                    if let Some(offset) = selection::offset_at(&text.clusters, point.x) {
+                        *nearest = Some((distance, offset));
+                    }
+                }
+            }
+            RenderData::Text { .. } => (),
+        }
+    }
+
+    /// The text selected in the window, if there's a selection and the
+    /// node it's in is still there.
+    pub fn selected_text(&mut self) -> Option<String> {
+        let selection = self.selection?;
         let client_size = self.client_size;
         let dpi_scale = Scale::new(self.dpi_scale);
         let content_size: Size2D<f32, LayoutPixel> = client_size.to_f32() / dpi_scale;
 
-        self.style_engine
-            .update(self.window.clone(), content_size * Scale::new(1.0));
+        let root_layout = self.layout_engine.layout(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            Scale::new(self.dpi_scale),
+        );
+        let node = selection::find_text_node(&root_layout, selection.node)?;
+        let content = selection::text_content(&node);
+        Some(selection::selected_text(&content, selection.range()).to_owned())
+    }
+
+    /// Finds the scroll and zoom containers under `point`, outermost
+    /// first. `point` and `position` are in the same space, which
+    /// differs from the window's inside zoom containers.
+    fn find_scroll_targets(
+        &self,
+        point: Point2D<f32, LogicalPixel>,
+        position: Point2D<f32, LogicalPixel>,
+        layout: &EqualRc<LayoutTreeNode>,
+        targets: &mut Vec<ScrollTarget>,
+    ) {
+        if let RenderData::Node(ref node) = layout.render {
+            let contains = Rect::new(position, layout.size).contains(point);
+            let scroll_offset = self.scroll_offset(node);
+            if let Some(id) = Self::scroll_container(node) {
+                if !contains {
+                    return;
+                }
+                targets.push(ScrollTarget::Scroll(id));
+            }
+            let mut point = point;
+            if let Some(id) = Self::zoom_container(node) {
+                if !contains {
+                    return;
+                }
+                targets.push(ScrollTarget::Zoom(id, point - position.to_vector()));
+                point = self.scroll.zoom(id).about(position).invert(point);
+            }
+            for layout in &layout.children {
+                self.find_scroll_targets(
+                    point,
+                    position + layout.position.to_vector() - scroll_offset,
+                    &layout.layout,
+                    targets,
+                );
+            }
+        }
+    }
+
+    fn scroll_targets(&mut self, x: f32, y: f32) -> Vec<ScrollTarget> {
+        let client_size = self.client_size;
+        let dpi_scale = Scale::new(self.dpi_scale);
+        let content_size: Size2D<f32, LayoutPixel> = client_size.to_f32() / dpi_scale;
 
-        let root_layout = self
-            .layout_engine
-            .layout(self.window.clone(), content_size * Scale::new(1.0));
+        let root_layout = self.layout_engine.layout(
+            self.window.clone(),
+            content_size * Scale::new(1.0),
+            Scale::new(self.dpi_scale),
+        );
 
+        let mut targets = vec![];
         for layout in &root_layout.children {
-            if self.process_child(event, layout.position, &layout.layout) {
-                return true;
-            }
+            self.find_scroll_targets(point2(x, y), layout.position, &layout.layout, &mut targets);
+        }
+        targets
+    }
+
+    /// Zooms the innermost zoom container under the given point by
+    /// `factor`, keeping the content under the point where it is.
+    /// Returns true if anything zoomed, in which case the window should
+    /// be redrawn. Like scrolling, this doesn't cause a new revision.
+    pub fn zoom_at(&mut self, x: f32, y: f32, factor: f32) -> bool {
+        let target = self
+            .scroll_targets(x, y)
+            .into_iter()
+            .rev()
+            .find_map(|target| match target {
+                ScrollTarget::Zoom(id, focus) => Some((id, focus)),
+                ScrollTarget::Scroll(_) => None,
+            });
+        match target {
+            Some((id, focus)) => self.scroll.zoom_by(id, focus, factor),
+            None => false,
         }
+    }
+
+    /// Scrolls the innermost scroll container under the given point
+    /// which isn't already scrolled to its edge, or pans the innermost
+    /// zoom container if it's inside of that. Returns true if anything
+    /// scrolled, in which case the window should be redrawn. This
+    /// doesn't cause a new revision of the application.
+    ///
+    /// When nothing can scroll any further, the innermost scroll
+    /// container is overscrolled instead.
+    pub fn scroll(&mut self, x: f32, y: f32, delta: LogicalVector) -> bool {
+        let now = Instant::now();
+        let targets = self.scroll_targets(x, y);
+        let scrolled = targets.iter().rev().any(|target| match *target {
+            ScrollTarget::Scroll(id) => self.scroll.scroll_by(id, delta, now),
+            ScrollTarget::Zoom(id, _) => self.scroll.pan_by(id, delta),
+        });
+        scrolled
+            || targets.iter().rev().any(|target| match *target {
+                ScrollTarget::Scroll(id) => self.scroll.overscroll_by(id, delta, now),
+                ScrollTarget::Zoom(..) => false,
+            })
+    }
 
-        false
+    /// Ends the current scroll gesture, which flings the content it was
+    /// scrolling. Returns true if the window should be redrawn to start
+    /// animating it.
+    pub fn end_scroll(&mut self) -> bool {
+        self.scroll.end_gesture(Instant::now())
     }
 }
@@ -0,0 +1,89 @@
+use std::env;
+use webrender::api::DebugFlags;
+
+/// The environment variable `RendererDebug::from_env` reads.
+const RENDERER_DEBUG_VAR: &str = "MOXIE_RENDERER_DEBUG";
+
+/// Webrender's own debugging views, for finding out why frames are slow
+/// to draw on the GPU. Set them for every window with
+/// `Runtime::renderer_debug`, or without rebuilding the application by
+/// listing the fields to turn on in the `MOXIE_RENDERER_DEBUG`
+/// environment variable, separated by commas:
+///
+/// ```sh
+/// MOXIE_RENDERER_DEBUG=overdraw,profiler cargo run
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RendererDebug {
+    /// Shades every pixel by how many times it was drawn in a frame.
+    pub overdraw: bool,
+    /// Shows frame times, and how many draw calls and batches each
+    /// frame took.
+    pub profiler: bool,
+    /// Draws every primitive in its own batch. Comparing the profiler
+    /// with and without this shows how much batching is saving.
+    pub no_batching: bool,
+    /// Shows the texture cache, where glyphs and images are kept.
+    pub texture_cache: bool,
+    /// Shows the intermediate targets things like shadows and clip
+    /// masks are drawn into.
+    pub render_targets: bool,
+}
+
+impl RendererDebug {
+    /// Reads the views to show from `MOXIE_RENDERER_DEBUG`, turning all
+    /// of them off if it isn't set. Unknown names are logged and
+    /// ignored.
+    pub fn from_env() -> RendererDebug {
+        env::var(RENDERER_DEBUG_VAR)
+            .map(|names| RendererDebug::parse(&names))
+            .unwrap_or_default()
+    }
+
+    fn parse(names: &str) -> RendererDebug {
+        let mut debug = RendererDebug::default();
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name {
+                "overdraw" => debug.overdraw = true,
+                "profiler" => debug.profiler = true,
+                "no_batching" => debug.no_batching = true,
+                "texture_cache" => debug.texture_cache = true,
+                "render_targets" => debug.render_targets = true,
+                _ => log::warn!("Unknown {} view: {:?}", RENDERER_DEBUG_VAR, name),
+            }
+        }
+        debug
+    }
+
+    pub(crate) fn flags(self) -> DebugFlags {
+        let mut flags = DebugFlags::empty();
+        flags.set(DebugFlags::SHOW_OVERDRAW, self.overdraw);
+        flags.set(DebugFlags::PROFILER_DBG, self.profiler);
+        flags.set(DebugFlags::DISABLE_BATCHING, self.no_batching);
+        flags.set(DebugFlags::TEXTURE_CACHE_DBG, self.texture_cache);
+        flags.set(DebugFlags::RENDER_TARGET_DBG, self.render_targets);
+        flags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RendererDebug;
+
+    #[test]
+    fn parses_views() {
+        assert_eq!(RendererDebug::parse(""), RendererDebug::default());
+        assert_eq!(
+            RendererDebug::parse("overdraw, texture_cache,,unknown"),
+            RendererDebug {
+                overdraw: true,
+                texture_cache: true,
+                ..RendererDebug::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+use winit::window::WindowId;
+
+/// Describes a frame, for the hooks registered with `Runtime`.
+#[derive(Clone, Debug)]
+pub struct FrameInfo {
+    /// `None` when rendering without a window, such as in tests.
+    pub window: Option<WindowId>,
+    /// How many times the window's DOM has changed.
+    pub revision: u64,
+    /// Counts up from zero for each window.
+    pub frame: u64,
+    /// The size of the window, in logical pixels.
+    pub width: f32,
+    pub height: f32,
+    pub dpi_scale: f32,
+    pub started: Instant,
+    /// Time spent on style and layout, once they've run.
+    pub layout_time: Option<Duration>,
+    /// Time spent building the display list, once it's been handed to
+    /// the render thread.
+    pub render_time: Option<Duration>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum FrameStage {
+    BeforeFrame,
+    AfterLayout,
+    AfterRender,
+}
+
+/// Callbacks which observe each stage of every frame.
+#[derive(Default)]
+pub(crate) struct FrameHooks {
+    hooks: RefCell<Vec<(FrameStage, Box<dyn FnMut(&FrameInfo)>)>>,
+}
+
+impl FrameHooks {
+    pub(crate) fn add(&self, stage: FrameStage, hook: Box<dyn FnMut(&FrameInfo)>) {
+        self.hooks.borrow_mut().push((stage, hook));
+    }
+
+    pub(crate) fn run(&self, stage: FrameStage, info: &FrameInfo) {
+        for (hook_stage, hook) in self.hooks.borrow_mut().iter_mut() {
+            if *hook_stage == stage {
+                hook(info);
+            }
+        }
+    }
+}
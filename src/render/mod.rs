@@ -1,6 +1,25 @@
 //! This module handles creating the paint tree, as well as rendering it
 //! and processing user input queries against it.
 
+mod cache;
 pub mod context;
+mod debug;
+mod hooks;
+mod overlay;
+mod pointer;
+mod presence;
+mod scroll;
+mod selection;
+mod shared;
+mod thread;
+mod transitions;
 
 pub use context::Context;
+pub use debug::RendererDebug;
+pub use hooks::FrameInfo;
+pub(crate) use hooks::{FrameHooks, FrameStage};
+pub use overlay::DebugOverlay;
+pub use scroll::{Overscroll, ScrollPhysics};
+pub(crate) use shared::ShareContext;
+pub use shared::SharedResources;
+pub use thread::RenderTarget;
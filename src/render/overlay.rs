@@ -0,0 +1,188 @@
+use super::context::convert_offsets;
+use crate::dom::node::AnyNode;
+use crate::layout::{LayoutTreeNode, LogicalPixel, LogicalSize, LogicalVector, RenderData};
+use crate::style::DisplayType;
+use crate::{Color, ColorSpace};
+use webrender::{
+    api::{
+        units::LayoutSideOffsets, BorderDetails, BorderRadius, BorderSide, BorderStyle,
+        CommonItemProperties, DisplayListBuilder, NormalBorder, SpaceAndClipInfo,
+    },
+    euclid::{point2, size2, Point2D, Rect, Scale},
+};
+
+const OUTLINE_COLOR: Color = Color {
+    red: 70,
+    green: 130,
+    blue: 220,
+    alpha: 200,
+    space: ColorSpace::Srgb,
+};
+const PADDING_COLOR: Color = Color {
+    red: 140,
+    green: 200,
+    blue: 120,
+    alpha: 100,
+    space: ColorSpace::Srgb,
+};
+const MARGIN_COLOR: Color = Color {
+    red: 245,
+    green: 175,
+    blue: 100,
+    alpha: 100,
+    space: ColorSpace::Srgb,
+};
+const BASELINE_COLOR: Color = Color {
+    red: 230,
+    green: 40,
+    blue: 120,
+    alpha: 220,
+    space: ColorSpace::Srgb,
+};
+const GRID_COLOR: Color = Color {
+    red: 0,
+    green: 160,
+    blue: 255,
+    alpha: 60,
+    space: ColorSpace::Srgb,
+};
+
+/// Draws layout information over a window's content, to help line
+/// elements up with a design. Set it for every window with
+/// `Runtime::debug_overlay`, or toggle it on a window with Ctrl+Shift+D
+/// (Cmd+Shift+D on macOS).
+///
+/// Boxes are drawn where layout put them, so they don't follow layout
+/// transitions, and aren't clipped by scroll containers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugOverlay {
+    /// Outlines every box, and shades its padding and margins.
+    pub boxes: bool,
+    /// Underlines each line of text along its baseline.
+    pub baselines: bool,
+    /// The spacing of a grid drawn over the whole window, in logical
+    /// pixels.
+    pub grid: Option<f32>,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> DebugOverlay {
+        DebugOverlay {
+            boxes: true,
+            baselines: true,
+            grid: Some(8.0),
+        }
+    }
+}
+
+/// Adds the overlay's items to a display list, after the content.
+pub(crate) struct OverlayPainter<'a> {
+    pub overlay: DebugOverlay,
+    pub builder: &'a mut DisplayListBuilder,
+    pub space_and_clip: SpaceAndClipInfo,
+    /// The width of one device pixel, which lines are drawn with.
+    pub hairline: f32,
+    /// The color space of the surface being drawn to.
+    pub color_space: ColorSpace,
+    /// How far a scroll container's content has been scrolled.
+    pub scroll_offset: &'a dyn Fn(&AnyNode) -> LogicalVector,
+}
+
+impl<'a> OverlayPainter<'a> {
+    /// Draws a solid band of the given widths inside the edges of
+    /// `rect`.
+    fn band(&mut self, rect: Rect<f32, LogicalPixel>, widths: LayoutSideOffsets, color: Color) {
+        if widths == LayoutSideOffsets::zero() {
+            return;
+        }
+        let rect = rect * Scale::new(1.0);
+        let side = BorderSide {
+            style: BorderStyle::Solid,
+            color: color.to_color_f(self.color_space),
+        };
+        self.builder.push_border(
+            &CommonItemProperties::new(rect, self.space_and_clip),
+            rect,
+            widths,
+            BorderDetails::Normal(NormalBorder {
+                left: side,
+                right: side,
+                top: side,
+                bottom: side,
+                radius: BorderRadius::uniform(0.0),
+                do_aa: false,
+            }),
+        );
+    }
+
+    fn line(&mut self, rect: Rect<f32, LogicalPixel>, color: Color) {
+        let rect = rect * Scale::new(1.0);
+        self.builder.push_rect(
+            &CommonItemProperties::new(rect, self.space_and_clip),
+            color.to_color_f(self.color_space),
+        );
+    }
+
+    pub fn draw_grid(&mut self, size: LogicalSize) {
+        let spacing = match self.overlay.grid {
+            Some(spacing) if spacing > 0.0 => spacing,
+            _ => return,
+        };
+        let mut x = 0.0;
+        while x < size.width {
+            let line = Rect::new(point2(x, 0.0), size2(self.hairline, size.height));
+            self.line(line, GRID_COLOR);
+            x += spacing;
+        }
+        let mut y = 0.0;
+        while y < size.height {
+            let line = Rect::new(point2(0.0, y), size2(size.width, self.hairline));
+            self.line(line, GRID_COLOR);
+            y += spacing;
+        }
+    }
+
+    pub fn draw_node(&mut self, position: Point2D<f32, LogicalPixel>, layout: &LayoutTreeNode) {
+        match layout.render {
+            RenderData::Node(ref node) => {
+                if self.overlay.boxes {
+                    let values = node.computed_values().get().unwrap();
+                    let rect = Rect::new(position, layout.size);
+                    // Layout leaves a box's margins after it, to the
+                    // right and below.
+                    let margin = layout.margin;
+                    let (right, bottom) = (margin.horizontal(), margin.vertical());
+                    let outer = Rect::new(position, layout.size + size2(right, bottom));
+                    let margins = LayoutSideOffsets::new(0.0, right, bottom, 0.0);
+                    self.band(outer, margins, MARGIN_COLOR);
+                    if let DisplayType::Block(block) = values.display {
+                        let inner = rect.inner_rect(values.border_thickness);
+                        self.band(inner, convert_offsets(block.padding), PADDING_COLOR);
+                    }
+                    let outline = LayoutSideOffsets::new_all_same(self.hairline);
+                    self.band(rect, outline, OUTLINE_COLOR);
+                }
+                let scroll_offset = (self.scroll_offset)(node);
+                for child in &layout.children {
+                    let position = position + child.position.to_vector() - scroll_offset;
+                    self.draw_node(position, &child.layout);
+                }
+            }
+            RenderData::Text { ref text, .. } => {
+                let first_glyph = text
+                    .fragments
+                    .iter()
+                    .filter_map(|fragment| fragment.glyphs.first())
+                    .next();
+                if let (true, Some(glyph)) = (self.overlay.baselines, first_glyph) {
+                    let y = position.y + glyph.offset.y;
+                    let line = Rect::new(
+                        point2(position.x, y),
+                        size2(layout.size.width, self.hairline),
+                    );
+                    self.line(line, BASELINE_COLOR);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use crate::dom::input::{InputEvent, PointerId, PointerInput, PointerKind, Propagation, State};
+use crate::dom::node::AnyNode;
+use crate::dom::Phase;
+use crate::layout::{LayoutTreeNode, RenderData};
+use crate::style::Interactions;
+use std::rc::Rc;
+
+/// Sends pointer events through the elements under the pointer, and
+/// keeps track of which elements the pointer is over and which element
+/// has captured it.
+///
+/// Nodes are matched up between revisions by their `topo::Id`, like
+/// the hover intent target, since the DOM is rebuilt on every revision.
+#[derive(Default)]
+pub struct PointerRouter {
+    /// The elements the pointer was last over, outermost first.
+    over: Vec<AnyNode>,
+    captured: Option<AnyNode>,
+    interactions: Interactions,
+    /// The pointer the next events come from.
+    pointer: PointerId,
+}
+
+/// What happened to an event sent through the router.
+#[derive(Default)]
+pub struct Routed {
+    /// Whether any element had a handler for it.
+    pub handled: bool,
+    /// The element which handled the event itself, rather than a
+    /// pointer event sent for it.
+    pub target: Option<AnyNode>,
+}
+
+impl PointerRouter {
+    /// The elements an event at the end of `path` goes to, outermost
+    /// first and leaving out the window. A captured pointer is over the
+    /// capturing element, wherever it is, so these are that element and
+    /// its ancestors while one has it. An element which was removed
+    /// can't keep it.
+    pub fn chain(&mut self, root: &LayoutTreeNode, path: &[usize]) -> Vec<AnyNode> {
+        let captured = self
+            .captured
+            .as_ref()
+            .and_then(|captured| path_to(root, captured));
+        if captured.is_none() {
+            self.captured = None;
+        }
+        ancestors(root, captured.as_ref().map_or(path, |path| &path[..]))
+    }
+
+    /// Sends an event to the elements in `chain`. Mouse buttons and
+    /// moves are first sent as pointer events, down the chain and back
+    /// up. Then the event itself goes to the elements the pointer event
+    /// reached on its way up, innermost first, until one handles it. An
+    /// element which stops a pointer event keeps the elements outside it
+    /// from acting on the press, release or move, and one which stops it
+    /// on the way down keeps all of them from doing so.
+    pub fn route(&mut self, event: &InputEvent, chain: &[AnyNode]) -> Routed {
+        let pointer = match *event {
+            InputEvent::MouseLeft {
+                state: State::Begin,
+                x,
+                y,
+            } => Some((PointerKind::Down, x, y)),
+            InputEvent::MouseLeft {
+                state: State::End,
+                x,
+                y,
+            }
+            | InputEvent::MouseLeft {
+                state: State::Cancel,
+                x,
+                y,
+            } => Some((PointerKind::Up, x, y)),
+            InputEvent::MouseMove { x, y, .. } => Some((PointerKind::Move, x, y)),
+            InputEvent::MouseHeld { .. }
+            | InputEvent::MouseRested { .. }
+            | InputEvent::Paste { .. } => None,
+            _ => return Routed::default(),
+        };
+
+        let mut handled = false;
+        let mut reached = chain;
+        if let Some((kind, x, y)) = pointer {
+            handled |= self.update_over(chain.to_vec(), x, y);
+            let (dispatched, stopped) = self.dispatch(kind, chain, x, y);
+            handled |= dispatched;
+            reached = match stopped {
+                Some((_, Phase::Capture)) => &[][..],
+                Some((index, _)) => &chain[index..],
+                None => chain,
+            };
+        }
+        let target = reached
+            .iter()
+            .rev()
+            .find(|node| node.process(event))
+            .cloned();
+        handled |= target.is_some();
+
+        if let Some((kind, _, _)) = pointer {
+            self.interactions.hovered = ids(&self.over);
+            match kind {
+                PointerKind::Down => {
+                    self.interactions.active = ids(chain);
+                    self.interactions.focused = chain.last().and_then(|node| node.id());
+                }
+                PointerKind::Up => {
+                    self.captured = None;
+                    self.interactions.active.clear();
+                }
+                _ => (),
+            }
+        }
+        Routed { handled, target }
+    }
+
+    /// Sets the pointer the following events come from. The window
+    /// follows one pointer at a time, and switches between them only
+    /// while nothing is pressed.
+    pub fn set_pointer(&mut self, pointer: PointerId) {
+        self.pointer = pointer;
+    }
+
+    /// The elements the pointer is over, has pressed, and last pressed,
+    /// for styling them.
+    pub fn interactions(&self) -> &Interactions {
+        &self.interactions
+    }
+
+    /// Sends leave events to the elements the pointer is no longer over,
+    /// innermost first, then enter events to the ones it moved onto,
+    /// outermost first.
+    fn update_over(&mut self, chain: Vec<AnyNode>, x: f32, y: f32) -> bool {
+        let previous = std::mem::replace(&mut self.over, chain);
+        let mut handled = false;
+        for node in previous.iter().rev() {
+            if !self.over.iter().any(|over| same_node(over, node)) {
+                handled |= send(node, PointerKind::Leave, self.pointer, x, y);
+            }
+        }
+        for node in &self.over {
+            if !previous.iter().any(|over| same_node(over, node)) {
+                handled |= send(node, PointerKind::Enter, self.pointer, x, y);
+            }
+        }
+        handled
+    }
+
+    /// Sends the event down `chain` to its last element, and back up,
+    /// until a handler stops it. Returns whether any element had a
+    /// handler, and the index of the one which stopped it along with
+    /// the phase it was in.
+    fn dispatch(
+        &mut self,
+        kind: PointerKind,
+        chain: &[AnyNode],
+        x: f32,
+        y: f32,
+    ) -> (bool, Option<(usize, Phase)>) {
+        let (target, ancestors) = match chain.split_last() {
+            Some(split) => split,
+            None => return (false, None),
+        };
+        let propagation = Rc::new(Propagation::default());
+        let route = ancestors
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (index, node, Phase::Capture))
+            .chain(Some((ancestors.len(), target, Phase::Target)))
+            .chain(
+                ancestors
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, node)| (index, node, Phase::Bubble)),
+            );
+
+        let mut handled = false;
+        for (index, node, phase) in route {
+            let input = PointerInput {
+                kind,
+                pointer: self.pointer,
+                x,
+                y,
+                phase,
+                propagation: propagation.clone(),
+            };
+            handled |= node.process(&InputEvent::Pointer(input));
+            match propagation.capture.take() {
+                Some(true) => self.captured = Some(node.clone()),
+                Some(false) => self.captured = None,
+                None => (),
+            }
+            if propagation.stopped.get() {
+                return (handled, Some((index, phase)));
+            }
+        }
+        (handled, None)
+    }
+}
+
+/// Sends an enter or leave event to a single element. Returns whether
+/// it had a handler for it.
+fn send(node: &AnyNode, kind: PointerKind, pointer: PointerId, x: f32, y: f32) -> bool {
+    node.process(&InputEvent::Pointer(PointerInput {
+        kind,
+        pointer,
+        x,
+        y,
+        phase: Phase::Target,
+        propagation: Rc::new(Propagation::default()),
+    }))
+}
+
+fn same_node(a: &AnyNode, b: &AnyNode) -> bool {
+    match (a.id(), b.id()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn ids(nodes: &[AnyNode]) -> Vec<topo::Id> {
+    nodes.iter().filter_map(|node| node.id()).collect()
+}
+
+/// The elements along `path`, outermost first, leaving out the window.
+fn ancestors(root: &LayoutTreeNode, path: &[usize]) -> Vec<AnyNode> {
+    (1..=path.len())
+        .filter_map(
+            |len| match root.descendant(&path[..len]).map(|layout| &layout.render) {
+                Some(RenderData::Node(node)) => Some(node.clone()),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Finds the path to the layout of `node` in this revision.
+fn path_to(layout: &LayoutTreeNode, node: &AnyNode) -> Option<Vec<usize>> {
+    if let RenderData::Node(ref this) = layout.render {
+        if same_node(this, node) {
+            return Some(vec![]);
+        }
+    }
+    layout
+        .children
+        .iter()
+        .enumerate()
+        .find_map(|(index, child)| {
+            let mut path = path_to(&child.layout, node)?;
+            path.insert(0, index);
+            Some(path)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PointerRouter, Routed};
+    use crate::dom::element::{Event, HasEvent};
+    use crate::dom::input::{InputEvent, State};
+    use crate::dom::node::AnyNode;
+    use crate::dom::{
+        Button, Capture, ClickEvent, Node, PointerDownEvent, PointerEnterEvent, PointerLeaveEvent,
+        PointerMoveEvent, View, Window,
+    };
+    use crate::layout::{LayoutChild, LayoutTreeNode, LogicalSideOffsets, RenderData};
+    use crate::util::equal_rc::EqualRc;
+    use crate::util::event_handler::EventHandler;
+    use euclid::{point2, size2};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    /// A window holding a view with another view and a button in it, at
+    /// the paths `[0]`, `[0, 0]` and `[0, 1]`.
+    struct Tree {
+        outer: Node<View>,
+        inner: Node<View>,
+        button: Node<Button>,
+        layout: LayoutTreeNode,
+    }
+
+    fn tree(ids: [topo::Id; 3]) -> Tree {
+        let inner = Node::new(View::default(), vec![]);
+        let button = Node::new(Button::default(), vec![]);
+        let outer = Node::new(
+            View::default(),
+            vec![inner.clone().into(), button.clone().into()],
+        );
+        outer.set_id(ids[0]);
+        inner.set_id(ids[1]);
+        button.set_id(ids[2]);
+        let window = Node::new(Window::default(), vec![outer.clone()]);
+        let layout = layout(
+            window.into(),
+            vec![layout(
+                outer.clone().into(),
+                vec![
+                    layout(inner.clone().into(), vec![]),
+                    layout(button.clone().into(), vec![]),
+                ],
+            )],
+        );
+        Tree {
+            outer,
+            inner,
+            button,
+            layout,
+        }
+    }
+
+    fn layout(node: AnyNode, children: Vec<LayoutTreeNode>) -> LayoutTreeNode {
+        LayoutTreeNode {
+            size: size2(100.0, 100.0),
+            margin: LogicalSideOffsets::zero(),
+            render: RenderData::Node(node),
+            children: children
+                .into_iter()
+                .map(|layout| LayoutChild {
+                    position: point2(0.0, 0.0),
+                    layout: EqualRc::new(layout),
+                })
+                .collect(),
+            overflowed: false,
+        }
+    }
+
+    /// Adds `name` to `log` when the element is sent an `E`.
+    fn on<Elt, E>(node: &Node<Elt>, log: &Log, name: &'static str)
+    where
+        Elt: HasEvent<E>,
+        E: Event + 'static,
+    {
+        let log = log.clone();
+        Elt::set_handler(
+            &mut node.handlers().borrow_mut(),
+            EventHandler::with_func(move |_: &E| log.borrow_mut().push(name)),
+        );
+    }
+
+    fn send(router: &mut PointerRouter, tree: &Tree, path: &[usize], event: InputEvent) -> Routed {
+        let chain = router.chain(&tree.layout, path);
+        router.route(&event, &chain)
+    }
+
+    fn left(state: State) -> InputEvent {
+        InputEvent::MouseLeft {
+            state,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+
+    fn move_to() -> InputEvent {
+        InputEvent::MouseMove {
+            x: 0.0,
+            y: 0.0,
+            trace: vec![],
+        }
+    }
+
+    #[test]
+    fn sends_events_down_and_back_up() {
+        let tree = tree([
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+        ]);
+        let log = Log::default();
+        on::<_, Capture<PointerDownEvent>>(&tree.outer, &log, "outer capture");
+        on::<_, PointerDownEvent>(&tree.outer, &log, "outer");
+        on::<_, Capture<PointerDownEvent>>(&tree.inner, &log, "inner capture");
+        on::<_, PointerDownEvent>(&tree.inner, &log, "inner");
+
+        let mut router = PointerRouter::default();
+        assert!(send(&mut router, &tree, &[0, 0], left(State::Begin)).handled);
+        assert_eq!(
+            *log.borrow(),
+            ["outer capture", "inner capture", "inner", "outer"]
+        );
+    }
+
+    #[test]
+    fn stopping_propagation_skips_outer_elements() {
+        let tree = tree([
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+        ]);
+        let log = Log::default();
+        on::<_, PointerDownEvent>(&tree.outer, &log, "outer");
+        on::<_, ClickEvent>(&tree.button, &log, "click");
+        let stop = log.clone();
+        Button::set_handler(
+            &mut tree.button.handlers().borrow_mut(),
+            EventHandler::with_func(move |down: &PointerDownEvent| {
+                stop.borrow_mut().push("button");
+                down.stop_propagation();
+            }),
+        );
+
+        // The button still acts on a press it stopped.
+        let mut router = PointerRouter::default();
+        let pressed = send(&mut router, &tree, &[0, 1], left(State::Begin));
+        assert_eq!(pressed.target, Some(tree.button.clone().into()));
+        send(&mut router, &tree, &[0, 1], left(State::End));
+        assert_eq!(*log.borrow(), ["button", "click"]);
+    }
+
+    #[test]
+    fn stopping_capture_keeps_the_target_from_acting() {
+        let tree = tree([
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+        ]);
+        let log = Log::default();
+        on::<_, PointerDownEvent>(&tree.button, &log, "button");
+        on::<_, ClickEvent>(&tree.button, &log, "click");
+        View::set_handler(
+            &mut tree.outer.handlers().borrow_mut(),
+            EventHandler::with_func(|down: &Capture<PointerDownEvent>| down.stop_propagation()),
+        );
+
+        let mut router = PointerRouter::default();
+        let pressed = send(&mut router, &tree, &[0, 1], left(State::Begin));
+        assert!(pressed.handled);
+        assert!(pressed.target.is_none());
+        send(&mut router, &tree, &[0, 1], left(State::End));
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn enters_and_leaves_elements() {
+        let tree = tree([
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+        ]);
+        let log = Log::default();
+        on::<_, PointerEnterEvent>(&tree.outer, &log, "outer enter");
+        on::<_, PointerLeaveEvent>(&tree.outer, &log, "outer leave");
+        on::<_, PointerEnterEvent>(&tree.inner, &log, "inner enter");
+        on::<_, PointerLeaveEvent>(&tree.inner, &log, "inner leave");
+        on::<_, PointerEnterEvent>(&tree.button, &log, "button enter");
+        on::<_, PointerLeaveEvent>(&tree.button, &log, "button leave");
+
+        let mut router = PointerRouter::default();
+        send(&mut router, &tree, &[0, 0], move_to());
+        assert_eq!(*log.borrow(), ["outer enter", "inner enter"]);
+        log.borrow_mut().clear();
+        send(&mut router, &tree, &[0, 1], move_to());
+        assert_eq!(*log.borrow(), ["inner leave", "button enter"]);
+        log.borrow_mut().clear();
+        send(&mut router, &tree, &[], move_to());
+        assert_eq!(*log.borrow(), ["button leave", "outer leave"]);
+    }
+
+    #[test]
+    fn keeps_capture_off_the_element() {
+        let ids = [
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+            topo::call!({ topo::Id::current() }),
+        ];
+        let log = Log::default();
+        let capturing = |tree: &Tree| {
+            on::<_, PointerMoveEvent>(&tree.inner, &log, "inner");
+            on::<_, PointerMoveEvent>(&tree.button, &log, "button");
+            View::set_handler(
+                &mut tree.inner.handlers().borrow_mut(),
+                EventHandler::with_func(|down: &PointerDownEvent| down.set_pointer_capture()),
+            );
+        };
+        let tree = tree(ids);
+        capturing(&tree);
+
+        let mut router = PointerRouter::default();
+        send(&mut router, &tree, &[0, 0], left(State::Begin));
+        send(&mut router, &tree, &[0, 1], move_to());
+        assert_eq!(*log.borrow(), ["inner"]);
+
+        // The next revision's nodes are matched up with these by id.
+        let next = self::tree(ids);
+        capturing(&next);
+        send(&mut router, &next, &[0, 1], move_to());
+        assert_eq!(*log.borrow(), ["inner", "inner"]);
+
+        send(&mut router, &next, &[0, 1], left(State::End));
+        send(&mut router, &next, &[0, 1], move_to());
+        assert_eq!(*log.borrow(), ["inner", "inner", "button"]);
+    }
+}
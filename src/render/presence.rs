@@ -0,0 +1,214 @@
+use crate::animation::{Animation, Transition};
+use crate::layout::{LayoutTreeNode, LogicalPoint, LogicalVector};
+use crate::util::equal_rc::EqualRc;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Where a node with an exit transition was last drawn, so that it can
+/// be drawn there again once it's removed. The position is kept relative
+/// to the parent's children, so that the node is drawn with its parent's
+/// clip, scroll offset and zoom.
+struct Drawn {
+    parent: Option<topo::Id>,
+    offset: LogicalVector,
+    layout: EqualRc<LayoutTreeNode>,
+    exit: Option<Transition>,
+}
+
+struct Exiting {
+    id: topo::Id,
+    parent: Option<topo::Id>,
+    offset: LogicalVector,
+    layout: EqualRc<LayoutTreeNode>,
+    opacity: Animation<f32>,
+}
+
+/// Fades nodes in when they're added, and out when they're removed,
+/// for nodes with `enter_transition` or `exit_transition` set. Nodes
+/// are matched up between frames using their `topo::Id`, like layout
+/// transitions.
+///
+/// The DOM is rebuilt on every revision, so removed nodes are gone by
+/// the time they're noticed here. Instead of delaying their removal,
+/// their last layout is kept and drawn over the rest of their parent's
+/// children until the exit transition finishes.
+#[derive(Default)]
+pub struct PresenceTransitions {
+    now: Option<Instant>,
+    /// Nodes in the first frame are already there, rather than added.
+    started: bool,
+    /// Set while removed nodes are being drawn.
+    drawing_exits: bool,
+    /// The id of each node whose children are being drawn, and where
+    /// its children's positions are measured from, innermost last.
+    parents: Vec<(Option<topo::Id>, LogicalPoint)>,
+    previous: HashMap<topo::Id, Drawn>,
+    current: HashMap<topo::Id, Drawn>,
+    entering: HashMap<topo::Id, Animation<f32>>,
+    exiting: Vec<Exiting>,
+}
+
+impl PresenceTransitions {
+    pub fn begin_frame(&mut self) {
+        self.now = Some(Instant::now());
+    }
+
+    /// Returns the opacity the node should be drawn with this frame.
+    pub fn resolve(
+        &mut self,
+        id: topo::Id,
+        position: LogicalPoint,
+        layout: &EqualRc<LayoutTreeNode>,
+        enter: Option<Transition>,
+        exit: Option<Transition>,
+    ) -> f32 {
+        if self.drawing_exits {
+            return 1.0;
+        }
+        let now = self.now.unwrap_or_else(Instant::now);
+        let (parent, origin) = self
+            .parents
+            .last()
+            .cloned()
+            .unwrap_or((None, LogicalPoint::origin()));
+        self.current.insert(
+            id,
+            Drawn {
+                parent,
+                offset: position - origin,
+                layout: layout.clone(),
+                exit,
+            },
+        );
+        // Added back before it finished leaving.
+        self.exiting.retain(|exiting| exiting.id != id);
+
+        if let Some(animation) = self.entering.get_mut(&id) {
+            return animation.value_at(now);
+        }
+        match enter {
+            Some(transition) if self.started && !self.previous.contains_key(&id) => {
+                let mut animation = Animation::new(transition, 0.0, 1.0);
+                let opacity = animation.value_at(now);
+                self.entering.insert(id, animation);
+                opacity
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Carries the given nodes over from the previous frame, for nodes
+    /// which weren't resolved this frame because they are part of a
+    /// cached subtree that wasn't rebuilt.
+    pub fn keep(&mut self, ids: &[topo::Id]) {
+        for id in ids {
+            if let Some(drawn) = self.previous.remove(id) {
+                self.current.insert(*id, drawn);
+            }
+        }
+    }
+
+    /// Called before drawing the children of a node, or of the window
+    /// when `parent` is `None`. `origin` is where the children's
+    /// positions are measured from, after scrolling.
+    pub fn push_parent(&mut self, parent: Option<topo::Id>, origin: LogicalPoint) {
+        self.parents.push((parent, origin));
+    }
+
+    /// Called before drawing a cached subtree at `position`, which is
+    /// drawn relative to its own origin rather than the window's.
+    pub fn push_subtree(&mut self, position: LogicalPoint) {
+        let (parent, origin) = self
+            .parents
+            .last()
+            .cloned()
+            .unwrap_or((None, LogicalPoint::origin()));
+        self.parents.push((parent, origin - position.to_vector()));
+    }
+
+    pub fn pop_parent(&mut self) {
+        self.parents.pop();
+    }
+
+    /// Returns where to draw each removed child of the innermost parent
+    /// which is still fading out, and its opacity. If any are returned,
+    /// nodes drawn until `end_exits` is called aren't tracked, since
+    /// they're part of removed subtrees.
+    pub fn begin_exits(&mut self) -> Vec<(LogicalPoint, EqualRc<LayoutTreeNode>, f32)> {
+        if self.drawing_exits {
+            return vec![];
+        }
+        let (parent, origin) = match self.parents.last() {
+            Some(&parent) => parent,
+            None => return vec![],
+        };
+        let now = self.now.unwrap_or_else(Instant::now);
+        let exits = self
+            .exiting
+            .iter_mut()
+            .filter(|exit| exit.parent == parent)
+            .map(|exit| {
+                let opacity = exit.opacity.value_at(now);
+                (origin + exit.offset, exit.layout.clone(), opacity)
+            })
+            .collect::<Vec<_>>();
+        self.drawing_exits = !exits.is_empty();
+        exits
+    }
+
+    pub fn end_exits(&mut self) {
+        self.drawing_exits = false;
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.entering.is_empty() || !self.exiting.is_empty()
+    }
+
+    /// Finishes the frame, and returns whether any transitions are still
+    /// running and need another frame to be rendered.
+    pub fn end_frame(&mut self) -> bool {
+        let now = self.now.take().unwrap_or_else(Instant::now);
+        let previous = std::mem::replace(&mut self.previous, HashMap::new());
+        for (id, drawn) in previous {
+            if self.current.contains_key(&id) {
+                continue;
+            }
+            if let Some(transition) = drawn.exit {
+                self.exiting.push(Exiting {
+                    id,
+                    parent: drawn.parent,
+                    offset: drawn.offset,
+                    layout: drawn.layout,
+                    opacity: Animation::new(transition, 1.0, 0.0),
+                });
+            }
+        }
+        // A removed node inside another one is already drawn with it.
+        let layouts = self
+            .exiting
+            .iter()
+            .map(|exit| exit.layout.clone())
+            .collect::<Vec<_>>();
+        self.exiting.retain(|exit| {
+            !layouts
+                .iter()
+                .any(|layout| *layout != exit.layout && contains(layout, &exit.layout))
+        });
+        self.previous = std::mem::replace(&mut self.current, HashMap::new());
+        self.started = true;
+
+        let current = &self.previous;
+        self.entering
+            .retain(|id, animation| current.contains_key(id) && !animation.finished_at(now));
+        self.exiting
+            .retain(|exiting| !exiting.opacity.finished_at(now));
+        self.is_animating()
+    }
+}
+
+fn contains(layout: &LayoutTreeNode, target: &EqualRc<LayoutTreeNode>) -> bool {
+    layout
+        .children
+        .iter()
+        .any(|child| child.layout == *target || contains(&child.layout, target))
+}
@@ -0,0 +1,687 @@
+use crate::animation::{Spring, SpringConfig};
+use crate::layout::{LayoutTreeNode, LogicalPoint, LogicalSize, LogicalVector};
+use crate::style::{ScrollPosition, ScrollPositions};
+use euclid::vec2;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How far zoom containers can be zoomed out and in.
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 10.0;
+
+/// Only scrolling this recent is used to find how fast a gesture was
+/// moving when it ended.
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+/// Flings slower than this, in logical pixels per second, stop.
+const MIN_VELOCITY: f32 = 20.0;
+/// How much of a gesture past the edge moves a bouncing container.
+const BOUNCE_RESISTANCE: f32 = 0.4;
+/// How long a bouncing container stays past its edge after the last
+/// scroll, for devices which don't report when a gesture ends.
+const BOUNCE_DELAY: Duration = Duration::from_millis(150);
+/// The most a glow can build up, in logical pixels of overscroll.
+pub const MAX_GLOW: f32 = 120.0;
+/// How much glow a fling hitting an edge causes, per logical pixel per
+/// second it was moving.
+const GLOW_PER_VELOCITY: f32 = 0.05;
+/// How quickly a glow fades, in the same units as `friction`.
+const GLOW_FADE: f32 = 6.0;
+
+/// How long overlay scrollbars take to fade in once scrolling starts,
+/// how long they stay after it stops, and how long they take to fade
+/// out after that.
+const SCROLLBAR_FADE_IN: Duration = Duration::from_millis(100);
+const SCROLLBAR_HOLD: Duration = Duration::from_millis(800);
+const SCROLLBAR_FADE_OUT: Duration = Duration::from_millis(300);
+/// The shortest a scrollbar thumb gets, in logical pixels.
+const MIN_THUMB: f32 = 20.0;
+
+/// What happens when a scroll container is scrolled past its edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overscroll {
+    /// The content stops at the edge.
+    Clamp,
+    /// The content follows the gesture past the edge, with resistance,
+    /// and springs back once it ends.
+    Bounce,
+    /// The content stops at the edge, which lights up more the further
+    /// past it the gesture goes.
+    Glow,
+}
+
+/// How scroll containers move once a scroll gesture ends, given to
+/// `Runtime::scroll_physics`. Only devices which report the end of a
+/// gesture, like trackpads, fling content; mouse wheels scroll by each
+/// notch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollPhysics {
+    /// Whether content keeps moving after a gesture ends.
+    pub inertia: bool,
+    /// How quickly flung content slows down. Its velocity falls by a
+    /// factor of e every `1 / friction` seconds.
+    pub friction: f32,
+    pub overscroll: Overscroll,
+}
+
+impl Default for ScrollPhysics {
+    fn default() -> ScrollPhysics {
+        ScrollPhysics {
+            inertia: true,
+            friction: 3.0,
+            overscroll: if cfg!(target_os = "macos") {
+                Overscroll::Bounce
+            } else {
+                Overscroll::Clamp
+            },
+        }
+    }
+}
+
+/// How the content of a zoom container is drawn: scaled by `scale`
+/// around the container's origin, and then moved by `pan`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoomTransform {
+    pub scale: f32,
+    pub pan: LogicalVector,
+}
+
+impl ZoomTransform {
+    pub fn identity() -> ZoomTransform {
+        ZoomTransform {
+            scale: 1.0,
+            pan: LogicalVector::zero(),
+        }
+    }
+
+    /// Where a point in the content is drawn.
+    pub fn apply(self, point: LogicalPoint) -> LogicalPoint {
+        point * self.scale + self.pan
+    }
+
+    /// Which point in the content is drawn at `point`.
+    pub fn invert(self, point: LogicalPoint) -> LogicalPoint {
+        (point - self.pan) / self.scale
+    }
+
+    /// Converts a transform of content relative to a container at
+    /// `origin` into one of the same points relative to whatever the
+    /// container's position is relative to.
+    pub fn about(self, origin: LogicalPoint) -> ZoomTransform {
+        ZoomTransform {
+            scale: self.scale,
+            pan: origin.to_vector() + self.pan - origin.to_vector() * self.scale,
+        }
+    }
+
+    /// Applies this transform, and then `outer`.
+    pub fn then(self, outer: ZoomTransform) -> ZoomTransform {
+        ZoomTransform {
+            scale: self.scale * outer.scale,
+            pan: self.pan * outer.scale + outer.pan,
+        }
+    }
+
+    /// Zooms by `factor`, keeping the content under `focus` where it is.
+    fn zoom_about(self, focus: LogicalPoint, factor: f32) -> ZoomTransform {
+        let scale = (self.scale * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        let content = self.invert(focus);
+        ZoomTransform {
+            scale,
+            pan: focus - content * scale,
+        }
+    }
+}
+
+/// When a container's overlay scrollbars were shown, and when it last
+/// moved, from which their opacity is found.
+#[derive(Clone, Copy, Default)]
+struct ScrollbarFade {
+    shown_at: Option<Instant>,
+    moved_at: Option<Instant>,
+}
+
+impl ScrollbarFade {
+    fn opacity(&self, now: Instant) -> f32 {
+        let (shown_at, moved_at) = match (self.shown_at, self.moved_at) {
+            (Some(shown_at), Some(moved_at)) => (shown_at, moved_at),
+            _ => return 0.0,
+        };
+        let fade_in =
+            now.saturating_duration_since(shown_at).as_secs_f32() / SCROLLBAR_FADE_IN.as_secs_f32();
+        let idle =
+            now.saturating_duration_since(moved_at).as_secs_f32() - SCROLLBAR_HOLD.as_secs_f32();
+        let fade_out = 1.0 - idle / SCROLLBAR_FADE_OUT.as_secs_f32();
+        fade_in.min(fade_out).max(0.0).min(1.0)
+    }
+
+    /// Shows the scrollbars, fading in from however visible they are.
+    fn moved(&mut self, now: Instant) {
+        let opacity = self.opacity(now);
+        if opacity < 1.0 {
+            self.shown_at = now
+                .checked_sub(SCROLLBAR_FADE_IN.mul_f32(opacity))
+                .or(Some(now));
+        }
+        self.moved_at = Some(now);
+    }
+}
+
+/// Where the thumb of a scrollbar `track` long starts, and how long it
+/// is, for a container scrolled to `offset` out of `max_offset`.
+/// Returns `None` if there's nothing to scroll to.
+pub fn thumb(track: f32, offset: f32, max_offset: f32) -> Option<(f32, f32)> {
+    if max_offset <= 0.0 || track <= 0.0 {
+        return None;
+    }
+    let length = (track * track / (track + max_offset)).max(MIN_THUMB.min(track));
+    let progress = (offset / max_offset).max(0.0).min(1.0);
+    Some(((track - length) * progress, length))
+}
+
+/// How a scroll container is moving on its own.
+enum Motion {
+    Still,
+    /// Moving at a velocity, in logical pixels per second, which slows
+    /// down with friction.
+    Fling(LogicalVector),
+    /// Springing back to the edge after bouncing past it.
+    Return(Spring<LogicalPoint>),
+}
+
+impl Default for Motion {
+    fn default() -> Motion {
+        Motion::Still
+    }
+}
+
+/// The position of a single scroll container.
+#[derive(Default)]
+struct Container {
+    name: Option<String>,
+    offset: LogicalVector,
+    max_offset: LogicalVector,
+    motion: Motion,
+    /// How far past each edge the current glow is, negative for the
+    /// top and left edges.
+    glow: LogicalVector,
+    /// When the container was scrolled recently, and by how much.
+    samples: Vec<(Instant, LogicalVector)>,
+    fade: ScrollbarFade,
+    /// The scrollbar opacity the container was last drawn with.
+    drawn_opacity: f32,
+}
+
+impl Container {
+    fn is_overscrolled(&self) -> bool {
+        clamp(self.offset, self.max_offset) != self.offset
+    }
+
+    /// Moves the container one frame further, and returns whether it
+    /// still has anywhere to go.
+    fn animate(&mut self, physics: &ScrollPhysics, now: Instant, elapsed: f32) -> bool {
+        let offset = self.offset;
+        let moving = self.step(physics, now, elapsed);
+        if self.offset != offset {
+            self.fade.moved(now);
+        }
+        moving
+    }
+
+    fn step(&mut self, physics: &ScrollPhysics, now: Instant, elapsed: f32) -> bool {
+        let clamped = clamp(self.offset, self.max_offset);
+        match self.motion {
+            Motion::Still => {
+                let idle = match self.samples.last() {
+                    Some(&(time, _)) => now.saturating_duration_since(time) >= BOUNCE_DELAY,
+                    None => true,
+                };
+                if idle && self.offset != clamped {
+                    self.motion = Motion::Return(Spring::new(
+                        self.offset.to_point(),
+                        SpringConfig::default(),
+                    ));
+                }
+            }
+            Motion::Fling(velocity) => {
+                let velocity = velocity * (-physics.friction * elapsed).exp();
+                let offset = self.offset + velocity * elapsed;
+                let clamped = clamp(offset, self.max_offset);
+                self.offset = offset;
+                self.motion = Motion::Fling(velocity);
+                if offset != clamped {
+                    match physics.overscroll {
+                        Overscroll::Bounce => {
+                            let mut spring =
+                                Spring::new(offset.to_point(), SpringConfig::default());
+                            spring.set_velocity(velocity.to_point());
+                            self.motion = Motion::Return(spring);
+                        }
+                        Overscroll::Glow => {
+                            let hit = |overshoot: f32, velocity: f32| {
+                                if overshoot != 0.0 {
+                                    velocity * GLOW_PER_VELOCITY
+                                } else {
+                                    0.0
+                                }
+                            };
+                            let overshoot = offset - clamped;
+                            self.glow = clamp_glow(
+                                self.glow
+                                    + vec2(
+                                        hit(overshoot.x, velocity.x),
+                                        hit(overshoot.y, velocity.y),
+                                    ),
+                            );
+                            self.offset = clamped;
+                            self.motion = Motion::Still;
+                        }
+                        Overscroll::Clamp => {
+                            self.offset = clamped;
+                            self.motion = Motion::Still;
+                        }
+                    }
+                } else if velocity.x.abs().max(velocity.y.abs()) < MIN_VELOCITY {
+                    self.motion = Motion::Still;
+                }
+            }
+            Motion::Return(ref mut spring) => {
+                spring.set_target(clamped.to_point());
+                spring.step(Duration::from_secs_f32(elapsed));
+                self.offset = spring.value().to_vector();
+                if spring.is_resting() {
+                    self.motion = Motion::Still;
+                }
+            }
+        }
+        self.glow = self.glow * (-GLOW_FADE * elapsed).exp();
+        if self.glow.x.abs().max(self.glow.y.abs()) < 0.5 {
+            self.glow = LogicalVector::zero();
+        }
+        match self.motion {
+            Motion::Still => self.offset != clamp(self.offset, self.max_offset),
+            _ => true,
+        }
+    }
+}
+
+/// Holds the scroll offsets of every scroll container in a window, and
+/// the transforms of every zoom container. Containers are identified
+/// by their `topo::Id`, so offsets survive the DOM being rebuilt.
+#[derive(Default)]
+pub struct ScrollState {
+    containers: HashMap<topo::Id, Container>,
+    zooms: HashMap<topo::Id, ZoomTransform>,
+    generation: usize,
+    physics: ScrollPhysics,
+    /// The container the current gesture is scrolling.
+    active: Option<topo::Id>,
+    /// When the last frame was animated, while anything is moving.
+    last_frame: Option<Instant>,
+    /// When the frame being drawn was started.
+    frame: Option<Instant>,
+}
+
+/// Finds the size of the content of a layout node, which is the
+/// furthest extent of any of its children.
+pub fn content_size(layout: &LayoutTreeNode) -> LogicalSize {
+    let mut size = LogicalSize::zero();
+    for child in &layout.children {
+        let bottom_right = child.position
+            + child.layout.size
+            + vec2(
+                child.layout.margin.horizontal(),
+                child.layout.margin.vertical(),
+            );
+        size.width = size.width.max(bottom_right.x);
+        size.height = size.height.max(bottom_right.y);
+    }
+    size
+}
+
+impl ScrollState {
+    pub fn set_physics(&mut self, physics: ScrollPhysics) {
+        self.physics = physics;
+    }
+
+    pub fn offset(&self, id: topo::Id) -> LogicalVector {
+        self.containers
+            .get(&id)
+            .map(|container| container.offset)
+            .unwrap_or_else(LogicalVector::zero)
+    }
+
+    /// How far the container can be scrolled in each direction.
+    pub fn max_offset(&self, id: topo::Id) -> LogicalVector {
+        self.containers
+            .get(&id)
+            .map(|container| container.max_offset)
+            .unwrap_or_else(LogicalVector::zero)
+    }
+
+    /// How visible the container's overlay scrollbars are in the frame
+    /// being drawn, from 0 to 1.
+    pub fn scrollbar_opacity(&self, id: topo::Id) -> f32 {
+        match (self.containers.get(&id), self.frame) {
+            (Some(container), Some(now)) => container.fade.opacity(now),
+            _ => 0.0,
+        }
+    }
+
+    /// How far past its edges the container has been scrolled, with
+    /// `Overscroll::Glow`.
+    pub fn glow(&self, id: topo::Id) -> LogicalVector {
+        self.containers
+            .get(&id)
+            .map(|container| container.glow)
+            .unwrap_or_else(LogicalVector::zero)
+    }
+
+    /// Records the size of a scroll container and its content after
+    /// layout, clamping the offset if the content has shrunk. Containers
+    /// which have bounced past their edges are left to spring back.
+    pub fn update_extent(
+        &mut self,
+        id: topo::Id,
+        name: Option<&str>,
+        viewport: LogicalSize,
+        content: LogicalSize,
+    ) {
+        let container = self.containers.entry(id).or_default();
+        container.name = name.map(ToOwned::to_owned);
+        if container.is_overscrolled() {
+            container.max_offset = vec2(
+                (content.width - viewport.width).max(0.0),
+                (content.height - viewport.height).max(0.0),
+            );
+            return;
+        }
+        container.max_offset = vec2(
+            (content.width - viewport.width).max(0.0),
+            (content.height - viewport.height).max(0.0),
+        );
+        let offset = clamp(container.offset, container.max_offset);
+        if offset != container.offset {
+            container.offset = offset;
+            self.generation += 1;
+        }
+    }
+
+    pub fn zoom(&self, id: topo::Id) -> ZoomTransform {
+        self.zooms
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(ZoomTransform::identity)
+    }
+
+    /// Zooms a container's content by `factor`, around `focus`, which is
+    /// relative to the container. Returns false if the container was
+    /// already zoomed as far as it goes.
+    pub fn zoom_by(&mut self, id: topo::Id, focus: LogicalPoint, factor: f32) -> bool {
+        let zoom = self.zoom(id);
+        let zoomed = zoom.zoom_about(focus, factor);
+        if zoomed == zoom {
+            return false;
+        }
+        self.zooms.insert(id, zoomed);
+        self.generation += 1;
+        true
+    }
+
+    /// Moves a zoom container's content the way scrolling by `delta`
+    /// would. Zoomed content has no edges, so this always succeeds.
+    pub fn pan_by(&mut self, id: topo::Id, delta: LogicalVector) -> bool {
+        let mut zoom = self.zoom(id);
+        zoom.pan -= delta;
+        self.zooms.insert(id, zoom);
+        self.generation += 1;
+        true
+    }
+
+    /// Incremented every time any scroll offset or zoom changes.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Scrolls a container by the given amount, as part of a gesture
+    /// which stops it moving on its own. Returns false if the container
+    /// was already at the edge.
+    pub fn scroll_by(&mut self, id: topo::Id, delta: LogicalVector, now: Instant) -> bool {
+        if let Some(container) = self.containers.get_mut(&id) {
+            let max = container.max_offset;
+            let offset = vec2(
+                scroll_axis(container.offset.x, delta.x, max.x),
+                scroll_axis(container.offset.y, delta.y, max.y),
+            );
+            if offset != container.offset {
+                track(container, offset - container.offset, now);
+                container.offset = offset;
+                self.active = Some(id);
+                self.generation += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Scrolls a container which is already at its edge further, which
+    /// depending on the `Overscroll` behavior either moves it past the
+    /// edge, lights up the edge, or does nothing. Returns true if the
+    /// container needs to be redrawn.
+    pub fn overscroll_by(&mut self, id: topo::Id, delta: LogicalVector, now: Instant) -> bool {
+        let overscroll = self.physics.overscroll;
+        let container = match self.containers.get_mut(&id) {
+            Some(container) if overscroll != Overscroll::Clamp => container,
+            _ => return false,
+        };
+        match overscroll {
+            Overscroll::Bounce => {
+                let delta = delta * BOUNCE_RESISTANCE;
+                track(container, delta, now);
+                container.offset += delta;
+            }
+            _ => container.glow = clamp_glow(container.glow + delta),
+        }
+        self.active = Some(id);
+        self.generation += 1;
+        true
+    }
+
+    /// Ends the current scroll gesture, flinging the container it was
+    /// scrolling if it was still moving. Returns true if the container
+    /// now needs to be animated.
+    pub fn end_gesture(&mut self, now: Instant) -> bool {
+        let physics = self.physics;
+        let container = match self.active.take() {
+            Some(id) => match self.containers.get_mut(&id) {
+                Some(container) => container,
+                None => return false,
+            },
+            None => return false,
+        };
+        let velocity = estimate_velocity(&container.samples, now);
+        container.samples.clear();
+        if physics.inertia && velocity.x.abs().max(velocity.y.abs()) >= MIN_VELOCITY {
+            container.motion = Motion::Fling(velocity);
+        }
+        match container.motion {
+            Motion::Still => container.is_overscrolled(),
+            _ => true,
+        }
+    }
+
+    /// Moves every container which is moving on its own up to `now`,
+    /// and fades glows and scrollbars. Returns true if anything is
+    /// still changing, in which case another frame should be rendered
+    /// soon.
+    pub fn animate(&mut self, now: Instant) -> bool {
+        self.frame = Some(now);
+        let elapsed = match self.last_frame {
+            Some(last_frame) => now.saturating_duration_since(last_frame).as_secs_f32(),
+            None => 0.0,
+        };
+        let mut animating = false;
+        for container in self.containers.values_mut() {
+            let before = (container.offset, container.glow);
+            let moving = container.animate(&self.physics, now, elapsed);
+            let opacity = container.fade.opacity(now);
+            if (container.offset, container.glow) != before || opacity != container.drawn_opacity {
+                container.drawn_opacity = opacity;
+                self.generation += 1;
+            }
+            animating |= moving || container.glow != LogicalVector::zero() || opacity > 0.0;
+        }
+        self.last_frame = if animating { Some(now) } else { None };
+        animating
+    }
+
+    /// The positions of all the named containers, for use by
+    /// `ScrollLink`s during styling.
+    pub fn positions(&self) -> ScrollPositions {
+        let mut positions = HashMap::new();
+        for container in self.containers.values() {
+            if let Some(ref name) = container.name {
+                positions.insert(
+                    name.clone(),
+                    ScrollPosition {
+                        x: container.offset.x,
+                        y: container.offset.y,
+                        max_x: container.max_offset.x,
+                        max_y: container.max_offset.y,
+                    },
+                );
+            }
+        }
+        ScrollPositions(positions)
+    }
+}
+
+fn clamp(offset: LogicalVector, max: LogicalVector) -> LogicalVector {
+    vec2(offset.x.max(0.0).min(max.x), offset.y.max(0.0).min(max.y))
+}
+
+fn clamp_glow(glow: LogicalVector) -> LogicalVector {
+    vec2(
+        glow.x.max(-MAX_GLOW).min(MAX_GLOW),
+        glow.y.max(-MAX_GLOW).min(MAX_GLOW),
+    )
+}
+
+/// Moves one axis of an offset by `delta`, without going past either
+/// edge. An offset which already bounced past an edge can move back,
+/// but no further out.
+fn scroll_axis(offset: f32, delta: f32, max: f32) -> f32 {
+    (offset + delta).max(offset.min(0.0)).min(offset.max(max))
+}
+
+/// Records scrolling done by a gesture, and stops the container moving
+/// on its own.
+fn track(container: &mut Container, delta: LogicalVector, now: Instant) {
+    container.motion = Motion::Still;
+    container.fade.moved(now);
+    container
+        .samples
+        .retain(|&(time, _)| now.saturating_duration_since(time) <= VELOCITY_WINDOW);
+    container.samples.push((now, delta));
+}
+
+/// Finds how fast recent scrolling was moving, in logical pixels per
+/// second. Scrolling which stopped a while before `now` isn't moving.
+fn estimate_velocity(samples: &[(Instant, LogicalVector)], now: Instant) -> LogicalVector {
+    let recent = samples
+        .iter()
+        .filter(|&&(time, _)| now.saturating_duration_since(time) <= VELOCITY_WINDOW)
+        .collect::<Vec<_>>();
+    let (first, last) = match (recent.first(), recent.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return LogicalVector::zero(),
+    };
+    let elapsed = last.saturating_duration_since(first).as_secs_f32();
+    if elapsed <= 0.0 {
+        return LogicalVector::zero();
+    }
+    // The first sample is the distance moved before the window began.
+    let distance = recent[1..]
+        .iter()
+        .fold(LogicalVector::zero(), |sum, &&(_, delta)| sum + delta);
+    distance / elapsed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{estimate_velocity, scroll_axis, thumb, ScrollbarFade, ZoomTransform};
+    use euclid::{point2, vec2};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn estimates_velocity() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let samples = vec![
+            (at(0), vec2(0.0, 100.0)),
+            (at(200), vec2(0.0, 10.0)),
+            (at(250), vec2(0.0, 10.0)),
+            (at(300), vec2(0.0, 10.0)),
+        ];
+        // Only the last 100ms count.
+        assert_eq!(estimate_velocity(&samples, at(300)), vec2(0.0, 200.0));
+        assert_eq!(estimate_velocity(&samples, at(1000)), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn fades_scrollbars() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut fade = ScrollbarFade::default();
+        assert_eq!(fade.opacity(at(0)), 0.0);
+        fade.moved(at(0));
+        assert!((fade.opacity(at(50)) - 0.5).abs() < 0.01);
+        assert_eq!(fade.opacity(at(800)), 1.0);
+        assert_eq!(fade.opacity(at(1100)), 0.0);
+        // Moving again while fading out fades back in from there.
+        let mut fade = ScrollbarFade::default();
+        fade.moved(at(0));
+        fade.moved(at(1000));
+        assert!((fade.opacity(at(1000)) - 1.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn sizes_thumbs() {
+        assert_eq!(thumb(100.0, 0.0, 0.0), None);
+        assert_eq!(thumb(100.0, 0.0, 100.0), Some((0.0, 50.0)));
+        assert_eq!(thumb(100.0, 100.0, 100.0), Some((50.0, 50.0)));
+        assert_eq!(thumb(100.0, 5000.0, 10000.0), Some((40.0, 20.0)));
+    }
+
+    #[test]
+    fn scrolls_back_from_past_edges() {
+        assert_eq!(scroll_axis(50.0, 80.0, 100.0), 100.0);
+        assert_eq!(scroll_axis(-30.0, -10.0, 100.0), -30.0);
+        assert_eq!(scroll_axis(-30.0, 10.0, 100.0), -20.0);
+        assert_eq!(scroll_axis(130.0, 10.0, 100.0), 130.0);
+    }
+
+    #[test]
+    fn zooms_about_focus() {
+        let focus = point2(30.0, 40.0);
+        let zoom = ZoomTransform::identity().zoom_about(focus, 2.0);
+        assert_eq!(zoom.scale, 2.0);
+        assert_eq!(zoom.apply(focus), focus);
+        let zoom = zoom.zoom_about(point2(0.0, 0.0), 0.5);
+        assert_eq!(zoom.apply(point2(0.0, 0.0)), point2(-15.0, -20.0));
+        assert_eq!(zoom.invert(zoom.apply(focus)), focus);
+    }
+
+    #[test]
+    fn composes_about_origins() {
+        let zoom = ZoomTransform {
+            scale: 2.0,
+            pan: vec2(5.0, 0.0),
+        };
+        let origin = point2(100.0, 100.0);
+        // The content's origin is drawn at the container's origin,
+        // moved by the pan.
+        assert_eq!(zoom.about(origin).apply(origin), point2(105.0, 100.0));
+        let twice = zoom.then(zoom);
+        assert_eq!(twice.apply(point2(1.0, 1.0)), point2(19.0, 4.0));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::dom::element::DynamicNode;
+use crate::dom::node::AnyNode;
+use crate::layout::{Cluster, LayoutTreeNode, RenderData};
+use std::ops::Range;
+
+/// Text selected by dragging the pointer across it. A selection stays
+/// within the node it started in, and its offsets are into that node's
+/// text content, which is all of its text children joined together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Selection {
+    pub node: topo::Id,
+    /// Where the pointer was pressed.
+    pub anchor: usize,
+    /// Where the pointer is now, which may be before the anchor.
+    pub focus: usize,
+    /// Set until the pointer is released.
+    pub dragging: bool,
+}
+
+impl Selection {
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.focus)..self.anchor.max(self.focus)
+    }
+}
+
+pub fn text_content(node: &AnyNode) -> String {
+    node.children()
+        .filter_map(|child| match child {
+            DynamicNode::Text(text) => Some(text),
+            DynamicNode::Node(_) => None,
+        })
+        .collect()
+}
+
+/// The selected part of `text`, which is empty if the text changed and
+/// no longer has the range in it.
+pub fn selected_text(text: &str, range: Range<usize>) -> &str {
+    let end = range.end.min(text.len());
+    text.get(range.start.min(end)..end).unwrap_or("")
+}
+
+/// Finds the node created at `id` among the nodes with text in `layout`.
+pub fn find_text_node(layout: &LayoutTreeNode, id: topo::Id) -> Option<AnyNode> {
+    match layout.render {
+        RenderData::Text { ref parent, .. } if parent.id() == Some(id) => Some(parent.clone()),
+        RenderData::Text { .. } => None,
+        RenderData::Node(_) => layout
+            .children
+            .iter()
+            .find_map(|child| find_text_node(&child.layout, id)),
+    }
+}
+
+/// The offset of the caret position nearest to `x` on a line of text,
+/// which is on whichever side of the character under it is closer.
+pub fn offset_at(clusters: &[Cluster], x: f32) -> Option<usize> {
+    let cluster = clusters
+        .iter()
+        .find(|cluster| x < cluster.right)
+        .or_else(|| clusters.last())?;
+    let before_middle = x < (cluster.left + cluster.right) / 2.0;
+    // Right-to-left characters start on the right.
+    if before_middle != cluster.rtl {
+        Some(cluster.start)
+    } else {
+        Some(cluster.end)
+    }
+}
+
+/// The horizontal extents of the selected characters on a line of
+/// text, with neighbouring characters merged. Characters from the same
+/// range can be apart on the line when it mixes directions.
+pub fn highlight(clusters: &[Cluster], range: Range<usize>) -> Vec<(f32, f32)> {
+    let mut spans: Vec<(f32, f32)> = vec![];
+    let selected = clusters
+        .iter()
+        .filter(|cluster| range.start <= cluster.start && cluster.end <= range.end);
+    for cluster in selected {
+        match spans.last_mut() {
+            Some(span) if span.1 == cluster.left => span.1 = cluster.right,
+            _ => spans.push((cluster.left, cluster.right)),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use super::{highlight, offset_at, selected_text};
+    use crate::layout::Cluster;
+
+    /// "ab" followed by the right-to-left "CD", which is drawn as "DC".
+    fn clusters() -> Vec<Cluster> {
+        let cluster = |start, left, rtl| Cluster {
+            start,
+            end: start + 1,
+            left,
+            right: left + 10.0,
+            rtl,
+        };
+        vec![
+            cluster(0, 0.0, false),
+            cluster(1, 10.0, false),
+            cluster(3, 20.0, true),
+            cluster(2, 30.0, true),
+        ]
+    }
+
+    #[test]
+    fn finds_offsets() {
+        let clusters = clusters();
+        assert_eq!(offset_at(&clusters, -5.0), Some(0));
+        assert_eq!(offset_at(&clusters, 6.0), Some(1));
+        assert_eq!(offset_at(&clusters, 24.0), Some(4));
+        assert_eq!(offset_at(&clusters, 36.0), Some(2));
+        assert_eq!(offset_at(&clusters, 100.0), Some(2));
+        assert_eq!(offset_at(&[], 0.0), None);
+    }
+
+    #[test]
+    fn highlights_selected_characters() {
+        let clusters = clusters();
+        assert_eq!(highlight(&clusters, 0..2), vec![(0.0, 20.0)]);
+        assert_eq!(highlight(&clusters, 1..3), vec![(10.0, 20.0), (30.0, 40.0)]);
+        assert_eq!(highlight(&clusters, 2..2), vec![]);
+        assert_eq!(selected_text("abCD", 1..3), "bC");
+        assert_eq!(selected_text("ab", 1..3), "b");
+    }
+}
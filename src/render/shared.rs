@@ -0,0 +1,180 @@
+use super::hooks::FrameHooks;
+use super::thread::RenderThread;
+use crate::layout::{FontLoader, FontRegistry};
+use glutin::{ContextBuilder, CreationError, GlRequest, NotCurrent, Robustness};
+use std::cell::RefCell;
+use std::rc::Rc;
+use winit::dpi::PhysicalSize;
+use winit::event_loop::{EventLoopProxy, EventLoopWindowTarget};
+
+pub const GL_REQUEST: GlRequest = GlRequest::GlThenGles {
+    opengl_version: (3, 2),
+    opengles_version: (3, 0),
+};
+
+/// Asks for contexts which report a GPU reset as a lost context, rather
+/// than carrying on in an undefined state. Contexts only share lists
+/// with contexts that have the same robustness.
+pub const GL_ROBUSTNESS: Robustness = Robustness::TryRobustLoseContextOnReset;
+
+/// Resources shared between every window, so that opening another
+/// window doesn't repeat the work already done for the first one.
+///
+/// Every window's GL context shares lists with a hidden context, so
+/// shader programs linked for one window are reused by the rest. Each
+/// window still has its own webrender `Renderer`, and with it its own
+/// glyph atlas and texture cache, as webrender doesn't support sharing
+/// those between renderers.
+///
+/// When the GPU can't be used, such as in a virtual machine, over
+/// remote desktop, or with missing drivers, rendering falls back to
+/// software. This needs a software OpenGL implementation, which Linux
+/// distributions ship with Mesa, and macOS has built in. See
+/// `ShareContext::new` for when it's chosen.
+#[derive(Clone)]
+pub struct SharedResources {
+    /// Never made current, so that new contexts can always share lists
+    /// with it.
+    pub(crate) share_context: Rc<glutin::Context<NotCurrent>>,
+    /// Set once rendering has fallen back to software.
+    pub(crate) software: bool,
+    pub(crate) render_thread: RenderThread,
+    pub(crate) fonts: Rc<RefCell<FontLoader>>,
+    pub(crate) hooks: Rc<FrameHooks>,
+}
+
+/// The first context every other context shares lists with, and
+/// whether it renders in software.
+pub(crate) struct ShareContext {
+    context: glutin::Context<NotCurrent>,
+    software: bool,
+}
+
+impl ShareContext {
+    /// Creates the first share context, falling back to software if the
+    /// GPU can't be used. Mesa is told to use software through the
+    /// environment, which can't be changed safely while other threads
+    /// may be reading it, so this has to be called before any threads
+    /// are started. Fails if neither can be used.
+    pub(crate) fn new(event_loop: &EventLoopWindowTarget<()>) -> Result<ShareContext, String> {
+        let reason = match create_share_context(event_loop, false) {
+            Ok(context) => {
+                return Ok(ShareContext {
+                    context,
+                    software: false,
+                })
+            }
+            Err(err) => err,
+        };
+        log::warn!(
+            "Couldn't render with the GPU, falling back to software rendering: {}",
+            reason
+        );
+        // Mesa reads this when each context is created.
+        std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+        match create_share_context(event_loop, true) {
+            Ok(context) => Ok(ShareContext {
+                context,
+                software: true,
+            }),
+            Err(err) => Err(format!(
+                "Couldn't render with the GPU ({}) or in software ({})",
+                reason, err
+            )),
+        }
+    }
+}
+
+impl SharedResources {
+    /// Creates the shared resources and starts finding fonts. The event
+    /// loop is woken up once fonts are ready to be polled.
+    pub(crate) fn new(
+        share_context: ShareContext,
+        events_proxy: EventLoopProxy<()>,
+        hooks: Rc<FrameHooks>,
+        font_registry: FontRegistry,
+    ) -> SharedResources {
+        let render_proxy = events_proxy.clone();
+        let render_thread = RenderThread::spawn(move || {
+            let _ = render_proxy.send_event(());
+        });
+        let fonts = FontLoader::spawn(font_registry, move || {
+            let _ = events_proxy.send_event(());
+        });
+        SharedResources {
+            share_context: Rc::new(share_context.context),
+            software: share_context.software,
+            render_thread,
+            fonts: Rc::new(RefCell::new(fonts)),
+            hooks,
+        }
+    }
+
+    /// Replaces the context new contexts share lists with, for after a
+    /// context has been lost, which may have taken the rest of its
+    /// share group with it. Shader programs are cached as binaries, so
+    /// they still don't need to be compiled again. If no context can be
+    /// created, the old one is kept, and opening windows fails later.
+    pub(crate) fn reset_share_context(&mut self, event_loop: &EventLoopWindowTarget<()>) {
+        match create_share_context(event_loop, self.software) {
+            Ok(context) => self.share_context = Rc::new(context),
+            Err(err) => {
+                if let Err(err) = self.use_software(event_loop, &err.to_string()) {
+                    log::error!("Couldn't replace the lost share context: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Switches to software rendering, after the GPU couldn't be used
+    /// for `reason`. Contexts created before the switch keep rendering
+    /// as they were, but new ones can't share lists with them.
+    ///
+    /// Other threads are running by now, so unlike `ShareContext::new`
+    /// this doesn't tell Mesa to use software, and only stops asking
+    /// for acceleration. macOS then picks its software renderer, and
+    /// elsewhere it's up to the driver.
+    pub(crate) fn use_software(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        reason: &str,
+    ) -> Result<(), String> {
+        log::warn!(
+            "Couldn't render with the GPU, falling back to software rendering: {}",
+            reason
+        );
+        let context = create_share_context(event_loop, true).map_err(|err| err.to_string())?;
+        self.share_context = Rc::new(context);
+        self.software = true;
+        Ok(())
+    }
+
+    /// Starts building a context which shares lists with the rest.
+    pub(crate) fn context_builder(&self) -> ContextBuilder<NotCurrent> {
+        context_builder(self.software).with_shared_lists(&*self.share_context)
+    }
+}
+
+fn context_builder<'a>(software: bool) -> ContextBuilder<'a, NotCurrent> {
+    // macOS picks its software renderer for contexts which don't ask
+    // for acceleration. Elsewhere it's up to the OpenGL implementation,
+    // which is told through the environment in `ShareContext::new`.
+    let acceleration = if !software {
+        Some(true)
+    } else if cfg!(target_os = "macos") {
+        Some(false)
+    } else {
+        None
+    };
+    ContextBuilder::new()
+        .with_gl(GL_REQUEST)
+        .with_gl_robustness(GL_ROBUSTNESS)
+        .with_hardware_acceleration(acceleration)
+}
+
+fn create_share_context(
+    event_loop: &EventLoopWindowTarget<()>,
+    software: bool,
+) -> Result<glutin::Context<NotCurrent>, CreationError> {
+    context_builder(software).build_headless(event_loop, PhysicalSize::new(1.0, 1.0))
+}
@@ -0,0 +1,468 @@
+use crate::util::latest_slot::{latest_slot, Reader, Writer};
+use crate::Color;
+use gleam::gl;
+use glutin::{ContextError, NotCurrent, PossiblyCurrent, RawContext};
+use rayon::ThreadPoolBuilder;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use webrender::api::{
+    units::DeviceIntRect, units::DeviceIntSize, DocumentId, RenderApi, RenderApiSender,
+    RenderNotifier, Transaction,
+};
+use webrender::euclid::Point2D;
+use webrender::{ProgramCache, Renderer, RendererOptions, ShaderPrecacheFlags};
+use winit::dpi::PhysicalSize;
+
+/// A display list which has been built on the UI thread, and is ready
+/// to be drawn.
+pub struct Frame {
+    pub transaction: Transaction,
+    pub size: DeviceIntSize,
+    pub dpi_scale: f32,
+    /// Signalled once the frame has been drawn, with its pixels if the
+    /// target is headless, and nothing otherwise. If the frame is
+    /// replaced by a newer one before being drawn, this is dropped
+    /// instead.
+    pub presented: Option<mpsc::Sender<Vec<u8>>>,
+}
+
+/// What a surface draws into.
+pub enum RenderTarget {
+    Window(RawContext<NotCurrent>),
+    /// An offscreen context, used for rendering in tests and printing.
+    #[cfg_attr(not(any(feature = "testing", feature = "print")), allow(dead_code))]
+    Headless(glutin::Context<NotCurrent>),
+}
+
+enum CurrentTarget {
+    Window(RawContext<PossiblyCurrent>),
+    Headless(glutin::Context<PossiblyCurrent>),
+}
+
+impl RenderTarget {
+    fn make_current(self) -> Result<CurrentTarget, ContextError> {
+        unsafe {
+            match self {
+                RenderTarget::Window(context) => context
+                    .make_current()
+                    .map(CurrentTarget::Window)
+                    .map_err(|(_, err)| err),
+                RenderTarget::Headless(context) => context
+                    .make_current()
+                    .map(CurrentTarget::Headless)
+                    .map_err(|(_, err)| err),
+            }
+        }
+    }
+}
+
+impl CurrentTarget {
+    fn context(&self) -> &glutin::Context<PossiblyCurrent> {
+        match self {
+            CurrentTarget::Window(context) => context.context(),
+            CurrentTarget::Headless(context) => context,
+        }
+    }
+
+    /// Makes the context current again, giving it back along with the
+    /// error if that fails.
+    fn make_current(self) -> Result<CurrentTarget, (CurrentTarget, ContextError)> {
+        unsafe {
+            match self {
+                CurrentTarget::Window(context) => context
+                    .make_current()
+                    .map(CurrentTarget::Window)
+                    .map_err(|(context, err)| (CurrentTarget::Window(context), err)),
+                CurrentTarget::Headless(context) => context
+                    .make_current()
+                    .map(CurrentTarget::Headless)
+                    .map_err(|(context, err)| (CurrentTarget::Headless(context), err)),
+            }
+        }
+    }
+}
+
+/// Identifies a surface on the render thread.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct SurfaceId(u64);
+
+enum Message {
+    Add {
+        target: RenderTarget,
+        size: DeviceIntSize,
+        dpi_scale: f32,
+        clear_color: Color,
+        frames: Reader<Frame>,
+        lost: Arc<AtomicBool>,
+        reply: mpsc::Sender<Result<(SurfaceId, RenderApiSender, DocumentId), String>>,
+    },
+    /// Replied to once the surface and its context are gone.
+    Remove(SurfaceId, mpsc::Sender<()>),
+    FrameBuilt(SurfaceId),
+    FrameReady(SurfaceId),
+}
+
+/// Used by Webrender to tell the render thread that a frame is ready.
+#[derive(Clone)]
+struct Notifier {
+    id: SurfaceId,
+    tx: mpsc::Sender<Message>,
+}
+
+impl RenderNotifier for Notifier {
+    fn clone(&self) -> Box<dyn RenderNotifier> {
+        Box::new(Clone::clone(self))
+    }
+
+    fn wake_up(&self) {
+        let _ = self.tx.send(Message::FrameReady(self.id));
+    }
+
+    fn new_frame_ready(&self, _: DocumentId, _: bool, _: bool, _: Option<u64>) {
+        self.wake_up();
+    }
+}
+
+/// Submits display lists to Webrender and presents the resulting
+/// frames, for every window.
+///
+/// The UI thread only builds display lists, and hands them over without
+/// waiting for them to be drawn, so a slow frame doesn't hold up event
+/// handling, and slow event handling doesn't hold up frames that have
+/// already been built. All of the GL contexts live on this thread.
+///
+/// When a surface's GL context is lost, such as after a driver reset or
+/// a switch between GPUs, the surface stops drawing and `wake` is
+/// called, so that the UI thread can replace it.
+#[derive(Clone)]
+pub struct RenderThread {
+    tx: mpsc::Sender<Message>,
+}
+
+impl RenderThread {
+    pub fn spawn(wake: impl Fn() + Send + 'static) -> RenderThread {
+        let (tx, rx) = mpsc::channel();
+        let thread_tx = tx.clone();
+        thread::Builder::new()
+            .name("Render".to_owned())
+            .spawn(move || run(rx, thread_tx, wake))
+            .unwrap();
+        RenderThread { tx }
+    }
+
+    /// Creates a Webrender instance drawing into the given target.
+    /// `clear_color` should already be in the target's color space.
+    /// Fails if the target's context can't be used, or doesn't support
+    /// everything Webrender needs, such as when its shaders don't
+    /// compile.
+    pub fn add_surface(
+        &self,
+        target: RenderTarget,
+        size: DeviceIntSize,
+        dpi_scale: f32,
+        clear_color: Color,
+    ) -> Result<RenderSurface, String> {
+        let (writer, reader) = latest_slot();
+        let (reply, response) = mpsc::channel();
+        let lost = Arc::new(AtomicBool::new(false));
+        self.tx
+            .send(Message::Add {
+                target,
+                size,
+                dpi_scale,
+                clear_color,
+                frames: reader,
+                lost: lost.clone(),
+                reply,
+            })
+            .unwrap();
+        let (id, api_sender, document) = response.recv().unwrap()?;
+        Ok(RenderSurface {
+            id,
+            tx: self.tx.clone(),
+            frames: writer,
+            lost,
+            api_sender,
+            document,
+        })
+    }
+}
+
+/// The UI thread's handle to a surface on the render thread. The
+/// surface is removed from the render thread when this is dropped,
+/// which waits until its context has been destroyed, so the native
+/// window it draws into has to be dropped after it.
+pub struct RenderSurface {
+    id: SurfaceId,
+    tx: mpsc::Sender<Message>,
+    frames: Writer<Frame>,
+    lost: Arc<AtomicBool>,
+    pub api_sender: RenderApiSender,
+    pub document: DocumentId,
+}
+
+impl RenderSurface {
+    /// Whether the surface's GL context was lost, in which case nothing
+    /// presented to it is drawn, and it needs to be replaced.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Hands a frame to the render thread. If the previous frame hasn't
+    /// been picked up yet, it's replaced.
+    pub fn present(&self, frame: Frame) {
+        self.frames.write(frame);
+        let _ = self.tx.send(Message::FrameBuilt(self.id));
+    }
+}
+
+impl Drop for RenderSurface {
+    fn drop(&mut self) {
+        let (done, removed) = mpsc::channel();
+        if self.tx.send(Message::Remove(self.id, done)).is_ok() {
+            // Fails only if the render thread has panicked, in which
+            // case it's not using the context anymore.
+            let _ = removed.recv();
+        }
+    }
+}
+
+/// A surface's state on the render thread.
+struct Surface {
+    /// Only `None` while switching which context is current.
+    target: Option<CurrentTarget>,
+    gl: Rc<dyn gl::Gl>,
+    renderer: Renderer,
+    api: RenderApi,
+    document: DocumentId,
+    frames: Reader<Frame>,
+    size: DeviceIntSize,
+    /// Whether a frame has been sent to Webrender and not drawn yet.
+    in_flight: bool,
+    presented: Vec<mpsc::Sender<Vec<u8>>>,
+    /// Shared with the surface's `RenderSurface`.
+    lost: Arc<AtomicBool>,
+}
+
+impl Surface {
+    fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Stops drawing to the surface. Frames waiting to be presented are
+    /// dropped, so nothing waits for them forever.
+    fn lose(&mut self, err: ContextError) {
+        log::warn!("Lost the GL context of a surface: {:?}", err);
+        self.lost.store(true, Ordering::SeqCst);
+        self.in_flight = false;
+        self.presented.clear();
+    }
+
+    /// Returns false if the context couldn't be made current, in which
+    /// case the surface has been lost.
+    fn make_current(&mut self) -> bool {
+        if self.is_lost() {
+            return false;
+        }
+        if !self.target.as_ref().unwrap().context().is_current() {
+            match self.target.take().unwrap().make_current() {
+                Ok(target) => self.target = Some(target),
+                Err((target, err)) => {
+                    self.target = Some(target);
+                    self.lose(err);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Sends the latest frame to Webrender, unless it's still busy with
+    /// the previous one.
+    fn submit(&mut self) {
+        if self.in_flight || self.is_lost() {
+            return;
+        }
+        if let Some(frame) = self.frames.read() {
+            if frame.size != self.size {
+                self.size = frame.size;
+                if let Some(CurrentTarget::Window(ref context)) = self.target {
+                    let size = PhysicalSize::new(frame.size.width as f64, frame.size.height as f64);
+                    context.resize(size);
+                }
+            }
+            self.presented.extend(frame.presented);
+            self.api.set_document_view(
+                self.document,
+                DeviceIntRect::new(Point2D::zero(), frame.size),
+                frame.dpi_scale,
+            );
+            self.api.send_transaction(self.document, frame.transaction);
+            self.in_flight = true;
+        }
+    }
+
+    fn draw(&mut self) {
+        if !self.in_flight || !self.make_current() {
+            return;
+        }
+        self.renderer.update();
+        let _ = self.renderer.render(self.size);
+        let _ = self.renderer.flush_pipeline_info();
+        let pixels = match self.target {
+            Some(CurrentTarget::Window(ref context)) => {
+                if let Err(err) = context.swap_buffers() {
+                    self.lose(err);
+                    return;
+                }
+                vec![]
+            }
+            _ if self.presented.is_empty() => vec![],
+            _ => read_pixels(&*self.gl, self.size),
+        };
+        self.in_flight = false;
+        for presented in self.presented.drain(..) {
+            let _ = presented.send(pixels.clone());
+        }
+    }
+}
+
+/// Reads back the framebuffer as RGBA, with rows from top to bottom.
+fn read_pixels(gl: &dyn gl::Gl, size: DeviceIntSize) -> Vec<u8> {
+    let pixels = gl.read_pixels(0, 0, size.width, size.height, gl::RGBA, gl::UNSIGNED_BYTE);
+    // GL's rows start from the bottom.
+    pixels
+        .chunks(size.width as usize * 4)
+        .rev()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn load_gl(context: &glutin::Context<PossiblyCurrent>) -> Rc<dyn gl::Gl> {
+    match context.get_api() {
+        glutin::Api::OpenGl => unsafe {
+            gl::GlFns::load_with(|symbol| context.get_proc_address(symbol) as *const _)
+        },
+        glutin::Api::OpenGlEs => unsafe {
+            gl::GlesFns::load_with(|symbol| context.get_proc_address(symbol) as *const _)
+        },
+        glutin::Api::WebGl => unimplemented!(),
+    }
+}
+
+fn run(rx: mpsc::Receiver<Message>, tx: mpsc::Sender<Message>, wake: impl Fn()) {
+    // Every window's context shares lists, so programs linked for one
+    // window are reused by the rest.
+    let program_cache = ProgramCache::new(None);
+    let workers = ThreadPoolBuilder::new()
+        .thread_name(|index| format!("WRWorker#{}", index))
+        .build()
+        .unwrap();
+    let workers = Arc::new(workers);
+    let mut surfaces: HashMap<SurfaceId, Surface> = HashMap::new();
+    let mut next_id = 0;
+
+    for message in rx {
+        match message {
+            Message::Add {
+                target,
+                size,
+                dpi_scale,
+                clear_color,
+                frames,
+                lost,
+                reply,
+            } => {
+                let id = SurfaceId(next_id);
+                next_id += 1;
+                let target = match target.make_current() {
+                    Ok(target) => target,
+                    Err(err) => {
+                        let _ = reply.send(Err(format!("{:?}", err)));
+                        continue;
+                    }
+                };
+                let gl = load_gl(target.context());
+                let notifier = Box::new(Notifier { id, tx: tx.clone() });
+                let renderer = Renderer::new(
+                    gl.clone(),
+                    notifier,
+                    RendererOptions {
+                        clear_color: Some(clear_color.to_color_f(clear_color.space)),
+                        device_pixel_ratio: dpi_scale,
+                        // Compile every shader up front, so that the
+                        // first frames don't stall while new shaders
+                        // are needed.
+                        precache_flags: ShaderPrecacheFlags::FULL_COMPILE,
+                        cached_programs: Some(program_cache.clone()),
+                        workers: Some(workers.clone()),
+                        ..Default::default()
+                    },
+                    None,
+                    size,
+                );
+                let (renderer, sender) = match renderer {
+                    Ok(renderer) => renderer,
+                    Err(err) => {
+                        let _ = reply.send(Err(format!("{:?}", err)));
+                        continue;
+                    }
+                };
+                let api = sender.create_api();
+                let document = api.add_document(size, 0);
+                let _ = reply.send(Ok((id, sender, document)));
+                surfaces.insert(
+                    id,
+                    Surface {
+                        target: Some(target),
+                        gl,
+                        renderer,
+                        api,
+                        document,
+                        frames,
+                        size,
+                        in_flight: false,
+                        presented: vec![],
+                        lost,
+                    },
+                );
+            }
+            Message::Remove(id, done) => {
+                if let Some(mut surface) = surfaces.remove(&id) {
+                    if surface.make_current() {
+                        surface.renderer.deinit();
+                    } else {
+                        // The GL objects of a lost context are already
+                        // gone, and deleting them would call into a
+                        // context which can't be made current. Dropping
+                        // the renderer without `deinit` trips its debug
+                        // assertions and panics this thread, so its
+                        // memory is leaked instead, which only happens
+                        // once per lost context.
+                        std::mem::forget(surface.renderer);
+                    }
+                }
+                let _ = done.send(());
+            }
+            Message::FrameBuilt(id) => {
+                if let Some(surface) = surfaces.get_mut(&id) {
+                    surface.submit();
+                }
+            }
+            Message::FrameReady(id) => {
+                if let Some(surface) = surfaces.get_mut(&id) {
+                    surface.draw();
+                    if surface.is_lost() {
+                        wake();
+                    } else {
+                        surface.submit();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::animation::{Animation, Transition};
+use crate::layout::LogicalRect;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Tracks where nodes were placed in the previous frame, so that nodes
+/// with `layout_transition` set can be animated from their old position
+/// and size to their new one. Nodes are matched up between frames using
+/// their `topo::Id`.
+#[derive(Default)]
+pub struct LayoutTransitions {
+    now: Option<Instant>,
+    previous: HashMap<topo::Id, LogicalRect>,
+    current: HashMap<topo::Id, LogicalRect>,
+    active: HashMap<topo::Id, Animation<LogicalRect>>,
+}
+
+impl LayoutTransitions {
+    pub fn begin_frame(&mut self) {
+        self.now = Some(Instant::now());
+    }
+
+    /// Returns the rect the node should be drawn at this frame, given
+    /// where layout placed it.
+    pub fn resolve(
+        &mut self,
+        id: topo::Id,
+        target: LogicalRect,
+        transition: Transition,
+    ) -> LogicalRect {
+        let now = self.now.unwrap_or_else(Instant::now);
+        self.current.insert(id, target);
+
+        if let Some(animation) = self.active.get_mut(&id) {
+            if *animation.target() != target {
+                // Retarget from wherever the node currently is, so that
+                // changes mid-transition don't cause a jump.
+                animation.retarget(transition, target, now);
+            }
+            return animation.value_at(now);
+        }
+
+        match self.previous.get(&id) {
+            Some(&from) if from != target => {
+                let mut animation = Animation::new_at(transition, from, target, now);
+                let value = animation.value_at(now);
+                self.active.insert(id, animation);
+                value
+            }
+            _ => target,
+        }
+    }
+
+    /// Carries the rects of the given nodes over from the previous
+    /// frame, for nodes which weren't resolved this frame because they
+    /// are part of a cached subtree that wasn't rebuilt.
+    pub fn keep(&mut self, ids: &[topo::Id]) {
+        for id in ids {
+            if let Some(&rect) = self.previous.get(id) {
+                self.current.insert(*id, rect);
+            }
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// Finishes the frame, and returns whether any transitions are still
+    /// running and need another frame to be rendered.
+    pub fn end_frame(&mut self) -> bool {
+        let now = self.now.take().unwrap_or_else(Instant::now);
+        self.previous = std::mem::replace(&mut self.current, HashMap::new());
+        let previous = &self.previous;
+        self.active
+            .retain(|id, animation| previous.contains_key(id) && !animation.finished_at(now));
+        !self.active.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LayoutTransitions;
+    use crate::animation::{Easing, Transition};
+    use crate::layout::LogicalRect;
+    use euclid::{point2, size2};
+    use std::time::{Duration, Instant};
+
+    const TRANSITION: Transition = Transition::Tween {
+        duration: Duration::from_millis(100),
+        easing: Easing::Linear,
+    };
+
+    fn rect(x: f32) -> LogicalRect {
+        LogicalRect::new(point2(x, 0.0), size2(10.0, 10.0))
+    }
+
+    /// Draws one frame with the node laid out at `x`, and returns where
+    /// it was drawn.
+    fn frame(transitions: &mut LayoutTransitions, id: topo::Id, now: Instant, x: f32) -> f32 {
+        transitions.now = Some(now);
+        let drawn = transitions.resolve(id, rect(x), TRANSITION);
+        transitions.end_frame();
+        drawn.origin.x
+    }
+
+    #[test]
+    fn transitions_between_layouts() {
+        let mut transitions = LayoutTransitions::default();
+        let id = topo::call!({ topo::Id::current() });
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(frame(&mut transitions, id, at(0), 0.0), 0.0);
+        assert!(!transitions.is_animating());
+
+        // Moving starts from where the node was.
+        assert_eq!(frame(&mut transitions, id, at(0), 100.0), 0.0);
+        assert!(transitions.is_animating());
+        assert_eq!(frame(&mut transitions, id, at(50), 100.0), 50.0);
+        assert_eq!(frame(&mut transitions, id, at(150), 100.0), 100.0);
+        assert!(!transitions.is_animating());
+    }
+
+    #[test]
+    fn replaces_transitions_in_flight() {
+        let mut transitions = LayoutTransitions::default();
+        let id = topo::call!({ topo::Id::current() });
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        frame(&mut transitions, id, at(0), 0.0);
+        frame(&mut transitions, id, at(0), 100.0);
+        assert_eq!(frame(&mut transitions, id, at(50), 100.0), 50.0);
+
+        // Moving back halfway through continues from where the node is
+        // drawn instead of jumping, and takes the whole duration again.
+        assert_eq!(frame(&mut transitions, id, at(50), 0.0), 50.0);
+        assert_eq!(frame(&mut transitions, id, at(100), 0.0), 25.0);
+        assert!(transitions.is_animating());
+        assert_eq!(frame(&mut transitions, id, at(150), 0.0), 0.0);
+        assert!(!transitions.is_animating());
+    }
+}
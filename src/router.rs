@@ -0,0 +1,308 @@
+//! Navigation between the views of an application, described by an
+//! enum of the routes it has.
+//!
+//! The router keeps a history of visited routes in state, which can be
+//! moved back and forward through like a browser's. The first route can
+//! be given as a command line argument, so that other applications and
+//! the shell can link to a particular view.
+//!
+//! ```rs
+//! #[derive(Clone, Debug, PartialEq)]
+//! enum Page {
+//!     Inbox,
+//!     Message(u32),
+//! }
+//!
+//! impl Route for Page {
+//!     fn parse(path: &str) -> Option<Page> {
+//!         match path.split('/').collect::<Vec<_>>()[..] {
+//!             ["inbox"] => Some(Page::Inbox),
+//!             ["message", id] => id.parse().ok().map(Page::Message),
+//!             _ => None,
+//!         }
+//!     }
+//!
+//!     fn path(&self) -> String {
+//!         match self {
+//!             Page::Inbox => "inbox".to_owned(),
+//!             Page::Message(id) => format!("message/{}", id),
+//!         }
+//!     }
+//! }
+//!
+//! #[topo::nested]
+//! fn mail() -> Node<View> {
+//!     let router = router!(|| Page::Inbox);
+//!     match router.current() {
+//!         Page::Inbox => inbox!(&router),
+//!         Page::Message(id) => message!(&router, *id),
+//!     }
+//! }
+//! ```
+//!
+//! Running `mail message/4` opens straight to the fourth message.
+
+use crate::dom::events::ClickEvent;
+use moxie::Key;
+use std::fmt;
+
+/// The views an application can navigate between.
+pub trait Route: Clone + fmt::Debug + PartialEq + 'static {
+    /// Reads a route from a deep link, such as a command line argument.
+    fn parse(path: &str) -> Option<Self>;
+
+    /// Writes the route in the form `parse` reads.
+    fn path(&self) -> String;
+}
+
+/// The routes which have been visited, and which of them is current.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History<R> {
+    entries: Vec<R>,
+    index: usize,
+}
+
+impl<R> History<R>
+where
+    R: Route,
+{
+    pub fn new(initial: R) -> History<R> {
+        History {
+            entries: vec![initial],
+            index: 0,
+        }
+    }
+
+    /// Starts at the first argument which is a valid route, or
+    /// `fallback` if there aren't any. The first argument, the name of
+    /// the program, is skipped.
+    pub fn from_args(args: impl IntoIterator<Item = String>, fallback: R) -> History<R> {
+        let initial = args
+            .into_iter()
+            .skip(1)
+            .filter_map(|arg| R::parse(&arg))
+            .next()
+            .unwrap_or(fallback);
+        History::new(initial)
+    }
+
+    pub fn current(&self) -> &R {
+        &self.entries[self.index]
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.index > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.index + 1 < self.entries.len()
+    }
+
+    /// Visits `route`, forgetting any routes that could have been gone
+    /// forward to. Visiting the current route does nothing.
+    pub fn push(&mut self, route: R) {
+        if *self.current() == route {
+            return;
+        }
+        self.entries.truncate(self.index + 1);
+        self.entries.push(route);
+        self.index += 1;
+    }
+
+    /// Replaces the current route without adding to the history.
+    pub fn replace(&mut self, route: R) {
+        self.entries[self.index] = route;
+    }
+
+    pub fn back(&mut self) {
+        if self.can_go_back() {
+            self.index -= 1;
+        }
+    }
+
+    pub fn forward(&mut self) {
+        if self.can_go_forward() {
+            self.index += 1;
+        }
+    }
+}
+
+/// A handle to the history of an application's routes. Create one with
+/// `router!`, and hand it to the components which navigate, either as
+/// an argument or with `provide`.
+#[derive(Clone)]
+pub struct Router<R> {
+    history: Key<History<R>>,
+}
+
+impl<R> fmt::Debug for Router<R>
+where
+    R: Route,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("history", &*self.history)
+            .finish()
+    }
+}
+
+impl<R> PartialEq for Router<R>
+where
+    R: Route,
+{
+    fn eq(&self, other: &Self) -> bool {
+        *self.history == *other.history
+    }
+}
+
+impl<R> Router<R>
+where
+    R: Route,
+{
+    /// Used by `router!`.
+    #[doc(hidden)]
+    pub fn new(history: Key<History<R>>) -> Router<R> {
+        Router { history }
+    }
+
+    /// The history as of the current revision.
+    pub fn history(&self) -> &History<R> {
+        &self.history
+    }
+
+    pub fn current(&self) -> &R {
+        self.history.current()
+    }
+
+    pub fn push(&self, route: R) {
+        self.navigate(|history| history.push(route));
+    }
+
+    pub fn replace(&self, route: R) {
+        self.navigate(|history| history.replace(route));
+    }
+
+    pub fn back(&self) {
+        self.navigate(History::back);
+    }
+
+    pub fn forward(&self) {
+        self.navigate(History::forward);
+    }
+
+    fn navigate(&self, f: impl FnOnce(&mut History<R>)) {
+        self.history.update(|history| {
+            let mut next = history.clone();
+            f(&mut next);
+            if next == *history {
+                None
+            } else {
+                Some(next)
+            }
+        });
+    }
+
+    /// Creates a click handler which visits `route`.
+    pub fn link(&self, route: R) -> impl FnMut(&ClickEvent) + 'static {
+        let router = self.clone();
+        move |_: &ClickEvent| router.push(route.clone())
+    }
+
+    /// Creates a click handler which goes back to the previous route.
+    pub fn back_link(&self) -> impl FnMut(&ClickEvent) + 'static {
+        let router = self.clone();
+        move |_: &ClickEvent| router.back()
+    }
+}
+
+/// Declares a router, starting at the route given on the command line,
+/// or the one returned by `fallback` if there isn't one.
+#[macro_export]
+macro_rules! router {
+    ($fallback:expr) => {
+        $crate::router::Router::new($crate::prelude::state!(|| {
+            $crate::router::History::from_args(::std::env::args(), ($fallback)())
+        }))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::{History, Route};
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Page {
+        Home,
+        Item(u32),
+    }
+
+    impl Route for Page {
+        fn parse(path: &str) -> Option<Page> {
+            if path == "home" {
+                Some(Page::Home)
+            } else if path.starts_with("item/") {
+                path[5..].parse().ok().map(Page::Item)
+            } else {
+                None
+            }
+        }
+
+        fn path(&self) -> String {
+            match self {
+                Page::Home => "home".to_owned(),
+                Page::Item(id) => format!("item/{}", id),
+            }
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn back_and_forward() {
+        let mut history = History::new(Page::Home);
+        history.push(Page::Item(1));
+        history.push(Page::Item(2));
+        history.back();
+        assert_eq!(*history.current(), Page::Item(1));
+        history.forward();
+        assert_eq!(*history.current(), Page::Item(2));
+        assert!(!history.can_go_forward());
+        history.back();
+        history.back();
+        history.back();
+        assert_eq!(*history.current(), Page::Home);
+        assert!(!history.can_go_back());
+    }
+
+    #[test]
+    fn push_discards_forward() {
+        let mut history = History::new(Page::Home);
+        history.push(Page::Item(1));
+        history.back();
+        history.push(Page::Item(2));
+        assert!(!history.can_go_forward());
+        history.back();
+        assert_eq!(*history.current(), Page::Home);
+        history.push(Page::Home);
+        assert_eq!(history, {
+            let mut expected = History::new(Page::Home);
+            expected.push(Page::Item(2));
+            expected.back();
+            expected
+        });
+    }
+
+    #[test]
+    fn deep_link() {
+        let history = History::from_args(args(&["app", "--verbose", "item/7"]), Page::Home);
+        assert_eq!(*history.current(), Page::Item(7));
+        let history = History::from_args(args(&["app", "item/x"]), Page::Home);
+        assert_eq!(*history.current(), Page::Home);
+        // The program's own name is never a route.
+        let history = History::from_args(args(&["home"]), Page::Item(1));
+        assert_eq!(*history.current(), Page::Item(1));
+        assert_eq!(Page::parse(&Page::Item(3).path()), Some(Page::Item(3)));
+    }
+}
@@ -0,0 +1,188 @@
+use super::Runtime;
+use crate::dom::{Node, Window};
+#[cfg(feature = "print")]
+use crate::print::{page_breaks, PageImage, PrintOptions};
+use crate::render::{Context, RenderTarget, ShareContext, SharedResources};
+use crate::Color;
+use std::time::Duration;
+use webrender::api::units::DevicePixel;
+use webrender::euclid::{size2, Size2D};
+use winit::dpi::PhysicalSize;
+use winit::event_loop::EventLoop;
+
+/// Much longer than when opening a window, since an image rendered
+/// with fallback fonts is no use for comparisons.
+const FONT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tests don't run on the main thread, so the event loop can't insist
+/// on it.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn new_event_loop() -> EventLoop<()> {
+    use winit::platform::unix::EventLoopExtUnix;
+    EventLoop::new_any_thread()
+}
+
+#[cfg(target_os = "windows")]
+fn new_event_loop() -> EventLoop<()> {
+    use winit::platform::windows::EventLoopExtWindows;
+    EventLoop::new_any_thread()
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+fn new_event_loop() -> EventLoop<()> {
+    EventLoop::new()
+}
+
+impl Runtime {
+    /// Sets up rendering without a window.
+    fn start_headless(&mut self) -> (EventLoop<()>, SharedResources) {
+        let event_loop = new_event_loop();
+        let share_context = ShareContext::new(&event_loop)
+            .unwrap_or_else(|err| panic!("Couldn't render offscreen: {}", err));
+        let shared = SharedResources::new(
+            share_context,
+            event_loop.create_proxy(),
+            self.hooks.clone(),
+            self.font_registry.clone(),
+        );
+        self.updates.set_proxy(event_loop.create_proxy());
+        (event_loop, shared)
+    }
+
+    /// Creates a context which renders a window offscreen, once its
+    /// fonts have loaded.
+    fn headless_context(
+        &self,
+        event_loop: &EventLoop<()>,
+        shared: &SharedResources,
+        dom_window: Node<Window>,
+        size: Size2D<i32, DevicePixel>,
+        dpi_scale: f32,
+        clear_color: Color,
+    ) -> Context {
+        let gl_context = shared
+            .context_builder()
+            .build_headless(
+                event_loop,
+                PhysicalSize::new(size.width as f64, size.height as f64),
+            )
+            .unwrap();
+        let mut context = Context::new(
+            RenderTarget::Headless(gl_context),
+            None,
+            size,
+            dpi_scale,
+            dom_window,
+            clear_color,
+            shared,
+        )
+        .unwrap_or_else(|err| panic!("Couldn't render offscreen: {}", err));
+        context.set_text_settings(self.options.text_settings);
+        context.wait_for_fonts(FONT_TIMEOUT);
+        context
+    }
+
+    /// Runs a single revision of the application, and renders each of
+    /// its windows offscreen, without opening them. The size is in
+    /// logical pixels. Returns the RGBA pixels of each window, with rows
+    /// from top to bottom.
+    #[cfg(feature = "testing")]
+    pub(crate) fn render_headless(
+        mut self,
+        width: f32,
+        height: f32,
+        dpi_scale: f32,
+    ) -> Vec<Vec<u8>> {
+        let (event_loop, shared) = self.start_headless();
+        let size = size2(
+            (width * dpi_scale).round() as i32,
+            (height * dpi_scale).round() as i32,
+        );
+        let app = self.moxie_runtime.run_once();
+        app.children()
+            .iter()
+            .map(|dom_window| {
+                self.headless_context(
+                    &event_loop,
+                    &shared,
+                    dom_window.clone(),
+                    size,
+                    dpi_scale,
+                    self.options.splash_color,
+                )
+                .capture()
+            })
+            .collect()
+    }
+
+    /// Runs a single revision of the application, and renders the
+    /// content of its first window onto as many pages as it takes.
+    #[cfg(feature = "print")]
+    pub(crate) fn render_pages(mut self, options: &PrintOptions) -> Vec<PageImage> {
+        let (event_loop, shared) = self.start_headless();
+        let dpi_scale = options.dpi / 96.0;
+        let (width, height) = options.content_size();
+        let size = size2(
+            (width * dpi_scale).round() as i32,
+            (height * dpi_scale).round() as i32,
+        );
+        let app = self.moxie_runtime.run_once();
+        let dom_window = app
+            .children()
+            .first()
+            .cloned()
+            .expect("the application has no windows");
+        // Pages are white, whatever the application's background.
+        let mut context = self.headless_context(
+            &event_loop,
+            &shared,
+            dom_window,
+            size,
+            dpi_scale,
+            Color::white(),
+        );
+
+        let (document_height, boxes) = context.layout_document(width);
+        let mut breaks = page_breaks(&boxes, height, document_height);
+        let starts = breaks.clone();
+        breaks.remove(0);
+        breaks.push(document_height);
+        starts
+            .into_iter()
+            .zip(breaks)
+            .map(|(start, end)| {
+                context.set_document_offset(start);
+                let rgba = context.capture();
+                // Whatever is below the break is printed on the next
+                // page instead.
+                let rows = ((end - start) * dpi_scale).round() as usize;
+                let mut pixels = rgba
+                    .chunks(4)
+                    .flat_map(|pixel| pixel[..3].iter().cloned())
+                    .collect::<Vec<u8>>();
+                let row_bytes = size.width as usize * 3;
+                for byte in pixels.iter_mut().skip(rows * row_bytes) {
+                    *byte = 255;
+                }
+                PageImage {
+                    width: size.width as u32,
+                    height: size.height as u32,
+                    pixels,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(not(unix))]
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// How long a connection can go without sending anything before it's
+/// dropped, so that one which never finishes can't keep later launches
+/// from being received.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The most a launch can take up, well beyond any real command line.
+const MAX_LAUNCH_LEN: u64 = 1 << 20;
+
+/// The arguments of a later launch of the application, which was handed
+/// to the running instance instead of starting a new one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Launch {
+    /// Where the application was launched from, since the arguments
+    /// may be relative paths.
+    pub working_dir: PathBuf,
+    /// Includes the name of the program.
+    pub args: Vec<String>,
+}
+
+/// Whether this launch became the running instance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Claim {
+    Primary,
+    /// The launch was handed to the running instance, and this process
+    /// should exit.
+    Forwarded,
+}
+
+impl Launch {
+    fn current() -> Launch {
+        Launch {
+            working_dir: env::current_dir().unwrap_or_default(),
+            args: env::args().collect(),
+        }
+    }
+
+    /// Separates the fields with NUL, which can't appear in arguments.
+    fn encode(&self) -> Vec<u8> {
+        let mut fields = vec![self.working_dir.to_string_lossy().into_owned()];
+        fields.extend(self.args.iter().cloned());
+        fields.join("\0").into_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Launch> {
+        let text = String::from_utf8(bytes.to_vec()).ok()?;
+        let mut fields = text.split('\0');
+        let working_dir = PathBuf::from(fields.next()?);
+        Some(Launch {
+            working_dir,
+            args: fields.map(str::to_owned).collect(),
+        })
+    }
+}
+
+/// A directory only the current user can use, for the socket and the
+/// token. `XDG_RUNTIME_DIR` is already private to the user, but the
+/// temporary directory may be shared, so another user could have made
+/// the directory first, in which case it's refused.
+#[cfg(unix)]
+fn instance_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    let uid = unsafe { libc::getuid() };
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("moxie-native"),
+        None => env::temp_dir().join(format!("moxie-native-{}", uid)),
+    };
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+        result => result?,
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} isn't private to the current user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// On Windows, local application data is in the user's profile, which
+/// other users can't read.
+#[cfg(not(unix))]
+fn instance_dir() -> io::Result<PathBuf> {
+    let dir = env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("moxie-native");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// A secret only processes which can read the instance directory know,
+/// which launches have to send along to be accepted. `RandomState` is
+/// seeded from the OS's random source, so hashing with it gives
+/// numbers which can't be guessed.
+fn new_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    (0..2)
+        .map(|half| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(nanos);
+            hasher.write_u32(half);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// Writes the token where only the current user can read it.
+fn write_token(path: &Path, token: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+/// The token, then the launch, separated by NUL.
+fn message(token: &str, launch: &Launch) -> Vec<u8> {
+    let mut message = token.as_bytes().to_vec();
+    message.push(0);
+    message.extend(launch.encode());
+    message
+}
+
+/// Returns the launch in a message, if it has the right token.
+fn accept(message: &[u8], token: &str) -> Option<Launch> {
+    let split = message.iter().position(|&byte| byte == 0)?;
+    let (sent, launch) = (&message[..split], &message[split + 1..]);
+    // Compared without stopping at the first difference, so that how
+    // long it takes doesn't give the token away.
+    let matches = sent.len() == token.len()
+        && sent
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if matches {
+        Launch::decode(launch)
+    } else {
+        None
+    }
+}
+
+/// Reads a launch from a connection, ignoring any which fail or don't
+/// have the token.
+fn receive(stream: impl Read, token: &str, on_launch: &impl Fn(Launch)) {
+    let mut bytes = vec![];
+    if stream.take(MAX_LAUNCH_LEN).read_to_end(&mut bytes).is_ok() {
+        if let Some(launch) = accept(&bytes, token) {
+            on_launch(launch);
+        }
+    }
+}
+
+/// Sends this launch to the instance listening on `socket`, if there is
+/// one. Returns whether there was.
+#[cfg(unix)]
+fn forward(socket: &Path, token_path: &Path) -> io::Result<bool> {
+    if let Ok(token) = fs::read_to_string(token_path) {
+        if let Ok(mut stream) = UnixStream::connect(socket) {
+            stream.write_all(&message(&token, &Launch::current()))?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Waits until no other launch holds the lock on `path`, then holds it
+/// until the returned file is dropped. The lock is released when the
+/// process exits, so a crash can't leave it held.
+#[cfg(unix)]
+fn lock(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Hands this launch to the running instance of the application called
+/// `name`, if there is one. Otherwise this becomes the running instance,
+/// and `on_launch` is called from a background thread with each later
+/// launch.
+#[cfg(unix)]
+pub fn claim(name: &str, on_launch: impl Fn(Launch) + Send + 'static) -> io::Result<Claim> {
+    let dir = instance_dir()?;
+    let socket = dir.join(format!("{}.sock", name));
+    let token_path = dir.join(format!("{}.token", name));
+    if forward(&socket, &token_path)? {
+        return Ok(Claim::Forwarded);
+    }
+    // Launches which got here at the same time take turns, so that one
+    // of them listens and the rest find it when they try again.
+    let _lock = lock(&dir.join(format!("{}.lock", name)))?;
+    if forward(&socket, &token_path)? {
+        return Ok(Claim::Forwarded);
+    }
+    // Nothing is listening, so the socket was left behind by an
+    // instance which didn't exit cleanly.
+    let _ = fs::remove_file(&socket);
+    let token = new_token();
+    write_token(&token_path, &token)?;
+    let listener = UnixListener::bind(&socket)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if stream.set_read_timeout(Some(READ_TIMEOUT)).is_ok() {
+                receive(stream, &token, &on_launch);
+            }
+        }
+    });
+    Ok(Claim::Primary)
+}
+
+/// Like the Unix version, but listens on a loopback port, which is
+/// written to a file for later launches to find. Any local process can
+/// connect to the port, so the token is what keeps out other users.
+#[cfg(not(unix))]
+pub fn claim(name: &str, on_launch: impl Fn(Launch) + Send + 'static) -> io::Result<Claim> {
+    let dir = instance_dir()?;
+    let port_path = dir.join(format!("{}.port", name));
+    let token_path = dir.join(format!("{}.token", name));
+    let port = fs::read_to_string(&port_path)
+        .ok()
+        .and_then(|port| port.trim().parse::<u16>().ok());
+    if let (Some(port), Ok(token)) = (port, fs::read_to_string(&token_path)) {
+        if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream.write_all(&message(&token, &Launch::current()))?;
+            return Ok(Claim::Forwarded);
+        }
+    }
+    let token = new_token();
+    write_token(&token_path, &token)?;
+    let listener = TcpListener::bind(("127.0.0.1", 0))?;
+    fs::write(&port_path, listener.local_addr()?.port().to_string())?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if stream.set_read_timeout(Some(READ_TIMEOUT)).is_ok() {
+                receive(stream, &token, &on_launch);
+            }
+        }
+    });
+    Ok(Claim::Primary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{accept, message, new_token, Launch};
+    #[cfg(unix)]
+    use super::{claim, instance_dir, Claim};
+    use std::path::PathBuf;
+    #[cfg(unix)]
+    use std::{fs, thread};
+
+    #[test]
+    fn encoding() {
+        let launch = Launch {
+            working_dir: PathBuf::from("/home/user/documents"),
+            args: vec!["editor".to_owned(), "notes.txt".to_owned(), "".to_owned()],
+        };
+        assert_eq!(Launch::decode(&launch.encode()), Some(launch));
+        assert_eq!(Launch::decode(&[0xff]), None);
+    }
+
+    #[test]
+    fn requires_token() {
+        let launch = Launch {
+            working_dir: PathBuf::from("/"),
+            args: vec!["editor".to_owned()],
+        };
+        let token = new_token();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, new_token());
+        assert_eq!(
+            accept(&message(&token, &launch), &token),
+            Some(launch.clone())
+        );
+        assert_eq!(accept(&message(&new_token(), &launch), &token), None);
+        assert_eq!(accept(&launch.encode(), &token), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn one_of_many_launches_is_primary() {
+        let name = format!("instance-test-{}", std::process::id());
+        let launches = (0..4)
+            .map(|_| {
+                let name = name.clone();
+                thread::spawn(move || claim(&name, |_| ()).unwrap())
+            })
+            .collect::<Vec<_>>();
+        let primaries = launches
+            .into_iter()
+            .map(|launch| launch.join().unwrap())
+            .filter(|&claimed| claimed == Claim::Primary)
+            .count();
+        assert_eq!(primaries, 1);
+
+        let dir = instance_dir().unwrap();
+        for extension in &["sock", "token", "lock"] {
+            let _ = fs::remove_file(dir.join(format!("{}.{}", name, extension)));
+        }
+    }
+}
@@ -1,41 +1,289 @@
 use crate::dom::devtools::DevToolsRegistry;
-use crate::dom::{App, Node};
+use crate::dom::input::PressTiming;
+use crate::dom::observer::MutationObservers;
+use crate::dom::{App, MutationObserver, Node};
+use crate::idle::IdleQueue;
+use crate::layout::FontRegistry;
+use crate::open::OpenQueue;
+use crate::persist::PersistStore;
+use crate::render::{
+    DebugOverlay, FrameHooks, FrameInfo, FrameStage, RendererDebug, ScrollPhysics, ShareContext,
+    SharedResources,
+};
+use crate::style::TextSettings;
+use crate::system::SystemState;
+use crate::util::intern;
+use crate::{Color, ColorSpace};
 use moxie::embed::Runtime as MoxieRuntime;
+use replay::{Recorder, Replayer, WindowInput};
 use std::collections::HashMap;
+use std::env;
 use std::iter;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+use updates::UpdateRegistry;
 use winit::{
-    event::Event,
-    event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
     window::WindowId,
 };
 
+#[cfg(any(feature = "testing", feature = "print"))]
+mod headless;
+mod instance;
+mod placement;
+mod replay;
+mod updates;
 mod window;
 
+pub use updates::{batch, update_channel, UpdateSender};
+
+/// The name a window's zoom is saved under, by its position in the
+/// application's list of windows.
+fn zoom_key(index: usize) -> String {
+    format!("window.{}.zoom", index)
+}
+
+/// Makes sure the event loop turns again by `time`.
+fn wake_by(control_flow: &mut ControlFlow, time: Instant) {
+    *control_flow = match *control_flow {
+        ControlFlow::Poll => ControlFlow::Poll,
+        ControlFlow::WaitUntil(deadline) if deadline < time => ControlFlow::WaitUntil(deadline),
+        _ => ControlFlow::WaitUntil(time),
+    };
+}
+
 /// Contains the event loop and the root component of the application.
 pub struct Runtime {
     moxie_runtime: MoxieRuntime<Box<dyn FnMut() -> Node<App> + 'static>>,
     windows: HashMap<WindowId, window::Window>,
     window_ids: Vec<WindowId>,
-    proxy: Option<EventLoopProxy<()>>,
+    persistence: Rc<PersistStore>,
+    idle: Rc<IdleQueue>,
+    open: Rc<OpenQueue>,
+    system: Rc<SystemState>,
+    updates: Rc<UpdateRegistry>,
+    hooks: Rc<FrameHooks>,
+    mutations: Rc<MutationObservers>,
+    recorder: Option<Recorder>,
+    replay_path: Option<PathBuf>,
+    /// While input is being replayed, live input is ignored.
+    replayer: Option<Replayer>,
+    /// Set when an event handler has run, so that a revision is run
+    /// once every pending event has been handled.
+    revision_pending: bool,
+    options: window::WindowOptions,
+    font_registry: FontRegistry,
+    shared: Option<SharedResources>,
+    instance_name: Option<String>,
+    launches: Option<mpsc::Receiver<instance::Launch>>,
 }
 
 impl Runtime {
     /// Create a new runtime based on the application's root component.
     pub fn new(mut root: impl FnMut() -> Node<App> + 'static) -> Runtime {
+        let persistence = Rc::new(PersistStore::default());
+        let root_persistence = persistence.clone();
+        let idle = Rc::new(IdleQueue::default());
+        let root_idle = idle.clone();
+        let open = Rc::new(OpenQueue::default());
+        let root_open = open.clone();
+        let system = Rc::new(SystemState::default());
+        let root_system = system.clone();
+        let updates = Rc::new(UpdateRegistry::default());
+        let root_updates = updates.clone();
+        let mutations = Rc::new(MutationObservers::default());
+        let root_mutations = mutations.clone();
         Runtime {
             moxie_runtime: MoxieRuntime::new(Box::new(move || {
-                illicit::child_env!(DevToolsRegistry => DevToolsRegistry::new()).enter(|| {
+                illicit::child_env!(
+                    DevToolsRegistry => DevToolsRegistry::new(),
+                    Rc<PersistStore> => root_persistence.clone(),
+                    Rc<IdleQueue> => root_idle.clone(),
+                    Rc<OpenQueue> => root_open.clone(),
+                    Rc<SystemState> => root_system.clone(),
+                    Rc<UpdateRegistry> => root_updates.clone()
+                )
+                .enter(|| {
                     topo::call!({
                         let registry = illicit::Env::expect::<DevToolsRegistry>();
+                        root_persistence.begin_revision();
                         let app = root();
                         registry.update(app.clone().into());
+                        root_mutations.update(app.clone().into());
                         app
                     })
                 })
             })),
             windows: HashMap::new(),
             window_ids: vec![],
-            proxy: None,
+            persistence,
+            idle,
+            open,
+            system,
+            updates,
+            hooks: Rc::new(FrameHooks::default()),
+            mutations,
+            revision_pending: false,
+            recorder: None,
+            replay_path: None,
+            replayer: None,
+            options: window::WindowOptions::default(),
+            font_registry: FontRegistry::default(),
+            shared: None,
+            instance_name: None,
+            launches: None,
+        }
+    }
+
+    /// Enable saving of `persistent_state!` values to the given file.
+    /// The file is read when the runtime starts, and written when a
+    /// window is closed or the application exits. The zoom of each
+    /// window, which the user sets with Ctrl and `+`, `-` or `0`, is
+    /// saved along with it.
+    pub fn persist_to(self, path: impl Into<PathBuf>) -> Runtime {
+        self.persistence.set_path(path.into());
+        self
+    }
+
+    /// Set the default text size and text scale factor used by every
+    /// window.
+    pub fn text_settings(mut self, text_settings: TextSettings) -> Runtime {
+        self.options.text_settings = text_settings;
+        self
+    }
+
+    /// Make fonts shipped with the application available to
+    /// `font_family`, in addition to the ones installed on the system.
+    pub fn fonts(mut self, registry: FontRegistry) -> Runtime {
+        self.font_registry = registry;
+        self
+    }
+
+    /// Set the color windows are filled with before their first frame
+    /// has been drawn. This should usually match the background of the
+    /// application, to avoid a flash of a different color on startup.
+    pub fn splash_color(mut self, color: Color) -> Runtime {
+        self.options.splash_color = color;
+        self
+    }
+
+    /// Show layout boxes, text baselines and a grid over every window,
+    /// as set by `overlay`. Without this, the overlay can still be
+    /// toggled on a window with Ctrl+Shift+D.
+    pub fn debug_overlay(mut self, overlay: DebugOverlay) -> Runtime {
+        self.options.debug_overlay = Some(overlay);
+        self
+    }
+
+    /// Show Webrender's debugging views in every window, for finding
+    /// out what makes frames slow to draw on the GPU. This replaces the
+    /// views listed in the `MOXIE_RENDERER_DEBUG` environment variable.
+    pub fn renderer_debug(mut self, debug: RendererDebug) -> Runtime {
+        self.options.renderer_debug = debug;
+        self
+    }
+
+    /// Set how long buttons have to be held for long press events, how
+    /// often they send repeat events while held, and how quickly they
+    /// have to be clicked twice for double click events.
+    pub fn press_timing(mut self, timing: PressTiming) -> Runtime {
+        self.options.press_timing = timing;
+        self
+    }
+
+    /// Set how long the pointer has to rest over a button before it's
+    /// sent a `HoverIntentEvent`.
+    pub fn hover_intent_delay(mut self, delay: Duration) -> Runtime {
+        self.options.hover_intent_delay = delay;
+        self
+    }
+
+    /// Set whether scrolled content keeps moving once a trackpad
+    /// gesture ends, and what happens when it's scrolled past its edge.
+    pub fn scroll_physics(mut self, physics: ScrollPhysics) -> Runtime {
+        self.options.scroll_physics = physics;
+        self
+    }
+
+    /// Set the color space windows are shown in, which colors are
+    /// converted to before they're drawn. This defaults to sRGB, and
+    /// should be set to Display P3 only when the windows are shown on
+    /// displays which use it, like most recent Apple displays, since
+    /// the colors are otherwise oversaturated. Colors outside of the
+    /// chosen space are clipped to the nearest color inside of it.
+    pub fn color_space(mut self, color_space: ColorSpace) -> Runtime {
+        self.options.color_space = color_space;
+        self
+    }
+
+    /// Record the input given to every window to the given file, so that
+    /// it can be played back with `replay_input_from`. The file is
+    /// written when a window is closed or the application exits.
+    pub fn record_input_to(mut self, path: impl Into<PathBuf>) -> Runtime {
+        self.recorder = Some(Recorder::new(path.into()));
+        self
+    }
+
+    /// Play back input recorded with `record_input_to` once the runtime
+    /// starts. Input is replayed at the pace it was recorded, and events
+    /// that arrived in the same turn of the event loop are replayed
+    /// together, so the same revisions are produced.
+    pub fn replay_input_from(mut self, path: impl Into<PathBuf>) -> Runtime {
+        self.replay_path = Some(path.into());
+        self
+    }
+
+    /// Only let one copy of the application run at a time. Launching it
+    /// again hands the new launch's arguments to the running copy, as if
+    /// they were given to `on_open`, and brings its first window to the
+    /// front, instead of starting another. `name` should be unique to
+    /// the application.
+    pub fn single_instance(mut self, name: impl Into<String>) -> Runtime {
+        self.instance_name = Some(name.into());
+        self
+    }
+
+    /// Call `hook` at the start of every frame, before style and layout
+    /// are updated.
+    pub fn on_before_frame(self, hook: impl FnMut(&FrameInfo) + 'static) -> Runtime {
+        self.hooks.add(FrameStage::BeforeFrame, Box::new(hook));
+        self
+    }
+
+    /// Call `hook` once layout has been performed for a frame.
+    pub fn on_after_layout(self, hook: impl FnMut(&FrameInfo) + 'static) -> Runtime {
+        self.hooks.add(FrameStage::AfterLayout, Box::new(hook));
+        self
+    }
+
+    /// Call `hook` once a frame's display list has been built and handed
+    /// to the render thread.
+    pub fn on_after_render(self, hook: impl FnMut(&FrameInfo) + 'static) -> Runtime {
+        self.hooks.add(FrameStage::AfterRender, Box::new(hook));
+        self
+    }
+
+    /// Call `observer` with the changes each revision makes to the DOM.
+    pub fn observe_mutations(self, observer: MutationObserver) -> Runtime {
+        self.mutations.add(observer);
+        self
+    }
+
+    fn save_state(&self) {
+        for (index, id) in self.window_ids.iter().enumerate() {
+            self.persistence
+                .set(&zoom_key(index), &self.windows[id].zoom());
+        }
+        if let Err(err) = self.persistence.save() {
+            log::warn!("Failed to save application state: {}", err);
+        }
+        if let Some(ref recorder) = self.recorder {
+            if let Err(err) = recorder.save() {
+                log::warn!("Failed to save input recording: {}", err);
+            }
         }
     }
 
@@ -46,20 +294,184 @@ impl Runtime {
         target: &EventLoopWindowTarget<()>,
         control_flow: &mut ControlFlow,
     ) {
-        let mut did_process = false;
         match event {
             Event::WindowEvent { event, window_id } => {
-                let window = self.windows.get_mut(&window_id).unwrap();
-                let res = window.process(event);
-                did_process = res;
+                if let WindowEvent::CloseRequested = event {
+                    self.save_state();
+                }
+                // Events can still arrive for a window which was replaced
+                // after losing its GL context.
+                let window = match self.windows.get_mut(&window_id) {
+                    Some(window) => window,
+                    None => return,
+                };
+                self.revision_pending |= match window::input_from_event(&event) {
+                    // Resizes still need to be handled while replaying,
+                    // since that's how recorded resizes take effect.
+                    Some(WindowInput::Resized { .. }) | None if self.replayer.is_some() => {
+                        window.process(event)
+                    }
+                    Some(_) if self.replayer.is_some() => false,
+                    Some(input) => {
+                        if let Some(ref mut recorder) = self.recorder {
+                            let index = self.window_ids.iter().position(|id| *id == window_id);
+                            recorder.record(index.unwrap_or(0), input);
+                        }
+                        window.input(input)
+                    }
+                    None => window.process(event),
+                };
+            }
+            Event::UserEvent(()) => {
+                self.recover_lost_windows(target);
+                for window in self.windows.values_mut() {
+                    window.poll_fonts();
+                }
+                self.revision_pending |= self.updates.poll();
+                self.receive_launches();
+            }
+            Event::EventsCleared => {
+                self.replay_input();
+                let now = Instant::now();
+                for window in self.windows.values_mut() {
+                    self.revision_pending |= window.flush_input();
+                    self.revision_pending |= window.poll_timers(now);
+                }
+                self.revision_pending |= self.open.deliver();
+                self.revision_pending |= self.system.check_clock(Instant::now(), SystemTime::now());
+                // Handling input counts as work, so idle callbacks wait
+                // for the next turn of the loop.
+                let busy = self.revision_pending;
+                self.revision_pending |= self.run_idle_tasks(busy, control_flow);
+
+                // Everything that changed this turn is committed as one
+                // revision.
+                if self.revision_pending | updates::take_revision_request() {
+                    self.revision_pending = false;
+                    self.update_runtime(target);
+                }
+                if let Some(ref mut recorder) = self.recorder {
+                    recorder.end_turn();
+                }
+                if let Some(time) = self.replayer.as_ref().and_then(Replayer::next_time) {
+                    wake_by(control_flow, time);
+                }
+                for time in self.windows.values().filter_map(window::Window::next_timer) {
+                    wake_by(control_flow, time);
+                }
             }
+            Event::LoopDestroyed => self.save_state(),
             _ => *control_flow = ControlFlow::Wait,
         }
-        if did_process {
-            self.update_runtime(target);
+    }
+
+    /// Replaces the windows whose GL contexts were lost, such as after a
+    /// driver reset or a switch between GPUs, which would otherwise be
+    /// left frozen or black.
+    fn recover_lost_windows(&mut self, target: &EventLoopWindowTarget<()>) {
+        let windows = &self.windows;
+        let lost = self
+            .window_ids
+            .iter()
+            .filter(|id| windows[*id].is_lost())
+            .cloned()
+            .collect::<Vec<_>>();
+        if lost.is_empty() {
+            return;
+        }
+        let shared = self.shared.as_mut().unwrap();
+        shared.reset_share_context(target);
+        for id in lost {
+            let mut window = self.windows.remove(&id).unwrap();
+            window.recover(target, shared);
+            let new_id = window.window_id();
+            self.windows.insert(new_id, window);
+            for window_id in &mut self.window_ids {
+                if *window_id == id {
+                    *window_id = new_id;
+                }
+            }
+        }
+    }
+
+    /// Gives the windows the next turn of recorded input, once it's due.
+    fn replay_input(&mut self) {
+        if let Some(ref mut replayer) = self.replayer {
+            for recorded in replayer.next_turn(Instant::now()) {
+                let window = self.window_ids.get(recorded.window);
+                if let Some(window) = window.and_then(|id| self.windows.get_mut(id)) {
+                    self.revision_pending |= window.replay(recorded.input);
+                }
+            }
+            if replayer.next_time().is_none() {
+                self.replayer = None;
+            }
+        }
+    }
+
+    /// Queues the arguments of any later launches of the application,
+    /// and brings the first window to the front for each.
+    fn receive_launches(&mut self) {
+        let launches = match self.launches {
+            Some(ref launches) => launches.try_iter().collect::<Vec<_>>(),
+            None => return,
+        };
+        for launch in launches {
+            self.open.push_args(launch.args, &launch.working_dir);
+            let first = self.window_ids.first();
+            if let Some(window) = first.and_then(|id| self.windows.get(id)) {
+                window.activate();
+            }
+        }
+    }
+
+    /// Hands this launch to the running copy of the application if
+    /// there is one, returning false if so.
+    fn claim_instance(&mut self, event_loop: &EventLoop<()>) -> bool {
+        let name = match self.instance_name {
+            Some(ref name) => name,
+            None => return true,
+        };
+        let (tx, rx) = mpsc::channel();
+        let proxy = event_loop.create_proxy();
+        let claim = instance::claim(name, move |launch| {
+            if tx.send(launch).is_ok() {
+                let _ = proxy.send_event(());
+            }
+        });
+        match claim {
+            Ok(instance::Claim::Primary) => {
+                self.launches = Some(rx);
+                true
+            }
+            Ok(instance::Claim::Forwarded) => false,
+            // Running as a second copy is better than not running.
+            Err(err) => {
+                log::warn!("Failed to claim single instance: {}", err);
+                true
+            }
         }
     }
 
+    /// Runs work scheduled with `run_when_idle`, once every event has
+    /// been handled. Returns true if anything ran, since the callbacks
+    /// may have changed state.
+    fn run_idle_tasks(&mut self, busy: bool, control_flow: &mut ControlFlow) -> bool {
+        let idle = !busy && !self.windows.values().any(window::Window::is_animating);
+        let queue = self.idle.clone();
+        let ran = illicit::child_env!(Rc<IdleQueue> => queue.clone())
+            .enter(|| queue.run(idle, Instant::now()));
+
+        *control_flow = match self.idle.next_deadline() {
+            None => ControlFlow::Wait,
+            // Keep the loop turning, so the next callback runs as soon
+            // as any events that arrived in the meantime are handled.
+            Some(_) if idle => ControlFlow::Poll,
+            Some(deadline) => ControlFlow::WaitUntil(deadline),
+        };
+        ran
+    }
+
     /// Updates the moxie runtime and reconciles the DOM changes,
     /// re-rendering if things have changed.
     fn update_runtime(&mut self, event_loop: &EventLoopWindowTarget<()>) {
@@ -83,10 +495,15 @@ impl Runtime {
                     self.window_ids.push(window_id);
                 }
                 (Some(dom_window), None) => {
+                    let parent = self.window_ids.last().map(|id| &self.windows[id]);
+                    let zoom = self.persistence.get(&zoom_key(self.window_ids.len()));
                     let window = window::Window::new(
                         dom_window.clone(),
+                        parent,
+                        zoom.unwrap_or(1.0),
                         event_loop,
-                        self.proxy.as_ref().unwrap().clone(),
+                        self.options,
+                        self.shared.as_mut().unwrap(),
                     );
                     let id = window.window_id();
                     self.windows.insert(id, window);
@@ -98,13 +515,45 @@ impl Runtime {
                 (None, None) => break,
             }
         }
+
+        // Release the text of nodes that were removed in this revision.
+        intern::collect_garbage();
     }
 
     /// Start up the application.
     pub fn start(mut self) {
         let event_loop = EventLoop::new();
+        // Comes before anything that starts a thread.
+        let share_context = match ShareContext::new(&event_loop) {
+            Ok(share_context) => share_context,
+            Err(err) => {
+                log::error!("Failed to start rendering: {}", err);
+                return;
+            }
+        };
+        if !self.claim_instance(&event_loop) {
+            return;
+        }
+
+        self.shared = Some(SharedResources::new(
+            share_context,
+            event_loop.create_proxy(),
+            self.hooks.clone(),
+            self.font_registry.clone(),
+        ));
+        self.updates.set_proxy(event_loop.create_proxy());
 
-        self.proxy = Some(event_loop.create_proxy());
+        if let Err(err) = self.persistence.load() {
+            log::warn!("Failed to load application state: {}", err);
+        }
+        self.open
+            .push_args(env::args(), &env::current_dir().unwrap_or_default());
+        if let Some(ref path) = self.replay_path {
+            match Replayer::load(path) {
+                Ok(replayer) => self.replayer = Some(replayer),
+                Err(err) => log::warn!("Failed to load input recording: {}", err),
+            }
+        }
 
         self.update_runtime(&event_loop);
 
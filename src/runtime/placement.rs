@@ -0,0 +1,123 @@
+use crate::dom::Placement;
+
+/// How far each window is offset from the previous one when cascading,
+/// in logical pixels.
+const CASCADE_OFFSET: f64 = 32.0;
+
+/// A rectangle on the desktop, in the OS's logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Bounds {
+    /// Where a rectangle of the given size is centered on this one.
+    fn center(&self, width: f64, height: f64) -> (f64, f64) {
+        (
+            self.x + (self.width - width) / 2.0,
+            self.y + (self.height - height) / 2.0,
+        )
+    }
+
+    /// Moves a rectangle of the given size at `(x, y)` inside this one,
+    /// keeping its top left corner visible if it's too large to fit.
+    fn contain(&self, (x, y): (f64, f64), width: f64, height: f64) -> (f64, f64) {
+        (
+            x.min(self.x + self.width - width).max(self.x),
+            y.min(self.y + self.height - height).max(self.y),
+        )
+    }
+}
+
+/// Decides where a new window of the given outer size goes. `monitor`
+/// is the monitor to place it on, and `parent` is the window it was
+/// opened after, if any. Returns `None` to leave it where the OS put it.
+pub fn place(
+    placement: Placement,
+    width: f64,
+    height: f64,
+    monitor: Bounds,
+    parent: Option<Bounds>,
+) -> Option<(f64, f64)> {
+    let position = match (placement, parent) {
+        (Placement::System, _) => return None,
+        (Placement::Center, _) | (_, None) => monitor.center(width, height),
+        (Placement::Cascade, Some(parent)) => {
+            (parent.x + CASCADE_OFFSET, parent.y + CASCADE_OFFSET)
+        }
+        (Placement::CenterOnParent, Some(parent)) => parent.center(width, height),
+    };
+    Some(monitor.contain(position, width, height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{place, Bounds};
+    use crate::dom::Placement;
+
+    const MONITOR: Bounds = Bounds {
+        x: 0.0,
+        y: 0.0,
+        width: 1000.0,
+        height: 800.0,
+    };
+
+    #[test]
+    fn center() {
+        assert_eq!(
+            place(Placement::Center, 200.0, 100.0, MONITOR, None),
+            Some((400.0, 350.0))
+        );
+        assert_eq!(place(Placement::System, 200.0, 100.0, MONITOR, None), None);
+    }
+
+    #[test]
+    fn relative_to_parent() {
+        let parent = Bounds {
+            x: 100.0,
+            y: 100.0,
+            width: 400.0,
+            height: 300.0,
+        };
+        assert_eq!(
+            place(Placement::Cascade, 400.0, 300.0, MONITOR, Some(parent)),
+            Some((132.0, 132.0))
+        );
+        assert_eq!(
+            place(
+                Placement::CenterOnParent,
+                200.0,
+                100.0,
+                MONITOR,
+                Some(parent)
+            ),
+            Some((200.0, 200.0))
+        );
+        // Without a parent, windows are centered on the monitor.
+        assert_eq!(
+            place(Placement::Cascade, 200.0, 100.0, MONITOR, None),
+            Some((400.0, 350.0))
+        );
+    }
+
+    #[test]
+    fn stays_on_monitor() {
+        let parent = Bounds {
+            x: 900.0,
+            y: 700.0,
+            width: 100.0,
+            height: 100.0,
+        };
+        assert_eq!(
+            place(Placement::Cascade, 300.0, 200.0, MONITOR, Some(parent)),
+            Some((700.0, 600.0))
+        );
+        assert_eq!(
+            place(Placement::Center, 2000.0, 100.0, MONITOR, None),
+            Some((0.0, 350.0))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The input handled by a window, in a form that can be recorded and
+/// replayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowInput {
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    MouseLeft {
+        pressed: bool,
+    },
+    /// In logical pixels, in the direction the device reported.
+    Scroll {
+        x: f32,
+        y: f32,
+    },
+    /// The end of a scroll gesture, from devices which report it.
+    ScrollEnded,
+    /// Scales the zoom container under the pointer by `factor`.
+    Zoom {
+        factor: f32,
+    },
+    Resized {
+        width: f64,
+        height: f64,
+    },
+}
+
+/// An input event, and when it happened.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedInput {
+    /// Which turn of the event loop the input arrived in. Input from one
+    /// turn is replayed together, so that it's coalesced and committed
+    /// in the same revisions as when it was recorded.
+    pub turn: u64,
+    /// Milliseconds since recording started.
+    pub time: u64,
+    /// The index of the window, in the order the app returns them.
+    pub window: usize,
+    pub input: WindowInput,
+}
+
+/// Serializes the inputs as one line each, in the form
+/// `turn time window kind arguments...`.
+pub fn encode(inputs: &[RecordedInput]) -> String {
+    let mut output = String::new();
+    for recorded in inputs {
+        let input = match recorded.input {
+            WindowInput::CursorMoved { x, y } => format!("move {} {}", x, y),
+            WindowInput::MouseLeft { pressed: true } => "press".to_owned(),
+            WindowInput::MouseLeft { pressed: false } => "release".to_owned(),
+            WindowInput::Scroll { x, y } => format!("scroll {} {}", x, y),
+            WindowInput::ScrollEnded => "scroll-end".to_owned(),
+            WindowInput::Zoom { factor } => format!("zoom {}", factor),
+            WindowInput::Resized { width, height } => format!("resize {} {}", width, height),
+        };
+        output.push_str(&format!(
+            "{} {} {} {}\n",
+            recorded.turn, recorded.time, recorded.window, input
+        ));
+    }
+    output
+}
+
+fn decode_line(line: &str) -> Option<RecordedInput> {
+    let mut parts = line.split_whitespace();
+    let turn = parts.next()?.parse().ok()?;
+    let time = parts.next()?.parse().ok()?;
+    let window = parts.next()?.parse().ok()?;
+    let kind = parts.next()?;
+    let mut arg = || parts.next().and_then(|part| part.parse::<f64>().ok());
+    let input = match kind {
+        "move" => WindowInput::CursorMoved {
+            x: arg()?,
+            y: arg()?,
+        },
+        "press" => WindowInput::MouseLeft { pressed: true },
+        "release" => WindowInput::MouseLeft { pressed: false },
+        "scroll" => WindowInput::Scroll {
+            x: arg()? as f32,
+            y: arg()? as f32,
+        },
+        "scroll-end" => WindowInput::ScrollEnded,
+        "zoom" => WindowInput::Zoom {
+            factor: arg()? as f32,
+        },
+        "resize" => WindowInput::Resized {
+            width: arg()?,
+            height: arg()?,
+        },
+        _ => return None,
+    };
+    Some(RecordedInput {
+        turn,
+        time,
+        window,
+        input,
+    })
+}
+
+/// Parses the output of `encode`, skipping malformed lines.
+pub fn decode(input: &str) -> Vec<RecordedInput> {
+    input.lines().filter_map(decode_line).collect()
+}
+
+/// Captures the input given to every window.
+pub struct Recorder {
+    path: PathBuf,
+    started: Instant,
+    turn: u64,
+    turn_used: bool,
+    inputs: Vec<RecordedInput>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Recorder {
+        Recorder {
+            path,
+            started: Instant::now(),
+            turn: 0,
+            turn_used: false,
+            inputs: vec![],
+        }
+    }
+
+    pub fn record(&mut self, window: usize, input: WindowInput) {
+        self.inputs.push(RecordedInput {
+            turn: self.turn,
+            time: self.started.elapsed().as_millis() as u64,
+            window,
+            input,
+        });
+        self.turn_used = true;
+    }
+
+    /// Called once every pending event has been handled.
+    pub fn end_turn(&mut self) {
+        if self.turn_used {
+            self.turn += 1;
+            self.turn_used = false;
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::write(&self.path, encode(&self.inputs))
+    }
+}
+
+/// Feeds recorded input back to the windows, one turn at a time, at the
+/// same pace it was recorded.
+pub struct Replayer {
+    inputs: VecDeque<RecordedInput>,
+    started: Instant,
+}
+
+impl Replayer {
+    pub fn new(inputs: Vec<RecordedInput>, started: Instant) -> Replayer {
+        Replayer {
+            inputs: inputs.into(),
+            started,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replayer> {
+        let contents = fs::read_to_string(path)?;
+        Ok(Replayer::new(decode(&contents), Instant::now()))
+    }
+
+    /// When the next turn is due.
+    pub fn next_time(&self) -> Option<Instant> {
+        self.inputs
+            .front()
+            .map(|input| self.started + Duration::from_millis(input.time))
+    }
+
+    /// Takes the input for the next turn, if it's due.
+    pub fn next_turn(&mut self, now: Instant) -> Vec<RecordedInput> {
+        let mut inputs = vec![];
+        match self.next_time() {
+            Some(time) if time <= now => (),
+            _ => return inputs,
+        }
+        let turn = self.inputs[0].turn;
+        while self.inputs.front().map(|input| input.turn) == Some(turn) {
+            inputs.extend(self.inputs.pop_front());
+        }
+        inputs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, RecordedInput, Replayer, WindowInput};
+    use std::time::{Duration, Instant};
+
+    fn recorded(turn: u64, time: u64, input: WindowInput) -> RecordedInput {
+        RecordedInput {
+            turn,
+            time,
+            window: 0,
+            input,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let inputs = vec![
+            recorded(0, 0, WindowInput::CursorMoved { x: 10.5, y: 20.0 }),
+            recorded(0, 1, WindowInput::MouseLeft { pressed: true }),
+            recorded(1, 16, WindowInput::MouseLeft { pressed: false }),
+            recorded(2, 40, WindowInput::Scroll { x: 0.0, y: -40.0 }),
+            recorded(2, 42, WindowInput::ScrollEnded),
+            recorded(2, 45, WindowInput::Zoom { factor: 1.5 }),
+            recorded(
+                3,
+                50,
+                WindowInput::Resized {
+                    width: 800.0,
+                    height: 600.0,
+                },
+            ),
+        ];
+        assert_eq!(decode(&encode(&inputs)), inputs);
+    }
+
+    #[test]
+    fn skips_malformed() {
+        let inputs = decode("0 0 0 move 1\n0 0 0 press\nnonsense\n");
+        assert_eq!(
+            inputs,
+            vec![recorded(0, 0, WindowInput::MouseLeft { pressed: true })]
+        );
+    }
+
+    #[test]
+    fn replays_by_turn() {
+        let started = Instant::now();
+        let mut replayer = Replayer::new(
+            vec![
+                recorded(0, 0, WindowInput::MouseLeft { pressed: true }),
+                recorded(0, 5, WindowInput::MouseLeft { pressed: false }),
+                recorded(1, 1000, WindowInput::MouseLeft { pressed: true }),
+            ],
+            started,
+        );
+        assert_eq!(replayer.next_turn(started).len(), 2);
+        assert!(replayer.next_turn(started).is_empty());
+        assert_eq!(
+            replayer.next_time(),
+            Some(started + Duration::from_millis(1000))
+        );
+        let later = started + Duration::from_secs(1);
+        assert_eq!(replayer.next_turn(later).len(), 1);
+        assert_eq!(replayer.next_time(), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::mpsc;
+use winit::event_loop::EventLoopProxy;
+
+thread_local! {
+    static REVISION_REQUESTED: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f`, and commits the state changes it makes as a single
+/// revision, with one layout and render.
+///
+/// Revisions only run once every pending event and update has been
+/// handled, so changes made by several handlers in the same turn of the
+/// event loop are already committed together. `batch` is needed for
+/// changes made anywhere else, which would otherwise not cause a
+/// revision at all.
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    let result = f();
+    REVISION_REQUESTED.with(|requested| requested.set(true));
+    result
+}
+
+/// Returns whether `batch` has been called since the last revision.
+pub(crate) fn take_revision_request() -> bool {
+    REVISION_REQUESTED.with(|requested| requested.replace(false))
+}
+
+/// What polling a channel found: whether any values were received,
+/// and whether it can still receive more.
+struct Polled {
+    received: bool,
+    open: bool,
+}
+
+/// The channels created with `update_channel`, which are polled
+/// whenever the event loop is woken up. A channel is removed once every
+/// `UpdateSender` for it has been dropped and its values are handled.
+#[derive(Default)]
+pub(crate) struct UpdateRegistry {
+    proxy: RefCell<Option<EventLoopProxy<()>>>,
+    channels: RefCell<Vec<Box<dyn FnMut() -> Polled>>>,
+}
+
+impl fmt::Debug for UpdateRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UpdateRegistry")
+            .field("channels", &self.channels.borrow().len())
+            .finish()
+    }
+}
+
+impl UpdateRegistry {
+    pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        self.proxy.replace(Some(proxy));
+    }
+
+    /// Hands every value that has been received to its handler.
+    /// Returns true if there were any.
+    pub(crate) fn poll(&self) -> bool {
+        // Handlers may create more channels, so the list isn't borrowed
+        // while they run.
+        let mut channels = vec![];
+        let mut received = false;
+        for mut channel in self.channels.replace(vec![]) {
+            let polled = channel();
+            received |= polled.received;
+            if polled.open {
+                channels.push(channel);
+            }
+        }
+        let mut current = self.channels.borrow_mut();
+        channels.append(&mut current);
+        *current = channels;
+        received
+    }
+
+    /// Calls `handler` with each value `rx` receives whenever the
+    /// registry is polled.
+    fn register<T: 'static>(&self, rx: mpsc::Receiver<T>, mut handler: impl FnMut(T) + 'static) {
+        self.channels.borrow_mut().push(Box::new(move || {
+            let mut received = false;
+            loop {
+                match rx.try_recv() {
+                    Ok(value) => {
+                        handler(value);
+                        received = true;
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {
+                        return Polled {
+                            received,
+                            open: true,
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        return Polled {
+                            received,
+                            open: false,
+                        }
+                    }
+                }
+            }
+        }));
+    }
+}
+
+/// Sends values to the UI thread from any other thread. Created with
+/// `update_channel`.
+pub struct UpdateSender<T> {
+    tx: mpsc::Sender<T>,
+    proxy: EventLoopProxy<()>,
+}
+
+impl<T> Clone for UpdateSender<T> {
+    fn clone(&self) -> UpdateSender<T> {
+        UpdateSender {
+            tx: self.tx.clone(),
+            proxy: self.proxy.clone(),
+        }
+    }
+}
+
+impl<T> UpdateSender<T>
+where
+    T: Send,
+{
+    /// Sends a value and wakes up the event loop. The value is given
+    /// back if the application has exited.
+    pub fn send(&self, value: T) -> Result<(), T> {
+        self.tx.send(value).map_err(|err| err.0)?;
+        let _ = self.proxy.send_event(());
+        Ok(())
+    }
+}
+
+/// Creates a channel for sending updates to the UI from other threads,
+/// such as the results of network requests. `handler` is called on the
+/// UI thread with each value, and every value received in one turn of
+/// the event loop is committed as a single revision.
+///
+/// This should usually be called from inside `once!`, since components
+/// run again every revision.
+pub fn update_channel<T>(handler: impl FnMut(T) + 'static) -> UpdateSender<T>
+where
+    T: Send + 'static,
+{
+    let registry = illicit::Env::expect::<Rc<UpdateRegistry>>();
+    let (tx, rx) = mpsc::channel();
+    registry.register(rx, handler);
+    let proxy = registry.proxy.borrow().clone().unwrap();
+    UpdateSender { tx, proxy }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UpdateRegistry;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    #[test]
+    fn removes_channels_without_senders() {
+        let registry = UpdateRegistry::default();
+        let values = Rc::new(RefCell::new(vec![]));
+        let (tx, rx) = mpsc::channel();
+        let handled = values.clone();
+        registry.register(rx, move |value| handled.borrow_mut().push(value));
+
+        tx.send(1).unwrap();
+        assert!(registry.poll());
+        assert!(!registry.poll());
+        assert_eq!(registry.channels.borrow().len(), 1);
+
+        // Values sent before the last sender is dropped are still
+        // handled.
+        tx.send(2).unwrap();
+        drop(tx);
+        assert!(registry.poll());
+        assert_eq!(*values.borrow(), vec![1, 2]);
+        assert_eq!(registry.channels.borrow().len(), 0);
+    }
+}
@@ -1,117 +1,872 @@
-use crate::dom::input;
-use crate::dom::{Node, Window as DomWindow};
-use crate::render::Context;
-use gleam::gl;
-use glutin::{ContextBuilder, ContextWrapper, PossiblyCurrent};
+use super::placement::{self, Bounds};
+use super::replay::WindowInput;
+use crate::clipboard;
+use crate::dom::input::{self, PointerId, PressTimer, PressTiming};
+use crate::dom::{Node, Placement, Titlebar, Window as DomWindow, WindowSize};
+use crate::layout::{LogicalSideOffsets, LogicalSize as ContentSize, LogicalVector};
+use crate::render::{
+    Context, DebugOverlay, RenderTarget, RendererDebug, ScrollPhysics, SharedResources,
+};
+use crate::style::{SafeArea, TextSettings};
+use crate::{Color, ColorSpace};
+use glutin::{NotCurrent, RawContext};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use webrender::euclid::size2;
 use winit::{
-    dpi::LogicalPosition,
-    event::{ElementState, MouseButton, WindowEvent},
-    event_loop::{EventLoopProxy, EventLoopWindowTarget},
+    dpi::{LogicalPosition, LogicalSize},
+    event::{
+        ElementState, KeyboardInput, MouseButton, MouseScrollDelta, Touch, TouchPhase,
+        VirtualKeyCode, WindowEvent,
+    },
+    event_loop::EventLoopWindowTarget,
     window::{Window as WinitWindow, WindowBuilder, WindowId},
 };
 
+/// How far one line of mouse wheel movement scrolls, in logical pixels.
+const LINE_HEIGHT: f32 = 40.0;
+
+/// How far the mouse wheel has to move, in logical pixels, to double
+/// the scale of a zoom container while control is held.
+const WHEEL_ZOOM_DISTANCE: f32 = 200.0;
+
+/// The zoom factors the zoom shortcuts step through, like a browser's.
+const ZOOM_LEVELS: [f32; 13] = [
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+
+/// The height of the titlebar over a macOS window's content, in the
+/// OS's logical pixels. winit can't report it, but it's the same for
+/// every window with a standard titlebar.
+const OVERLAY_TITLEBAR_HEIGHT: f32 = 28.0;
+
+/// How long to wait for the default font before showing a new window.
+/// If finding it takes longer, the first frame is drawn without text.
+const FONT_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Settings from the `Runtime` which apply to every window.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowOptions {
+    pub text_settings: TextSettings,
+    /// The color the window is cleared to, which is what is visible
+    /// before the first frame has been drawn.
+    pub splash_color: Color,
+    /// Shown from the start when set, and otherwise what the debug
+    /// overlay shortcut shows.
+    pub debug_overlay: Option<DebugOverlay>,
+    pub renderer_debug: RendererDebug,
+    pub press_timing: PressTiming,
+    /// How long the pointer has to stop for before a `MouseRested`
+    /// event is sent.
+    pub hover_intent_delay: Duration,
+    pub scroll_physics: ScrollPhysics,
+    pub color_space: ColorSpace,
+}
+
+impl Default for WindowOptions {
+    fn default() -> WindowOptions {
+        WindowOptions {
+            text_settings: TextSettings::default(),
+            splash_color: Color::white(),
+            debug_overlay: None,
+            renderer_debug: RendererDebug::from_env(),
+            press_timing: PressTiming::default(),
+            hover_intent_delay: Duration::from_millis(300),
+            scroll_physics: ScrollPhysics::default(),
+            color_space: ColorSpace::Srgb,
+        }
+    }
+}
+
+/// Converts the events which can be recorded and replayed.
+pub fn input_from_event(event: &WindowEvent) -> Option<WindowInput> {
+    match *event {
+        WindowEvent::CursorMoved { position, .. } => Some(WindowInput::CursorMoved {
+            x: position.x,
+            y: position.y,
+        }),
+        WindowEvent::MouseInput {
+            state,
+            button: MouseButton::Left,
+            ..
+        } => Some(WindowInput::MouseLeft {
+            pressed: state == ElementState::Pressed,
+        }),
+        // Trackpads report when a gesture ends, which flings the content.
+        WindowEvent::MouseWheel {
+            phase: TouchPhase::Ended,
+            ..
+        } => Some(WindowInput::ScrollEnded),
+        WindowEvent::MouseWheel {
+            delta, modifiers, ..
+        } => {
+            let delta = match delta {
+                MouseScrollDelta::LineDelta(x, y) => LogicalVector::new(x, y) * LINE_HEIGHT,
+                MouseScrollDelta::PixelDelta(pos) => LogicalVector::new(pos.x as f32, pos.y as f32),
+            };
+            if modifiers.ctrl {
+                return Some(WindowInput::Zoom {
+                    factor: 2f32.powf(delta.y / WHEEL_ZOOM_DISTANCE),
+                });
+            }
+            Some(WindowInput::Scroll {
+                x: delta.x,
+                y: delta.y,
+            })
+        }
+        WindowEvent::Resized(size) => Some(WindowInput::Resized {
+            width: size.width,
+            height: size.height,
+        }),
+        _ => None,
+    }
+}
+
+/// High frequency input which is waiting to be dispatched. Mice can
+/// report movement at 1000Hz, so movement and scrolling are collected
+/// until every pending event has been received, and dispatched once.
+#[derive(Default)]
+struct PendingInput {
+    moves: Vec<(f32, f32)>,
+    scroll: LogicalVector,
+    scroll_ended: bool,
+    zoom: Option<f32>,
+}
+
+/// Opens a native window along with a GL context, and starts drawing
+/// into it with `start`. If either fails, rendering falls back to
+/// software and the window is opened again. The window stays hidden
+/// until it's shown, which should be once the first frame has been
+/// drawn, so that it never shows up empty.
+fn open<T, F>(
+    window_builder: WindowBuilder,
+    event_loop: &EventLoopWindowTarget<()>,
+    shared: &mut SharedResources,
+    mut start: F,
+) -> (T, WinitWindow)
+where
+    F: FnMut(RawContext<NotCurrent>, &WinitWindow, &SharedResources) -> Result<T, String>,
+{
+    let window_builder = window_builder
+        .with_decorations(true)
+        .with_transparent(true)
+        .with_visible(false);
+    loop {
+        let opened = shared
+            .context_builder()
+            .build_windowed(window_builder.clone(), event_loop)
+            .map_err(|err| err.to_string())
+            .and_then(|gl_context| {
+                // The GL context is handed to the render thread, and
+                // only the window itself stays on this one.
+                let (gl_context, window) = unsafe { gl_context.split() };
+                start(gl_context, &window, shared).map(|started| (started, window))
+            });
+        match opened {
+            Ok(opened) => return opened,
+            Err(err) if !shared.software => {
+                if let Err(err) = shared.use_software(event_loop, &err) {
+                    panic!("Couldn't render a window in software either: {}", err);
+                }
+            }
+            Err(err) => panic!("Couldn't render a window: {}", err),
+        }
+    }
+}
+
+/// Has the content extend under the titlebar if `titlebar` asks for it
+/// and the platform supports it.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+fn with_titlebar(window_builder: WindowBuilder, titlebar: Titlebar) -> WindowBuilder {
+    #[cfg(target_os = "macos")]
+    {
+        use winit::platform::macos::WindowBuilderExtMacOS;
+        if titlebar == Titlebar::Overlay {
+            return window_builder
+                .with_titlebar_transparent(true)
+                .with_title_hidden(true)
+                .with_fullsize_content_view(true);
+        }
+    }
+    window_builder
+}
+
+/// How much of a window's content is covered by its titlebar. `ratio`
+/// converts the OS's logical pixels to the window's.
+fn safe_area(titlebar: Titlebar, ratio: f32) -> SafeArea {
+    let top = match titlebar {
+        Titlebar::Overlay if cfg!(target_os = "macos") => OVERLAY_TITLEBAR_HEIGHT * ratio,
+        _ => 0.0,
+    };
+    SafeArea(LogicalSideOffsets::new(top, 0.0, 0.0, 0.0))
+}
+
 /// Wrapper around a `winit::Window` and a `Context` for rendering the
 /// DOM.
 pub struct Window {
-    gl_context: ContextWrapper<PossiblyCurrent, WinitWindow>,
+    /// Dropped before `window`, since dropping it waits for the render
+    /// thread to destroy the GL context drawing into the window.
     context: Context,
+    window: WinitWindow,
+    /// In the window's logical pixels, which differ from the OS's when
+    /// the scale factor is overridden.
     cursor_pos: LogicalPosition,
+    /// Set by the DOM window's `scale_factor` attribute.
+    scale_factor: Option<f32>,
+    /// Set by the user with the zoom shortcuts, and multiplied with the
+    /// scale factor.
+    zoom: f32,
+    /// Set by the DOM window's `min_content_size` attribute.
+    min_content_size: bool,
+    /// The minimum size last given to the OS.
+    min_size: Option<LogicalSize>,
+    /// Set by the DOM window's `size` attribute.
+    size: WindowSize,
+    /// Set by the DOM window's `titlebar` attribute when it was opened.
+    titlebar: Titlebar,
+    /// The size last given to the window to fit its content.
+    fitted_size: Option<LogicalSize>,
+    /// Whether the last frame had layout transitions still running.
+    animating: bool,
+    /// What the debug overlay shortcut shows.
+    debug_overlay: DebugOverlay,
+    pending: PendingInput,
+    press_timing: PressTiming,
+    /// When the left button was pressed, while it's held down.
+    pressed_at: Option<Instant>,
+    /// When an element asked to be sent a `MouseHeld` event.
+    wake_at: Option<Instant>,
+    hover_intent_delay: Duration,
+    /// When to send a `MouseRested` event, if the pointer doesn't move
+    /// again before then.
+    rest_at: Option<Instant>,
+    /// Where each finger on a touch screen is, in the window's logical
+    /// pixels. Two of them pinch zoom containers.
+    touches: HashMap<u64, (f32, f32)>,
+    /// The finger standing in for the mouse, if one is down. Other
+    /// fingers don't press anything.
+    primary_touch: Option<u64>,
 }
 
 impl Window {
+    /// Opens a window. `parent` is the window before it in the app's
+    /// list of windows, which it may be placed relative to.
     pub fn new(
         dom_window: Node<DomWindow>,
+        parent: Option<&Window>,
+        zoom: f32,
         event_loop: &EventLoopWindowTarget<()>,
-        proxy: EventLoopProxy<()>,
+        options: WindowOptions,
+        shared: &mut SharedResources,
     ) -> Window {
-        let window_builder = WindowBuilder::new()
-            .with_title(&dom_window.element().title[..])
-            .with_decorations(true)
-            .with_transparent(true);
-
-        let gl_context = ContextBuilder::new()
-            .with_gl(glutin::GlRequest::GlThenGles {
-                opengl_version: (3, 2),
-                opengles_version: (3, 0),
-            })
-            .build_windowed(window_builder, &event_loop)
-            .unwrap();
-
-        let gl_context = unsafe { gl_context.make_current().unwrap() };
-
-        let gl = match gl_context.get_api() {
-            glutin::Api::OpenGl => unsafe {
-                gl::GlFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
-            },
-            glutin::Api::OpenGlEs => unsafe {
-                gl::GlesFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
-            },
-            glutin::Api::WebGl => unimplemented!(),
+        let titlebar = dom_window.element().titlebar;
+        let window_builder = with_titlebar(
+            WindowBuilder::new().with_title(&dom_window.element().title[..]),
+            titlebar,
+        );
+        let scale_factor = dom_window.element().scale_factor;
+        let min_content_size = dom_window.element().min_content_size;
+        let size = dom_window.element().size;
+        let placement = dom_window.element().placement;
+        let start = |gl_context, window: &WinitWindow, shared: &SharedResources| {
+            let inner_size = window.inner_size().to_physical(window.hidpi_factor());
+            let dpi_scale = scale_factor.unwrap_or(window.hidpi_factor() as f32) * zoom;
+            Context::new(
+                RenderTarget::Window(gl_context),
+                Some(window.id()),
+                size2(inner_size.width as i32, inner_size.height as i32),
+                dpi_scale,
+                dom_window.clone(),
+                options.splash_color.to_space(options.color_space),
+                shared,
+            )
         };
+        let (mut context, window) = open(window_builder, event_loop, shared, start);
 
-        let mut context = Context::new(gl, gl_context.window(), proxy, dom_window);
-        context.render();
-        gl_context.swap_buffers().unwrap();
+        window.set_resizable(size == WindowSize::Manual);
+        let hidpi_factor = window.hidpi_factor() as f32;
+        let ratio = hidpi_factor / (scale_factor.unwrap_or(hidpi_factor) * zoom);
+        context.set_safe_area(safe_area(titlebar, ratio));
+        context.set_text_settings(options.text_settings);
+        context.set_debug_overlay(options.debug_overlay);
+        context.set_renderer_debug(options.renderer_debug);
+        context.set_scroll_physics(options.scroll_physics);
+        context.set_color_space(options.color_space);
+        context.wait_for_fonts(FONT_TIMEOUT);
+        let animating = context.render_and_wait();
+        if animating {
+            window.request_redraw();
+        }
 
-        Window {
-            gl_context,
+        let mut window = Window {
             context,
+            window,
             cursor_pos: LogicalPosition::new(0.0, 0.0),
+            scale_factor,
+            zoom,
+            min_content_size,
+            min_size: None,
+            size,
+            titlebar,
+            fitted_size: None,
+            animating,
+            debug_overlay: options.debug_overlay.unwrap_or_default(),
+            pending: PendingInput::default(),
+            press_timing: options.press_timing,
+            pressed_at: None,
+            wake_at: None,
+            hover_intent_delay: options.hover_intent_delay,
+            rest_at: None,
+            touches: HashMap::new(),
+            primary_touch: None,
+        };
+        if window.fit_to_content() {
+            window.animating = window.context.render_and_wait();
         }
+        window.update_min_size();
+        window.place(placement, parent);
+        window.window.set_visible(true);
+        window
     }
 
     pub fn window_id(&self) -> WindowId {
-        self.gl_context.window().id()
+        self.window.id()
+    }
+
+    /// Whether the window's GL context was lost, so that it needs to be
+    /// recovered with `recover`.
+    pub fn is_lost(&self) -> bool {
+        self.context.is_lost()
+    }
+
+    /// Replaces the native window with a new one in the same place, with
+    /// a new GL context, since a lost context can't be used again. The
+    /// DOM and the state of scrolling and transitions are kept, and the
+    /// old window is closed once the new one has been drawn. The new
+    /// window has a different id.
+    pub fn recover(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        shared: &mut SharedResources,
+    ) {
+        let mut window_builder = WindowBuilder::new()
+            .with_title(&self.context.dom_window().element().title[..])
+            .with_inner_size(self.window.inner_size())
+            .with_resizable(self.size == WindowSize::Manual);
+        window_builder = with_titlebar(window_builder, self.titlebar);
+        if let Some(min_size) = self.min_size {
+            window_builder = window_builder.with_min_inner_size(min_size);
+        }
+        let context = &mut self.context;
+        let start = |gl_context, window: &WinitWindow, shared: &SharedResources| {
+            context.replace_target(RenderTarget::Window(gl_context), Some(window.id()), shared)
+        };
+        let ((), window) = open(window_builder, event_loop, shared, start);
+        if let Ok(position) = self.window.outer_position() {
+            window.set_outer_position(position);
+        }
+        let old = std::mem::replace(&mut self.window, window);
+        self.animating = self.context.render_and_wait();
+        if self.animating {
+            self.window.request_redraw();
+        }
+        self.window.set_visible(true);
+        drop(old);
+    }
+
+    /// Brings the window to the user's attention. winit has no way to
+    /// focus a window, so this only shows it if it was hidden; most
+    /// platforms raise a window when it's shown.
+    pub fn activate(&self) {
+        self.window.set_visible(true);
+    }
+
+    fn toggle_debug_overlay(&mut self) {
+        let overlay = match self.context.debug_overlay() {
+            Some(_) => None,
+            None => Some(self.debug_overlay),
+        };
+        self.context.set_debug_overlay(overlay);
+        self.render();
+    }
+
+    /// Copies the selected text to the clipboard.
+    fn copy(&mut self) {
+        match self.context.selected_text() {
+            Some(ref text) if !text.is_empty() => clipboard::set_text(text),
+            _ => (),
+        }
+    }
+
+    /// Sends the text on the clipboard to the window's elements. Returns
+    /// true if a handler ran.
+    fn paste(&mut self) -> bool {
+        match clipboard::get_text() {
+            Some(text) => self.dispatch(&input::InputEvent::Paste {
+                x: self.cursor_pos.x as f32,
+                y: self.cursor_pos.y as f32,
+                text,
+            }),
+            None => false,
+        }
     }
 
     pub fn set_dom_window(&mut self, new_node: Node<DomWindow>) {
-        self.gl_context
-            .window()
-            .set_title(&new_node.element().title[..]);
+        self.window.set_title(&new_node.element().title[..]);
+        if new_node.element().scale_factor != self.scale_factor {
+            self.scale_factor = new_node.element().scale_factor;
+            let size = self.window.inner_size();
+            self.resize(size.width, size.height);
+        }
+        self.min_content_size = new_node.element().min_content_size;
+        if new_node.element().size != self.size {
+            self.size = new_node.element().size;
+            self.fitted_size = None;
+            self.window.set_resizable(self.size == WindowSize::Manual);
+        }
         self.context.set_dom_window(new_node);
     }
 
+    /// The scale factor the window is rendered with, including zoom.
+    fn scale_factor(&self) -> f64 {
+        let scale_factor = match self.scale_factor {
+            Some(scale_factor) => scale_factor as f64,
+            None => self.window.hidpi_factor(),
+        };
+        scale_factor * self.zoom as f64
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Zooms in or out by one level, or back to 100% when `direction`
+    /// is zero.
+    fn step_zoom(&mut self, direction: i32) {
+        let zoom = match direction {
+            0 => 1.0,
+            _ if direction > 0 => ZOOM_LEVELS
+                .iter()
+                .cloned()
+                .find(|level| *level > self.zoom)
+                .unwrap_or(self.zoom),
+            _ => ZOOM_LEVELS
+                .iter()
+                .cloned()
+                .rev()
+                .find(|level| *level < self.zoom)
+                .unwrap_or(self.zoom),
+        };
+        if zoom != self.zoom {
+            self.zoom = zoom;
+            self.fitted_size = None;
+            let size = self.window.inner_size();
+            self.resize(size.width, size.height);
+            self.render();
+        }
+    }
+
+    /// Resizes the context, given the size in the OS's logical pixels.
+    fn resize(&mut self, width: f64, height: f64) {
+        let size = LogicalSize::new(width, height).to_physical(self.window.hidpi_factor());
+        self.context.resize(size, self.scale_factor() as f32);
+        let ratio = self.window.hidpi_factor() / self.scale_factor();
+        self.context
+            .set_safe_area(safe_area(self.titlebar, ratio as f32));
+    }
+
+    /// Re-renders the window if fonts have finished loading.
+    pub fn poll_fonts(&mut self) {
+        if self.context.poll_fonts() {
+            self.render();
+        }
+    }
+
     pub fn render(&mut self) {
-        self.context.render();
-        self.gl_context.swap_buffers().unwrap();
+        self.animating = self.context.render();
+        if self.fit_to_content() {
+            self.animating = self.context.render();
+        }
+        self.update_min_size();
+        if self.animating {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Keeps the window from being resized smaller than its content, if
+    /// the DOM window asks for it.
+    fn update_min_size(&mut self) {
+        let min_size = if self.min_content_size {
+            let size = self.context.min_content_size();
+            Some(self.to_os_size(size))
+        } else {
+            None
+        };
+        if min_size != self.min_size {
+            self.window.set_min_inner_size(min_size);
+            self.min_size = min_size;
+        }
+    }
+
+    /// Resizes the window to fit its content, if the DOM window asks
+    /// for it. Returns true if the window was resized, in which case it
+    /// needs to be rendered again.
+    fn fit_to_content(&mut self) -> bool {
+        if self.size != WindowSize::FitContent {
+            return false;
+        }
+        let monitor = self.window.current_monitor();
+        let monitor_size = monitor.size().to_logical(self.window.hidpi_factor());
+        let ratio = self.scale_factor() / self.window.hidpi_factor();
+        let max_size = ContentSize::new(
+            (monitor_size.width / ratio) as f32,
+            (monitor_size.height / ratio) as f32,
+        );
+        let size = self.context.natural_size(max_size);
+        let size = self.to_os_size(size);
+        if Some(size) == self.fitted_size {
+            return false;
+        }
+        self.fitted_size = Some(size);
+        self.window.set_inner_size(size);
+        self.resize(size.width, size.height);
+        true
+    }
+
+    /// Moves a window which is being opened to where `placement` says.
+    fn place(&self, placement: Placement, parent: Option<&Window>) {
+        let size = self.window.outer_size();
+        let (monitor, parent) = match parent {
+            Some(parent) => (parent.monitor_bounds(), parent.bounds()),
+            None => (self.monitor_bounds(), None),
+        };
+        if let Some((x, y)) = placement::place(placement, size.width, size.height, monitor, parent)
+        {
+            self.window.set_outer_position(LogicalPosition::new(x, y));
+        }
+    }
+
+    /// The window's frame, in the OS's logical pixels, if the platform
+    /// can report where windows are.
+    fn bounds(&self) -> Option<Bounds> {
+        let position = self.window.outer_position().ok()?;
+        let size = self.window.outer_size();
+        Some(Bounds {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    /// The bounds of the monitor the window is on, in the OS's logical
+    /// pixels.
+    fn monitor_bounds(&self) -> Bounds {
+        let monitor = self.window.current_monitor();
+        let hidpi_factor = self.window.hidpi_factor();
+        let position = monitor.position().to_logical(hidpi_factor);
+        let size = monitor.size().to_logical(hidpi_factor);
+        Bounds {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        }
+    }
+
+    /// Converts a size in the window's logical pixels to the OS's, which
+    /// differ when the scale factor is overridden.
+    fn to_os_size(&self, size: ContentSize) -> LogicalSize {
+        let ratio = self.scale_factor() / self.window.hidpi_factor();
+        LogicalSize::new(size.width as f64 * ratio, size.height as f64 * ratio)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animating
     }
 
     pub fn process(&mut self, event: WindowEvent) -> bool {
         match event {
             WindowEvent::RedrawRequested => {
-                self.context.render();
-            }
-            WindowEvent::Resized(size) => {
-                println!("resize {}x{}", size.width, size.height);
-                let factor = self.gl_context.window().hidpi_factor();
-                self.context.resize(size.to_physical(factor), factor as f32);
                 self.render();
+                false
             }
-            WindowEvent::CursorMoved { position, .. } => {
-                self.cursor_pos = position;
-                let event = input::InputEvent::MouseMove {
-                    x: self.cursor_pos.x as f32,
-                    y: self.cursor_pos.y as f32,
-                };
-                return self.context.process(&event);
-            }
-            WindowEvent::MouseInput {
-                state,
-                button: MouseButton::Left,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        modifiers,
+                        ..
+                    },
                 ..
             } => {
-                let event = input::InputEvent::MouseLeft {
-                    state: match state {
-                        ElementState::Pressed => input::State::Begin,
-                        ElementState::Released => input::State::End,
-                    },
-                    x: self.cursor_pos.x as f32,
-                    y: self.cursor_pos.y as f32,
+                let command = if cfg!(target_os = "macos") {
+                    modifiers.logo
+                } else {
+                    modifiers.ctrl
                 };
-                return self.context.process(&event);
+                match key {
+                    VirtualKeyCode::D if command && modifiers.shift => self.toggle_debug_overlay(),
+                    VirtualKeyCode::Equals | VirtualKeyCode::Add if command => self.step_zoom(1),
+                    VirtualKeyCode::Minus | VirtualKeyCode::Subtract if command => {
+                        self.step_zoom(-1)
+                    }
+                    VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 if command => self.step_zoom(0),
+                    VirtualKeyCode::C if command => self.copy(),
+                    VirtualKeyCode::V if command => return self.paste(),
+                    _ => (),
+                }
+                false
+            }
+            WindowEvent::Touch(touch) => self.touch(touch),
+            event => match input_from_event(&event) {
+                Some(input) => self.input(input),
+                None => false,
+            },
+        }
+    }
+
+    /// Handles input. Returns true if a handler ran, in which case a new
+    /// revision is needed.
+    pub fn input(&mut self, input: WindowInput) -> bool {
+        match input {
+            WindowInput::Resized { width, height } => {
+                println!("resize {}x{}", width, height);
+                self.resize(width, height);
+                self.render();
+            }
+            // OSes may also report touches as mouse events, which would
+            // move the pointer the touch is standing in for.
+            WindowInput::CursorMoved { .. } | WindowInput::MouseLeft { .. }
+                if self.primary_touch.is_some() => {}
+            WindowInput::CursorMoved { x, y } => {
+                let ratio = self.window.hidpi_factor() / self.scale_factor();
+                let (x, y) = (x * ratio, y * ratio);
+                self.cursor_pos = LogicalPosition::new(x, y);
+                self.pending.moves.push((x as f32, y as f32));
+            }
+            WindowInput::MouseLeft { pressed } => {
+                return self.press(if pressed {
+                    input::State::Begin
+                } else {
+                    input::State::End
+                });
+            }
+            WindowInput::Scroll { x, y } => {
+                self.pending.scroll -= LogicalVector::new(x, y);
+            }
+            WindowInput::ScrollEnded => self.pending.scroll_ended = true,
+            WindowInput::Zoom { factor } => {
+                self.pending.zoom = Some(self.pending.zoom.unwrap_or(1.0) * factor);
             }
-            _ => (),
         }
         false
     }
+
+    /// Sends a press, release or cancel of the left button where the
+    /// pointer is. Returns true if a handler ran.
+    fn press(&mut self, state: input::State) -> bool {
+        // Movement before the click has to be seen first, so that the
+        // right element is hovered.
+        let moved = self.flush_input();
+        let pressed = state == input::State::Begin;
+        self.pressed_at = if pressed { Some(Instant::now()) } else { None };
+        let event = input::InputEvent::MouseLeft {
+            state,
+            x: self.cursor_pos.x as f32,
+            y: self.cursor_pos.y as f32,
+        };
+        let ran = self.dispatch(&event);
+        if !pressed {
+            self.wake_at = None;
+        }
+        ran || moved
+    }
+
+    /// Zooms the zoom container between the first two fingers on a
+    /// touch screen by how much further apart they've moved, and lets
+    /// the first finger press elements like the mouse. Returns true if
+    /// a handler ran.
+    fn touch(&mut self, touch: Touch) -> bool {
+        let ratio = self.window.hidpi_factor() / self.scale_factor();
+        let location = (
+            (touch.location.x * ratio) as f32,
+            (touch.location.y * ratio) as f32,
+        );
+        let ran = self.follow_touch(&touch, location);
+        let pinch = |touches: &HashMap<u64, (f32, f32)>| {
+            let mut fingers = touches.values();
+            match (fingers.next(), fingers.next()) {
+                (Some(a), Some(b)) => {
+                    let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                    Some((middle, (a.0 - b.0).hypot(a.1 - b.1)))
+                }
+                _ => None,
+            }
+        };
+        let before = pinch(&self.touches);
+        match touch.phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                self.touches.insert(touch.id, location);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                return ran;
+            }
+        }
+        if touch.phase != TouchPhase::Moved || self.touches.len() != 2 {
+            return ran;
+        }
+        if let (Some(((x, y), before)), Some((_, after))) = (before, pinch(&self.touches)) {
+            if before > 0.0 && after > 0.0 && self.context.zoom_at(x, y, after / before) {
+                self.render();
+            }
+        }
+        ran
+    }
+
+    /// Makes the first finger put down while nothing else is pressed
+    /// stand in for the mouse, so that elements can be tapped, held and
+    /// dragged. A second finger cancels the press instead of moving it,
+    /// since the two are then pinching, and the fingers put down after
+    /// that are ignored until they're all lifted. Returns true if a
+    /// handler ran.
+    fn follow_touch(&mut self, touch: &Touch, (x, y): (f32, f32)) -> bool {
+        let primary = self.primary_touch == Some(touch.id);
+        match touch.phase {
+            TouchPhase::Started if self.touches.is_empty() && self.pressed_at.is_none() => {
+                self.primary_touch = Some(touch.id);
+                self.context.set_pointer(PointerId::Touch(touch.id));
+                self.cursor_pos = LogicalPosition::new(x.into(), y.into());
+                self.pending.moves.push((x, y));
+                self.press(input::State::Begin)
+            }
+            TouchPhase::Started if self.primary_touch.is_some() => {
+                self.end_touch(input::State::Cancel)
+            }
+            TouchPhase::Moved if primary => {
+                self.cursor_pos = LogicalPosition::new(x.into(), y.into());
+                self.pending.moves.push((x, y));
+                false
+            }
+            TouchPhase::Ended if primary => self.end_touch(input::State::End),
+            TouchPhase::Cancelled if primary => self.end_touch(input::State::Cancel),
+            _ => false,
+        }
+    }
+
+    /// Releases or cancels the press of the finger standing in for the
+    /// mouse, and goes back to following the mouse.
+    fn end_touch(&mut self, state: input::State) -> bool {
+        let ran = self.press(state);
+        self.primary_touch = None;
+        self.context.set_pointer(PointerId::Mouse);
+        ran
+    }
+
+    /// Handles recorded input. Resizes are replayed by resizing the
+    /// window, which then reports the new size like any other resize.
+    pub fn replay(&mut self, input: WindowInput) -> bool {
+        match input {
+            WindowInput::Resized { width, height } => {
+                self.window.set_inner_size(LogicalSize::new(width, height));
+                false
+            }
+            input => self.input(input),
+        }
+    }
+
+    /// Dispatches coalesced input. Returns true if a handler ran, in
+    /// which case a new revision is needed.
+    pub fn flush_input(&mut self) -> bool {
+        let pending = std::mem::replace(&mut self.pending, PendingInput::default());
+        let (x, y) = (self.cursor_pos.x as f32, self.cursor_pos.y as f32);
+
+        // Scrolling only moves content around, so it's handled without
+        // causing a new revision.
+        let mut scrolled =
+            pending.scroll != LogicalVector::zero() && self.context.scroll(x, y, pending.scroll);
+        scrolled |= pending.scroll_ended && self.context.end_scroll();
+        if scrolled {
+            self.render();
+        }
+        if let Some(factor) = pending.zoom {
+            if self.context.zoom_at(x, y, factor) {
+                self.render();
+            }
+        }
+
+        if pending.moves.is_empty() {
+            return false;
+        }
+        self.rest_at = Some(Instant::now() + self.hover_intent_delay);
+        let event = input::InputEvent::MouseMove {
+            x,
+            y,
+            trace: pending.moves,
+        };
+        self.dispatch(&event)
+    }
+
+    /// Sends a `MouseHeld` event if the left button is down and an
+    /// element's deadline has passed, and a `MouseRested` event once
+    /// the pointer has stopped for long enough. Returns true if a
+    /// handler ran.
+    pub fn poll_timers(&mut self, now: Instant) -> bool {
+        let (x, y) = (self.cursor_pos.x as f32, self.cursor_pos.y as f32);
+        let mut ran = false;
+        match self.wake_at {
+            Some(wake_at) if wake_at <= now && self.pressed_at.is_some() => {
+                self.wake_at = None;
+                ran |= self.dispatch(&input::InputEvent::MouseHeld { x, y });
+            }
+            _ => (),
+        }
+        match self.rest_at {
+            Some(rest_at) if rest_at <= now => {
+                self.rest_at = None;
+                ran |= self.dispatch(&input::InputEvent::MouseRested { x, y });
+            }
+            _ => (),
+        }
+        ran
+    }
+
+    /// When `poll_timers` next needs to be called.
+    pub fn next_timer(&self) -> Option<Instant> {
+        let held = self.pressed_at.and(self.wake_at);
+        match (held, self.rest_at) {
+            (Some(held), Some(rest_at)) => Some(held.min(rest_at)),
+            (held, rest_at) => held.or(rest_at),
+        }
+    }
+
+    /// Gives an event to the elements under the pointer, along with the
+    /// state of the left button, and keeps the earliest time any of
+    /// them asked to hear from it again. Every pointer event goes this
+    /// way: the context hit tests its layout, and its `PointerRouter`
+    /// sends the event through the capture and bubble phases to the
+    /// element which captured the pointer or else the one under it.
+    fn dispatch(&mut self, event: &input::InputEvent) -> bool {
+        let timer = Rc::new(PressTimer::new(
+            self.press_timing,
+            self.pressed_at,
+            Instant::now(),
+        ));
+        let interactions = self.context.interactions().clone();
+        let ran = illicit::child_env!(Rc<PressTimer> => timer.clone())
+            .enter(|| self.context.process(event));
+        // Interaction styles only need the window restyled, not a new
+        // revision.
+        if *self.context.interactions() != interactions {
+            self.window.request_redraw();
+        }
+        self.wake_at = match (self.wake_at, timer.requested()) {
+            (Some(wake_at), Some(requested)) => Some(wake_at.min(requested)),
+            (wake_at, requested) => wake_at.or(requested),
+        };
+        ran
+    }
 }
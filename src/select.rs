@@ -0,0 +1,133 @@
+//! Derived state which is only recomputed when the state it's derived
+//! from changes, and which can be shared by every component that needs
+//! it.
+//!
+//! A `Selector` wraps an expensive derivation, like filtering or
+//! totalling a list. It remembers the inputs it last ran with, and
+//! hands back the previous result while they're unchanged. Selectors
+//! are cheap to clone and share one cache, so they can be declared once
+//! and passed down through the environment instead of as arguments.
+//!
+//! ```rs
+//! type VisibleTodos = Selector<(Rc<Vec<Todo>>, Filter), Vec<Todo>>;
+//!
+//! #[topo::nested]
+//! fn todo_app() -> Node<App> {
+//!     let todos: Key<Rc<Vec<Todo>>> = state!(|| Rc::new(vec![]));
+//!     let filter: Key<Filter> = state!(|| Filter::All);
+//!     let visible: VisibleTodos = once!(|| Selector::new(|(todos, filter)| filter.apply(todos)));
+//!     illicit::child_env!(VisibleTodos => visible).enter(|| {
+//!         // ...
+//!     })
+//! }
+//!
+//! #[topo::nested]
+//! fn remaining(todos: Rc<Vec<Todo>>, filter: Filter) -> Node<Span> {
+//!     let visible = illicit::Env::expect::<VisibleTodos>().select((todos, filter));
+//!     // ...
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// A cached derivation. See the module documentation.
+pub struct Selector<I, O> {
+    compute: Rc<dyn Fn(&I) -> O>,
+    cache: Rc<RefCell<Option<(I, Rc<O>)>>>,
+}
+
+impl<I, O> Clone for Selector<I, O> {
+    fn clone(&self) -> Self {
+        Selector {
+            compute: self.compute.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<I, O> fmt::Debug for Selector<I, O>
+where
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cache = self.cache.borrow();
+        f.debug_struct("Selector")
+            .field("inputs", &cache.as_ref().map(|(inputs, _)| inputs))
+            .finish()
+    }
+}
+
+impl<I, O> PartialEq for Selector<I, O> {
+    /// Clones of a selector are equal, so that passing one to a
+    /// component doesn't defeat memoization.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.cache, &other.cache)
+    }
+}
+
+impl<I, O> Selector<I, O>
+where
+    I: PartialEq,
+{
+    pub fn new(compute: impl Fn(&I) -> O + 'static) -> Selector<I, O> {
+        Selector {
+            compute: Rc::new(compute),
+            cache: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Returns the result of the derivation for `inputs`, only running
+    /// it if they differ from the last call's.
+    ///
+    /// Only the latest result is kept, so components deriving from
+    /// different inputs should each have their own selector.
+    pub fn select(&self, inputs: I) -> Rc<O> {
+        if let Some((ref cached, ref output)) = *self.cache.borrow() {
+            if *cached == inputs {
+                return output.clone();
+            }
+        }
+        let output = Rc::new((self.compute)(&inputs));
+        self.cache.replace(Some((inputs, output.clone())));
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Selector;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn recomputes_when_inputs_change() {
+        let runs = Rc::new(Cell::new(0));
+        let counter = runs.clone();
+        let total = Selector::new(move |items: &Vec<u32>| {
+            counter.set(counter.get() + 1);
+            items.iter().sum::<u32>()
+        });
+        assert_eq!(*total.select(vec![1, 2, 3]), 6);
+        assert_eq!(*total.select(vec![1, 2, 3]), 6);
+        assert_eq!(runs.get(), 1);
+        assert_eq!(*total.select(vec![4]), 4);
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn clones_share_cache() {
+        let runs = Rc::new(Cell::new(0));
+        let counter = runs.clone();
+        let double = Selector::new(move |value: &u32| {
+            counter.set(counter.get() + 1);
+            value * 2
+        });
+        let shared = double.clone();
+        assert_eq!(*double.select(5), 10);
+        assert_eq!(*shared.select(5), 10);
+        assert_eq!(runs.get(), 1);
+        assert_eq!(double, shared);
+    }
+}
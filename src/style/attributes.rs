@@ -1,7 +1,11 @@
-use super::{ComputedValues, Direction, DisplayType};
+use super::{
+    BlockLength, ComputedValues, Direction, DisplayType, Float, FontStyle, LineHeight,
+    MarginCollapse, Overflow, OverflowWrap, RenderCache, SafeArea, Scrollbar, TextDecoration,
+    TextDirection, TextOverflow, TextSettings, WhiteSpace, WordBreak,
+};
+use crate::animation::Transition;
 use crate::layout::{LogicalLength, LogicalSize};
 use crate::Color;
-use std::borrow::Cow;
 
 /// Represents a position or size that can be specified in multiple
 /// units, which are resolved during styling.
@@ -11,14 +15,82 @@ pub struct Value {
     pub ems: f32,
     pub view_width: f32,
     pub view_height: f32,
+    /// A fraction of the size of the parent's content box. This is only
+    /// used by `width` and `height`, and ignored elsewhere; `define_style!`
+    /// rejects it for margins, padding and borders.
+    pub percent: f32,
+    /// A multiple of the window's safe area inset on the same side, so
+    /// that content isn't covered by a titlebar drawn over it. This is
+    /// only used by `padding`, and ignored elsewhere.
+    pub safe_area: f32,
+    /// Takes up the space the parent has left beside the element, so
+    /// that `auto` left and right margins center it. This is only used
+    /// by the left and right margins of a child of a vertical element,
+    /// and the other units are ignored when it's set.
+    pub auto: bool,
 }
 
 struct ValueContext {
     pixels_per_em: f32,
+    text_scale: f32,
     viewport: LogicalSize,
 }
 
 impl Value {
+    pub fn px(pixels: f32) -> Value {
+        Value {
+            pixels,
+            ..Default::default()
+        }
+    }
+
+    pub fn em(ems: f32) -> Value {
+        Value {
+            ems,
+            ..Default::default()
+        }
+    }
+
+    /// A percentage of the width of the window.
+    pub fn vw(percent: f32) -> Value {
+        Value {
+            view_width: percent / 100.0,
+            ..Default::default()
+        }
+    }
+
+    /// A percentage of the height of the window.
+    pub fn vh(percent: f32) -> Value {
+        Value {
+            view_height: percent / 100.0,
+            ..Default::default()
+        }
+    }
+
+    /// A percentage of the size of the parent's content box.
+    pub fn percent(percent: f32) -> Value {
+        Value {
+            percent: percent / 100.0,
+            ..Default::default()
+        }
+    }
+
+    /// The window's safe area inset on the side it's used for.
+    pub fn safe_area() -> Value {
+        Value {
+            safe_area: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// A margin which takes up the space left beside the element.
+    pub fn auto() -> Value {
+        Value {
+            auto: true,
+            ..Default::default()
+        }
+    }
+
     fn resolve(&self, ctx: &ValueContext) -> LogicalLength {
         let pixels = self.pixels
             + self.ems * ctx.pixels_per_em
@@ -26,6 +98,54 @@ impl Value {
             + self.view_height * ctx.viewport.height;
         LogicalLength::new(pixels)
     }
+
+    /// Like `resolve`, but pixel sizes are multiplied by the text scale.
+    /// Ems are relative to a text size which is already scaled.
+    fn resolve_text_size(&self, ctx: &ValueContext) -> LogicalLength {
+        let pixels = self.pixels * ctx.text_scale
+            + self.ems * ctx.pixels_per_em
+            + self.view_width * ctx.viewport.width
+            + self.view_height * ctx.viewport.height;
+        LogicalLength::new(pixels)
+    }
+
+    /// Like `resolve`, but keeps the percentage to be resolved during
+    /// layout.
+    fn resolve_block(&self, ctx: &ValueContext) -> BlockLength {
+        BlockLength {
+            length: self.resolve(ctx),
+            percent: self.percent,
+        }
+    }
+}
+
+impl From<f32> for Value {
+    fn from(pixels: f32) -> Value {
+        Value::px(pixels)
+    }
+}
+
+/// The height of each line of text. Lengths in ems are relative to the
+/// element's own text size, and are inherited as that length.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LineHeightValue {
+    Normal,
+    /// A multiple of the text size of each element the line height is
+    /// inherited by.
+    Multiple(f32),
+    Length(Value),
+}
+
+impl From<f32> for LineHeightValue {
+    fn from(multiple: f32) -> LineHeightValue {
+        LineHeightValue::Multiple(multiple)
+    }
+}
+
+impl From<Value> for LineHeightValue {
+    fn from(length: Value) -> LineHeightValue {
+        LineHeightValue::Length(length)
+    }
 }
 
 /// Decides how a given element should be laid out.
@@ -37,6 +157,8 @@ pub enum Display {
     Inline,
 }
 
+/// Sizes for each side of an element. Sides which are `None` are left
+/// unchanged.
 #[derive(Clone, Debug, PartialEq)]
 pub struct SideOffsets {
     pub left: Option<Value>,
@@ -45,31 +167,98 @@ pub struct SideOffsets {
     pub bottom: Option<Value>,
 }
 
+impl SideOffsets {
+    /// Specifies each side, in the same order as CSS.
+    pub fn new(top: Value, right: Value, bottom: Value, left: Value) -> SideOffsets {
+        SideOffsets {
+            left: Some(left),
+            right: Some(right),
+            top: Some(top),
+            bottom: Some(bottom),
+        }
+    }
+
+    pub fn all(value: impl Into<Value>) -> SideOffsets {
+        let value = value.into();
+        SideOffsets::new(value.clone(), value.clone(), value.clone(), value)
+    }
+
+    pub fn symmetric(vertical: impl Into<Value>, horizontal: impl Into<Value>) -> SideOffsets {
+        let vertical = vertical.into();
+        let horizontal = horizontal.into();
+        SideOffsets::new(vertical.clone(), horizontal.clone(), vertical, horizontal)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct CommonAttributes {
     pub display: Option<Display>,
     pub direction: Option<Direction>,
+    pub overflow: Option<Overflow>,
+    pub float: Option<Float>,
+    pub margin_collapse: Option<MarginCollapse>,
+    pub columns: Option<u32>,
     pub text_size: Option<Value>,
     pub text_color: Option<Color>,
-    pub font_family: Option<Cow<'static, str>>,
+    pub font_family: Option<&'static str>,
     pub font_weight: Option<u32>,
+    pub font_style: Option<FontStyle>,
+    pub line_height: Option<LineHeightValue>,
+    pub letter_spacing: Option<Value>,
+    pub text_direction: Option<TextDirection>,
+    pub word_break: Option<WordBreak>,
+    pub overflow_wrap: Option<OverflowWrap>,
+    pub white_space: Option<WhiteSpace>,
+    pub tab_size: Option<u32>,
+    pub max_lines: Option<u32>,
+    pub text_overflow: Option<TextOverflow>,
+    pub text_decoration: Option<TextDecoration>,
+    pub selection_color: Option<Color>,
     pub background_color: Option<Color>,
     pub border_radius: Option<Value>,
     pub border_thickness: SideOffsets,
     pub border_color: Option<Color>,
+    pub scrollbar: Option<Scrollbar>,
+    pub scrollbar_thumb_color: Option<Color>,
+    pub scrollbar_track_color: Option<Color>,
+    pub scrollbar_thickness: Option<Value>,
     pub padding: SideOffsets,
     pub margin: SideOffsets,
     pub width: Option<Value>,
     pub height: Option<Value>,
+    pub min_width: Option<Value>,
+    pub min_height: Option<Value>,
+    pub max_width: Option<Value>,
+    pub max_height: Option<Value>,
+    pub layout_transition: Option<Transition>,
+    pub enter_transition: Option<Transition>,
+    pub exit_transition: Option<Transition>,
+    pub render_cache: Option<RenderCache>,
 }
 
 pub const DEFAULT_ATTRIBUTES: CommonAttributes = CommonAttributes {
     display: None,
     direction: None,
+    overflow: None,
+    float: None,
+    margin_collapse: None,
+    columns: None,
     text_size: None,
     text_color: None,
     font_family: None,
     font_weight: None,
+    font_style: None,
+    line_height: None,
+    letter_spacing: None,
+    text_direction: None,
+    word_break: None,
+    overflow_wrap: None,
+    white_space: None,
+    tab_size: None,
+    max_lines: None,
+    text_overflow: None,
+    text_decoration: None,
+    selection_color: None,
     background_color: None,
     border_radius: None,
     border_thickness: SideOffsets {
@@ -79,6 +268,10 @@ pub const DEFAULT_ATTRIBUTES: CommonAttributes = CommonAttributes {
         bottom: None,
     },
     border_color: None,
+    scrollbar: None,
+    scrollbar_thumb_color: None,
+    scrollbar_track_color: None,
+    scrollbar_thickness: None,
     padding: SideOffsets {
         left: None,
         right: None,
@@ -93,6 +286,14 @@ pub const DEFAULT_ATTRIBUTES: CommonAttributes = CommonAttributes {
     },
     width: None,
     height: None,
+    min_width: None,
+    min_height: None,
+    max_width: None,
+    max_height: None,
+    layout_transition: None,
+    enter_transition: None,
+    exit_transition: None,
+    render_cache: None,
 };
 
 impl Default for CommonAttributes {
@@ -102,12 +303,18 @@ impl Default for CommonAttributes {
 }
 
 impl CommonAttributes {
-    #[illicit::from_env(viewport_size: &LogicalSize)]
+    #[illicit::from_env(
+        viewport_size: &LogicalSize,
+        text_settings: &TextSettings,
+        safe_area: &SafeArea
+    )]
     pub(super) fn apply(&self, values: &mut ComputedValues) {
-        let ctx = ValueContext {
-            pixels_per_em: 16.0, // todo
+        let mut ctx = ValueContext {
+            pixels_per_em: values.text_size.get(),
+            text_scale: text_settings.scale,
             viewport: *viewport_size,
         };
+        let insets = safe_area.0;
         if let Some(display) = self.display {
             match display {
                 Display::Block => values.display = DisplayType::Block(Default::default()),
@@ -119,37 +326,107 @@ impl CommonAttributes {
                 block.direction = direction;
             }
         }
+        if let Some(overflow) = self.overflow {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.overflow = overflow;
+            }
+        }
+        if let Some(float) = self.float {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.float = float;
+            }
+        }
+        if let Some(margin_collapse) = self.margin_collapse {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.margin_collapse = margin_collapse;
+            }
+        }
+        if let Some(columns) = self.columns {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.columns = Some(columns);
+            }
+        }
         if let Some(ref text_size) = self.text_size {
-            values.text_size = text_size.resolve(&ctx);
+            values.text_size = text_size.resolve_text_size(&ctx);
+            ctx.pixels_per_em = values.text_size.get();
+        }
+        if let Some(ref line_height) = self.line_height {
+            values.line_height = match line_height {
+                LineHeightValue::Normal => LineHeight::Normal,
+                LineHeightValue::Multiple(multiple) => LineHeight::Multiple(*multiple),
+                LineHeightValue::Length(length) => LineHeight::Length(length.resolve(&ctx)),
+            };
+        }
+        if let Some(ref letter_spacing) = self.letter_spacing {
+            values.letter_spacing = letter_spacing.resolve(&ctx);
+        }
+        if let Some(text_direction) = self.text_direction {
+            values.text_direction = text_direction;
+        }
+        if let Some(word_break) = self.word_break {
+            values.word_break = word_break;
+        }
+        if let Some(overflow_wrap) = self.overflow_wrap {
+            values.overflow_wrap = overflow_wrap;
+        }
+        if let Some(white_space) = self.white_space {
+            values.white_space = white_space;
+        }
+        if let Some(tab_size) = self.tab_size {
+            values.tab_size = tab_size;
+        }
+        if let Some(max_lines) = self.max_lines {
+            values.max_lines = Some(max_lines);
+        }
+        if let Some(text_overflow) = self.text_overflow {
+            values.text_overflow = text_overflow;
+        }
+        if let Some(text_decoration) = self.text_decoration {
+            values.text_decoration = text_decoration;
+        }
+        if let Some(selection_color) = self.selection_color {
+            values.selection_color = selection_color;
         }
         if let Some(ref padding) = self.padding.left {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.padding.left = padding.resolve(&ctx).get();
+                block.padding.left = padding.resolve(&ctx).get() + padding.safe_area * insets.left;
             }
         }
         if let Some(ref padding) = self.padding.right {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.padding.right = padding.resolve(&ctx).get();
+                block.padding.right =
+                    padding.resolve(&ctx).get() + padding.safe_area * insets.right;
             }
         }
         if let Some(ref padding) = self.padding.top {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.padding.top = padding.resolve(&ctx).get();
+                block.padding.top = padding.resolve(&ctx).get() + padding.safe_area * insets.top;
             }
         }
         if let Some(ref padding) = self.padding.bottom {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.padding.bottom = padding.resolve(&ctx).get();
+                block.padding.bottom =
+                    padding.resolve(&ctx).get() + padding.safe_area * insets.bottom;
             }
         }
         if let Some(ref margin) = self.margin.left {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.margin.left = margin.resolve(&ctx).get();
+                block.auto_margin_left = margin.auto;
+                block.margin.left = if margin.auto {
+                    0.0
+                } else {
+                    margin.resolve(&ctx).get()
+                };
             }
         }
         if let Some(ref margin) = self.margin.right {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.margin.right = margin.resolve(&ctx).get();
+                block.auto_margin_right = margin.auto;
+                block.margin.right = if margin.auto {
+                    0.0
+                } else {
+                    margin.resolve(&ctx).get()
+                };
             }
         }
         if let Some(ref margin) = self.margin.top {
@@ -164,12 +441,32 @@ impl CommonAttributes {
         }
         if let Some(ref width) = self.width {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.width = Some(width.resolve(&ctx));
+                block.width = Some(width.resolve_block(&ctx));
             }
         }
         if let Some(ref height) = self.height {
             if let DisplayType::Block(ref mut block) = values.display {
-                block.height = Some(height.resolve(&ctx));
+                block.height = Some(height.resolve_block(&ctx));
+            }
+        }
+        if let Some(ref min_width) = self.min_width {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.min_width = Some(min_width.resolve_block(&ctx));
+            }
+        }
+        if let Some(ref min_height) = self.min_height {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.min_height = Some(min_height.resolve_block(&ctx));
+            }
+        }
+        if let Some(ref max_width) = self.max_width {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.max_width = Some(max_width.resolve_block(&ctx));
+            }
+        }
+        if let Some(ref max_height) = self.max_height {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.max_height = Some(max_height.resolve_block(&ctx));
             }
         }
         if let Some(ref border_radius) = self.border_radius {
@@ -190,11 +487,44 @@ impl CommonAttributes {
         if let Some(text_color) = self.text_color {
             values.text_color = text_color;
         }
+        if let Some(font_family) = self.font_family {
+            values.font_family = font_family;
+        }
+        if let Some(font_weight) = self.font_weight {
+            values.font_weight = font_weight;
+        }
+        if let Some(font_style) = self.font_style {
+            values.font_style = font_style;
+        }
         if let Some(border_color) = self.border_color {
             values.border_color = border_color;
         }
+        if let Some(scrollbar) = self.scrollbar {
+            values.scrollbar = scrollbar;
+        }
+        if let Some(scrollbar_thumb_color) = self.scrollbar_thumb_color {
+            values.scrollbar_thumb_color = scrollbar_thumb_color;
+        }
+        if let Some(scrollbar_track_color) = self.scrollbar_track_color {
+            values.scrollbar_track_color = scrollbar_track_color;
+        }
+        if let Some(ref scrollbar_thickness) = self.scrollbar_thickness {
+            values.scrollbar_thickness = scrollbar_thickness.resolve(&ctx);
+        }
         if let Some(background_color) = self.background_color {
             values.background_color = background_color;
         }
+        if let Some(layout_transition) = self.layout_transition {
+            values.layout_transition = Some(layout_transition);
+        }
+        if let Some(enter_transition) = self.enter_transition {
+            values.enter_transition = Some(enter_transition);
+        }
+        if let Some(exit_transition) = self.exit_transition {
+            values.exit_transition = Some(exit_transition);
+        }
+        if let Some(render_cache) = self.render_cache {
+            values.render_cache = render_cache;
+        }
     }
 }
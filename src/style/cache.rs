@@ -0,0 +1,141 @@
+use super::{
+    ComputedValues, FontStyle, InheritedValues, LineHeight, OverflowWrap, SafeArea, StyleData,
+    TextDecoration, TextDirection, TextOverflow, TextSettings, WhiteSpace, WordBreak,
+};
+use crate::layout::LogicalSize;
+use crate::Color;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The cache is cleared once it grows past this many entries, which
+/// can happen when a scroll link or transition keeps changing the text
+/// size that children inherit.
+const MAX_ENTRIES: usize = 4096;
+
+/// Everything that computed values depend on, apart from the viewport
+/// size, text settings and safe area, which clear the whole cache when they
+/// change. Elements are expected to return the same initial values
+/// from `create_computed_values` for every node of the same type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StyleKey {
+    element: TypeId,
+    style: Option<*const StyleData>,
+    /// Bit `n` is set if the `n`th sub style matched.
+    matched: u64,
+    text_size: u32,
+    text_color: Option<Color>,
+    font_family: &'static str,
+    font_weight: u32,
+    font_style: FontStyle,
+    /// The variant of the line height, and its value.
+    line_height: (u8, u32),
+    letter_spacing: u32,
+    text_direction: TextDirection,
+    word_break: WordBreak,
+    overflow_wrap: OverflowWrap,
+    white_space: WhiteSpace,
+    tab_size: u32,
+    max_lines: Option<u32>,
+    text_overflow: TextOverflow,
+    text_decoration: TextDecoration,
+    selection_color: Color,
+}
+
+impl StyleKey {
+    pub(super) fn new(
+        element: TypeId,
+        style: Option<&'static StyleData>,
+        matched: u64,
+        inherited: &InheritedValues,
+    ) -> StyleKey {
+        let line_height = match inherited.line_height {
+            LineHeight::Normal => (0, 0),
+            LineHeight::Multiple(multiple) => (1, multiple.to_bits()),
+            LineHeight::Length(length) => (2, length.get().to_bits()),
+        };
+        StyleKey {
+            element,
+            style: style.map(|style| style as *const StyleData),
+            matched,
+            text_size: inherited.text_size.get().to_bits(),
+            text_color: inherited.text_color,
+            font_family: inherited.font_family,
+            font_weight: inherited.font_weight,
+            font_style: inherited.font_style,
+            line_height,
+            letter_spacing: inherited.letter_spacing.get().to_bits(),
+            text_direction: inherited.text_direction,
+            word_break: inherited.word_break,
+            overflow_wrap: inherited.overflow_wrap,
+            white_space: inherited.white_space,
+            tab_size: inherited.tab_size,
+            max_lines: inherited.max_lines,
+            text_overflow: inherited.text_overflow,
+            text_decoration: inherited.text_decoration,
+            selection_color: inherited.selection_color,
+        }
+    }
+}
+
+/// Counts how effective the cache has been.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StyleCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Shares the result of style resolution between nodes with identical
+/// inputs, such as the rows of a long list. Those nodes also share the
+/// same allocation for their computed values.
+#[derive(Debug, Default)]
+pub struct StyleCache {
+    entries: HashMap<StyleKey, Arc<ComputedValues>>,
+    viewport: Option<LogicalSize>,
+    text_settings: Option<TextSettings>,
+    safe_area: Option<SafeArea>,
+    stats: StyleCacheStats,
+}
+
+impl StyleCache {
+    /// Clears the cache if anything that every entry depends on has
+    /// changed since the last run.
+    pub fn begin(
+        &mut self,
+        viewport: LogicalSize,
+        text_settings: TextSettings,
+        safe_area: SafeArea,
+    ) {
+        if self.viewport != Some(viewport)
+            || self.text_settings != Some(text_settings)
+            || self.safe_area != Some(safe_area)
+        {
+            self.entries.clear();
+            self.viewport = Some(viewport);
+            self.text_settings = Some(text_settings);
+            self.safe_area = Some(safe_area);
+        }
+    }
+
+    pub fn get_or_insert_with(
+        &mut self,
+        key: StyleKey,
+        compute: impl FnOnce() -> ComputedValues,
+    ) -> Arc<ComputedValues> {
+        if let Some(values) = self.entries.get(&key) {
+            self.stats.hits += 1;
+            return values.clone();
+        }
+        self.stats.misses += 1;
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.clear();
+        }
+        let values = Arc::new(compute());
+        self.entries.insert(key, values.clone());
+        values
+    }
+
+    pub fn stats(&self) -> StyleCacheStats {
+        self.stats
+    }
+}
@@ -1,11 +1,23 @@
-use crate::dom::{element::DynamicNode, node::NodeRef, Node, Window};
+use crate::animation::Transition;
+use crate::dom::{
+    element::{DynamicNode, ElementStates},
+    node::NodeRef,
+    Node, Window,
+};
 use crate::layout::{LogicalLength, LogicalSideOffsets, LogicalSize};
 use crate::Color;
 use moxie::embed::Runtime;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
 
 mod attributes;
+mod cache;
 
 pub use attributes::*;
+pub use cache::StyleCacheStats;
+use cache::{StyleCache, StyleKey};
 
 /// Specifies which direction layout should be performed in.
 #[derive(Clone, PartialEq, Copy, Debug)]
@@ -14,27 +26,211 @@ pub enum Direction {
     Horizontal,
 }
 
+/// Specifies what happens to children which don't fit inside of an
+/// element.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum Overflow {
+    /// Children are drawn outside of the element's bounds.
+    Visible,
+    /// Children are clipped to the element's bounds, and can be
+    /// scrolled along the element's direction.
+    Scroll,
+    /// Children are clipped to the element's bounds, and can be zoomed
+    /// with Ctrl and the mouse wheel or by pinching, and panned by
+    /// scrolling. Only elements created with `mox!` can be zoomed.
+    Zoom,
+}
+
+/// When the scrollbars of a scroll container are shown. They are drawn
+/// over the edge of the content in every mode, rather than taking room
+/// from it.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum Scrollbar {
+    /// Fade in while the content is scrolling, and out once it stops.
+    Overlay,
+    /// Shown whenever there is content to scroll to.
+    Always,
+    Hidden,
+}
+
+/// Specifies whether a block inside inline content is taken out of the
+/// line it's on, so that the lines after it wrap around it.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum Float {
+    None,
+    /// Moved to the left edge of its container.
+    Left,
+    /// Moved to the right edge of its container.
+    Right,
+}
+
+/// Specifies how the margins of adjacent children of a vertical
+/// element combine.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum MarginCollapse {
+    /// The margins are added together.
+    Separate,
+    /// Only the larger of the margins is kept, as in CSS, so that items
+    /// in a list can have the same margin on every side without the gap
+    /// between them doubling. Negative margins are combined with the
+    /// positive margin.
+    Collapse,
+}
+
+/// Specifies whether an element and its children are rendered on
+/// their own, so that they can be reused between frames.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum RenderCache {
+    /// The element is rendered along with its parent.
+    None,
+    /// The element's subtree is rendered into a separate display list,
+    /// which is only rebuilt when the subtree changes. This is useful
+    /// for large parts of the UI which rarely change, like sidebars.
+    Subtree,
+}
+
+/// The order that text is written in. Right-to-left text is still
+/// broken into lines from its start, but each line is laid out from
+/// the right edge of its container, with runs of left-to-right text
+/// such as numbers kept in their own order.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+}
+
+/// Where lines of text can be broken.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum WordBreak {
+    /// At spaces, and between Chinese, Japanese and Korean characters.
+    Normal,
+    /// Between any two characters.
+    BreakAll,
+    /// Only at spaces, which keeps Korean words together.
+    KeepAll,
+}
+
+/// What happens to a word too long to fit on a line by itself, such as
+/// a URL or a hash.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum OverflowWrap {
+    /// The word overflows the line.
+    Normal,
+    /// The word is broken at whichever character reaches the end of the
+    /// line.
+    Anywhere,
+}
+
+/// How spaces and newlines in text are treated.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum WhiteSpace {
+    /// Lines wrap at spaces, and newlines are treated as spaces.
+    Normal,
+    /// Lines only break at newlines, and spaces at the start of a line
+    /// are kept, for showing code or logs verbatim.
+    Pre,
+    /// Like `Pre`, but long lines also wrap.
+    PreWrap,
+    /// Like `Normal`, but lines never wrap.
+    NoWrap,
+}
+
+/// Whether text is drawn upright or slanted.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum FontStyle {
+    Normal,
+    /// The family's italic face.
+    Italic,
+    /// A slanted version of the family's upright face.
+    Oblique,
+}
+
+/// A line drawn along text, such as under links or through deleted
+/// text. Its position and thickness come from the font.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum TextDecoration {
+    None,
+    Underline,
+    LineThrough,
+    Overline,
+}
+
+/// How text cut off by `max_lines` ends.
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum TextOverflow {
+    /// The last line ends at the last word that fits.
+    Clip,
+    /// The last line ends with "…", shortened to make room for it.
+    Ellipsis,
+}
+
 #[derive(Default, PartialEq, Clone, Copy, Debug)]
 pub struct InlineValues {}
 
+/// A width or height, which can depend on the size of the parent's
+/// content box, and so is only known during layout.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct BlockLength {
+    pub length: LogicalLength,
+    /// A fraction of the size of the parent's content box.
+    pub percent: f32,
+}
+
+impl BlockLength {
+    /// Resolves the length against the size of the parent's content
+    /// box. A percentage of a parent which can grow without bound,
+    /// like the content of a scroll container, is treated as unset.
+    pub fn resolve(&self, parent: f32) -> Option<f32> {
+        if self.percent == 0.0 {
+            Some(self.length.get())
+        } else if parent.is_finite() {
+            Some(self.length.get() + self.percent * parent)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct BlockValues {
     pub direction: Direction,
+    pub overflow: Overflow,
+    /// Only applies inside inline content. Elsewhere, floated blocks
+    /// are laid out like any other.
+    pub float: Float,
+    /// Applies to the element's children.
+    pub margin_collapse: MarginCollapse,
+    /// Lays the children out in this many columns of equal width
+    /// instead, putting each child in the column which is shortest so
+    /// far. Children take their natural width, so they're usually
+    /// given a width of `100 %` to fill their column.
+    pub columns: Option<u32>,
     pub margin: LogicalSideOffsets,
+    /// Whether the left and right margins were set to `auto`, in which
+    /// case `margin` holds zero for them until the parent centers the
+    /// element in the space it has left.
+    pub auto_margin_left: bool,
+    pub auto_margin_right: bool,
     pub padding: LogicalSideOffsets,
-    pub width: Option<LogicalLength>,
-    pub height: Option<LogicalLength>,
-    pub min_width: Option<LogicalLength>,
-    pub min_height: Option<LogicalLength>,
-    pub max_width: Option<LogicalLength>,
-    pub max_height: Option<LogicalLength>,
+    pub width: Option<BlockLength>,
+    pub height: Option<BlockLength>,
+    pub min_width: Option<BlockLength>,
+    pub min_height: Option<BlockLength>,
+    pub max_width: Option<BlockLength>,
+    pub max_height: Option<BlockLength>,
 }
 
 impl Default for BlockValues {
     fn default() -> Self {
         BlockValues {
             direction: Direction::Vertical,
+            overflow: Overflow::Visible,
+            float: Float::None,
+            margin_collapse: MarginCollapse::Separate,
+            columns: None,
             margin: LogicalSideOffsets::new_all_same(0.0),
+            auto_margin_left: false,
+            auto_margin_right: false,
             padding: LogicalSideOffsets::new_all_same(0.0),
             width: None,
             height: None,
@@ -46,6 +242,29 @@ impl Default for BlockValues {
     }
 }
 
+/// The height of each line of text, which is inherited by children.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LineHeight {
+    /// Lines are as tall as the font's ascent and descent.
+    Normal,
+    /// A multiple of the text size, which is applied to the text size
+    /// of each child rather than inherited as a length.
+    Multiple(f32),
+    Length(LogicalLength),
+}
+
+impl LineHeight {
+    /// The height in logical pixels for text of the given size, or
+    /// `None` to use the font's metrics.
+    pub fn resolve(&self, text_size: f32) -> Option<f32> {
+        match *self {
+            LineHeight::Normal => None,
+            LineHeight::Multiple(multiple) => Some(multiple * text_size),
+            LineHeight::Length(length) => Some(length.get()),
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum DisplayType {
     Inline(InlineValues),
@@ -57,10 +276,49 @@ pub struct ComputedValues {
     pub display: DisplayType,
     pub text_size: LogicalLength,
     pub text_color: Color,
+    /// A list of font families to draw text with, separated by commas,
+    /// such as `"Fira Code", monospace`. Characters missing from every
+    /// font in the list are drawn with the default font.
+    pub font_family: &'static str,
+    /// How heavy text is, from 100 to 900, where 400 is normal and 700
+    /// is bold. The nearest weight a font has is used.
+    pub font_weight: u32,
+    pub font_style: FontStyle,
+    pub line_height: LineHeight,
+    /// Extra space added after every character.
+    pub letter_spacing: LogicalLength,
+    pub text_direction: TextDirection,
+    pub word_break: WordBreak,
+    pub overflow_wrap: OverflowWrap,
+    pub white_space: WhiteSpace,
+    /// How far apart tab stops are, in spaces.
+    pub tab_size: u32,
+    /// Text laid out in more lines than this is cut off after them.
+    pub max_lines: Option<u32>,
+    pub text_overflow: TextOverflow,
+    /// Drawn in the text color.
+    pub text_decoration: TextDecoration,
+    /// Drawn behind selected text.
+    pub selection_color: Color,
     pub background_color: Color,
     pub border_radius: LogicalLength,
     pub border_thickness: LogicalSideOffsets,
     pub border_color: Color,
+    /// Only applies to scroll containers.
+    pub scrollbar: Scrollbar,
+    pub scrollbar_thumb_color: Color,
+    pub scrollbar_track_color: Color,
+    pub scrollbar_thickness: LogicalLength,
+    /// When set, changes to this element's layout are animated using
+    /// the given transition instead of happening instantly.
+    pub layout_transition: Option<Transition>,
+    /// Fades the element in when it's added after its window first
+    /// appears.
+    pub enter_transition: Option<Transition>,
+    /// Fades the element out when it's removed, by drawing it where it
+    /// was last laid out until the transition finishes.
+    pub exit_transition: Option<Transition>,
+    pub render_cache: RenderCache,
 }
 
 impl Default for ComputedValues {
@@ -69,14 +327,298 @@ impl Default for ComputedValues {
             display: DisplayType::Block(BlockValues::default()),
             text_size: LogicalLength::new(16.0),
             text_color: Color::black(),
+            font_family: "sans-serif",
+            font_weight: 400,
+            font_style: FontStyle::Normal,
+            line_height: LineHeight::Normal,
+            letter_spacing: LogicalLength::new(0.0),
+            text_direction: TextDirection::Ltr,
+            word_break: WordBreak::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            white_space: WhiteSpace::Normal,
+            tab_size: 8,
+            max_lines: None,
+            text_overflow: TextOverflow::Clip,
+            text_decoration: TextDecoration::None,
+            selection_color: Color::new(51, 144, 255, 96),
             background_color: Color::clear(),
             border_radius: LogicalLength::new(0.0),
             border_thickness: LogicalSideOffsets::new_all_same(0.0),
             border_color: Color::clear(),
+            scrollbar: Scrollbar::Overlay,
+            scrollbar_thumb_color: Color::new(0, 0, 0, 128),
+            scrollbar_track_color: Color::clear(),
+            scrollbar_thickness: LogicalLength::new(6.0),
+            layout_transition: None,
+            enter_transition: None,
+            exit_transition: None,
+            render_cache: RenderCache::None,
         }
     }
 }
 
+/// The computed values which children take from their parent instead
+/// of their element's defaults.
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct InheritedValues {
+    text_size: LogicalLength,
+    /// `None` at the root, where the element's default is used.
+    text_color: Option<Color>,
+    font_family: &'static str,
+    font_weight: u32,
+    font_style: FontStyle,
+    line_height: LineHeight,
+    letter_spacing: LogicalLength,
+    text_direction: TextDirection,
+    word_break: WordBreak,
+    overflow_wrap: OverflowWrap,
+    white_space: WhiteSpace,
+    tab_size: u32,
+    max_lines: Option<u32>,
+    text_overflow: TextOverflow,
+    text_decoration: TextDecoration,
+    selection_color: Color,
+}
+
+impl InheritedValues {
+    fn from_parent(parent: &ComputedValues) -> InheritedValues {
+        InheritedValues {
+            text_size: parent.text_size,
+            text_color: Some(parent.text_color),
+            font_family: parent.font_family,
+            font_weight: parent.font_weight,
+            font_style: parent.font_style,
+            line_height: parent.line_height,
+            letter_spacing: parent.letter_spacing,
+            text_direction: parent.text_direction,
+            word_break: parent.word_break,
+            overflow_wrap: parent.overflow_wrap,
+            white_space: parent.white_space,
+            tab_size: parent.tab_size,
+            max_lines: parent.max_lines,
+            text_overflow: parent.text_overflow,
+            text_decoration: parent.text_decoration,
+            selection_color: parent.selection_color,
+        }
+    }
+
+    fn root(text_settings: &TextSettings) -> InheritedValues {
+        let defaults = ComputedValues::default();
+        InheritedValues {
+            text_size: LogicalLength::new(text_settings.default_size * text_settings.scale),
+            text_color: None,
+            font_family: defaults.font_family,
+            font_weight: defaults.font_weight,
+            font_style: defaults.font_style,
+            line_height: defaults.line_height,
+            letter_spacing: defaults.letter_spacing,
+            text_direction: defaults.text_direction,
+            word_break: defaults.word_break,
+            overflow_wrap: defaults.overflow_wrap,
+            white_space: defaults.white_space,
+            tab_size: defaults.tab_size,
+            max_lines: defaults.max_lines,
+            text_overflow: defaults.text_overflow,
+            text_decoration: defaults.text_decoration,
+            selection_color: defaults.selection_color,
+        }
+    }
+}
+
+/// Holds the computed values of a node. Nodes with identical style
+/// inputs share a single allocation.
+#[derive(Default, Debug)]
+pub struct ComputedValuesCell(RefCell<Option<Arc<ComputedValues>>>);
+
+impl ComputedValuesCell {
+    pub fn get(&self) -> Option<Arc<ComputedValues>> {
+        self.0.borrow().clone()
+    }
+
+    pub fn set(&self, values: Option<Arc<ComputedValues>>) {
+        self.0.replace(values);
+    }
+}
+
+/// Controls the size of text across the whole application.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextSettings {
+    /// The text size of the root of each window, in logical pixels.
+    pub default_size: f32,
+    /// Multiplies every text size, so that users who have enlarged
+    /// text in their accessibility settings get a larger UI. Lengths
+    /// specified in `em` are scaled along with the text.
+    pub scale: f32,
+}
+
+impl Default for TextSettings {
+    fn default() -> TextSettings {
+        TextSettings {
+            default_size: 16.0,
+            scale: 1.0,
+        }
+    }
+}
+
+/// How far in from each edge of a window its content is covered by the
+/// system's chrome, in logical pixels, such as by a titlebar drawn over
+/// the content. Padding set to `safe_area` keeps content clear of it:
+///
+/// ```rs
+/// define_style! {
+///     static TOOLBAR = {
+///         padding: safe_area + 8 px,
+///     };
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SafeArea(pub LogicalSideOffsets);
+
+impl Default for SafeArea {
+    fn default() -> SafeArea {
+        SafeArea(LogicalSideOffsets::new_all_same(0.0))
+    }
+}
+
+/// How far a scroll container has been scrolled, and how far it can
+/// be scrolled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScrollPosition {
+    pub x: f32,
+    pub y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl ScrollPosition {
+    /// How far along the container has been scrolled vertically, from
+    /// 0 to 1.
+    pub fn progress_y(&self) -> f32 {
+        if self.max_y > 0.0 {
+            self.y / self.max_y
+        } else {
+            0.0
+        }
+    }
+
+    /// How far along the container has been scrolled horizontally, from
+    /// 0 to 1.
+    pub fn progress_x(&self) -> f32 {
+        if self.max_x > 0.0 {
+            self.x / self.max_x
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The current scroll positions of every named scroll container.
+#[derive(Clone, Debug, Default)]
+pub struct ScrollPositions(pub HashMap<String, ScrollPosition>);
+
+/// The ways the user is interacting with an element, which style
+/// definitions select with `:hover`, `:active` and `:focus`:
+///
+/// ```rs
+/// define_style! {
+///     static LINK = {
+///         text_color: rgb(0, 0, 238),
+///         if :hover { text_decoration: underline }
+///         if :active { text_color: rgb(238, 0, 0) }
+///     };
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Interaction {
+    /// The pointer is over the element, or one of its descendants.
+    pub hover: bool,
+    /// The element, or one of its descendants, was pressed and hasn't
+    /// been released yet.
+    pub active: bool,
+    /// The element was the innermost one under the last press. This
+    /// stands in for keyboard focus until there is one.
+    pub focus: bool,
+}
+
+impl ElementStates for Interaction {
+    fn has_state(&self, name: &str) -> bool {
+        match name {
+            "hover" => self.hover,
+            "active" => self.active,
+            "focus" => self.focus,
+            _ => false,
+        }
+    }
+}
+
+/// Which elements of a window the user is interacting with. Nodes are
+/// identified by their `topo::Id`, so that nodes which replace them on
+/// later revisions keep their state, and only nodes created with
+/// `mox!` can be in any of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interactions {
+    pub hovered: Vec<topo::Id>,
+    pub active: Vec<topo::Id>,
+    pub focused: Option<topo::Id>,
+}
+
+impl Interactions {
+    /// The states of the node with the given id.
+    pub fn of(&self, id: Option<topo::Id>) -> Interaction {
+        match id {
+            Some(id) => Interaction {
+                hover: self.hovered.contains(&id),
+                active: self.active.contains(&id),
+                focus: self.focused == Some(id),
+            },
+            None => Interaction::default(),
+        }
+    }
+}
+
+/// Binds an element's computed values to the scroll position of the
+/// scroll container with the given `scroll_name`. The function is
+/// re-run whenever the container scrolls, without a new revision of
+/// the application's components, which makes it suitable for parallax
+/// headers and shrinking toolbars.
+///
+/// ```rs
+/// fn shrink_title(scroll: &ScrollPosition, values: &mut ComputedValues) {
+///     values.text_size = LogicalLength::new(32.0 - 12.0 * scroll.progress_y());
+/// }
+///
+/// <span scroll_link={ScrollLink::new("feed", shrink_title)}>"Title"</span>
+/// ```
+#[derive(Clone, Copy)]
+pub struct ScrollLink {
+    pub source: &'static str,
+    pub apply: fn(&ScrollPosition, &mut ComputedValues),
+}
+
+impl ScrollLink {
+    pub fn new(
+        source: &'static str,
+        apply: fn(&ScrollPosition, &mut ComputedValues),
+    ) -> ScrollLink {
+        ScrollLink { source, apply }
+    }
+}
+
+impl PartialEq for ScrollLink {
+    fn eq(&self, other: &ScrollLink) -> bool {
+        self.source == other.source && self.apply as usize == other.apply as usize
+    }
+}
+
+impl std::fmt::Debug for ScrollLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ScrollLink")
+            .field("source", &self.source)
+            .field("apply", &"<fn(&ScrollPosition, &mut ComputedValues)>")
+            .finish()
+    }
+}
+
 pub struct SubStyle {
     pub selector: fn(NodeRef) -> bool,
     pub attributes: CommonAttributes,
@@ -124,52 +666,185 @@ impl PartialEq for Style {
 /// Used to annotate the node tree with computed values from styling.
 pub struct StyleEngine {
     runtime: Runtime<fn()>,
+    text_settings: TextSettings,
+    safe_area: SafeArea,
+    cache: Rc<RefCell<StyleCache>>,
 }
 
 impl StyleEngine {
     pub fn new() -> StyleEngine {
         StyleEngine {
             runtime: Runtime::new(StyleEngine::run_styling),
+            text_settings: TextSettings::default(),
+            safe_area: SafeArea::default(),
+            cache: Rc::new(RefCell::new(StyleCache::default())),
         }
     }
 
-    fn update_style(node: NodeRef, parent: Option<&ComputedValues>) {
-        let mut computed = node.create_computed_values();
+    pub fn cache_stats(&self) -> StyleCacheStats {
+        self.cache.borrow().stats()
+    }
+
+    pub fn set_text_settings(&mut self, text_settings: TextSettings) {
+        self.text_settings = text_settings;
+    }
+
+    pub fn set_safe_area(&mut self, safe_area: SafeArea) {
+        self.safe_area = safe_area;
+    }
 
-        if let Some(parent) = parent {
-            computed.text_size = parent.text_size;
-            computed.text_color = parent.text_color;
+    fn compute_values(
+        node: NodeRef,
+        style: Option<&'static StyleData>,
+        matched: u64,
+        inherited: &InheritedValues,
+    ) -> ComputedValues {
+        let mut computed = node.create_computed_values();
+        computed.text_size = inherited.text_size;
+        if let Some(text_color) = inherited.text_color {
+            computed.text_color = text_color;
         }
+        computed.font_family = inherited.font_family;
+        computed.font_weight = inherited.font_weight;
+        computed.font_style = inherited.font_style;
+        computed.line_height = inherited.line_height;
+        computed.letter_spacing = inherited.letter_spacing;
+        computed.text_direction = inherited.text_direction;
+        computed.word_break = inherited.word_break;
+        computed.overflow_wrap = inherited.overflow_wrap;
+        computed.white_space = inherited.white_space;
+        computed.tab_size = inherited.tab_size;
+        computed.max_lines = inherited.max_lines;
+        computed.text_overflow = inherited.text_overflow;
+        computed.text_decoration = inherited.text_decoration;
+        computed.selection_color = inherited.selection_color;
 
-        let style = node.style();
-        if let Some(Style(style)) = style {
+        if let Some(style) = style {
             style.attributes.apply(&mut computed);
-            for sub_style in style.sub_styles {
+            for (index, sub_style) in style.sub_styles.iter().enumerate().take(64) {
+                if matched & (1 << index) != 0 {
+                    sub_style.attributes.apply(&mut computed);
+                }
+            }
+        }
+
+        computed
+    }
+
+    /// Computes the values of a node and its descendants. Nodes whose
+    /// values changed are marked as needing layout, along with their
+    /// ancestors. Returns true if anything in the subtree needs layout.
+    fn update_style(
+        node: NodeRef,
+        parent: Option<&ComputedValues>,
+        scroll_positions: &ScrollPositions,
+        interactions: &Interactions,
+        text_settings: &TextSettings,
+        cache: &mut StyleCache,
+    ) -> bool {
+        let inherited = match parent {
+            Some(parent) => InheritedValues::from_parent(parent),
+            None => InheritedValues::root(text_settings),
+        };
+
+        // Sub styles select on these, so they're set before matching.
+        node.set_interaction(interactions.of(node.id()));
+        let style = node.style().map(|Style(style)| style);
+        let sub_styles = style.map(|style| style.sub_styles).unwrap_or(&[]);
+        let mut matched = 0u64;
+        for (index, sub_style) in sub_styles.iter().enumerate().take(64) {
+            if (sub_style.selector)(node) {
+                matched |= 1 << index;
+            }
+        }
+
+        let compute = || Self::compute_values(node, style, matched, &inherited);
+        let mut computed = if sub_styles.len() <= 64 {
+            let key = StyleKey::new(node.type_id(), style, matched, &inherited);
+            cache.get_or_insert_with(key, compute)
+        } else {
+            // Too many sub styles to fit in the key, so apply them all
+            // without caching.
+            let mut computed = Self::compute_values(node, style, 0, &inherited);
+            for sub_style in sub_styles {
                 if (sub_style.selector)(node) {
                     sub_style.attributes.apply(&mut computed);
                 }
             }
+            Arc::new(computed)
+        };
+
+        if let Some(link) = node.scroll_link() {
+            if let Some(position) = scroll_positions.0.get(link.source) {
+                (link.apply)(position, Arc::make_mut(&mut computed));
+            }
         }
 
-        node.computed_values().set(Some(computed));
+        let changed = match node.computed_values().get() {
+            Some(previous) => *previous != *computed,
+            None => true,
+        };
+        node.computed_values().set(Some(computed.clone()));
 
+        let mut dirty = changed || node.layout_cell().is_dirty();
         for child in node.children() {
             if let DynamicNode::Node(node) = child {
-                Self::update_style(node, Some(&computed));
+                dirty |= Self::update_style(
+                    node,
+                    Some(&*computed),
+                    scroll_positions,
+                    interactions,
+                    text_settings,
+                    cache,
+                );
             }
         }
+        if dirty {
+            node.layout_cell().mark_dirty();
+        }
+        dirty
     }
 
-    #[illicit::from_env(node: &Node<Window>)]
+    #[illicit::from_env(
+        node: &Node<Window>,
+        size: &LogicalSize,
+        scroll_positions: &ScrollPositions,
+        interactions: &Interactions,
+        text_settings: &TextSettings,
+        safe_area: &SafeArea,
+        cache: &Rc<RefCell<StyleCache>>
+    )]
     fn run_styling() {
-        Self::update_style(node.into(), None);
+        let mut cache = cache.borrow_mut();
+        cache.begin(*size, *text_settings, *safe_area);
+        Self::update_style(
+            node.into(),
+            None,
+            scroll_positions,
+            interactions,
+            text_settings,
+            &mut cache,
+        );
     }
 
-    /// Update the node tree with computed values.
-    pub fn update(&mut self, node: Node<Window>, size: LogicalSize) {
+    /// Update the node tree with computed values. Styles which depend
+    /// on `interactions` are recomputed when it changes, without a new
+    /// revision of the DOM.
+    pub fn update(
+        &mut self,
+        node: Node<Window>,
+        size: LogicalSize,
+        scroll_positions: ScrollPositions,
+        interactions: Interactions,
+    ) {
         illicit::child_env!(
             Node<Window> => node,
-            LogicalSize => size
+            LogicalSize => size,
+            ScrollPositions => scroll_positions,
+            Interactions => interactions,
+            TextSettings => self.text_settings,
+            SafeArea => self.safe_area,
+            Rc<RefCell<StyleCache>> => self.cache.clone()
         )
         .enter(|| topo::call!(self.runtime.run_once()))
     }
@@ -0,0 +1,127 @@
+//! Loads data on a background thread, so that a component can show a
+//! placeholder until it arrives instead of keeping an `Option` of the
+//! data in state and filling it in by hand.
+//!
+//! `suspend!` starts loading the first time it runs, and again whenever
+//! its inputs change. Results are handed back through an
+//! `update_channel`, so they're committed like any other update, and a
+//! result for inputs which have since changed is never shown.
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn profile(user: UserId) -> Node<View> {
+//!     let details = suspend!(user, |user| fetch_profile(user));
+//!     details.show(
+//!         || mox! { <view><span>"Loading..."</span></view> },
+//!         |details| mox! { <view><span>{% "{}", details.name}</span></view> },
+//!     )
+//! }
+//! ```
+
+use crate::runtime::{update_channel, UpdateSender};
+use moxie::Key;
+use std::rc::Rc;
+use std::thread;
+
+/// The state of data being loaded by `suspend!`.
+#[derive(Debug)]
+pub enum Load<T> {
+    Pending,
+    Ready(Rc<T>),
+}
+
+impl<T> Clone for Load<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Load::Pending => Load::Pending,
+            Load::Ready(value) => Load::Ready(value.clone()),
+        }
+    }
+}
+
+impl<T> Load<T> {
+    /// Returns the data, if it has been loaded.
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Load::Pending => None,
+            Load::Ready(value) => Some(value),
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.ready().is_none()
+    }
+
+    /// Creates the placeholder while the data is loading, and the
+    /// content once it's ready.
+    pub fn show<R>(&self, placeholder: impl FnOnce() -> R, content: impl FnOnce(&T) -> R) -> R {
+        match self {
+            Load::Pending => placeholder(),
+            Load::Ready(value) => content(value),
+        }
+    }
+}
+
+/// The latest result, along with the inputs it was loaded for.
+#[doc(hidden)]
+pub type Loaded<I, T> = Option<(I, Rc<T>)>;
+
+/// Creates the channel results are sent back through. Used by
+/// `suspend!`.
+#[doc(hidden)]
+pub fn channel<I, T>(result: &Key<Loaded<I, T>>) -> UpdateSender<(I, T)>
+where
+    I: Send + 'static,
+    T: Send + 'static,
+{
+    let result = result.clone();
+    update_channel(move |(inputs, value)| {
+        result.update(|_| Some(Some((inputs, Rc::new(value)))));
+    })
+}
+
+/// Loads the data for `inputs` on a new thread. Used by `suspend!`.
+#[doc(hidden)]
+pub fn spawn<I, T>(
+    inputs: I,
+    sender: UpdateSender<(I, T)>,
+    load: impl FnOnce(I) -> T + Send + 'static,
+) where
+    I: Clone + Send + 'static,
+    T: Send + 'static,
+{
+    thread::spawn(move || {
+        let value = load(inputs.clone());
+        let _ = sender.send((inputs, value));
+    });
+}
+
+/// Whether the latest result was loaded for the current inputs. Used by
+/// `suspend!`.
+#[doc(hidden)]
+pub fn current<I, T>(inputs: &I, result: &Key<Loaded<I, T>>) -> Load<T>
+where
+    I: PartialEq,
+{
+    match **result {
+        Some((ref loaded_for, ref value)) if loaded_for == inputs => Load::Ready(value.clone()),
+        _ => Load::Pending,
+    }
+}
+
+/// Loads data on a background thread with `load`, which is given
+/// `inputs`, and returns a `Load` which is pending until it finishes.
+/// Loading starts again whenever `inputs` changes, and the data is
+/// pending again until the new result arrives.
+#[macro_export]
+macro_rules! suspend {
+    ($inputs:expr, $load:expr) => {{
+        let inputs = $inputs;
+        let result = $crate::prelude::state!(|| None);
+        let sender = $crate::prelude::once!(|| $crate::suspense::channel(&result));
+        $crate::prelude::memo!(inputs.clone(), |inputs| {
+            $crate::suspense::spawn(inputs.clone(), sender.clone(), $load)
+        });
+        $crate::suspense::current(&inputs, &result)
+    }};
+}
@@ -0,0 +1,86 @@
+//! Lets components react to changes in the system the application is
+//! running on.
+//!
+//! A revision is run whenever the system clock jumps, such as when the
+//! user changes the time or time zone, or the computer wakes from
+//! sleep, so components showing the time can refresh it by reading
+//! `time_changes` as part of a memo's key:
+//!
+//! ```rs
+//! #[topo::nested]
+//! fn clock() -> Node<Span> {
+//!     let now = memo!(time_changes(), |_| Local::now());
+//!     // ...
+//! }
+//! ```
+//!
+//! Jumps are only noticed once the event loop wakes, so an application
+//! which is otherwise waiting on nothing sees them with its next event.
+//!
+//! winit doesn't report power saving, session locking, or the system
+//! theme changing, so those can't be followed yet.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How far the system clock can drift from the monotonic clock between
+/// two checks without counting as a change.
+const TOLERANCE: Duration = Duration::from_secs(1);
+
+/// Follows the system clock by comparing it with the monotonic clock,
+/// which isn't affected by the time being changed.
+#[derive(Debug, Default)]
+pub(crate) struct SystemState {
+    time_changes: Cell<u64>,
+    last_check: Cell<Option<(Instant, SystemTime)>>,
+}
+
+impl SystemState {
+    /// Returns true if the system clock jumped since the last check.
+    pub(crate) fn check_clock(&self, instant: Instant, time: SystemTime) -> bool {
+        let last = self.last_check.replace(Some((instant, time)));
+        let (last_instant, last_time) = match last {
+            Some(last) => last,
+            None => return false,
+        };
+        let elapsed = instant.duration_since(last_instant);
+        let drift = match time.duration_since(last_time) {
+            Ok(time_elapsed) if time_elapsed > elapsed => time_elapsed - elapsed,
+            Ok(time_elapsed) => elapsed - time_elapsed,
+            // The clock went backwards.
+            Err(err) => elapsed + err.duration(),
+        };
+        let changed = drift > TOLERANCE;
+        if changed {
+            self.time_changes.set(self.time_changes.get() + 1);
+        }
+        changed
+    }
+}
+
+/// The number of times the system clock has jumped since the
+/// application started. Anything derived from the current time should
+/// be recomputed when this changes.
+pub fn time_changes() -> u64 {
+    illicit::Env::expect::<Rc<SystemState>>().time_changes.get()
+}
+
+#[cfg(test)]
+mod test {
+    use super::SystemState;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn notices_clock_jumps() {
+        let state = SystemState::default();
+        let instant = Instant::now();
+        let time = SystemTime::now();
+        let secs = Duration::from_secs;
+        assert!(!state.check_clock(instant, time));
+        assert!(!state.check_clock(instant + secs(5), time + secs(5)));
+        assert!(state.check_clock(instant + secs(10), time + secs(3600)));
+        assert!(state.check_clock(instant + secs(15), time));
+        assert_eq!(state.time_changes.get(), 2);
+    }
+}
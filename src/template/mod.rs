@@ -0,0 +1,306 @@
+//! Runtime templates, which build DOM subtrees from an XML-like string
+//! instead of the mox! macro. This lets the structure of a view be
+//! tweaked without recompiling.
+//!
+//! ```rs
+//! let template = Template::parse(r#"
+//!     <view style="CARD">
+//!         <span>Hello, {name}!</span>
+//!         <button on_click="greet"><span>Greet</span></button>
+//!     </view>
+//! "#)?;
+//! let bindings = Bindings::new()
+//!     .style("CARD", CARD_STYLE)
+//!     .value("name", "moxie")
+//!     .handler("greet", |_| println!("hi"));
+//! let node: Node<View> = template.instantiate(&bindings)?;
+//! ```
+//!
+//! Templates only describe structure. Styles and event handlers are
+//! defined in Rust and referenced by name, while `{name}` references in
+//! text and attribute values are substituted from the bindings.
+
+use crate::dom::element::{Element, HasAttribute, HasEvent};
+use crate::dom::elements::{button::ButtonChild, span::SpanChild, view::ViewChild};
+use crate::dom::{AttrStyle, Button, ClickEvent, Node, Span, View};
+use crate::style::Style;
+use crate::util::event_handler::EventHandler;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+mod parser;
+
+pub use parser::ParseError;
+use parser::{TemplateNode, TextPart};
+
+/// Errors that can occur while loading or instantiating a template.
+#[derive(Debug)]
+pub enum TemplateError {
+    Io(std::io::Error),
+    Parse(ParseError),
+    UnknownElement(String),
+    UnknownAttribute {
+        element: String,
+        attribute: String,
+    },
+    UnknownBinding(String),
+    UnknownStyle(String),
+    UnknownHandler(String),
+    /// Text was placed directly inside an element other than `<span>`.
+    UnexpectedText(String),
+    /// The element can't be parented to the element it's inside of.
+    InvalidChild {
+        parent: String,
+        child: String,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Io(err) => write!(fmt, "Failed to read template: {}", err),
+            TemplateError::Parse(err) => write!(fmt, "Failed to parse template: {}", err),
+            TemplateError::UnknownElement(name) => write!(fmt, "Unknown element <{}>", name),
+            TemplateError::UnknownAttribute { element, attribute } => {
+                write!(fmt, "Unknown attribute {} on <{}>", attribute, element)
+            }
+            TemplateError::UnknownBinding(name) => write!(fmt, "No value bound to {{{}}}", name),
+            TemplateError::UnknownStyle(name) => write!(fmt, "No style named {}", name),
+            TemplateError::UnknownHandler(name) => write!(fmt, "No handler named {}", name),
+            TemplateError::UnexpectedText(parent) => {
+                write!(fmt, "Text is not allowed inside of <{}>", parent)
+            }
+            TemplateError::InvalidChild { parent, child } => {
+                write!(fmt, "<{}> can't be a child of <{}>", child, parent)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for TemplateError {
+    fn from(err: std::io::Error) -> Self {
+        TemplateError::Io(err)
+    }
+}
+
+impl From<ParseError> for TemplateError {
+    fn from(err: ParseError) -> Self {
+        TemplateError::Parse(err)
+    }
+}
+
+/// The named values, styles and handlers that a template can refer to.
+#[derive(Default)]
+pub struct Bindings {
+    values: HashMap<String, String>,
+    styles: HashMap<String, Style>,
+    handlers: HashMap<String, EventHandler<ClickEvent>>,
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Default::default()
+    }
+
+    /// Bind a value which is substituted for `{name}` in text and
+    /// attribute values.
+    pub fn value(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.values.insert(name.into(), value.to_string());
+        self
+    }
+
+    /// Make a style available to `style="name"` attributes.
+    pub fn style(mut self, name: impl Into<String>, style: Style) -> Self {
+        self.styles.insert(name.into(), style);
+        self
+    }
+
+    /// Make a click handler available to `on_click="name"` attributes.
+    pub fn handler(
+        mut self,
+        name: impl Into<String>,
+        func: impl FnMut(&ClickEvent) + 'static,
+    ) -> Self {
+        self.handlers
+            .insert(name.into(), EventHandler::with_func(func));
+        self
+    }
+
+    fn resolve(&self, parts: &[TextPart]) -> Result<String, TemplateError> {
+        let mut result = String::new();
+        for part in parts {
+            match part {
+                TextPart::Literal(text) => result.push_str(text),
+                TextPart::Binding(name) => match self.values.get(name) {
+                    Some(value) => result.push_str(value),
+                    None => return Err(TemplateError::UnknownBinding(name.clone())),
+                },
+            }
+        }
+        Ok(result)
+    }
+
+    fn get_style(&self, name: &str) -> Result<Style, TemplateError> {
+        self.styles
+            .get(name)
+            .copied()
+            .ok_or_else(|| TemplateError::UnknownStyle(name.to_owned()))
+    }
+
+    fn get_handler(&self, name: &str) -> Result<EventHandler<ClickEvent>, TemplateError> {
+        self.handlers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| TemplateError::UnknownHandler(name.to_owned()))
+    }
+}
+
+/// A parsed template, which can be instantiated any number of times.
+#[derive(Clone, Debug)]
+pub struct Template {
+    root: TemplateNode,
+}
+
+impl Template {
+    /// Parse a template from a string. The template must contain a
+    /// single `<view>` root element.
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        Ok(Template {
+            root: parser::parse(source)?,
+        })
+    }
+
+    /// Read and parse a template from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Template, TemplateError> {
+        let source = std::fs::read_to_string(path)?;
+        Template::parse(&source)
+    }
+
+    /// Build the DOM described by this template.
+    pub fn instantiate(&self, bindings: &Bindings) -> Result<Node<View>, TemplateError> {
+        match self.root {
+            TemplateNode::Element {
+                ref name,
+                ref attributes,
+                ref children,
+            } if name == View::ELEMENT_NAME => build_view(attributes, children, bindings),
+            TemplateNode::Element { ref name, .. } => Err(TemplateError::InvalidChild {
+                parent: "template".to_owned(),
+                child: name.clone(),
+            }),
+            TemplateNode::Text(_) => Err(TemplateError::UnexpectedText("template".to_owned())),
+        }
+    }
+}
+
+type Attributes = [(String, Vec<TextPart>)];
+
+fn apply_style<Elt>(
+    element: &mut Elt,
+    attributes: &Attributes,
+    bindings: &Bindings,
+) -> Result<(), TemplateError>
+where
+    Elt: Element + HasAttribute<AttrStyle>,
+{
+    for (name, value) in attributes {
+        match &name[..] {
+            "style" => {
+                let style = bindings.get_style(&bindings.resolve(value)?)?;
+                element.set_attribute(Some(style));
+            }
+            "on_click" if Elt::ELEMENT_NAME == Button::ELEMENT_NAME => (),
+            _ => {
+                return Err(TemplateError::UnknownAttribute {
+                    element: Elt::ELEMENT_NAME.to_owned(),
+                    attribute: name.clone(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds a child that can appear inside of any container element.
+fn build_child<Child>(
+    parent: &str,
+    node: &TemplateNode,
+    bindings: &Bindings,
+) -> Result<Child, TemplateError>
+where
+    Child: From<Node<View>> + From<Node<Span>> + From<Node<Button>>,
+{
+    match node {
+        TemplateNode::Element {
+            name,
+            attributes,
+            children,
+        } => match &name[..] {
+            "view" => Ok(build_view(attributes, children, bindings)?.into()),
+            "span" => Ok(build_span(attributes, children, bindings)?.into()),
+            "button" => Ok(build_button(attributes, children, bindings)?.into()),
+            "app" | "window" => Err(TemplateError::InvalidChild {
+                parent: parent.to_owned(),
+                child: name.clone(),
+            }),
+            _ => Err(TemplateError::UnknownElement(name.clone())),
+        },
+        TemplateNode::Text(_) => Err(TemplateError::UnexpectedText(parent.to_owned())),
+    }
+}
+
+fn build_view(
+    attributes: &Attributes,
+    children: &[TemplateNode],
+    bindings: &Bindings,
+) -> Result<Node<View>, TemplateError> {
+    let mut element = View::default();
+    apply_style(&mut element, attributes, bindings)?;
+    let children = children
+        .iter()
+        .map(|child| build_child::<ViewChild>(View::ELEMENT_NAME, child, bindings))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Node::new(element, children))
+}
+
+fn build_span(
+    attributes: &Attributes,
+    children: &[TemplateNode],
+    bindings: &Bindings,
+) -> Result<Node<Span>, TemplateError> {
+    let mut element = Span::default();
+    apply_style(&mut element, attributes, bindings)?;
+    let children = children
+        .iter()
+        .map(|child| match child {
+            TemplateNode::Text(parts) => bindings.resolve(parts).map(SpanChild::from),
+            _ => build_child::<SpanChild>(Span::ELEMENT_NAME, child, bindings),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Node::new(element, children))
+}
+
+fn build_button(
+    attributes: &Attributes,
+    children: &[TemplateNode],
+    bindings: &Bindings,
+) -> Result<Node<Button>, TemplateError> {
+    let mut element = Button::default();
+    apply_style(&mut element, attributes, bindings)?;
+    let children = children
+        .iter()
+        .map(|child| build_child::<ButtonChild>(Button::ELEMENT_NAME, child, bindings))
+        .collect::<Result<Vec<_>, _>>()?;
+    let node = Node::new(element, children);
+    for (name, value) in attributes {
+        if name == "on_click" {
+            let handler = bindings.get_handler(&bindings.resolve(value)?)?;
+            <Button as HasEvent<ClickEvent>>::set_handler(
+                &mut *node.handlers().borrow_mut(),
+                handler,
+            );
+        }
+    }
+    Ok(node)
+}
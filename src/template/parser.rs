@@ -0,0 +1,333 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A piece of text or attribute value, which may reference a binding
+/// using `{name}` syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextPart {
+    Literal(String),
+    Binding(String),
+}
+
+/// One node of a parsed template, before it has been turned into DOM
+/// nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateNode {
+    Element {
+        name: String,
+        attributes: Vec<(String, Vec<TextPart>)>,
+        children: Vec<TemplateNode>,
+    },
+    Text(Vec<TextPart>),
+}
+
+/// Describes where and why a template failed to parse.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    iter: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn offset(&mut self) -> usize {
+        match self.iter.peek() {
+            Some(&(index, _)) => index,
+            None => self.source.len(),
+        }
+    }
+
+    fn error<T>(&mut self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            offset: self.offset(),
+            message: message.into(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.iter.peek().map(|&(_, ch)| ch)
+    }
+
+    fn starts_with(&mut self, pattern: &str) -> bool {
+        let offset = self.offset();
+        self.source[offset..].starts_with(pattern)
+    }
+
+    fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.iter.next();
+        }
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), ParseError> {
+        if self.peek() == Some(ch) {
+            self.iter.next();
+            Ok(())
+        } else {
+            self.error(format!("Expected '{}'", ch))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.iter.next();
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), ParseError> {
+        self.skip("<!--".len());
+        loop {
+            if self.starts_with("-->") {
+                self.skip("-->".len());
+                return Ok(());
+            }
+            if self.iter.next().is_none() {
+                return self.error("Unterminated comment");
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let mut name = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_alphanumeric() || ch == '_' || ch == '-' {
+                name.push(ch);
+                self.iter.next();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            self.error("Expected a name")
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn parse_entity(&mut self) -> Result<char, ParseError> {
+        self.expect('&')?;
+        let mut name = String::new();
+        loop {
+            match self.iter.next() {
+                Some((_, ';')) => break,
+                Some((_, ch)) => name.push(ch),
+                None => return self.error("Unterminated entity"),
+            }
+        }
+        match &name[..] {
+            "lt" => Ok('<'),
+            "gt" => Ok('>'),
+            "amp" => Ok('&'),
+            "quot" => Ok('"'),
+            "apos" => Ok('\''),
+            "lbrace" => Ok('{'),
+            "rbrace" => Ok('}'),
+            _ => self.error(format!("Unknown entity &{};", name)),
+        }
+    }
+
+    /// Parses text until `end` is seen (which is not consumed), splitting
+    /// out `{binding}` references.
+    fn parse_text(&mut self, end: char) -> Result<Vec<TextPart>, ParseError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        while let Some(ch) = self.peek() {
+            if ch == end {
+                break;
+            }
+            match ch {
+                '&' => literal.push(self.parse_entity()?),
+                '{' => {
+                    self.iter.next();
+                    self.skip_whitespace();
+                    let name = self.parse_name()?;
+                    self.skip_whitespace();
+                    self.expect('}')?;
+                    if !literal.is_empty() {
+                        parts.push(TextPart::Literal(std::mem::replace(
+                            &mut literal,
+                            String::new(),
+                        )));
+                    }
+                    parts.push(TextPart::Binding(name));
+                }
+                '}' => return self.error("Unmatched '}'"),
+                _ => {
+                    literal.push(ch);
+                    self.iter.next();
+                }
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TextPart::Literal(literal));
+        }
+        Ok(parts)
+    }
+
+    fn parse_attribute(&mut self) -> Result<(String, Vec<TextPart>), ParseError> {
+        let name = self.parse_name()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        self.expect('"')?;
+        let value = self.parse_text('"')?;
+        self.expect('"')?;
+        Ok((name, value))
+    }
+
+    fn parse_element(&mut self) -> Result<TemplateNode, ParseError> {
+        self.expect('<')?;
+        let name = self.parse_name()?;
+        let mut attributes = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('/') => {
+                    self.iter.next();
+                    self.expect('>')?;
+                    return Ok(TemplateNode::Element {
+                        name,
+                        attributes,
+                        children: vec![],
+                    });
+                }
+                Some('>') => {
+                    self.iter.next();
+                    break;
+                }
+                Some(_) => attributes.push(self.parse_attribute()?),
+                None => return self.error("Unexpected end of template"),
+            }
+        }
+
+        let children = self.parse_children()?;
+
+        self.expect('<')?;
+        self.expect('/')?;
+        let close = self.parse_name()?;
+        if close != name {
+            return self.error(format!("Expected </{}>, found </{}>", name, close));
+        }
+        self.skip_whitespace();
+        self.expect('>')?;
+
+        Ok(TemplateNode::Element {
+            name,
+            attributes,
+            children,
+        })
+    }
+
+    fn parse_children(&mut self) -> Result<Vec<TemplateNode>, ParseError> {
+        let mut children = vec![];
+        loop {
+            if self.starts_with("</") || self.peek().is_none() {
+                return Ok(children);
+            }
+            if self.starts_with("<!--") {
+                self.skip_comment()?;
+            } else if self.starts_with("<") {
+                children.push(self.parse_element()?);
+            } else {
+                let parts = self.parse_text('<')?;
+                let is_blank = parts.iter().all(|part| match part {
+                    TextPart::Literal(text) => text.trim().is_empty(),
+                    TextPart::Binding(_) => false,
+                });
+                if !is_blank {
+                    children.push(TemplateNode::Text(parts));
+                }
+            }
+        }
+    }
+}
+
+/// Parses a template consisting of a single root element. Whitespace
+/// only text is discarded, and comments are skipped.
+pub fn parse(source: &str) -> Result<TemplateNode, ParseError> {
+    let mut parser = Parser {
+        source,
+        iter: source.char_indices().peekable(),
+    };
+    let mut roots = parser.parse_children()?;
+    if parser.peek().is_some() {
+        return parser.error("Unexpected closing tag");
+    }
+    let root = match roots.len() {
+        1 => roots.remove(0),
+        _ => return parser.error("Expected exactly one root element"),
+    };
+    match root {
+        TemplateNode::Element { .. } => Ok(root),
+        TemplateNode::Text(_) => parser.error("Expected an element at the root"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, TemplateNode, TextPart};
+
+    #[test]
+    fn nested_elements() {
+        let root =
+            parse("<view style=\"ROW\"> <span>Hello, {name}!</span> <view/> </view>").unwrap();
+        let expect = TemplateNode::Element {
+            name: "view".to_owned(),
+            attributes: vec![(
+                "style".to_owned(),
+                vec![TextPart::Literal("ROW".to_owned())],
+            )],
+            children: vec![
+                TemplateNode::Element {
+                    name: "span".to_owned(),
+                    attributes: vec![],
+                    children: vec![TemplateNode::Text(vec![
+                        TextPart::Literal("Hello, ".to_owned()),
+                        TextPart::Binding("name".to_owned()),
+                        TextPart::Literal("!".to_owned()),
+                    ])],
+                },
+                TemplateNode::Element {
+                    name: "view".to_owned(),
+                    attributes: vec![],
+                    children: vec![],
+                },
+            ],
+        };
+        assert_eq!(root, expect);
+    }
+
+    #[test]
+    fn entities_and_comments() {
+        let root = parse("<span><!-- note -->&lt;&lbrace;x&rbrace;&gt;</span>").unwrap();
+        let expect = TemplateNode::Element {
+            name: "span".to_owned(),
+            attributes: vec![],
+            children: vec![TemplateNode::Text(vec![TextPart::Literal(
+                "<{x}>".to_owned(),
+            )])],
+        };
+        assert_eq!(root, expect);
+    }
+
+    #[test]
+    fn mismatched_close() {
+        assert!(parse("<view><span></view></span>").is_err());
+        assert!(parse("<view></view><view></view>").is_err());
+        assert!(parse("<view>").is_err());
+    }
+}
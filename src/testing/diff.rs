@@ -0,0 +1,108 @@
+//! A perceptual image comparison, based on the YIQ color difference
+//! used by pixelmatch.
+
+/// The largest possible value of `color_delta`.
+const MAX_DELTA: f32 = 35215.0;
+
+/// The color of mismatched pixels in the diff image.
+const MISMATCH_COLOR: [u8; 4] = [255, 0, 0, 255];
+
+/// How strongly matching pixels show through in the diff image.
+const FADE: f32 = 0.1;
+
+/// The result of comparing two images.
+pub struct Diff {
+    /// How many pixels differ by more than the threshold.
+    pub mismatched: usize,
+    /// An RGBA image the same size as the inputs, with matching pixels
+    /// faded to grey and mismatched ones in red.
+    pub image: Vec<u8>,
+}
+
+/// Blends a color channel onto a white background.
+fn blend(channel: f32, alpha: f32) -> f32 {
+    255.0 + (channel - 255.0) * alpha
+}
+
+/// Converts an RGBA pixel to YIQ, on a white background.
+fn yiq(pixel: &[u8]) -> (f32, f32, f32) {
+    let alpha = pixel[3] as f32 / 255.0;
+    let r = blend(pixel[0] as f32, alpha);
+    let g = blend(pixel[1] as f32, alpha);
+    let b = blend(pixel[2] as f32, alpha);
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23,
+        r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_89,
+        r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+    )
+}
+
+/// The squared perceptual distance between two RGBA pixels.
+fn color_delta(a: &[u8], b: &[u8]) -> f32 {
+    if a == b {
+        return 0.0;
+    }
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+/// Compares two RGBA images of the same size. A pixel is mismatched if
+/// its perceptual difference is above `threshold`, which ranges from 0
+/// for an exact match to 1 for any difference at all being accepted.
+pub fn diff(expected: &[u8], actual: &[u8], threshold: f32) -> Diff {
+    assert_eq!(expected.len(), actual.len(), "images differ in size");
+    let max_delta = MAX_DELTA * threshold * threshold;
+    let mut mismatched = 0;
+    let mut image = Vec::with_capacity(expected.len());
+    for (expected, actual) in expected.chunks(4).zip(actual.chunks(4)) {
+        if color_delta(expected, actual) > max_delta {
+            mismatched += 1;
+            image.extend_from_slice(&MISMATCH_COLOR);
+        } else {
+            let (y, _, _) = yiq(expected);
+            let grey = blend(y, FADE * expected[3] as f32 / 255.0) as u8;
+            image.extend_from_slice(&[grey, grey, grey, 255]);
+        }
+    }
+    Diff { mismatched, image }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, MISMATCH_COLOR};
+
+    #[test]
+    fn identical() {
+        let image = [10, 20, 30, 255, 200, 100, 0, 128];
+        let result = diff(&image, &image, 0.0);
+        assert_eq!(result.mismatched, 0);
+        assert_eq!(result.image.len(), image.len());
+    }
+
+    #[test]
+    fn small_differences_pass() {
+        let expected = [100, 100, 100, 255];
+        let actual = [102, 101, 100, 255];
+        assert_eq!(diff(&expected, &actual, 0.1).mismatched, 0);
+        assert_eq!(diff(&expected, &actual, 0.0).mismatched, 1);
+    }
+
+    #[test]
+    fn marks_mismatches() {
+        let expected = [255, 0, 0, 255, 0, 0, 0, 255];
+        let actual = [0, 0, 255, 255, 0, 0, 0, 255];
+        let result = diff(&expected, &actual, 0.1);
+        assert_eq!(result.mismatched, 1);
+        assert_eq!(result.image[..4], MISMATCH_COLOR);
+        assert_ne!(result.image[4..], MISMATCH_COLOR);
+    }
+
+    #[test]
+    fn transparent_is_white() {
+        let expected = [255, 255, 255, 255];
+        let actual = [0, 0, 0, 0];
+        assert_eq!(diff(&expected, &actual, 0.0).mismatched, 0);
+    }
+}
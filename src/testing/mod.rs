@@ -0,0 +1,155 @@
+//! Golden image tests, which render a component tree offscreen and
+//! compare it against a PNG checked into the repository.
+//!
+//! ```rs
+//! #[test]
+//! fn card() {
+//!     let image = render(|| card_app!(), 320.0, 240.0, 2.0);
+//!     assert_golden(&image, "tests/golden/card.png", 0.1);
+//! }
+//! ```
+//!
+//! A missing golden image fails the test, so that one which was never
+//! checked in isn't quietly recreated on CI. Run the tests with
+//! `UPDATE_GOLDENS=1` in the environment to write new golden images, or
+//! to overwrite them all after an intended change.
+//!
+//! Nodes can be found by their text and accessibility attributes with
+//! the queries in this module, like `find_by_label`.
+
+mod diff;
+mod query;
+
+pub use query::{find_all_by_role, find_by_label, find_by_text};
+
+use crate::dom::{App, Node};
+use crate::Runtime;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// An RGBA image, with rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+fn invalid_data(err: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+impl Image {
+    /// Reads an 8-bit RGBA PNG.
+    pub fn load(path: &Path) -> io::Result<Image> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let (info, mut reader) = decoder.read_info().map_err(invalid_data)?;
+        if info.color_type != png::ColorType::RGBA || info.bit_depth != png::BitDepth::Eight {
+            return Err(invalid_data("expected an 8-bit RGBA image"));
+        }
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).map_err(invalid_data)?;
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(invalid_data)?;
+        writer.write_image_data(&self.pixels).map_err(invalid_data)
+    }
+}
+
+/// Renders the first window of the application without opening it.
+/// The size is in logical pixels, and the image is `dpi_scale` times
+/// larger.
+pub fn render(
+    root: impl FnMut() -> Node<App> + 'static,
+    width: f32,
+    height: f32,
+    dpi_scale: f32,
+) -> Image {
+    let pixels = Runtime::new(root)
+        .render_headless(width, height, dpi_scale)
+        .into_iter()
+        .next()
+        .expect("the application has no windows");
+    Image {
+        width: (width * dpi_scale).round() as u32,
+        height: (height * dpi_scale).round() as u32,
+        pixels,
+    }
+}
+
+/// Compares the image against the golden image at `path`, and panics
+/// if any pixel's perceptual difference is above `threshold`, which
+/// ranges from 0 to 1. On failure, the rendered image is written next
+/// to the golden one with the extension `.actual.png`, along with a
+/// `.diff.png` highlighting the differences in red. If the golden image
+/// doesn't exist, the rendered image is written to `.actual.png` too.
+pub fn assert_golden(image: &Image, path: impl AsRef<Path>, threshold: f32) {
+    let path = path.as_ref();
+    let actual_path = path.with_extension("actual.png");
+    let diff_path = path.with_extension("diff.png");
+
+    if env::var("UPDATE_GOLDENS").map_or(false, |update| update == "1") {
+        image.save(path).unwrap();
+        return;
+    }
+    if !path.exists() {
+        image.save(&actual_path).unwrap();
+        panic!(
+            "{} doesn't exist. The rendered image was written to {}; run with UPDATE_GOLDENS=1 to accept it",
+            path.display(),
+            actual_path.display()
+        );
+    }
+    let golden = Image::load(path)
+        .unwrap_or_else(|err| panic!("failed to load {}: {}", path.display(), err));
+
+    if (golden.width, golden.height) != (image.width, image.height) {
+        image.save(&actual_path).unwrap();
+        panic!(
+            "{} is {}x{}, but the rendered image is {}x{}. It was written to {}",
+            path.display(),
+            golden.width,
+            golden.height,
+            image.width,
+            image.height,
+            actual_path.display()
+        );
+    }
+
+    let diff = diff::diff(&golden.pixels, &image.pixels, threshold);
+    if diff.mismatched > 0 {
+        image.save(&actual_path).unwrap();
+        let diff_image = Image {
+            width: image.width,
+            height: image.height,
+            pixels: diff.image,
+        };
+        diff_image.save(&diff_path).unwrap();
+        panic!(
+            "{} pixels differ from {}. The rendered image was written to {}, and the differences to {}",
+            diff.mismatched,
+            path.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+
+    // Clean up after an earlier failure.
+    let _ = fs::remove_file(actual_path);
+    let _ = fs::remove_file(diff_path);
+}
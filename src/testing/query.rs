@@ -0,0 +1,120 @@
+use crate::dom::element::DynamicNode;
+use crate::dom::node::AnyNode;
+use crate::dom::Role;
+
+/// Finds the first node in `root`, in document order, whose `label`
+/// attribute is `label`. This finds elements the way a screen reader
+/// user would, so tests don't break when the markup around them
+/// changes.
+///
+/// ```rs
+/// let save = find_by_label(&root, "Save").expect("no save button");
+/// assert_eq!(save.role(), Role::Button);
+/// ```
+pub fn find_by_label(root: &AnyNode, label: &str) -> Option<AnyNode> {
+    find(root, &|node| node.label() == Some(label))
+}
+
+/// Finds the first node in `root`, in document order, whose own text is
+/// `text`. Text in the node's children isn't included, so this finds
+/// the span holding the text rather than every element around it.
+///
+/// The handles returned by these queries can be sent input directly
+/// with `process`:
+///
+/// ```rs
+/// let button = &find_all_by_role(&root, Role::Button)[0];
+/// button.process(&InputEvent::MouseLeft { state: State::Begin, x: 0.0, y: 0.0 });
+/// ```
+pub fn find_by_text(root: &AnyNode, text: &str) -> Option<AnyNode> {
+    find(root, &|node| own_text(node) == text)
+}
+
+/// Finds every node in `root` with the given role, in document order.
+pub fn find_all_by_role(root: &AnyNode, role: Role) -> Vec<AnyNode> {
+    let mut found = vec![];
+    find_all(root, &|node| node.role() == role, &mut found);
+    found
+}
+
+fn own_text(node: &AnyNode) -> String {
+    node.children()
+        .filter_map(|child| match child {
+            DynamicNode::Text(text) => Some(text),
+            DynamicNode::Node(_) => None,
+        })
+        .collect()
+}
+
+fn find(node: &AnyNode, matches: &dyn Fn(&AnyNode) -> bool) -> Option<AnyNode> {
+    if matches(node) {
+        return Some(node.clone());
+    }
+    node.children().find_map(|child| match child {
+        DynamicNode::Node(child) => find(&child.to_owned(), matches),
+        DynamicNode::Text(_) => None,
+    })
+}
+
+fn find_all(node: &AnyNode, matches: &dyn Fn(&AnyNode) -> bool, found: &mut Vec<AnyNode>) {
+    if matches(node) {
+        found.push(node.clone());
+    }
+    for child in node.children() {
+        if let DynamicNode::Node(child) = child {
+            find_all(&child.to_owned(), matches, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_all_by_role, find_by_label, find_by_text};
+    use crate::dom::element::HasAttribute;
+    use crate::dom::elements::button::ButtonChild;
+    use crate::dom::elements::span::SpanChild;
+    use crate::dom::elements::view::ViewChild;
+    use crate::dom::node::AnyNode;
+    use crate::dom::{AttrLabel, AttrRole, Button, Node, Role, Span, View};
+
+    #[test]
+    fn finds_nodes_by_label() {
+        let mut button = Button::default();
+        HasAttribute::<AttrLabel>::set_attribute(&mut button, "Save".to_owned());
+        let mut toolbar = View::default();
+        HasAttribute::<AttrLabel>::set_attribute(&mut toolbar, "Toolbar".to_owned());
+        HasAttribute::<AttrRole>::set_attribute(&mut toolbar, Role::List);
+        let toolbar = Node::new(toolbar, vec![ViewChild::Button(Node::new(button, vec![]))]);
+        let root: AnyNode = Node::new(View::default(), vec![ViewChild::View(toolbar)]).into();
+
+        let save = find_by_label(&root, "Save").unwrap();
+        assert_eq!(save.role(), Role::Button);
+        assert_eq!(find_by_label(&root, "Toolbar").unwrap().role(), Role::List);
+        assert!(find_by_label(&root, "Cancel").is_none());
+    }
+
+    #[test]
+    fn finds_nodes_by_text_and_role() {
+        let text = |text: &str| {
+            let span = Node::new(
+                Span::default(),
+                vec![SpanChild::Text(text.to_owned().into())],
+            );
+            Node::new(Button::default(), vec![ButtonChild::Span(span)])
+        };
+        let root: AnyNode = Node::new(
+            View::default(),
+            vec![
+                ViewChild::Button(text("Save")),
+                ViewChild::Button(text("Cancel")),
+            ],
+        )
+        .into();
+
+        let cancel = find_by_text(&root, "Cancel").unwrap();
+        assert_eq!(cancel.role(), Role::Text);
+        assert!(find_by_text(&root, "Save and close").is_none());
+        assert_eq!(find_all_by_role(&root, Role::Button).len(), 2);
+        assert_eq!(find_all_by_role(&root, Role::Group), vec![root]);
+    }
+}
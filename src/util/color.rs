@@ -1,13 +1,69 @@
 use std::fmt;
 use webrender::api::ColorF;
 
-/// Represents an 8-bit RGBA color in sRGB color space.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// The color space the components of a color are in. Both use the
+/// sRGB transfer function, and differ in their primaries.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ColorSpace {
+    Srgb,
+    /// A wider gamut than sRGB, with more saturated reds and greens,
+    /// which most recent Apple displays can show.
+    DisplayP3,
+}
+
+impl Default for ColorSpace {
+    fn default() -> ColorSpace {
+        ColorSpace::Srgb
+    }
+}
+
+/// Converts linear Display P3 to linear sRGB.
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_940_2, -0.224_940_2, 0.0],
+    [-0.042_056_955, 1.042_056_9, 0.0],
+    [-0.019_637_555, -0.078_636_05, 1.098_273_6],
+];
+
+/// Converts linear sRGB to linear Display P3.
+const SRGB_TO_P3: [[f32; 3]; 3] = [
+    [0.822_461_97, 0.177_538_03, 0.0],
+    [0.033_194_2, 0.966_805_8, 0.0],
+    [0.017_082_632, 0.072_397_44, 0.910_519_9],
+];
+
+/// Decodes a component from the sRGB transfer function.
+fn to_linear(component: f32) -> f32 {
+    if component <= 0.040_45 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear component with the sRGB transfer function.
+fn from_linear(component: f32) -> f32 {
+    if component <= 0.003_130_8 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn transform(matrix: &[[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+    let row = |row: &[f32; 3]| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+    [row(&matrix[0]), row(&matrix[1]), row(&matrix[2])]
+}
+
+/// Represents an 8-bit RGBA color. The color components are encoded
+/// with the sRGB transfer function, in the given color space, and alpha
+/// is linear.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
     pub alpha: u8,
+    pub space: ColorSpace,
 }
 
 impl Color {
@@ -17,6 +73,16 @@ impl Color {
             green,
             blue,
             alpha,
+            space: ColorSpace::Srgb,
+        }
+    }
+
+    /// A color in the Display P3 color space. On displays which can't
+    /// show it, it's drawn as the nearest sRGB color.
+    pub fn display_p3(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+        Color {
+            space: ColorSpace::DisplayP3,
+            ..Color::new(red, green, blue, alpha)
         }
     }
 
@@ -51,24 +117,92 @@ impl Color {
             Err(())
         }
     }
+
+    /// The color's components, without the transfer function applied,
+    /// in its own color space.
+    fn linear(&self) -> [f32; 3] {
+        let component = |value: u8| to_linear(value as f32 / 255.0);
+        [
+            component(self.red),
+            component(self.green),
+            component(self.blue),
+        ]
+    }
+
+    /// Converts the color to `space`. Colors outside of the gamut of
+    /// `space` are clipped to it.
+    pub fn to_space(&self, space: ColorSpace) -> Color {
+        if space == self.space {
+            return *self;
+        }
+        let matrix = match space {
+            ColorSpace::Srgb => &P3_TO_SRGB,
+            ColorSpace::DisplayP3 => &SRGB_TO_P3,
+        };
+        let [red, green, blue] = transform(matrix, self.linear());
+        let component = |value: f32| (from_linear(value.max(0.0).min(1.0)) * 255.0).round() as u8;
+        Color {
+            red: component(red),
+            green: component(green),
+            blue: component(blue),
+            alpha: self.alpha,
+            space,
+        }
+    }
+
+    /// The color as drawn on a surface in the `output` color space.
+    pub fn to_color_f(&self, output: ColorSpace) -> ColorF {
+        let color = self.to_space(output);
+        ColorF::new(
+            color.red as f32 / 255.0,
+            color.green as f32 / 255.0,
+            color.blue as f32 / 255.0,
+            color.alpha as f32 / 255.0,
+        )
+    }
+
+    /// Blends between this color and `other`, where `t` goes from 0 for
+    /// this color to 1 for `other`. The components are blended in
+    /// linear light, which keeps the colors between two saturated ones
+    /// from going dark and muddy the way they do when blending the
+    /// encoded values. The result is in this color's color space.
+    pub fn mix(&self, other: &Color, t: f32) -> Color {
+        let other = other.to_space(self.space);
+        let (from, to) = (self.linear(), other.linear());
+        let component = |index: usize| {
+            let value = from[index] + (to[index] - from[index]) * t;
+            (from_linear(value.max(0.0).min(1.0)) * 255.0).round() as u8
+        };
+        let alpha = self.alpha as f32 + (other.alpha as f32 - self.alpha as f32) * t;
+        Color {
+            red: component(0),
+            green: component(1),
+            blue: component(2),
+            alpha: alpha.round().max(0.0).min(255.0) as u8,
+            space: self.space,
+        }
+    }
 }
 
+/// Converts for an sRGB surface.
 impl Into<ColorF> for Color {
     fn into(self) -> ColorF {
-        ColorF::new(
-            self.red as f32 / 255.0,
-            self.green as f32 / 255.0,
-            self.blue as f32 / 255.0,
-            self.alpha as f32 / 255.0,
-        )
+        self.to_color_f(ColorSpace::Srgb)
     }
 }
 
 /// Displays the color using either `rgb(R, G, B)` or `rgba(R, G, B, A)`
-/// format.
+/// format, or `p3(R, G, B)` or `p3a(R, G, B, A)` for Display P3 colors.
 impl fmt::Display for Color {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        if self.alpha == 255 {
+        if self.space == ColorSpace::DisplayP3 {
+            let (red, green, blue) = (self.red, self.green, self.blue);
+            if self.alpha == 255 {
+                write!(fmt, "p3({}, {}, {})", red, green, blue)
+            } else {
+                write!(fmt, "p3a({}, {}, {}, {})", red, green, blue, self.alpha)
+            }
+        } else if self.alpha == 255 {
             write!(fmt, "rgb({}, {}, {})", self.red, self.green, self.blue)
         } else {
             write!(
@@ -79,3 +213,36 @@ impl fmt::Display for Color {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Color, ColorSpace};
+
+    #[test]
+    fn converts_between_spaces() {
+        let orange = Color::new(255, 128, 0, 255);
+        assert_eq!(orange.to_space(ColorSpace::Srgb), orange);
+        let p3 = orange.to_space(ColorSpace::DisplayP3);
+        assert_eq!(p3, Color::display_p3(239, 135, 51, 255));
+        // Only off by rounding to 8 bits.
+        let back = p3.to_space(ColorSpace::Srgb);
+        assert_eq!((back.red, back.green), (255, 128));
+        assert!(back.blue <= 1);
+        // The most saturated P3 red is outside of sRGB.
+        let red = Color::display_p3(255, 0, 0, 255).to_space(ColorSpace::Srgb);
+        assert_eq!((red.red, red.green, red.blue), (255, 0, 0));
+    }
+
+    #[test]
+    fn mixes_in_linear_light() {
+        let red = Color::new(255, 0, 0, 255);
+        let green = Color::new(0, 255, 0, 255);
+        let middle = red.mix(&green, 0.5);
+        // Blending the encoded values would give 128.
+        assert_eq!((middle.red, middle.green, middle.blue), (188, 188, 0));
+        assert_eq!(red.mix(&green, 0.0), red);
+        assert_eq!(red.mix(&green, 1.0), green);
+        let clear = Color::clear().mix(&Color::white(), 0.5);
+        assert_eq!(clear.alpha, 128);
+    }
+}
@@ -0,0 +1,169 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Strings longer than this are unlikely to be repeated, so they are
+/// not worth the cost of looking up.
+const MAX_INTERNED_LEN: usize = 64;
+
+/// An immutable, cheaply cloned string. Short strings are interned, so
+/// every copy of a repeated label shares one allocation.
+#[derive(Clone)]
+pub struct IStr(Arc<str>);
+
+impl IStr {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for IStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for IStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for IStr {
+    fn eq(&self, other: &IStr) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for IStr {}
+
+impl Hash for IStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Debug for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl<'a> From<&'a str> for IStr {
+    fn from(value: &'a str) -> IStr {
+        intern(value)
+    }
+}
+
+impl From<String> for IStr {
+    fn from(value: String) -> IStr {
+        intern(&value)
+    }
+}
+
+/// How much interning has saved.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InternStats {
+    /// The number of distinct strings in the interner.
+    pub strings: usize,
+    /// The total length of the distinct strings.
+    pub bytes: usize,
+    /// Lookups which found an existing string.
+    pub hits: usize,
+    /// Lookups which had to allocate a new string.
+    pub misses: usize,
+}
+
+#[derive(Default)]
+struct Interner {
+    strings: HashSet<IStr>,
+    stats: InternStats,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+/// Returns a shared copy of the string, allocating it only if it hasn't
+/// been seen before.
+pub fn intern(value: &str) -> IStr {
+    if value.len() > MAX_INTERNED_LEN {
+        return IStr(value.into());
+    }
+    INTERNER.with(|interner| {
+        let mut interner = interner.borrow_mut();
+        if let Some(existing) = interner.strings.get(value) {
+            let existing = existing.clone();
+            interner.stats.hits += 1;
+            return existing;
+        }
+        let string = IStr(value.into());
+        interner.strings.insert(string.clone());
+        interner.stats.misses += 1;
+        interner.stats.strings += 1;
+        interner.stats.bytes += value.len();
+        string
+    })
+}
+
+/// Drops interned strings which are no longer used anywhere else.
+pub fn collect_garbage() {
+    INTERNER.with(|interner| {
+        let mut interner = interner.borrow_mut();
+        interner
+            .strings
+            .retain(|string| Arc::strong_count(&string.0) > 1);
+        let strings = interner.strings.len();
+        let bytes = interner.strings.iter().map(|string| string.len()).sum();
+        interner.stats.strings = strings;
+        interner.stats.bytes = bytes;
+    })
+}
+
+pub fn stats() -> InternStats {
+    INTERNER.with(|interner| interner.borrow().stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{collect_garbage, intern, stats};
+    use std::sync::Arc;
+
+    #[test]
+    fn shares_allocations() {
+        let first = intern("Delete");
+        let second = intern(&"Delete".to_owned());
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+        assert_eq!(&*first, "Delete");
+    }
+
+    #[test]
+    fn long_strings_not_interned() {
+        let long = "x".repeat(100);
+        let first = intern(&long);
+        let second = intern(&long);
+        assert!(!Arc::ptr_eq(&first.0, &second.0));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn garbage_collection() {
+        let kept = intern("kept");
+        drop(intern("dropped"));
+        collect_garbage();
+        assert_eq!(stats().strings, 1);
+        assert!(Arc::ptr_eq(&kept.0, &intern("kept").0));
+    }
+}
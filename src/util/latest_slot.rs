@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+/// Hands values from one thread to another, keeping only the latest.
+///
+/// There's a single slot, holding the most recently written value until
+/// the reader takes it. If the writer writes another value before the
+/// reader gets to it, the older one is replaced, so the reader only ever
+/// sees the latest. Values are moved in and out rather than built in
+/// place, so the lock is only held long enough to swap one, and neither
+/// side waits for the other to finish with a value.
+pub fn latest_slot<T>() -> (Writer<T>, Reader<T>) {
+    let slot = Arc::new(Mutex::new(None));
+    (Writer { slot: slot.clone() }, Reader { slot })
+}
+
+pub struct Writer<T> {
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Writer<T> {
+    /// Publishes a value. Returns the unread value it replaced, if the
+    /// reader hadn't taken the previous one yet.
+    pub fn write(&self, value: T) -> Option<T> {
+        self.slot.lock().unwrap().replace(value)
+    }
+}
+
+pub struct Reader<T> {
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Reader<T> {
+    /// Takes the latest value, if one has been written since the last
+    /// read.
+    pub fn read(&self) -> Option<T> {
+        self.slot.lock().unwrap().take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::latest_slot;
+    use std::thread;
+
+    #[test]
+    fn reads_latest() {
+        let (writer, reader) = latest_slot();
+        assert_eq!(reader.read(), None);
+        assert_eq!(writer.write(1), None);
+        assert_eq!(writer.write(2), Some(1));
+        assert_eq!(reader.read(), Some(2));
+        assert_eq!(reader.read(), None);
+    }
+
+    #[test]
+    fn across_threads() {
+        let (writer, reader) = latest_slot();
+        thread::spawn(move || {
+            for i in 0..100 {
+                writer.write(i);
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(reader.read(), Some(99));
+    }
+}
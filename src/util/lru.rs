@@ -0,0 +1,76 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map which holds at most `capacity` entries, dropping the one used
+/// least recently to make room for new ones.
+pub struct Lru<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    /// Every key, by when it was last used.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    pub fn new(capacity: usize) -> Lru<K, V> {
+        Lru {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.tick;
+        let (value, used) = self.entries.get_mut(key)?;
+        let key = self.order.remove(&*used).unwrap();
+        *used = tick;
+        self.order.insert(tick, key);
+        self.tick += 1;
+        Some(value)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        }
+        while self.entries.len() >= self.capacity.max(1) {
+            let oldest = *self.order.keys().next().unwrap();
+            let key = self.order.remove(&oldest).unwrap();
+            self.entries.remove(&key);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+        self.tick += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lru;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get(&"a"), Some(&1));
+        lru.insert("c", 3);
+        assert_eq!(lru.entries.len(), 2);
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(&1));
+        assert_eq!(lru.get(&"c"), Some(&3));
+        lru.insert("c", 4);
+        assert_eq!(lru.get(&"c"), Some(&4));
+        assert_eq!(lru.entries.len(), 2);
+    }
+}
@@ -1,4 +1,7 @@
 pub mod color;
 pub mod equal_rc;
 pub mod event_handler;
+pub mod intern;
+pub mod latest_slot;
+pub mod lru;
 pub mod word_break_iter;
@@ -1,13 +1,36 @@
+use crate::style::WordBreak;
 use std::iter::Peekable;
 use std::str::CharIndices;
 
 /// An iterator over word breaks. It leaves whitespace in, which
 /// distinguishes it from `str::split_whitespace()`, leaving it at the
-/// start of each item produced. This will also eventually insert break
-/// points between CJK characters, as spaces are not common.
+/// start of each item produced. Unless told to keep words together, it
+/// also breaks between CJK characters, as spaces are not common.
 pub struct WordBreakIterator<'a> {
     string: &'a str,
     iter: Peekable<CharIndices<'a>>,
+    mode: WordBreak,
+}
+
+/// Chinese and Japanese characters, and Korean syllables, each of which
+/// can start a line.
+fn is_cjk(ch: char) -> bool {
+    match ch as u32 {
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Punctuation which stays on the line with the character before it.
+fn is_closing(ch: char) -> bool {
+    ch.is_ascii_punctuation()
+        || match ch {
+            '、' | '。' | '，' | '．' | '！' | '？' | '：' | '；' | '）' | '」' | '』' | '】'
+            | '〉' | '》' | 'ー' => true,
+            _ => false,
+        }
 }
 
 impl<'a> Iterator for WordBreakIterator<'a> {
@@ -15,10 +38,10 @@ impl<'a> Iterator for WordBreakIterator<'a> {
 
     fn next(&mut self) -> Option<&'a str> {
         let mut first_index = None;
-        let mut seen_non_ws = false;
+        let mut last_char = None;
         loop {
-            let result = self.iter.peek();
-            let index = if let Some(&(index, _)) = result {
+            let result = self.iter.peek().cloned();
+            let index = if let Some((index, _)) = result {
                 index
             } else {
                 self.string.len()
@@ -26,16 +49,17 @@ impl<'a> Iterator for WordBreakIterator<'a> {
             if first_index.is_none() {
                 first_index = Some(index);
             }
-            let is_whitespace_or_end = if let Some((_, ch)) = result {
-                ch.is_whitespace()
-            } else {
-                true
+            let next_char = result.map(|(_, ch)| ch).filter(|ch| !ch.is_whitespace());
+            let breaks = match (last_char, next_char) {
+                (Some(_), None) => true,
+                (Some(last), Some(next)) => self.breaks_between(last, next),
+                (None, _) => false,
             };
-            if seen_non_ws && is_whitespace_or_end {
+            if breaks {
                 return Some(&self.string[first_index.unwrap()..index]);
             }
-            if !is_whitespace_or_end {
-                seen_non_ws = true;
+            if next_char.is_some() {
+                last_char = next_char;
             }
 
             if result.is_none() {
@@ -53,9 +77,22 @@ impl<'a> Iterator for WordBreakIterator<'a> {
 
 impl<'a> WordBreakIterator<'a> {
     pub fn new(string: &'a str) -> WordBreakIterator<'a> {
+        WordBreakIterator::with_mode(string, WordBreak::Normal)
+    }
+
+    pub fn with_mode(string: &'a str, mode: WordBreak) -> WordBreakIterator<'a> {
         WordBreakIterator {
             string,
             iter: string.char_indices().peekable(),
+            mode,
+        }
+    }
+
+    fn breaks_between(&self, last: char, next: char) -> bool {
+        match self.mode {
+            WordBreak::Normal => (is_cjk(last) || is_cjk(next)) && !is_closing(next),
+            WordBreak::BreakAll => true,
+            WordBreak::KeepAll => false,
         }
     }
 }
@@ -63,6 +100,7 @@ impl<'a> WordBreakIterator<'a> {
 #[cfg(test)]
 mod test {
     use super::WordBreakIterator;
+    use crate::style::WordBreak;
 
     #[test]
     fn test_iter() {
@@ -95,4 +133,22 @@ mod test {
         assert!(expect.len() == result.len());
         assert!(expect[0] == result[0]);
     }
+
+    #[test]
+    fn break_modes() {
+        let words = |string, mode| WordBreakIterator::with_mode(string, mode).collect::<Vec<_>>();
+        assert_eq!(
+            words("日本語です。", WordBreak::Normal),
+            vec!["日", "本", "語", "で", "す。"]
+        );
+        assert_eq!(
+            words("한국어 단어", WordBreak::KeepAll),
+            vec!["한국어", " 단어"]
+        );
+        assert_eq!(
+            words("ab cd", WordBreak::BreakAll),
+            vec!["a", "b", " c", "d"]
+        );
+        assert_eq!(words("foo bar", WordBreak::Normal), vec!["foo", " bar"]);
+    }
 }
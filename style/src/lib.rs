@@ -9,7 +9,7 @@ use {
     syn::token,
     syn::{
         braced, parenthesized, parse_macro_input, Attribute as SynAttribute, Ident, Lit, LitInt,
-        Token, Visibility,
+        LitStr, Token, Visibility,
     },
 };
 
@@ -28,6 +28,19 @@ enum Selector {
 
 impl Parse for Selector {
     fn parse(input: ParseStream) -> Result<Self> {
+        // `:hover`, `:active` and `:focus` select on how the user is
+        // interacting with the element, whatever kind it is.
+        if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            let ident = input.parse::<Ident>()?;
+            return match &ident.to_string()[..] {
+                "hover" | "active" | "focus" => Ok(Selector::State(ident)),
+                _ => Err(Error::new(
+                    ident.span(),
+                    "Expected one of hover, active, focus",
+                )),
+            };
+        }
         let ident = input.parse::<Ident>()?;
         match &ident.to_string()[..] {
             "element" => {
@@ -60,15 +73,41 @@ enum LengthItem {
     Ems(f32),
     ViewWidth(f32),
     ViewHeight(f32),
+    Percent(f32),
+    /// The window's safe area inset on the side being set.
+    SafeArea,
+}
+
+fn is_unit(ident: &Ident) -> bool {
+    match &ident.to_string()[..] {
+        "px" | "em" | "vw" | "vh" => true,
+        _ => false,
+    }
 }
 
 impl Parse for LengthItem {
     fn parse(input: ParseStream) -> Result<Self> {
+        if let Ok(ident) = input.fork().parse::<Ident>() {
+            if ident == "safe_area" {
+                input.parse::<Ident>()?;
+                return Ok(LengthItem::SafeArea);
+            }
+        }
         let value = match input.parse::<Lit>()? {
             Lit::Int(int) => int.base10_parse::<f32>()?,
             Lit::Float(float) => float.base10_parse::<f32>()?,
-            _ => unimplemented!(),
+            lit => return Err(Error::new(lit.span(), "Expected a number")),
         };
+        if input.peek(Token![%]) {
+            input.parse::<Token![%]>()?;
+            return Ok(LengthItem::Percent(value));
+        }
+        // Numbers without a unit are pixels, so that shorthands like
+        // `margin: auto 0` and `padding: (8, 16)` can be written.
+        match input.fork().parse::<Ident>() {
+            Ok(ref ident) if is_unit(ident) => (),
+            _ => return Ok(LengthItem::Pixels(value)),
+        }
         let ident = input.parse::<Ident>()?;
         match &ident.to_string()[..] {
             "px" => Ok(LengthItem::Pixels(value)),
@@ -77,7 +116,7 @@ impl Parse for LengthItem {
             "vh" => Ok(LengthItem::ViewHeight(value)),
             _ => Err(Error::new(
                 ident.span(),
-                "Expected one of px, em, vw, or vh",
+                "Expected one of px, em, vw, vh, or %",
             )),
         }
     }
@@ -96,6 +135,8 @@ struct LengthValues {
     ems: f32,
     view_width: f32,
     view_height: f32,
+    percent: f32,
+    safe_area: f32,
 }
 
 impl Length {
@@ -132,6 +173,14 @@ impl Length {
                 view_height: *value / 100.0,
                 ..Default::default()
             },
+            Length::Const(LengthItem::Percent(value)) => LengthValues {
+                percent: *value / 100.0,
+                ..Default::default()
+            },
+            Length::Const(LengthItem::SafeArea) => LengthValues {
+                safe_area: 1.0,
+                ..Default::default()
+            },
             Length::Add(left, right) => {
                 let left = left.eval();
                 let right = right.eval();
@@ -140,6 +189,8 @@ impl Length {
                     ems: left.ems + right.ems,
                     view_width: left.view_width + right.view_width,
                     view_height: left.view_height + right.view_height,
+                    percent: left.percent + right.percent,
+                    safe_area: left.safe_area + right.safe_area,
                 }
             }
             Length::Sub(left, right) => {
@@ -150,6 +201,8 @@ impl Length {
                     ems: left.ems - right.ems,
                     view_width: left.view_width - right.view_width,
                     view_height: left.view_height - right.view_height,
+                    percent: left.percent - right.percent,
+                    safe_area: left.safe_area - right.safe_area,
                 }
             }
         }
@@ -169,12 +222,17 @@ impl ToTokens for Length {
             ems,
             view_width,
             view_height,
+            percent,
+            safe_area,
         } = self.eval();
         tokens.extend(quote!(::moxie_native::style::Value {
             pixels: #pixels,
             ems: #ems,
             view_width: #view_width,
             view_height: #view_height,
+            percent: #percent,
+            safe_area: #safe_area,
+            auto: false,
         }));
     }
 }
@@ -184,13 +242,15 @@ struct Color {
     green: u8,
     blue: u8,
     alpha: u8,
+    display_p3: bool,
 }
 
 impl Parse for Color {
     fn parse(input: ParseStream) -> Result<Self> {
         let ty = input.parse::<Ident>()?;
+        let display_p3 = ty == "p3" || ty == "p3a";
         match &ty.to_string()[..] {
-            "rgb" => {
+            "rgb" | "p3" => {
                 let content;
                 parenthesized!(content in input);
                 let punctuated = content.parse_terminated::<LitInt, Token![,]>(LitInt::parse)?;
@@ -200,7 +260,7 @@ impl Parse for Color {
                             .last()
                             .map(|int| int.span())
                             .unwrap_or(punctuated.span()),
-                        "rgb() requires exactly 3 arguments",
+                        format!("{}() requires exactly 3 arguments", ty),
                     ));
                 }
                 let red = punctuated[0].base10_parse::<u8>()?;
@@ -212,9 +272,10 @@ impl Parse for Color {
                     green,
                     blue,
                     alpha: 255,
+                    display_p3,
                 })
             }
-            "rgba" => {
+            "rgba" | "p3a" => {
                 let content;
                 parenthesized!(content in input);
                 let punctuated = content.parse_terminated::<LitInt, Token![,]>(LitInt::parse)?;
@@ -224,7 +285,7 @@ impl Parse for Color {
                             .last()
                             .map(|int| int.span())
                             .unwrap_or(punctuated.span()),
-                        "rgba() requires exactly 4 arguments",
+                        format!("{}() requires exactly 4 arguments", ty),
                     ));
                 }
                 let red = punctuated[0].base10_parse::<u8>()?;
@@ -237,9 +298,10 @@ impl Parse for Color {
                     green,
                     blue,
                     alpha,
+                    display_p3,
                 })
             }
-            _ => return Err(Error::new(ty.span(), "Expected rgb or rgba")),
+            _ => return Err(Error::new(ty.span(), "Expected rgb, rgba, p3 or p3a")),
         }
     }
 }
@@ -251,16 +313,105 @@ impl ToTokens for Color {
             green,
             blue,
             alpha,
+            display_p3,
         } = self;
+        let space = if *display_p3 {
+            quote!(DisplayP3)
+        } else {
+            quote!(Srgb)
+        };
         tokens.extend(quote!(::moxie_native::Color {
             red: #red,
             green: #green,
             blue: #blue,
             alpha: #alpha,
+            space: ::moxie_native::ColorSpace::#space,
         }))
     }
 }
 
+enum Transition {
+    Tween { millis: u64, easing: Ident },
+    Spring { stiffness: f32, damping: f32 },
+}
+
+fn parse_number(input: ParseStream) -> Result<f32> {
+    match input.parse::<Lit>()? {
+        Lit::Int(int) => int.base10_parse::<f32>(),
+        Lit::Float(float) => float.base10_parse::<f32>(),
+        lit => Err(Error::new(lit.span(), "Expected a number")),
+    }
+}
+
+impl Parse for Transition {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            if ident.to_string() != "spring" {
+                return Err(Error::new(ident.span(), "Expected a duration or spring()"));
+            }
+            let content;
+            parenthesized!(content in input);
+            let stiffness = content.call(parse_number)?;
+            content.parse::<Token![,]>()?;
+            let damping = content.call(parse_number)?;
+            return Ok(Transition::Spring { stiffness, damping });
+        }
+
+        let value = input.call(parse_number)?;
+        let unit = input.parse::<Ident>()?;
+        let millis = match &unit.to_string()[..] {
+            "ms" => value,
+            "s" => value * 1000.0,
+            _ => return Err(Error::new(unit.span(), "Expected one of ms or s")),
+        };
+        let easing = if input.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            let canonical = match &ident.to_string()[..] {
+                "linear" => "Linear",
+                "ease_in" => "EaseIn",
+                "ease_out" => "EaseOut",
+                "ease_in_out" => "EaseInOut",
+                _ => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "Expected one of linear, ease_in, ease_out, or ease_in_out",
+                    ))
+                }
+            };
+            Ident::new(canonical, ident.span())
+        } else {
+            Ident::new("EaseInOut", unit.span())
+        };
+        Ok(Transition::Tween {
+            millis: millis.round() as u64,
+            easing,
+        })
+    }
+}
+
+impl ToTokens for Transition {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            Transition::Tween { millis, easing } => quote!(
+                ::moxie_native::animation::Transition::Tween {
+                    duration: ::std::time::Duration::from_millis(#millis),
+                    easing: ::moxie_native::animation::Easing::#easing,
+                }
+            ),
+            Transition::Spring { stiffness, damping } => quote!(
+                ::moxie_native::animation::Transition::Spring(
+                    ::moxie_native::animation::SpringConfig {
+                        stiffness: #stiffness,
+                        damping: #damping,
+                        mass: 1.0,
+                    }
+                )
+            ),
+        })
+    }
+}
+
 fn parse_length_or_auto(input: ParseStream) -> Result<Option<Length>> {
     if let Ok(ident) = input.fork().parse::<Ident>() {
         if ident.to_string() == "auto" {
@@ -276,39 +427,100 @@ struct SideOffsets {
     right: Option<Length>,
     top: Option<Length>,
     bottom: Option<Length>,
+    /// Set for margins, where `auto` on the left or right takes up the
+    /// space the parent has left rather than leaving the side unchanged.
+    auto_horizontal: bool,
 }
 
-impl Parse for SideOffsets {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let left: Option<Length> = input.call(parse_length_or_auto)?;
-        if input.peek(Token![,]) {
-            return Ok(SideOffsets {
-                left: left.clone(),
-                right: left.clone(),
-                top: left.clone(),
-                bottom: left,
-            });
-        }
-        let top = input.call(parse_length_or_auto)?;
-        let right = input.call(parse_length_or_auto)?;
-        let bottom = input.call(parse_length_or_auto)?;
+impl SideOffsets {
+    /// Expands a list of one to four values the same way CSS does: all
+    /// sides; vertical and horizontal; top, horizontal and bottom; or
+    /// top, right, bottom and left.
+    fn from_values(mut values: Vec<Option<Length>>, span: proc_macro2::Span) -> Result<Self> {
+        let (top, right, bottom, left) = match values.len() {
+            1 => {
+                let all = values.remove(0);
+                (all.clone(), all.clone(), all.clone(), all)
+            }
+            2 => {
+                let horizontal = values.remove(1);
+                let vertical = values.remove(0);
+                (vertical.clone(), horizontal.clone(), vertical, horizontal)
+            }
+            3 => {
+                let bottom = values.remove(2);
+                let horizontal = values.remove(1);
+                let top = values.remove(0);
+                (top, horizontal.clone(), bottom, horizontal)
+            }
+            4 => {
+                let left = values.remove(3);
+                let bottom = values.remove(2);
+                let right = values.remove(1);
+                let top = values.remove(0);
+                (top, right, bottom, left)
+            }
+            _ => return Err(Error::new(span, "Expected between 1 and 4 values")),
+        };
         Ok(SideOffsets {
             left,
             right,
             top,
             bottom,
+            auto_horizontal: false,
         })
     }
+
+    /// Percentages aren't resolved for margins, padding or borders, so
+    /// they're rejected here rather than quietly doing nothing.
+    fn parse_without_percent(input: ParseStream) -> Result<Self> {
+        let span = input.cursor().span();
+        let offsets = input.parse::<SideOffsets>()?;
+        let sides = [&offsets.left, &offsets.right, &offsets.top, &offsets.bottom];
+        let has_percent = sides
+            .iter()
+            .filter_map(|side| side.as_ref())
+            .any(|length| length.eval().percent != 0.0);
+        if has_percent {
+            return Err(Error::new(
+                span,
+                "Percentages are only supported for widths and heights",
+            ));
+        }
+        Ok(offsets)
+    }
+}
+
+impl Parse for SideOffsets {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let span = input.cursor().span();
+        if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let values =
+                content.parse_terminated::<Option<Length>, Token![,]>(parse_length_or_auto)?;
+            return SideOffsets::from_values(values.into_iter().collect(), span);
+        }
+
+        let mut values = vec![];
+        while !input.is_empty() && !input.peek(Token![,]) {
+            values.push(input.call(parse_length_or_auto)?);
+        }
+        SideOffsets::from_values(values, span)
+    }
 }
 
-struct OptionLength(Option<Length>);
+/// A side, which is `auto` when it's `None` and `auto` is true.
+struct OptionLength(Option<Length>, bool);
 
 impl ToTokens for OptionLength {
     fn to_tokens(&self, stream: &mut TokenStream) {
-        if let Some(ref length) = self.0 {
-            stream.extend(quote!(Some(#length)))
-        } else {
-            stream.extend(quote!(None))
+        match *self {
+            OptionLength(Some(ref length), _) => stream.extend(quote!(Some(#length))),
+            OptionLength(None, true) => {
+                stream.extend(quote!(Some(::moxie_native::style::Value::auto())))
+            }
+            OptionLength(None, false) => stream.extend(quote!(None)),
         }
     }
 }
@@ -320,11 +532,12 @@ impl ToTokens for SideOffsets {
             right,
             top,
             bottom,
+            auto_horizontal,
         } = self;
-        let left = OptionLength(left.clone());
-        let right = OptionLength(right.clone());
-        let top = OptionLength(top.clone());
-        let bottom = OptionLength(bottom.clone());
+        let left = OptionLength(left.clone(), *auto_horizontal);
+        let right = OptionLength(right.clone(), *auto_horizontal);
+        let top = OptionLength(top.clone(), false);
+        let bottom = OptionLength(bottom.clone(), false);
         stream.extend(quote!(
             ::moxie_native::style::SideOffsets {
                 left: #left,
@@ -336,10 +549,64 @@ impl ToTokens for SideOffsets {
     }
 }
 
+/// A line height, which is a multiple of the text size when it's a
+/// number without a unit, unlike other lengths.
+enum LineHeight {
+    Normal,
+    Multiple(f32),
+    Length(Length),
+}
+
+impl Parse for LineHeight {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            return if ident == "normal" {
+                Ok(LineHeight::Normal)
+            } else {
+                Err(Error::new(
+                    ident.span(),
+                    "Expected normal, a multiple, or a length",
+                ))
+            };
+        }
+        let fork = input.fork();
+        let multiple = match fork.parse::<Lit>()? {
+            Lit::Int(int) => int.base10_parse::<f32>()?,
+            Lit::Float(float) => float.base10_parse::<f32>()?,
+            lit => return Err(Error::new(lit.span(), "Expected a number")),
+        };
+        if fork.is_empty() || fork.peek(Token![,]) {
+            input.parse::<Lit>()?;
+            Ok(LineHeight::Multiple(multiple))
+        } else {
+            Ok(LineHeight::Length(input.parse()?))
+        }
+    }
+}
+
+impl ToTokens for LineHeight {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            LineHeight::Normal => quote!(::moxie_native::style::LineHeightValue::Normal),
+            LineHeight::Multiple(multiple) => {
+                quote!(::moxie_native::style::LineHeightValue::Multiple(#multiple))
+            }
+            LineHeight::Length(length) => {
+                quote!(::moxie_native::style::LineHeightValue::Length(#length))
+            }
+        })
+    }
+}
+
 enum Value {
     Length(Length),
+    LineHeight(LineHeight),
     Color(Color),
     SideOffsets(SideOffsets),
+    Transition(Transition),
+    Count(u32),
+    Text(LitStr),
     Enum(Ident, Ident),
 }
 
@@ -347,8 +614,12 @@ impl ToTokens for Value {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
             Value::Length(value) => tokens.extend(quote!(Some(#value))),
+            Value::LineHeight(value) => tokens.extend(quote!(Some(#value))),
             Value::Color(value) => tokens.extend(quote!(Some(#value))),
             Value::SideOffsets(value) => value.to_tokens(tokens),
+            Value::Transition(value) => tokens.extend(quote!(Some(#value))),
+            Value::Count(value) => tokens.extend(quote!(Some(#value))),
+            Value::Text(value) => tokens.extend(quote!(Some(#value))),
             Value::Enum(enum_ty, variant) => {
                 tokens.extend(quote!(Some(::moxie_native::style::#enum_ty::#variant)))
             }
@@ -384,8 +655,12 @@ impl Enum {
 
 enum AttributeType {
     Length,
+    LineHeight,
     Color,
     SideOffsets,
+    Transition,
+    Count,
+    Text,
     Enum(Enum),
     Unknown,
 }
@@ -394,8 +669,18 @@ impl AttributeType {
     fn from_name(name: &str) -> AttributeType {
         match name {
             "padding" | "margin" | "border_thickness" => AttributeType::SideOffsets,
-            "width" | "height" | "text_size" | "border_radius" => AttributeType::Length,
+            "width" | "height" | "min_width" | "min_height" | "max_width" | "max_height"
+            | "text_size" | "border_radius" | "letter_spacing" => AttributeType::Length,
+            "scrollbar_thickness" => AttributeType::Length,
+            "line_height" => AttributeType::LineHeight,
             "text_color" | "background_color" | "border_color" => AttributeType::Color,
+            "scrollbar_thumb_color" | "scrollbar_track_color" => AttributeType::Color,
+            "selection_color" => AttributeType::Color,
+            "layout_transition" | "enter_transition" | "exit_transition" => {
+                AttributeType::Transition
+            }
+            "max_lines" | "tab_size" | "font_weight" | "columns" => AttributeType::Count,
+            "font_family" => AttributeType::Text,
             "direction" => AttributeType::Enum(Enum {
                 name: "Direction",
                 variants: &[
@@ -409,6 +694,198 @@ impl AttributeType {
                     },
                 ],
             }),
+            "overflow" => AttributeType::Enum(Enum {
+                name: "Overflow",
+                variants: &[
+                    EnumItem {
+                        short_name: "visible",
+                        canonical_name: "Visible",
+                    },
+                    EnumItem {
+                        short_name: "scroll",
+                        canonical_name: "Scroll",
+                    },
+                    EnumItem {
+                        short_name: "zoom",
+                        canonical_name: "Zoom",
+                    },
+                ],
+            }),
+            "scrollbar" => AttributeType::Enum(Enum {
+                name: "Scrollbar",
+                variants: &[
+                    EnumItem {
+                        short_name: "overlay",
+                        canonical_name: "Overlay",
+                    },
+                    EnumItem {
+                        short_name: "always",
+                        canonical_name: "Always",
+                    },
+                    EnumItem {
+                        short_name: "hidden",
+                        canonical_name: "Hidden",
+                    },
+                ],
+            }),
+            "float" => AttributeType::Enum(Enum {
+                name: "Float",
+                variants: &[
+                    EnumItem {
+                        short_name: "none",
+                        canonical_name: "None",
+                    },
+                    EnumItem {
+                        short_name: "left",
+                        canonical_name: "Left",
+                    },
+                    EnumItem {
+                        short_name: "right",
+                        canonical_name: "Right",
+                    },
+                ],
+            }),
+            "margin_collapse" => AttributeType::Enum(Enum {
+                name: "MarginCollapse",
+                variants: &[
+                    EnumItem {
+                        short_name: "separate",
+                        canonical_name: "Separate",
+                    },
+                    EnumItem {
+                        short_name: "collapse",
+                        canonical_name: "Collapse",
+                    },
+                ],
+            }),
+            "text_direction" => AttributeType::Enum(Enum {
+                name: "TextDirection",
+                variants: &[
+                    EnumItem {
+                        short_name: "ltr",
+                        canonical_name: "Ltr",
+                    },
+                    EnumItem {
+                        short_name: "rtl",
+                        canonical_name: "Rtl",
+                    },
+                ],
+            }),
+            "word_break" => AttributeType::Enum(Enum {
+                name: "WordBreak",
+                variants: &[
+                    EnumItem {
+                        short_name: "normal",
+                        canonical_name: "Normal",
+                    },
+                    EnumItem {
+                        short_name: "break_all",
+                        canonical_name: "BreakAll",
+                    },
+                    EnumItem {
+                        short_name: "keep_all",
+                        canonical_name: "KeepAll",
+                    },
+                ],
+            }),
+            "overflow_wrap" => AttributeType::Enum(Enum {
+                name: "OverflowWrap",
+                variants: &[
+                    EnumItem {
+                        short_name: "normal",
+                        canonical_name: "Normal",
+                    },
+                    EnumItem {
+                        short_name: "anywhere",
+                        canonical_name: "Anywhere",
+                    },
+                ],
+            }),
+            "white_space" => AttributeType::Enum(Enum {
+                name: "WhiteSpace",
+                variants: &[
+                    EnumItem {
+                        short_name: "normal",
+                        canonical_name: "Normal",
+                    },
+                    EnumItem {
+                        short_name: "pre",
+                        canonical_name: "Pre",
+                    },
+                    EnumItem {
+                        short_name: "pre_wrap",
+                        canonical_name: "PreWrap",
+                    },
+                    EnumItem {
+                        short_name: "nowrap",
+                        canonical_name: "NoWrap",
+                    },
+                ],
+            }),
+            "font_style" => AttributeType::Enum(Enum {
+                name: "FontStyle",
+                variants: &[
+                    EnumItem {
+                        short_name: "normal",
+                        canonical_name: "Normal",
+                    },
+                    EnumItem {
+                        short_name: "italic",
+                        canonical_name: "Italic",
+                    },
+                    EnumItem {
+                        short_name: "oblique",
+                        canonical_name: "Oblique",
+                    },
+                ],
+            }),
+            "text_overflow" => AttributeType::Enum(Enum {
+                name: "TextOverflow",
+                variants: &[
+                    EnumItem {
+                        short_name: "clip",
+                        canonical_name: "Clip",
+                    },
+                    EnumItem {
+                        short_name: "ellipsis",
+                        canonical_name: "Ellipsis",
+                    },
+                ],
+            }),
+            "text_decoration" => AttributeType::Enum(Enum {
+                name: "TextDecoration",
+                variants: &[
+                    EnumItem {
+                        short_name: "none",
+                        canonical_name: "None",
+                    },
+                    EnumItem {
+                        short_name: "underline",
+                        canonical_name: "Underline",
+                    },
+                    EnumItem {
+                        short_name: "line_through",
+                        canonical_name: "LineThrough",
+                    },
+                    EnumItem {
+                        short_name: "overline",
+                        canonical_name: "Overline",
+                    },
+                ],
+            }),
+            "render_cache" => AttributeType::Enum(Enum {
+                name: "RenderCache",
+                variants: &[
+                    EnumItem {
+                        short_name: "none",
+                        canonical_name: "None",
+                    },
+                    EnumItem {
+                        short_name: "subtree",
+                        canonical_name: "Subtree",
+                    },
+                ],
+            }),
             "display" => AttributeType::Enum(Enum {
                 name: "Display",
                 variants: &[
@@ -433,8 +910,16 @@ impl Parse for Attribute {
         input.parse::<Token![:]>()?;
         let value = match AttributeType::from_name(name.to_string().as_ref()) {
             AttributeType::Length => Value::Length(input.parse()?),
+            AttributeType::LineHeight => Value::LineHeight(input.parse()?),
             AttributeType::Color => Value::Color(input.parse()?),
-            AttributeType::SideOffsets => Value::SideOffsets(input.parse()?),
+            AttributeType::SideOffsets => {
+                let mut offsets = SideOffsets::parse_without_percent(input)?;
+                offsets.auto_horizontal = name == "margin";
+                Value::SideOffsets(offsets)
+            }
+            AttributeType::Transition => Value::Transition(input.parse()?),
+            AttributeType::Count => Value::Count(input.parse::<LitInt>()?.base10_parse()?),
+            AttributeType::Text => Value::Text(input.parse()?),
             AttributeType::Enum(enum_ty) => {
                 let ident = input.parse::<Ident>()?;
                 if let Some(canonical) = enum_ty.lookup(&ident.to_string()[..]) {
@@ -608,8 +1093,32 @@ impl ToTokens for StyleList {
 
 #[cfg(test)]
 mod tests {
+    use super::SideOffsets;
+    use syn::parse::Parser;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn side_offsets_accept_lengths() {
+        let offsets = SideOffsets::parse_without_percent
+            .parse_str("4 px 2 em")
+            .unwrap();
+        assert_eq!(offsets.top.unwrap().eval().pixels, 4.0);
+        assert_eq!(offsets.right.unwrap().eval().ems, 2.0);
+        assert_eq!(offsets.bottom.unwrap().eval().pixels, 4.0);
+        assert_eq!(offsets.left.unwrap().eval().ems, 2.0);
+    }
+
+    #[test]
+    fn side_offsets_reject_percentages() {
+        assert!(SideOffsets::parse_without_percent
+            .parse_str("10 %")
+            .is_err());
+        assert!(SideOffsets::parse_without_percent
+            .parse_str("(4 px, 8 px + 5 %)")
+            .is_err());
+    }
 }